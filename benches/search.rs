@@ -0,0 +1,108 @@
+//! Search backend benchmarks over synthetic corpora.
+//!
+//! Run with `cargo bench` (ripgrep only) or `cargo bench --features bench`
+//! to include Tantivy index builds and ranked search. Criterion reports
+//! throughput in documents per second for each corpus size.
+
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use kvault::commands::generate_synthetic_corpus;
+use kvault::corpus::Corpus;
+use kvault::search::ripgrep::RipgrepBackend;
+use kvault::search::{SearchBackend, SearchOptions};
+use tempfile::TempDir;
+
+#[cfg(feature = "ranked")]
+use kvault::search::tantivy::{IndexMode, TantivyBackend};
+
+/// Corpus sizes each benchmark runs against.
+const CORPUS_SIZES: [usize; 3] = [100, 1_000, 5_000];
+
+/// Query term present in most synthetic documents.
+const QUERY: &str = "lambda";
+
+fn corpus(size: usize) -> (TempDir, Corpus) {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let corpus = generate_synthetic_corpus(temp_dir.path(), size).expect("Failed to generate");
+    (temp_dir, corpus)
+}
+
+fn options() -> SearchOptions {
+    SearchOptions {
+        limit: Some(10),
+        ..Default::default()
+    }
+}
+
+fn ripgrep_search(c: &mut Criterion) {
+    let rg = RipgrepBackend::new();
+    let mut group = c.benchmark_group("ripgrep_search");
+    // Each search spawns `rg`, so samples are slow
+    group.sample_size(10);
+
+    for size in CORPUS_SIZES {
+        let (_temp_dir, corpus) = corpus(size);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &corpus, |b, corpus| {
+            b.iter(|| rg.search(black_box(QUERY), corpus, &options()).unwrap());
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "ranked")]
+fn tantivy_index(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tantivy_index");
+    group.sample_size(10);
+
+    for size in CORPUS_SIZES {
+        let (_temp_dir, corpus) = corpus(size);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &corpus, |b, corpus| {
+            b.iter(|| {
+                let backend =
+                    TantivyBackend::open_for_corpus(corpus, IndexMode::ReadWrite).unwrap();
+                backend.index_corpus(corpus).unwrap();
+            });
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "ranked")]
+fn tantivy_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tantivy_search");
+
+    for size in CORPUS_SIZES {
+        let (_temp_dir, corpus) = corpus(size);
+        TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite)
+            .unwrap()
+            .index_corpus(&corpus)
+            .unwrap();
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadOnly).unwrap();
+
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &corpus, |b, corpus| {
+            b.iter(|| {
+                backend
+                    .search(black_box(QUERY), corpus, &options())
+                    .unwrap()
+            });
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "ranked")]
+criterion_group!(benches, ripgrep_search, tantivy_index, tantivy_search);
+
+#[cfg(not(feature = "ranked"))]
+criterion_group!(benches, ripgrep_search);
+
+criterion_main!(benches);
@@ -2,7 +2,13 @@
 //!
 //! Provides command-line argument parsing using clap.
 
-use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+pub use crate::commands::LineRange;
+pub use crate::corpus::CategoryFix;
+pub use crate::search::{CategoryMatch, CrossOrder, HighlightMode, MatchMode};
 
 /// Default number of search results to return.
 pub const DEFAULT_SEARCH_LIMIT: usize = 10;
@@ -20,11 +26,58 @@ pub enum Backend {
     Auto,
 }
 
+/// How a `--category` filter matches, from the `--category-prefix` flag.
+#[must_use]
+pub fn category_match(category_prefix: bool) -> CategoryMatch {
+    if category_prefix {
+        CategoryMatch::Prefix
+    } else {
+        CategoryMatch::Exact
+    }
+}
+
+/// The output format from `--format`, or JSON with the `--json` shorthand.
+#[must_use]
+pub fn output_format(format: OutputFormat, json: bool) -> OutputFormat {
+    if json { OutputFormat::Json } else { format }
+}
+
+/// Output format for commands that print results.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text (default).
+    #[default]
+    Text,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// One file path per line, each listed once (e.g., for `search --within`).
+    Paths,
+    /// A JSON array of results or documents.
+    Json,
+}
+
+/// Output format for `tag-graph`.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum GraphFormat {
+    /// One `tag, tag: count` line per pair (default).
+    #[default]
+    Text,
+    /// A Graphviz DOT graph with each pair's count as its edge weight.
+    Dot,
+    /// A JSON array of `{tags, count}`.
+    Json,
+}
+
 /// Command-line interface for kvault.
 #[derive(Parser)]
 #[command(name = "kvault")]
 #[command(author, version, about = "Searchable knowledge corpus", long_about = None)]
 pub struct Cli {
+    /// Use the settings of a `[profiles.<name>]` config section
+    /// (default: `KVAULT_PROFILE`).
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
     /// The subcommand to run.
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -34,70 +87,486 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Search the knowledge corpus for documents matching a query.
-    Search {
-        /// The search query string.
-        query: String,
+    Search(SearchArgs),
 
-        /// Maximum number of results to return.
-        #[arg(short, long, default_value_t = DEFAULT_SEARCH_LIMIT)]
-        limit: usize,
-
-        /// Filter results to this category only.
+    /// List all documents in the corpus.
+    List {
+        /// Filter results to this category only (default:
+        /// `[list] default_category`).
         #[arg(short, long)]
         category: Option<String>,
 
-        /// Use case-sensitive matching (default is case-insensitive).
-        #[arg(short = 's', long)]
-        case_sensitive: bool,
+        /// List every category, even if `[list] default_category` is set.
+        #[arg(long, conflicts_with = "category")]
+        all_categories: bool,
+
+        /// Also match categories nested under `--category` (e.g., `aws`
+        /// matches `aws/lambda`).
+        #[arg(long, requires = "category")]
+        category_prefix: bool,
+
+        /// Only list documents with this tag (repeatable; all must match).
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
 
-        /// Search backend to use.
-        #[arg(short, long, default_value = "ripgrep")]
-        backend: Backend,
+        /// Show each document's word count.
+        #[arg(long)]
+        word_count: bool,
 
-        /// Enable fuzzy search with specified edit distance (1-2).
-        /// Only available with the `ranked` backend.
+        /// List a document found in several corpora (same path and title)
+        /// once, from the first configured corpus.
+        #[arg(long, alias = "dedupe-across-corpora")]
+        dedupe: bool,
+
+        /// Only list manifest entries whose file is missing.
+        #[arg(long)]
+        broken: bool,
+
+        /// Show at most this many documents.
         #[arg(short, long)]
-        fuzzy: Option<u8>,
+        limit: Option<usize>,
+
+        /// Skip this many documents before the first one shown.
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+
+        /// Print the JSON Schema for manifest.json instead of listing.
+        #[arg(long, conflicts_with_all = ["category", "all_categories", "category_prefix", "tags", "word_count", "dedupe", "broken", "limit", "offset", "format", "json", "fail_on_empty"])]
+        json_schema: bool,
+
+        /// Exit with status 3 if no documents are found.
+        #[arg(long)]
+        fail_on_empty: bool,
+
+        /// Output format.
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+
+        /// Shorthand for `--format json`.
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
     },
 
-    /// List all documents in the corpus.
-    List {
-        /// Filter results to this category only.
-        #[arg(short, long)]
-        category: Option<String>,
+    /// Query document metadata with a boolean expression.
+    ///
+    /// Example: `category = aws AND (tag = lambda OR title ~ serverless)`
+    Query {
+        /// The metadata query expression.
+        expr: String,
     },
 
     /// Add a new document to the corpus.
-    Add {
-        /// Human-readable document title.
-        #[arg(short, long)]
-        title: String,
+    Add(AddArgs),
 
-        /// Category for grouping (e.g., "aws", "rust").
-        #[arg(short = 'C', long)]
-        category: String,
+    /// Get the full contents of a document by its path.
+    Get {
+        /// Document path (e.g., "aws/lambda-patterns.md").
+        path: String,
+
+        /// Only output lines START through END (1-indexed, inclusive).
+        #[arg(long, value_name = "START:END")]
+        line_range: Option<LineRange>,
+
+        /// Truncate output to at most this many bytes.
+        #[arg(long, value_name = "N")]
+        limit_bytes: Option<usize>,
+
+        /// Append the documents this one links to (direct links only, up
+        /// to 32 KiB of linked content in total).
+        #[arg(long)]
+        resolve_links: bool,
+    },
+
+    /// Show a document's `[[wikilinks]]` and markdown links, and whether
+    /// each resolves to a document.
+    Links {
+        /// Document path (e.g., "notes/index.md").
+        #[arg(required_unless_present = "check_all")]
+        path: Option<String>,
+
+        /// Check every document in every corpus and report broken links.
+        #[arg(long, conflicts_with = "path")]
+        check_all: bool,
+    },
+
+    /// Find documents with duplicate content in each corpus.
+    Duplicates {
+        /// Keep the first document of each identical-content group, record
+        /// the others' paths as its aliases, and delete them.
+        #[arg(long)]
+        merge: bool,
+
+        /// Also report documents at least this similar (0-1, by word
+        /// shingles). Requires the `similarity` feature.
+        #[cfg(feature = "similarity")]
+        #[arg(long, value_name = "THRESHOLD")]
+        similarity: Option<f64>,
+    },
+
+    /// Delete a document and its manifest entry.
+    #[command(alias = "delete")]
+    Remove {
+        /// Document path (e.g., "aws/lambda-patterns.md").
+        path: String,
+    },
 
-        /// Comma-separated tags for additional classification.
-        #[arg(short = 'T', long)]
-        tags: Option<String>,
+    /// Replace a document's content, read from stdin or a file.
+    Update {
+        /// Document path (e.g., "aws/lambda-patterns.md").
+        path: String,
 
         /// Read content from file instead of stdin.
         #[arg(short, long)]
         file: Option<String>,
     },
 
-    /// Get the full contents of a document by its path.
-    Get {
+    /// Add or remove tags on an existing document.
+    Tag {
         /// Document path (e.g., "aws/lambda-patterns.md").
         path: String,
+
+        /// Comma-separated tags to add.
+        #[arg(long, value_delimiter = ',')]
+        add: Vec<String>,
+
+        /// Comma-separated tags to remove.
+        #[arg(long, value_delimiter = ',')]
+        remove: Vec<String>,
+    },
+
+    /// Show how often each pair of tags appears on the same document, most
+    /// frequent first.
+    TagGraph {
+        /// Output format.
+        #[arg(long, default_value = "text")]
+        format: GraphFormat,
+    },
+
+    /// Check that each document's file exists, can be read, and is under its
+    /// category's directory.
+    Verify {
+        /// Resolve mismatches by moving files into their category's
+        /// directory, or by setting categories to the files' directories.
+        #[arg(long, value_name = "HOW")]
+        fix: Option<CategoryFix>,
+
+        /// Make documents whose files can't be read readable by their owner
+        /// (Unix only).
+        #[arg(long)]
+        fix_permissions: bool,
+
+        /// Report for CI without changing anything: print each problem,
+        /// including manifests that fail to load, as a JSON object per line
+        /// (`kind`, `path`, `message`) and exit nonzero if there are any.
+        #[arg(long, conflicts_with_all = ["fix", "fix_permissions"])]
+        ci: bool,
+    },
+
+    /// Add manifest entries for markdown files added by hand, and drop
+    /// entries whose files are gone.
+    Sync,
+
+    /// Write a markdown index linking every document, grouped by category.
+    GenIndex {
+        /// File to write (e.g., "index.md"); links are relative to its
+        /// directory.
+        output: PathBuf,
+    },
+
+    /// Lock a corpus so commands refuse to change its documents or manifest.
+    Lock {
+        /// Corpus root (default: the first configured corpus).
+        corpus: Option<String>,
+    },
+
+    /// Unlock a corpus locked with `kvault lock`.
+    Unlock {
+        /// Corpus root (default: the first configured corpus).
+        corpus: Option<String>,
     },
 
     /// Build or rebuild the search index for all corpora.
     /// Requires the `ranked` feature.
     #[cfg(feature = "ranked")]
-    Index,
+    Index {
+        /// Merge existing index segments instead of rebuilding.
+        #[arg(long, conflicts_with_all = ["resume", "full", "force"])]
+        compact: bool,
+
+        /// Continue an interrupted indexing run, skipping documents it
+        /// already indexed.
+        #[arg(long, conflicts_with = "full")]
+        resume: bool,
+
+        /// Rebuild every index from scratch instead of re-indexing only
+        /// changed documents.
+        #[arg(long)]
+        full: bool,
+
+        /// Check every document even in corpora whose manifest and files are
+        /// unchanged since their last complete index build, which are
+        /// otherwise skipped.
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Show how many indexed documents contain a term, how often it occurs,
+    /// and its BM25 inverse document frequency.
+    /// Requires the `ranked` feature.
+    #[cfg(feature = "ranked")]
+    TermStats {
+        /// The term to look up (a single word).
+        term: String,
+    },
+
+    /// Run a query through both ripgrep and the ranked index and show each
+    /// backend's results, then the documents only one of them found.
+    /// Requires the `ranked` feature and an index.
+    #[cfg(feature = "ranked")]
+    Compare {
+        /// The search query string.
+        query: String,
+
+        /// Maximum number of results from each backend (default 10, or
+        /// `[search] default_limit`).
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Filter results to this category only (default:
+        /// `[search] default_category`).
+        #[arg(short, long)]
+        category: Option<String>,
+
+        /// Search every category, even if `[search] default_category` is
+        /// set.
+        #[arg(long, conflicts_with = "category")]
+        all_categories: bool,
+    },
 
     /// Start the MCP server for AI editor integration.
     #[cfg(feature = "mcp")]
-    Serve,
+    Serve {
+        /// Only expose documents in these categories (repeatable or
+        /// comma-separated; default: all).
+        #[arg(long, value_delimiter = ',', value_name = "CATEGORY")]
+        allowed_categories: Vec<String>,
+    },
+}
+
+/// Arguments for the `add` command.
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct AddArgs {
+    /// Human-readable document title (default: `title` from the content's
+    /// YAML frontmatter; required when content is typed at a terminal).
+    #[arg(short, long, required_if_eq("manifest_only", "true"))]
+    pub title: Option<String>,
+
+    /// Category for grouping (e.g., "aws", "rust"; default: `category`
+    /// from the content's YAML frontmatter; required when content is typed
+    /// at a terminal).
+    #[arg(short = 'C', long, required_if_eq("manifest_only", "true"))]
+    pub category: Option<String>,
+
+    /// Comma-separated tags for additional classification (default: `tags`
+    /// from the content's YAML frontmatter).
+    #[arg(short = 'T', long)]
+    pub tags: Option<String>,
+
+    /// Read content from file instead of stdin.
+    #[arg(short, long)]
+    pub file: Option<String>,
+
+    /// Seed content from a configured template when stdin is empty.
+    #[arg(long, conflicts_with = "file")]
+    pub template: Option<String>,
+
+    /// Read the whole document as JSON from stdin:
+    /// `{"title", "category", "tags", "content"}`.
+    #[arg(long, conflicts_with_all = ["title", "category", "tags", "file", "template"])]
+    pub stdin_json: bool,
+
+    /// Read several documents from stdin, one JSON object per line as for
+    /// `--stdin-json`, and add all of them or, if any fails, none.
+    #[arg(long, conflicts_with_all = ["title", "category", "tags", "file", "template", "stdin_json"])]
+    pub stdin_jsonl: bool,
+
+    /// Fail instead of warning if the category already has a document
+    /// with the same title.
+    #[arg(long)]
+    pub no_duplicate_titles: bool,
+
+    /// Record a file already where `add` would write it (by default
+    /// `<category>/<slug>.md`) in the manifest, without writing any content.
+    #[arg(long, conflicts_with_all = ["file", "template", "stdin_json", "stdin_jsonl"])]
+    pub manifest_only: bool,
+
+    /// Configured corpus to add to, by path or directory name (required
+    /// when several corpora are configured).
+    #[arg(long, value_name = "PATH_OR_NAME")]
+    pub corpus: Option<String>,
+}
+
+/// Arguments for the `search` command.
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct SearchArgs {
+    /// The search query string (with `--interactive`, the starting query).
+    #[cfg_attr(
+        feature = "tui",
+        arg(required_unless_present_any = ["queries_from_stdin", "interactive"])
+    )]
+    #[cfg_attr(
+        not(feature = "tui"),
+        arg(required_unless_present = "queries_from_stdin")
+    )]
+    pub query: Option<String>,
+
+    /// Read queries from stdin, one per line, and print the results of each
+    /// under a header naming the query (or, with `--format json`, as an
+    /// array of `{query, results}`).
+    #[arg(long, conflicts_with_all = ["query", "export_to"])]
+    pub queries_from_stdin: bool,
+
+    /// Search as you type in a full-screen view and print the document
+    /// picked with Enter. Only available with the `tui` feature.
+    #[cfg(feature = "tui")]
+    #[arg(
+        long,
+        conflicts_with_all = ["queries_from_stdin", "export_to", "format", "json", "fail_on_empty"]
+    )]
+    pub interactive: bool,
+
+    /// Maximum number of results to return (default 10, or
+    /// `[search] default_limit`).
+    #[arg(short, long)]
+    pub limit: Option<usize>,
+
+    /// Return at most this many matches from any one document (ripgrep).
+    #[arg(long, value_name = "K")]
+    pub max_per_file: Option<usize>,
+
+    /// Show each document once, on its first match, with its number of
+    /// matches (ripgrep).
+    #[arg(long, conflicts_with = "max_per_file")]
+    pub group: bool,
+
+    /// Score ripgrep matches by matching lines and query words in the
+    /// title, best-scoring documents first, instead of ordering them by
+    /// `--order`. Reads every match rather than stopping at `--limit`.
+    #[arg(long)]
+    pub rank: bool,
+
+    /// Filter results to this category only (default:
+    /// `[search] default_category`).
+    #[arg(short, long)]
+    pub category: Option<String>,
+
+    /// Search every category, even if `[search] default_category` is set.
+    #[arg(long, conflicts_with = "category")]
+    pub all_categories: bool,
+
+    /// Also match categories nested under `--category` (e.g., `aws`
+    /// matches `aws/lambda`).
+    #[arg(long, requires = "category")]
+    pub category_prefix: bool,
+
+    /// Only return documents with this tag (repeatable; all must match).
+    #[arg(short, long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Use case-sensitive matching (default is case-insensitive).
+    #[arg(short = 's', long)]
+    pub case_sensitive: bool,
+
+    /// Search backend to use.
+    #[arg(short, long, default_value = "ripgrep")]
+    pub backend: Backend,
+
+    /// How to match the query: literal substring, whole word, or regex.
+    #[arg(short, long, default_value = "substring")]
+    pub match_mode: MatchMode,
+
+    /// Enable fuzzy search with specified edit distance (1-2).
+    /// Only available with the `ranked` backend.
+    #[arg(short, long)]
+    pub fuzzy: Option<u8>,
+
+    /// Suggest a corrected query when nothing matches, from the terms of
+    /// indexed corpora. Needs `--backend ranked` or `auto`.
+    #[cfg(feature = "ranked")]
+    #[arg(long)]
+    pub suggest: bool,
+
+    /// Highlight matched text (default: ansi on a terminal unless `NO_COLOR`
+    /// is set, otherwise none).
+    #[arg(long)]
+    pub highlight: Option<HighlightMode>,
+
+    /// Don't color matched text, even on a terminal.
+    #[arg(long, conflicts_with = "highlight")]
+    pub no_color: bool,
+
+    /// Output format.
+    #[arg(long, default_value = "text")]
+    pub format: OutputFormat,
+
+    /// Shorthand for `--format json`.
+    #[arg(long, conflicts_with = "format")]
+    pub json: bool,
+
+    /// Exit with status 3 if there are no matches.
+    #[arg(long)]
+    pub fail_on_empty: bool,
+
+    /// Show how each result was scored (diagnostic).
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Show a document found in several corpora (same path and title)
+    /// once, from the first configured corpus.
+    #[arg(long, alias = "dedupe-across-corpora")]
+    pub dedupe: bool,
+
+    /// Title results with their file names instead of manifest titles, to
+    /// spot stale manifest entries.
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Only search the files listed in FILE, one path per line, such as the
+    /// output of an earlier `search --format paths`. Relative paths resolve
+    /// against the current directory. Requires ripgrep.
+    #[arg(long, value_name = "FILE")]
+    pub within: Option<PathBuf>,
+
+    /// Also match the query against document titles and tags, listing
+    /// documents that match only there without a line number. Ripgrep only;
+    /// the ranked index already searches titles.
+    #[arg(long)]
+    pub include_metadata: bool,
+
+    /// Copy the matched documents into a new corpus in this directory,
+    /// which must be empty or not yet exist.
+    #[arg(long, value_name = "DIR")]
+    pub export_to: Option<PathBuf>,
+
+    /// Search even if the query is shorter than `[search] min_query_length`.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Leave out each result's `title: path (line N)` header.
+    #[arg(long)]
+    pub no_header: bool,
+
+    /// Leave out the "N result(s) found" footer.
+    #[arg(long)]
+    pub no_footer: bool,
+
+    /// Print this line between results (e.g., `---`).
+    #[arg(long, value_name = "STR", allow_hyphen_values = true)]
+    pub separator: Option<String>,
+
+    /// How unranked results from several corpora are ordered.
+    #[arg(long, default_value = "config-order")]
+    pub order: CrossOrder,
 }
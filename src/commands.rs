@@ -1,17 +1,32 @@
 //! Command implementations shared by CLI and MCP server.
 
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
 use std::path::{Path, PathBuf};
 
-use crate::cli::Backend;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{Backend, DEFAULT_SEARCH_LIMIT};
 use crate::config::{Config, expand_tilde};
-use crate::corpus::{Corpus, Document};
+use crate::corpus::category::{CategoryResolver, ConfigRulesResolver};
+use crate::corpus::links::{self, Link};
+use crate::corpus::{
+    CategoryFix, Corpus, Document, DocumentLayout, LOCK_FILE, Manifest, ManifestFormat,
+    ValidationIssue, humanize_title, infer_content_type, is_ignored_corpus_path, is_locked,
+    label_key, labels_match,
+};
+use crate::query::Expr;
 use crate::search::ripgrep::RipgrepBackend;
-use crate::search::{SearchBackend, SearchOptions, SearchResult};
+use crate::search::{CategoryMatch, CrossOrder, SearchBackend, SearchOptions, SearchResult};
 use crate::storage::StorageBackend;
 use crate::storage::local::LocalStorageBackend;
 
 #[cfg(feature = "ranked")]
-use crate::search::tantivy::{IndexMode, TantivyBackend};
+use crate::search::overlay;
+#[cfg(feature = "ranked")]
+use crate::search::tantivy::{IndexMode, Rebuild, TantivyBackend, TermStats};
+#[cfg(feature = "s3")]
+use crate::storage::s3::S3StorageBackend;
 
 /// Maximum length for user-provided strings (title, category, etc.).
 const MAX_INPUT_LENGTH: usize = 200;
@@ -130,44 +145,190 @@ pub fn parse_tags(tags: Option<String>) -> Vec<String> {
     .unwrap_or_default()
 }
 
+/// Document metadata from a YAML frontmatter block.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct Frontmatter {
+    /// `title:`, if set.
+    pub title: Option<String>,
+    /// `category:`, if set.
+    pub category: Option<String>,
+    /// `tags:`, as a list or a comma-separated string.
+    #[serde(default, deserialize_with = "deserialize_frontmatter_tags")]
+    pub tags: Vec<String>,
+}
+
+fn deserialize_frontmatter_tags<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Tags {
+        List(Vec<String>),
+        Text(String),
+    }
+
+    Ok(match Option::<Tags>::deserialize(deserializer)? {
+        Some(Tags::List(tags)) => parse_tags(Some(tags.join(","))),
+        Some(Tags::Text(tags)) => parse_tags(Some(tags)),
+        None => Vec::new(),
+    })
+}
+
+/// Split a leading `---` YAML frontmatter block off document content.
+///
+/// The block runs to the next line that is `---` or `...`. Fields other
+/// than `title`, `category`, and `tags` are ignored. Content without a
+/// complete block, or whose block doesn't open with a `key:` line (such as a
+/// body that starts with a `---` rule), is returned whole, with empty
+/// frontmatter.
+///
+/// # Returns
+///
+/// The frontmatter and the content after it, without leading blank lines.
+///
+/// # Errors
+///
+/// Returns an error if the block isn't valid YAML.
+pub fn split_frontmatter(content: &str) -> anyhow::Result<(Frontmatter, &str)> {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return Ok((Frontmatter::default(), content));
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            let yaml = &rest[..offset];
+            let frontmatter = if yaml.trim().is_empty() {
+                Frontmatter::default()
+            } else if starts_like_mapping(yaml) {
+                serde_yaml_ng::from_str(yaml)
+                    .map_err(|e| anyhow::anyhow!("Invalid frontmatter: {e}"))?
+            } else {
+                return Ok((Frontmatter::default(), content));
+            };
+            let body = rest[offset + line.len()..].trim_start_matches(['\n', '\r']);
+            return Ok((frontmatter, body));
+        }
+        offset += line.len();
+    }
+
+    Ok((Frontmatter::default(), content))
+}
+
+/// Whether the first line of `yaml` that isn't blank or a comment opens
+/// with a `key:`, as a frontmatter block would, rather than prose between
+/// two `---` rules.
+fn starts_like_mapping(yaml: &str) -> bool {
+    yaml.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .and_then(|line| line.split_once(':'))
+        .is_some_and(|(key, _)| {
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        })
+}
+
+/// Load the config for a command that works on the configured corpus paths.
+///
+/// # Errors
+///
+/// Returns an error if [`Config::load`] fails, or if `[corpus.s3]` is set:
+/// commands only reach local corpora, and silently ignoring the bucket would
+/// leave its documents unsearched and new ones written to disk instead.
+fn load_config() -> anyhow::Result<Config> {
+    let config = Config::load()?;
+    if let Some(s3) = &config.corpus.s3 {
+        anyhow::bail!(
+            "[corpus.s3] is set (bucket {}), but commands only work on local corpus \
+             paths; remove the section, or reach the bucket through `s3_storage` \
+             from the kvault library",
+            s3.bucket
+        );
+    }
+    Ok(config)
+}
+
 /// Search across all configured corpora.
 ///
 /// # Arguments
 ///
 /// * `query` - The search query string
-/// * `limit` - Maximum number of results to return
-/// * `category` - Optional category filter
-/// * `case_sensitive` - Use case-sensitive matching (default is case-insensitive)
 /// * `backend` - Search backend to use (ripgrep, ranked, or auto)
-/// * `fuzzy` - Optional fuzzy search edit distance (only for ranked backend)
+/// * `options` - Limit, category filter, case sensitivity, fuzzy distance,
+///   match mode, and whether to explain scores
 ///
 /// # Returns
 ///
-/// A vector of search results from all configured corpora, sorted by relevance.
+/// Search results from all configured corpora, sorted by relevance (or by
+/// `options.cross_corpus_order` when unscored), plus warnings for any corpora
+/// that failed to load or search.
 ///
 /// # Errors
 ///
-/// Returns an error if config loading fails or all search operations fail.
-/// Individual corpus failures are logged but don't fail the entire search.
+/// Returns an error if config loading fails, the query is shorter than
+/// `[search] min_query_length` (unless `options.allow_short_query` is set),
+/// or all search operations fail. Individual corpus failures are reported as
+/// warnings but don't fail the entire search.
 pub fn search(
     query: &str,
-    limit: usize,
-    category: Option<String>,
-    case_sensitive: bool,
     backend: Backend,
-    fuzzy: Option<u8>,
-) -> anyhow::Result<Vec<SearchResult>> {
-    let config = Config::load()?;
+    options: &SearchOptions,
+) -> anyhow::Result<SearchOutcome> {
+    let mut batch = search_many(&[query], backend, options)?;
+    let mut outcome = batch.outcomes.remove(0)?;
+    batch.warnings.append(&mut outcome.warnings);
+    outcome.warnings = batch.warnings;
+    Ok(outcome)
+}
 
-    let options = SearchOptions {
-        limit: Some(limit),
-        category,
-        case_sensitive,
-        fuzzy,
-    };
+/// Run several searches across all configured corpora, loading the config,
+/// corpora, and backends once for all of them.
+///
+/// Each query is searched as by [`search`], except that corpora which failed
+/// to load are reported once for the batch rather than with every query.
+///
+/// # Returns
+///
+/// The outcome of each query, in order, or why it failed (e.g., it is too
+/// short, or every corpus failed); one query failing doesn't stop the rest.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails.
+pub fn search_many<Q: AsRef<str>>(
+    queries: &[Q],
+    backend: Backend,
+    options: &SearchOptions,
+) -> anyhow::Result<SearchBatch> {
+    let config = load_config()?;
+    let queries: Vec<String> = queries
+        .iter()
+        .map(|query| prepare_query(&config, query.as_ref()))
+        .collect();
 
-    let mut all_results = Vec::new();
-    let mut errors = Vec::new();
+    let rg = config
+        .search
+        .ripgrep_path
+        .as_deref()
+        .map_or_else(RipgrepBackend::new, |path| {
+            RipgrepBackend::with_binary(expand_tilde(path))
+        })
+        .with_excludes(config.search.exclude_globs.iter().cloned())
+        .with_category_resolver(ConfigRulesResolver::new(
+            config.corpus.category_rules.clone(),
+        ));
+    let options = &with_search_defaults(&config, options);
+
+    // Each corpus with its backend, or why no backend could be opened
+    let mut searchers = Vec::new();
+    let mut load_errors = Vec::new();
 
     for path_str in &config.corpus.paths {
         let path = expand_tilde(path_str);
@@ -178,82 +339,530 @@ pub fn search(
 
         match Corpus::load(&path) {
             Ok(corpus) => {
-                let results = search_corpus(query, &corpus, &options, backend);
+                let searcher = backend_for(
+                    &corpus,
+                    backend,
+                    &rg,
+                    options,
+                    config.search.index_hint_documents(),
+                );
+                searchers.push((corpus, searcher));
+            }
+            Err(e) => load_errors.push(format!("Load {}: {e}", path.display())),
+        }
+    }
+
+    let outcomes = queries
+        .iter()
+        .map(|query| {
+            if !options.allow_short_query {
+                check_query_length(query, config.search.min_query_length())?;
+            }
+
+            let mut per_corpus = Vec::new();
+            let mut errors = Vec::new();
+
+            for (corpus, searcher) in &searchers {
+                let results = match searcher {
+                    Ok(searcher) => search_corpus(query, corpus, options, searcher.as_ref()),
+                    Err(e) => Err(anyhow::anyhow!("{e}")),
+                };
                 match results {
-                    Ok(results) => all_results.extend(results),
-                    Err(e) => errors.push(format!("Search in {}: {e}", path.display())),
+                    Ok(results) => per_corpus.push((corpus.root.clone(), results)),
+                    Err(e) => errors.push(format!("Search in {}: {e}", corpus.root.display())),
                 }
             }
-            Err(e) => errors.push(format!("Load {}: {e}", path.display())),
-        }
+
+            if options.dedupe {
+                dedupe_results(&mut per_corpus);
+            }
+            let mut all_results = merge_results(per_corpus, options.cross_corpus_order);
+
+            // If we got no results and had errors, report them
+            let reasons: Vec<&str> = load_errors
+                .iter()
+                .chain(&errors)
+                .map(String::as_str)
+                .collect();
+            if all_results.is_empty() && !reasons.is_empty() {
+                anyhow::bail!("Search failed:\n  {}", reasons.join("\n  "));
+            }
+
+            if let Some(limit) = options.limit {
+                all_results.truncate(limit);
+            }
+            Ok(SearchOutcome {
+                results: all_results,
+                warnings: errors,
+            })
+        })
+        .collect();
+
+    Ok(SearchBatch {
+        outcomes,
+        warnings: load_errors,
+    })
+}
+
+/// The number of results a search returns when no limit is given:
+/// `[search] default_limit`, or [`DEFAULT_SEARCH_LIMIT`].
+///
+/// # Errors
+///
+/// Returns an error if config loading fails.
+pub fn default_search_limit() -> anyhow::Result<usize> {
+    Ok(load_config()?
+        .search
+        .default_limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT))
+}
+
+/// `options` with the `[search]` settings from `config` applied: the
+/// snippet strategy, `default_limit` (or [`DEFAULT_SEARCH_LIMIT`]) when no
+/// limit is set, and `default_category` when no category is set, unless
+/// `options.all_categories` is.
+fn with_search_defaults(config: &Config, options: &SearchOptions) -> SearchOptions {
+    let default_category = (!options.all_categories)
+        .then(|| config.search.default_category.clone())
+        .flatten();
+    SearchOptions {
+        limit: options
+            .limit
+            .or(config.search.default_limit)
+            .or(Some(DEFAULT_SEARCH_LIMIT)),
+        category: options.category.clone().or(default_category),
+        snippet_strategy: config.search.snippet_strategy,
+        ..options.clone()
+    }
+}
+
+/// Reject a query shorter than `min_length` characters once trimmed.
+///
+/// Empty queries pass: backends already return nothing for them.
+fn check_query_length(query: &str, min_length: usize) -> anyhow::Result<()> {
+    let length = query.trim().chars().count();
+    if length > 0 && length < min_length {
+        anyhow::bail!(
+            "Query '{}' is shorter than {min_length} characters and would match \
+             too much; use --force to search anyway, or lower \
+             [search] min_query_length",
+            query.trim()
+        );
     }
+    Ok(())
+}
 
-    // If we got no results and had errors, report them
-    if all_results.is_empty() && !errors.is_empty() {
-        anyhow::bail!("Search failed:\n  {}", errors.join("\n  "));
+/// The query as backends should see it: normalized with [`normalize_query`]
+/// if `[search] normalize_query` is set, otherwise unchanged.
+fn prepare_query(config: &Config, query: &str) -> String {
+    if config.search.normalize_query {
+        normalize_query(query)
+    } else {
+        query.to_string()
     }
+}
+
+/// Trim a query, collapse runs of whitespace to single spaces, and replace
+/// curly quotes with their ASCII equivalents.
+///
+/// A query of only whitespace becomes empty, so backends return no results
+/// for it as for any empty query.
+fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(['\u{2018}', '\u{2019}', '\u{201A}', '\u{201B}'], "'")
+        .replace(['\u{201C}', '\u{201D}', '\u{201E}', '\u{201F}'], "\"")
+}
 
-    // Sort by score if available (ranked backend), otherwise keep order
-    all_results.sort_by(|a, b| match (b.score, a.score) {
+/// Order results by descending score (ranked backend), breaking ties by
+/// title, path, and then line number so equal scores sort deterministically.
+///
+/// Results without scores compare equal on score, so the tie-breakers apply
+/// and matches within one document are in line order.
+fn compare_results(a: &SearchResult, b: &SearchResult) -> std::cmp::Ordering {
+    let by_score = match (b.score, a.score) {
         (Some(b_score), Some(a_score)) => b_score
             .partial_cmp(&a_score)
             .unwrap_or(std::cmp::Ordering::Equal),
         _ => std::cmp::Ordering::Equal,
-    });
+    };
+
+    by_score
+        .then_with(|| a.title.cmp(&b.title))
+        .then_with(|| a.path.cmp(&b.path))
+        .then_with(|| a.line_number().cmp(&b.line_number()))
+}
 
-    all_results.truncate(limit);
-    Ok(all_results)
+/// Drop results for documents already found in an earlier corpus.
+///
+/// Documents are identified by their path relative to the corpus root and
+/// their title, so the first configured corpus's copy wins.
+fn dedupe_results(per_corpus: &mut [(PathBuf, Vec<SearchResult>)]) {
+    let mut seen: HashSet<(PathBuf, String)> = HashSet::new();
+
+    for (root, results) in per_corpus {
+        let key = |result: &SearchResult| {
+            let relative = result.path.strip_prefix(&*root).unwrap_or(&result.path);
+            (relative.to_path_buf(), result.title.clone())
+        };
+
+        results.retain(|result| !seen.contains(&key(result)));
+        seen.extend(results.iter().map(key));
+    }
+}
+
+/// Make scores from different corpora comparable before they are merged.
+///
+/// BM25 scores depend on each index's own term statistics (a term rare in
+/// one corpus has a higher IDF there), so raw scores from different indexes
+/// don't share a scale. When more than one corpus has scored results, each
+/// corpus's scores are divided by its top score: every corpus's best match
+/// scores 1.0 and the rest keep their spacing relative to it. Explanations
+/// gain a line showing the division. Results from a single corpus keep
+/// their raw scores.
+///
+/// Scores are relative to each corpus's own results, not to any absolute
+/// measure of relevance: a corpus whose only match is weak still scores it
+/// 1.0, level with the best match elsewhere.
+fn normalize_scores(per_corpus: &mut [(PathBuf, Vec<SearchResult>)]) {
+    let scored_corpora = per_corpus
+        .iter()
+        .filter(|(_, results)| results.iter().any(|result| result.score.is_some()))
+        .count();
+    if scored_corpora < 2 {
+        return;
+    }
+
+    for (_, results) in per_corpus {
+        let top = results
+            .iter()
+            .filter_map(|result| result.score)
+            .fold(0.0_f32, f32::max);
+        if top <= 0.0 || !top.is_finite() {
+            continue;
+        }
+
+        for result in results.iter_mut() {
+            let Some(raw) = result.score else {
+                continue;
+            };
+            let normalized = raw / top;
+            result.score = Some(normalized);
+            if let Some(explanation) = &mut result.explanation {
+                let tree: Vec<String> = explanation
+                    .lines()
+                    .map(|line| format!("  {line}"))
+                    .collect();
+                *explanation = format!(
+                    "{normalized:.4} normalized: divided by the corpus's top score {top:.4}\n{}",
+                    tree.join("\n")
+                );
+            }
+        }
+    }
+}
+
+/// Combine per-corpus results (in config order) into one list.
+///
+/// If any result is scored, scores are normalized across corpora (see
+/// [`normalize_scores`]) and everything is ordered by [`compare_results`];
+/// otherwise `order` decides how corpora are arranged.
+fn merge_results(
+    mut per_corpus: Vec<(PathBuf, Vec<SearchResult>)>,
+    order: CrossOrder,
+) -> Vec<SearchResult> {
+    let scored = per_corpus
+        .iter()
+        .flat_map(|(_, results)| results)
+        .any(|result| result.score.is_some());
+
+    if order == CrossOrder::Corpus {
+        per_corpus.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+    if scored {
+        normalize_scores(&mut per_corpus);
+    }
+
+    if scored || order == CrossOrder::Title {
+        let mut all_results: Vec<SearchResult> = per_corpus
+            .into_iter()
+            .flat_map(|(_, results)| results)
+            .collect();
+        all_results.sort_by(compare_results);
+        return all_results;
+    }
+
+    per_corpus
+        .into_iter()
+        .flat_map(|(_, mut results)| {
+            results.sort_by(compare_results);
+            results
+        })
+        .collect()
 }
 
-/// Search a single corpus using the specified backend.
+/// Search a single corpus with `backend`, after rejecting options the
+/// backend doesn't support.
 fn search_corpus(
     query: &str,
     corpus: &Corpus,
     options: &SearchOptions,
-    backend: Backend,
+    backend: &dyn SearchBackend,
 ) -> anyhow::Result<Vec<SearchResult>> {
-    match backend {
-        Backend::Ripgrep => {
-            let rg = RipgrepBackend::new();
-            rg.search(query, corpus, options)
-        }
-        #[cfg(feature = "ranked")]
-        Backend::Ranked => {
-            if !TantivyBackend::index_exists(corpus) {
-                anyhow::bail!(
-                    "No index found for corpus at {}. Run `kvault index` first.",
-                    corpus.root.display()
-                );
+    backend.capabilities().check(backend.name(), options)?;
+    backend.search(query, corpus, options)
+}
+
+/// Choose the backend that searches a corpus.
+///
+/// `ranked` searches the corpus's Tantivy index, merged with documents
+/// changed in this process since the index was built (see [`overlay`]).
+/// `auto` uses the index when one exists, unless `options.within` is set,
+/// which only ripgrep supports; without one, corpora of at least
+/// `index_hint_documents` documents get a note on stderr suggesting
+/// `kvault index`. When ripgrep is requested but unavailable,
+/// falls back to the index (if compiled in and built for this corpus) with a
+/// warning on stderr.
+///
+/// # Errors
+///
+/// Returns an error if `ranked` is requested for a corpus without an index,
+/// or the index cannot be opened.
+#[cfg_attr(
+    not(feature = "ranked"),
+    allow(unused_variables, clippy::unnecessary_wraps)
+)]
+fn backend_for(
+    corpus: &Corpus,
+    backend: Backend,
+    rg: &RipgrepBackend,
+    options: &SearchOptions,
+    index_hint_documents: usize,
+) -> anyhow::Result<Box<dyn SearchBackend>> {
+    #[cfg(feature = "ranked")]
+    {
+        let indexed = TantivyBackend::index_exists(corpus);
+        let use_index = match backend {
+            Backend::Ranked if !indexed => anyhow::bail!(
+                "No index found for corpus at {}. Run `kvault index` first.",
+                corpus.root.display()
+            ),
+            Backend::Ranked => true,
+            Backend::Auto => {
+                let documents = corpus.documents().len();
+                if !indexed && documents >= index_hint_documents {
+                    eprintln!(
+                        "Note: {} has {documents} documents and no index; \
+                         run `kvault index` for faster ranked search",
+                        corpus.root.display()
+                    );
+                }
+                indexed && options.within.is_none()
             }
-            let tantivy = TantivyBackend::open_for_corpus(corpus, IndexMode::ReadOnly)?;
-            tantivy.search(query, corpus, options)
-        }
-        Backend::Auto => {
-            // Auto-select: use Tantivy if index exists, otherwise ripgrep
-            #[cfg(feature = "ranked")]
-            if TantivyBackend::index_exists(corpus) {
-                let tantivy = TantivyBackend::open_for_corpus(corpus, IndexMode::ReadOnly)?;
-                return tantivy.search(query, corpus, options);
+            Backend::Ripgrep => {
+                let fallback = indexed && rg.check().is_err();
+                if fallback {
+                    eprintln!(
+                        "Warning: ripgrep unavailable, using ranked index for {}",
+                        corpus.root.display()
+                    );
+                }
+                fallback
             }
+        };
+        if use_index {
+            let index = TantivyBackend::open_for_corpus(corpus, IndexMode::ReadOnly)?;
+            return Ok(Box::new(overlay::OverlayBackend::new(index)));
+        }
+    }
+    Ok(Box::new(rg.clone()))
+}
 
-            let rg = RipgrepBackend::new();
-            rg.search(query, corpus, options)
+/// Suggest a corrected query for a search that returned no results.
+///
+/// Only corpora searched with the Tantivy backend (explicitly, or via `auto`
+/// when an index exists) can produce suggestions, since they are drawn from
+/// indexed terms. Of `options`, only the category (or the configured default
+/// category, as for [`search`]) is used.
+///
+/// # Returns
+///
+/// The first suggestion found across configured corpora, or `None`.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails or a suggestion lookup fails.
+#[cfg(feature = "ranked")]
+pub fn suggest(
+    query: &str,
+    backend: Backend,
+    options: &SearchOptions,
+) -> anyhow::Result<Option<String>> {
+    if matches!(backend, Backend::Ripgrep) {
+        return Ok(None);
+    }
+
+    let config = load_config()?;
+    let query = &prepare_query(&config, query);
+    let options = SearchOptions {
+        category: with_search_defaults(&config, options).category,
+        ..Default::default()
+    };
+
+    for path_str in &config.corpus.paths {
+        let path = expand_tilde(path_str);
+
+        if !path.exists() {
+            continue;
+        }
+
+        let Ok(corpus) = Corpus::load(&path) else {
+            continue;
+        };
+
+        if !TantivyBackend::index_exists(&corpus) {
+            continue;
+        }
+
+        let tantivy = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadOnly)?;
+        if let Some(suggestion) = tantivy.suggest(query, &corpus, &options)? {
+            return Ok(Some(suggestion));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Copy the documents behind search results into a new corpus at `dest`.
+///
+/// Each document is copied once, however many results it has, keeping its
+/// path and manifest entry from the configured corpus it came from. Copies
+/// are written unencrypted.
+///
+/// # Returns
+///
+/// The new corpus, loaded from `dest`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `dest` exists and isn't an empty directory
+/// - A result isn't a document in a configured corpus
+/// - Documents from different corpora share a path
+/// - A document or the manifest cannot be read or written
+pub fn export_results_to_corpus(results: &[SearchResult], dest: &Path) -> anyhow::Result<Corpus> {
+    if dest.exists() && std::fs::read_dir(dest)?.next().is_some() {
+        anyhow::bail!("Export destination is not empty: {}", dest.display());
+    }
+
+    let corpora = configured_corpora(&load_config()?);
+    let dest_storage = LocalStorageBackend::new(dest.to_path_buf());
+    let mut manifest = Manifest::empty();
+    let mut exported = HashSet::new();
+
+    for result in results {
+        let (corpus, doc) = result_document(&corpora, result)?;
+        if !exported.insert(corpus.resolve_document_path(doc)) {
+            continue;
+        }
+        if manifest.find_document_index(&doc.path).is_some() {
+            anyhow::bail!(
+                "Documents from several corpora have the path {}",
+                doc.path.display()
+            );
         }
+
+        let content = read_corpus_document(corpus, doc)?;
+        dest_storage.write_document(&doc.path, &content)?;
+        manifest.documents.push(Document {
+            encrypted: false,
+            ..doc.clone()
+        });
     }
+
+    dest_storage.write_manifest(&manifest)?;
+    Ok(Corpus::load(dest)?)
+}
+
+/// Read the full content of the document behind a search result.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, the result isn't a document in
+/// a configured corpus, or the document cannot be read.
+pub fn read_result(result: &SearchResult) -> anyhow::Result<String> {
+    let corpora = configured_corpora(&load_config()?);
+    let (corpus, doc) = result_document(&corpora, result)?;
+    read_corpus_document(corpus, doc)
+}
+
+/// Load every configured corpus that exists, skipping ones that fail to load.
+fn configured_corpora(config: &Config) -> Vec<Corpus> {
+    config
+        .corpus
+        .paths
+        .iter()
+        .map(|path_str| expand_tilde(path_str))
+        .filter(|path| path.exists())
+        .filter_map(|path| Corpus::load(&path).ok())
+        .collect()
+}
+
+/// Find the corpus and manifest entry a search result's absolute path
+/// belongs to.
+fn result_document<'a>(
+    corpora: &'a [Corpus],
+    result: &SearchResult,
+) -> anyhow::Result<(&'a Corpus, &'a Document)> {
+    corpora
+        .iter()
+        .find_map(|corpus| {
+            let relative = result.path.strip_prefix(&corpus.root).ok()?;
+            corpus.find_document(relative).map(|doc| (corpus, doc))
+        })
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Not a document in a configured corpus: {}",
+                result.path.display()
+            )
+        })
+}
+
+/// Read a document through its corpus's storage, decrypting if needed.
+fn read_corpus_document(corpus: &Corpus, doc: &Document) -> anyhow::Result<String> {
+    validate_path_within_root(&corpus.root, &doc.path)?;
+    let storage = LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest)?;
+    Ok(storage.read_document(&doc.path)?)
 }
 
 /// Build or rebuild the search index for all configured corpora.
 ///
+/// # Arguments
+///
+/// * `rebuild` - Start each index over, or continue runs that were
+///   interrupted (see [`TantivyBackend::index_corpus_resumable`]); `None`
+///   re-indexes only changed documents (see
+///   [`TantivyBackend::index_incremental`]), skipping corpora whose index is
+///   up to date (see [`TantivyBackend::is_up_to_date`])
+/// * `force` - Re-index changed documents even in corpora whose index is up
+///   to date
+///
+/// Indexes built for another language than `[corpus.index] language` are
+/// replaced (see [`TantivyBackend::open_for_indexing`]).
+///
 /// # Returns
 ///
-/// The number of corpora successfully indexed.
+/// The number of corpora successfully indexed, or skipped as up to date.
 ///
 /// # Errors
 ///
 /// Returns an error if config loading fails or all index operations fail.
 #[cfg(feature = "ranked")]
-pub fn index_all() -> anyhow::Result<usize> {
-    let config = Config::load()?;
+pub fn index_all(rebuild: Option<Rebuild>, force: bool) -> anyhow::Result<usize> {
+    let config = load_config()?;
+    let language = config.corpus.index.language();
     let mut indexed_count = 0;
     let mut errors = Vec::new();
 
@@ -265,14 +874,44 @@ pub fn index_all() -> anyhow::Result<usize> {
         }
 
         match Corpus::load(&path) {
-            Ok(corpus) => match TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite) {
-                Ok(backend) => match backend.index(&corpus) {
-                    Ok(()) => {
-                        println!("Indexed: {}", path.display());
+            Ok(corpus) => match TantivyBackend::open_for_indexing(&corpus, language) {
+                Ok(backend) if rebuild.is_none() && !force && backend.is_up_to_date(&corpus) => {
+                    println!("Index up to date: {}", path.display());
+                    indexed_count += 1;
+                }
+                Ok(backend) if rebuild.is_none() => match backend.index_incremental(&corpus) {
+                    Ok(report) => {
+                        overlay::clear(&corpus.root);
+                        println!(
+                            "Indexed: {} ({} changed, {} removed, {} unchanged)",
+                            path.display(),
+                            report.indexed,
+                            report.removed,
+                            report.unchanged
+                        );
                         indexed_count += 1;
                     }
                     Err(e) => errors.push(format!("Index {}: {e}", path.display())),
                 },
+                Ok(backend) => {
+                    match backend.index_corpus_resumable(&corpus, rebuild.unwrap_or_default(), None)
+                    {
+                        Ok(progress) => {
+                            overlay::clear(&corpus.root);
+                            if progress.skipped > 0 {
+                                println!(
+                                    "Indexed: {} (resumed after {} document(s))",
+                                    path.display(),
+                                    progress.skipped
+                                );
+                            } else {
+                                println!("Indexed: {}", path.display());
+                            }
+                            indexed_count += 1;
+                        }
+                        Err(e) => errors.push(format!("Index {}: {e}", path.display())),
+                    }
+                }
                 Err(e) => errors.push(format!("Open index {}: {e}", path.display())),
             },
             Err(e) => errors.push(format!("Load {}: {e}", path.display())),
@@ -290,23 +929,22 @@ pub fn index_all() -> anyhow::Result<usize> {
     Ok(indexed_count)
 }
 
-/// List documents from all configured corpora.
-///
-/// # Arguments
+/// Compact the search index of every configured corpus.
 ///
-/// * `category` - Optional category filter
+/// Merges each index's segments into one. Corpora without an index are
+/// skipped with a warning.
 ///
 /// # Returns
 ///
-/// A vector of document info from all configured corpora.
+/// The number of corpora successfully compacted.
 ///
 /// # Errors
 ///
-/// Returns an error if config loading fails or all corpora fail to load.
-/// Individual corpus failures are logged but don't fail the entire list.
-pub fn list(category: Option<&str>) -> anyhow::Result<Vec<DocumentInfo>> {
-    let config = Config::load()?;
-    let mut documents = Vec::new();
+/// Returns an error if config loading fails or all compaction operations fail.
+#[cfg(feature = "ranked")]
+pub fn compact_all() -> anyhow::Result<usize> {
+    let config = load_config()?;
+    let mut compacted_count = 0;
     let mut errors = Vec::new();
 
     for path_str in &config.corpus.paths {
@@ -316,295 +954,3360 @@ pub fn list(category: Option<&str>) -> anyhow::Result<Vec<DocumentInfo>> {
             continue;
         }
 
-        match Corpus::load(&path) {
-            Ok(corpus) => {
-                for doc in corpus.documents() {
-                    if let Some(cat) = category
-                        && doc.category != cat
-                    {
-                        continue;
-                    }
+        let corpus = match Corpus::load(&path) {
+            Ok(corpus) => corpus,
+            Err(e) => {
+                errors.push(format!("Load {}: {e}", path.display()));
+                continue;
+            }
+        };
 
-                    documents.push(DocumentInfo {
-                        title: doc.title.clone(),
-                        category: doc.category.clone(),
-                        tags: doc.tags.clone(),
-                        path: corpus.resolve_document_path(doc),
-                    });
-                }
+        if !TantivyBackend::index_exists(&corpus) {
+            errors.push(format!("No index for {}", path.display()));
+            continue;
+        }
+
+        match TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite)
+            .and_then(|backend| backend.compact())
+        {
+            Ok(report) => {
+                println!(
+                    "Compacted: {} ({} -> {} segments)",
+                    path.display(),
+                    report.segments_before,
+                    report.segments_after
+                );
+                compacted_count += 1;
             }
-            Err(e) => errors.push(format!("Load {}: {e}", path.display())),
+            Err(e) => errors.push(format!("Compact {}: {e}", path.display())),
         }
     }
 
-    // If we got no documents and had errors, report them
-    if documents.is_empty() && !errors.is_empty() {
-        anyhow::bail!("List failed:\n  {}", errors.join("\n  "));
+    if compacted_count == 0 && !errors.is_empty() {
+        anyhow::bail!("Compaction failed:\n  {}", errors.join("\n  "));
+    }
+
+    if !errors.is_empty() {
+        eprintln!("Warnings:\n  {}", errors.join("\n  "));
     }
 
-    Ok(documents)
+    Ok(compacted_count)
 }
 
-/// Get the contents of a document by its path.
-///
-/// # Arguments
+/// Look up a term's statistics in the search index of every configured
+/// corpus, to see why it scores as it does (see [`TermStats::idf`]).
 ///
-/// * `doc_path` - Relative path to the document (e.g., "aws/lambda-patterns.md")
+/// Corpora without an index are skipped.
 ///
 /// # Returns
 ///
-/// The document content as a string.
+/// Each indexed corpus's display name (see [`Corpus::name`]) with its
+/// statistics for the term.
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - The document is not found in any corpus
-/// - The path is invalid or attempts path traversal
-/// - The document cannot be read
-pub fn get(doc_path: &str) -> anyhow::Result<String> {
-    let config = Config::load()?;
+/// Returns an error if config loading fails, the term is not a single word,
+/// no corpus has an index, or an index cannot be read.
+#[cfg(feature = "ranked")]
+pub fn term_stats(term: &str) -> anyhow::Result<Vec<(String, TermStats)>> {
+    let config = load_config()?;
+    let mut stats = Vec::new();
 
-    // Early validation of the requested path
-    let requested_path = PathBuf::from(doc_path);
-    if requested_path.to_string_lossy().contains("..") {
-        anyhow::bail!("Invalid document path: contains '..' component");
+    for path_str in &config.corpus.paths {
+        let path = expand_tilde(path_str);
+
+        if !path.exists() {
+            continue;
+        }
+
+        let Ok(corpus) = Corpus::load(&path) else {
+            continue;
+        };
+
+        if !TantivyBackend::index_exists(&corpus) {
+            continue;
+        }
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadOnly)?;
+        stats.push((corpus.name(), backend.term_stats(term)?));
+    }
+
+    if stats.is_empty() {
+        anyhow::bail!("No search index found (run `kvault index` first)");
+    }
+    Ok(stats)
+}
+
+/// One query's results from the ripgrep and ranked backends, for comparing
+/// them (see [`compare_backends`]).
+#[cfg(feature = "ranked")]
+#[derive(Debug, Clone, Default)]
+pub struct BackendComparison {
+    /// Results from ripgrep.
+    pub ripgrep: SearchOutcome,
+    /// Results from the ranked index.
+    pub ranked: SearchOutcome,
+}
+
+#[cfg(feature = "ranked")]
+impl BackendComparison {
+    /// Documents ripgrep found that the ranked index didn't, in path order.
+    #[must_use]
+    pub fn only_ripgrep(&self) -> Vec<&Path> {
+        documents_missing_from(&self.ripgrep.results, &self.ranked.results)
+    }
+
+    /// Documents the ranked index found that ripgrep didn't, in path order.
+    #[must_use]
+    pub fn only_ranked(&self) -> Vec<&Path> {
+        documents_missing_from(&self.ranked.results, &self.ripgrep.results)
+    }
+}
+
+/// Paths of documents with results in `found` but none in `other`.
+#[cfg(feature = "ranked")]
+fn documents_missing_from<'a>(found: &'a [SearchResult], other: &[SearchResult]) -> Vec<&'a Path> {
+    let other: HashSet<&Path> = other.iter().map(|result| result.path.as_path()).collect();
+    let mut missing: Vec<&Path> = found
+        .iter()
+        .map(|result| result.path.as_path())
+        .filter(|path| !other.contains(path))
+        .collect();
+    missing.sort_unstable();
+    missing.dedup();
+    missing
+}
+
+/// Run a query through both ripgrep and the ranked index across all
+/// configured corpora, to see how the backends differ.
+///
+/// Each backend is searched as by [`search`] with the same options, so
+/// `options.limit` applies to each separately and a document one backend
+/// ranks below the limit counts as not found by it.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, or either search fails as a
+/// whole (for example, when no corpus has an index).
+#[cfg(feature = "ranked")]
+pub fn compare_backends(query: &str, options: &SearchOptions) -> anyhow::Result<BackendComparison> {
+    Ok(BackendComparison {
+        ripgrep: search(query, Backend::Ripgrep, options)?,
+        ranked: search(query, Backend::Ranked, options)?,
+    })
+}
+
+/// List documents from all configured corpora.
+///
+/// # Arguments
+///
+/// * `category` - Which categories to list
+/// * `category_match` - How a named category is compared with each
+///   document's category
+/// * `tags` - Only list documents with every one of these tags (compared
+///   whole, ignoring case)
+/// * `word_counts` - Fill in word counts missing from the manifest by reading
+///   those documents
+/// * `dedupe` - List a document present in several corpora (same relative
+///   path and title) once, from the first configured corpus that has it
+/// * `broken` - Only list manifest entries whose file doesn't exist
+///
+/// # Returns
+///
+/// Document info from all configured corpora, plus warnings for any corpora
+/// that failed to load.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails or all corpora fail to load.
+/// Individual corpus failures are reported as warnings but don't fail the
+/// entire list.
+pub fn list(
+    category: ListCategory<'_>,
+    category_match: CategoryMatch,
+    tags: &[String],
+    word_counts: bool,
+    dedupe: bool,
+    broken: bool,
+) -> anyhow::Result<ListOutcome> {
+    let config = load_config()?;
+    let category = match category {
+        ListCategory::Default => config.list.default_category.as_deref(),
+        ListCategory::All => None,
+        ListCategory::Named(category) => Some(category),
+    };
+    let mut outcome = collect_documents(&config, "List", word_counts, dedupe, |doc| {
+        category.is_none_or(|cat| category_match.matches(cat, &doc.category))
+            && tags
+                .iter()
+                .all(|tag| doc.tags.iter().any(|t| labels_match(t, tag)))
+    })?;
+    if broken {
+        outcome.documents.retain(|doc| !doc.path.exists());
+    }
+    Ok(outcome)
+}
+
+/// The category [`list`] is limited to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListCategory<'a> {
+    /// `[list] default_category`, or every category if it isn't set.
+    Default,
+    /// Every category, even if `[list] default_category` is set.
+    All,
+    /// Only this category.
+    Named(&'a str),
+}
+
+impl<'a> ListCategory<'a> {
+    /// `category` if given, otherwise every category with `all`, or else
+    /// the configured default.
+    #[must_use]
+    pub fn from_flags(category: Option<&'a str>, all: bool) -> Self {
+        match category {
+            Some(category) => Self::Named(category),
+            None if all => Self::All,
+            None => Self::Default,
+        }
+    }
+}
+
+/// JSON Schema describing the `manifest.json` format.
+///
+/// # Errors
+///
+/// Returns an error if the schema cannot be serialized.
+pub fn manifest_schema() -> anyhow::Result<String> {
+    let schema = schemars::schema_for!(Manifest);
+    Ok(serde_json::to_string_pretty(&schema)?)
+}
+
+/// Query document metadata with a boolean expression.
+///
+/// Evaluates the expression against each manifest entry's `category`, `tags`,
+/// and `title`, independent of any search backend. See [`crate::query`] for
+/// the grammar (e.g., `category = aws AND tag = lambda`).
+///
+/// # Arguments
+///
+/// * `expr` - The metadata query expression
+///
+/// # Returns
+///
+/// Document info for every matching document, plus warnings for any corpora
+/// that failed to load.
+///
+/// # Errors
+///
+/// Returns an error if the expression is malformed, config loading fails,
+/// or all corpora fail to load.
+pub fn query_metadata(expr: &str) -> anyhow::Result<ListOutcome> {
+    let expr = Expr::parse(expr).map_err(|e| anyhow::anyhow!("Invalid query: {e}"))?;
+    collect_documents(&load_config()?, "Query", false, false, |doc| {
+        expr.matches(doc)
+    })
+}
+
+/// Count how often each pair of tags appears together on a document, across
+/// all configured corpora.
+///
+/// Tags are compared ignoring case, keeping the casing first seen. A
+/// document present in several corpora (same relative path and title) is
+/// counted once. Corpora that fail to load are reported on stderr.
+///
+/// # Returns
+///
+/// Each pair, in alphabetical order within the pair, with its count; most
+/// frequent first, ties in alphabetical order.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails or all corpora fail to load.
+pub fn tag_cooccurrence() -> anyhow::Result<Vec<((String, String), usize)>> {
+    let outcome = collect_documents(&load_config()?, "Tag graph", false, true, |_| true)?;
+    if !outcome.warnings.is_empty() {
+        eprintln!("Warnings:\n  {}", outcome.warnings.join("\n  "));
+    }
+    Ok(count_tag_pairs(&outcome.documents))
+}
+
+/// Count co-occurring tag pairs over `documents` (see [`tag_cooccurrence`]).
+fn count_tag_pairs(documents: &[DocumentInfo]) -> Vec<((String, String), usize)> {
+    // Tags by lowercased key, with the casing first seen
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut counts: HashMap<(String, String), usize> = HashMap::new();
+
+    for doc in documents {
+        let mut keys: Vec<String> = doc
+            .tags
+            .iter()
+            .map(|tag| {
+                let key = tag.to_lowercase();
+                names.entry(key.clone()).or_insert_with(|| tag.clone());
+                key
+            })
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+
+        for (i, first) in keys.iter().enumerate() {
+            for second in &keys[i + 1..] {
+                *counts.entry((first.clone(), second.clone())).or_default() += 1;
+            }
+        }
     }
 
+    let mut pairs: Vec<((String, String), usize)> = counts
+        .into_iter()
+        .map(|((first, second), count)| ((names[&first].clone(), names[&second].clone()), count))
+        .collect();
+    pairs.sort_by(|(a, a_count), (b, b_count)| {
+        b_count
+            .cmp(a_count)
+            .then_with(|| a.0.to_lowercase().cmp(&b.0.to_lowercase()))
+            .then_with(|| a.1.to_lowercase().cmp(&b.1.to_lowercase()))
+    });
+    pairs
+}
+
+/// Collect documents from the corpora `config` lists that satisfy `filter`.
+///
+/// `operation` names the caller in the aggregated error message. With
+/// `word_counts`, documents lacking a cached word count are read to compute one.
+/// With `dedupe`, documents already collected from an earlier corpus under the
+/// same relative path and title are skipped.
+fn collect_documents(
+    config: &Config,
+    operation: &str,
+    word_counts: bool,
+    dedupe: bool,
+    filter: impl Fn(&Document) -> bool,
+) -> anyhow::Result<ListOutcome> {
+    let mut documents = Vec::new();
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+
     for path_str in &config.corpus.paths {
-        let corpus_path = expand_tilde(path_str);
+        let path = expand_tilde(path_str);
 
-        if !corpus_path.exists() {
+        if !path.exists() {
             continue;
         }
 
-        if let Ok(corpus) = Corpus::load(&corpus_path) {
-            for doc in corpus.documents() {
-                if doc.path.to_string_lossy() == doc_path {
-                    // Validate the resolved path stays within corpus root
-                    let full_path = validate_path_within_root(&corpus.root, &doc.path)?;
-                    return std::fs::read_to_string(&full_path).map_err(Into::into);
+        match Corpus::load(&path) {
+            Ok(corpus) => {
+                let storage = if word_counts {
+                    LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest).ok()
+                } else {
+                    None
+                };
+
+                for doc in corpus.documents() {
+                    if !filter(doc) {
+                        continue;
+                    }
+                    if dedupe && !seen.insert((doc.path.clone(), doc.title.clone())) {
+                        continue;
+                    }
+
+                    let word_count = doc.word_count.or_else(|| {
+                        let content = storage.as_ref()?.read_document(&doc.path).ok()?;
+                        Some(count_words(&content))
+                    });
+
+                    documents.push(DocumentInfo {
+                        title: doc.title.clone(),
+                        category: doc.category.clone(),
+                        tags: doc.tags.clone(),
+                        path: corpus.resolve_document_path(doc),
+                        word_count,
+                        content_type: doc.resolved_content_type().to_string(),
+                    });
                 }
             }
+            Err(e) => errors.push(format!("Load {}: {e}", path.display())),
         }
     }
 
-    anyhow::bail!("Document not found: {doc_path}")
+    // If we got no documents and had errors, report them
+    if documents.is_empty() && !errors.is_empty() {
+        anyhow::bail!("{operation} failed:\n  {}", errors.join("\n  "));
+    }
+
+    Ok(ListOutcome {
+        documents,
+        warnings: errors,
+    })
 }
 
-/// Information about a document with resolved path.
+/// Write a markdown index of every document in all configured corpora.
 ///
-/// Used for list and add results. The path is absolute (resolved from corpus root).
-#[derive(Debug, Clone)]
-pub struct DocumentInfo {
-    /// Human-readable document title.
-    pub title: String,
-    /// Category for grouping (e.g., "aws", "rust").
-    pub category: String,
-    /// Tags for additional classification.
-    pub tags: Vec<String>,
-    /// Absolute path to the document file.
-    pub path: PathBuf,
+/// Documents are grouped under a `##` header per category (categories in
+/// alphabetical order) and listed as links sorted by title. Links are
+/// relative to the directory `output` is written to, so the index can sit
+/// alongside the published corpus.
+///
+/// # Arguments
+///
+/// * `output` - Path of the markdown file to write (overwritten if present)
+///
+/// # Returns
+///
+/// The indexed documents, plus warnings for any corpora that failed to load.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, all corpora fail to load, or
+/// the file cannot be written.
+pub fn generate_index(output: &Path) -> anyhow::Result<ListOutcome> {
+    let outcome = collect_documents(&load_config()?, "Index", false, false, |_| true)?;
+
+    let output = std::path::absolute(output)?;
+    let base = output.parent().unwrap_or(Path::new("/"));
+    let markdown = render_index(&outcome.documents, base)?;
+
+    std::fs::write(&output, markdown)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {e}", output.display()))?;
+    Ok(outcome)
 }
 
-/// Add a new document to the knowledge corpus.
+/// Render the markdown for [`generate_index`], linking documents relative
+/// to `base`.
+fn render_index(documents: &[DocumentInfo], base: &Path) -> anyhow::Result<String> {
+    // Categories differing only in case share a heading, spelled as first seen
+    let mut by_category: BTreeMap<String, (&str, Vec<&DocumentInfo>)> = BTreeMap::new();
+    for doc in documents {
+        by_category
+            .entry(label_key(&doc.category))
+            .or_insert_with(|| (&doc.category, Vec::new()))
+            .1
+            .push(doc);
+    }
+
+    let mut markdown = String::from("# Index\n");
+    for (category, mut docs) in by_category.into_values() {
+        docs.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.path.cmp(&b.path)));
+
+        let _ = write!(markdown, "\n## {category}\n\n");
+        for doc in docs {
+            let link = relative_link(&std::path::absolute(&doc.path)?, base);
+            let _ = writeln!(markdown, "- [{}](<{link}>)", escape_link_text(&doc.title));
+        }
+    }
+    Ok(markdown)
+}
+
+/// `text` with backslashes, brackets, and parentheses escaped, so it can't
+/// end a markdown link's text early.
+fn escape_link_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '(' | ')') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Path from the directory `base` to `path` (both absolute), with `/`
+/// separators for use in a markdown link.
+fn relative_link(path: &Path, base: &Path) -> String {
+    let path: Vec<_> = path.components().collect();
+    let base: Vec<_> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+
+    let parents = std::iter::repeat_n("..".to_string(), base.len() - common);
+    let rest = path[common..]
+        .iter()
+        .map(|part| part.as_os_str().to_string_lossy().into_owned());
+    parents.chain(rest).collect::<Vec<_>>().join("/")
+}
+
+/// Total bytes of linked document content [`get`] appends when resolving
+/// links.
+pub const LINKED_CONTENT_LIMIT: usize = 32 * 1024;
+
+/// The documents `content` (from `source`) links to, as sections to append
+/// to it. Links back to `source`, broken links, and repeated targets are
+/// skipped; documents past [`LINKED_CONTENT_LIMIT`] are listed without
+/// content.
+fn linked_sections(
+    corpus: &Corpus,
+    storage: &LocalStorageBackend,
+    source: &Document,
+    content: &str,
+) -> String {
+    let mut seen = HashSet::from([source.path.clone()]);
+    let mut budget = LINKED_CONTENT_LIMIT;
+    let mut sections = String::new();
+
+    for link in links::extract_links(content) {
+        let Some(doc) = links::resolve_link(corpus, &source.path, &link)
+            .and_then(|path| corpus.find_document(&path))
+        else {
+            continue;
+        };
+        if !seen.insert(doc.path.clone()) {
+            continue;
+        }
+
+        let body = if budget == 0 {
+            "(omitted: linked content limit reached)".to_string()
+        } else {
+            let read = validate_path_within_root(&corpus.root, &doc.path)
+                .and_then(|_| Ok(storage.read_document(&doc.path)?));
+            match read {
+                Ok(linked) => {
+                    let linked = truncate_content(linked, budget);
+                    budget = budget.saturating_sub(linked.len());
+                    linked
+                }
+                Err(e) => format!("(unreadable: {e})"),
+            }
+        };
+        let _ = write!(
+            sections,
+            "\n\n---\n\n# Linked: {} ({})\n\n{}",
+            doc.title,
+            doc.path.display(),
+            body.trim_end()
+        );
+    }
+    sections
+}
+
+/// Get the contents of a document by its path.
 ///
 /// # Arguments
 ///
-/// * `title` - Human-readable document title
-/// * `content` - Document content (markdown)
-/// * `category` - Category for grouping (e.g., "aws", "rust")
-/// * `tags` - Optional tags for classification
+/// * `doc_path` - Relative path to the document (e.g., "aws/lambda-patterns.md")
+/// * `line_range` - Optional 1-indexed inclusive range of lines to return;
+///   see [`select_lines`]
+/// * `limit_bytes` - Optional cap on returned content (applied after
+///   `line_range` and `resolve_links`); longer content is truncated on a
+///   UTF-8 boundary and marked with [`TRUNCATION_MARKER`]
+/// * `resolve_links` - Append the documents the content links to (see
+///   [`links::resolve_link`]), each under a `# Linked: <title> (<path>)`
+///   heading. Only direct links are followed, each document is included
+///   once, and their content is capped at [`LINKED_CONTENT_LIMIT`] in total.
 ///
 /// # Returns
 ///
-/// Information about the created document including its path.
+/// The document content as a string.
 ///
 /// # Errors
 ///
 /// Returns an error if:
-/// - No corpus path is configured
-/// - Title or category contain invalid characters
-/// - Document already exists
-/// - Storage operations fail
-pub fn add(
-    title: &str,
-    content: &str,
-    category: &str,
-    tags: Vec<String>,
-) -> anyhow::Result<DocumentInfo> {
-    // Validate inputs before any file operations
-    if title.is_empty() {
-        anyhow::bail!("Title cannot be empty");
+/// - The document is not found in any corpus
+/// - The path is invalid or attempts path traversal
+/// - The document cannot be read
+pub fn get(
+    doc_path: &str,
+    line_range: Option<LineRange>,
+    limit_bytes: Option<usize>,
+    resolve_links: bool,
+) -> anyhow::Result<String> {
+    let config = load_config()?;
+
+    // Early validation of the requested path
+    let requested_path = PathBuf::from(doc_path);
+    if requested_path.to_string_lossy().contains("..") {
+        anyhow::bail!("Invalid document path: contains '..' component");
     }
-    if title.len() > MAX_INPUT_LENGTH {
-        anyhow::bail!(
-            "Title too long: {} chars (max {MAX_INPUT_LENGTH})",
-            title.len()
-        );
+
+    for path_str in &config.corpus.paths {
+        let corpus_path = expand_tilde(path_str);
+
+        if !corpus_path.exists() {
+            continue;
+        }
+
+        // Merged documents are still found by their former paths
+        if let Ok(corpus) = Corpus::load(&corpus_path)
+            && let Some(doc) = corpus.find_document(&requested_path)
+        {
+            // Validate the resolved path stays within corpus root
+            validate_path_within_root(&corpus.root, &doc.path)?;
+            let storage = LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest)?;
+            let mut content = storage.read_document(&doc.path)?;
+            if let Some(range) = line_range {
+                content = select_lines(&content, range);
+            }
+            if resolve_links {
+                let linked = linked_sections(&corpus, &storage, doc, &content);
+                content.push_str(&linked);
+            }
+            return Ok(match limit_bytes {
+                Some(limit) => truncate_content(content, limit),
+                None => content,
+            });
+        }
     }
 
-    validate_identifier(category, "Category")?;
+    anyhow::bail!("Document not found: {doc_path}")
+}
 
-    // Validate tags
-    for tag in &tags {
-        if !tag.is_empty() {
-            validate_identifier(tag, "Tag")?;
+/// Look up a document's metadata by its path or one of its aliases.
+///
+/// Corpora are searched in config order, as with [`get`].
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, the path contains `..`, or the
+/// document is not found in any corpus.
+pub fn document_info(doc_path: &str) -> anyhow::Result<DocumentInfo> {
+    let config = load_config()?;
+
+    if doc_path.contains("..") {
+        anyhow::bail!("Invalid document path: contains '..' component");
+    }
+
+    for path_str in &config.corpus.paths {
+        let corpus_path = expand_tilde(path_str);
+
+        if !corpus_path.exists() {
+            continue;
+        }
+
+        if let Ok(corpus) = Corpus::load(&corpus_path)
+            && let Some(doc) = corpus.find_document(Path::new(doc_path))
+        {
+            return Ok(DocumentInfo {
+                title: doc.title.clone(),
+                category: doc.category.clone(),
+                tags: doc.tags.clone(),
+                path: corpus.resolve_document_path(doc),
+                word_count: doc.word_count,
+                content_type: doc.resolved_content_type().to_string(),
+            });
         }
     }
 
-    let config = Config::load()?;
+    anyhow::bail!("Document not found: {doc_path}")
+}
 
-    let corpus_path = config
-        .corpus
-        .paths
-        .first()
-        .ok_or_else(|| anyhow::anyhow!("No corpus path configured"))?;
+/// An inclusive, 1-indexed range of lines, parsed from `START:END`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    /// First line to include (1-indexed).
+    pub start: usize,
+    /// Last line to include (1-indexed, inclusive).
+    pub end: usize,
+}
 
-    let root = expand_tilde(corpus_path);
-    let storage = LocalStorageBackend::new(root.clone());
+impl std::str::FromStr for LineRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected START:END, got '{s}'"))?;
+        let parse = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid line number '{n}'"))
+        };
+        let (start, end) = (parse(start)?, parse(end)?);
+
+        if start == 0 {
+            return Err("line numbers start at 1".to_string());
+        }
+        if start > end {
+            return Err(format!("start line {start} is after end line {end}"));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+/// Select an inclusive, 1-indexed range of lines from `content`, keeping
+/// their line endings.
+///
+/// An `end` past the last line is clamped to it; a `start` past the last line
+/// yields an empty string.
+#[must_use]
+pub fn select_lines(content: &str, range: LineRange) -> String {
+    content
+        .split_inclusive('\n')
+        .skip(range.start - 1)
+        .take(range.end - range.start + 1)
+        .collect()
+}
+
+/// Marker appended to content cut short by `get --limit-bytes`.
+pub const TRUNCATION_MARKER: &str = "\n… [truncated]";
+
+/// Truncate content to at most `limit` bytes, backing off to the nearest
+/// UTF-8 char boundary and appending [`TRUNCATION_MARKER`].
+///
+/// Content that already fits is returned unchanged.
+#[must_use]
+pub fn truncate_content(mut content: String, limit: usize) -> String {
+    if content.len() <= limit {
+        return content;
+    }
+
+    let mut cut = limit;
+    while !content.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    content.truncate(cut);
+    content.push_str(TRUNCATION_MARKER);
+    content
+}
+
+/// Results of a search across all configured corpora.
+#[derive(Debug, Clone, Default)]
+pub struct SearchOutcome {
+    /// Matching results, sorted by relevance.
+    pub results: Vec<SearchResult>,
+    /// Per-corpus failures that didn't prevent other corpora from being searched.
+    pub warnings: Vec<String>,
+}
+
+/// Results of several searches run by [`search_many`].
+#[derive(Debug, Default)]
+pub struct SearchBatch {
+    /// Each query's outcome, in order, or why it failed.
+    pub outcomes: Vec<anyhow::Result<SearchOutcome>>,
+    /// Corpora that failed to load, which every query went without.
+    pub warnings: Vec<String>,
+}
+
+/// Documents listed across all configured corpora.
+#[derive(Debug, Clone, Default)]
+pub struct ListOutcome {
+    /// Matching documents.
+    pub documents: Vec<DocumentInfo>,
+    /// Per-corpus failures that didn't prevent other corpora from being listed.
+    pub warnings: Vec<String>,
+}
+
+/// Overview of one corpus: its name, size, and most common categories.
+#[derive(Debug, Clone)]
+pub struct CorpusSummary {
+    /// Display name (see [`Corpus::name`]).
+    pub name: String,
+    /// Number of documents in the manifest.
+    pub document_count: usize,
+    /// Categories with their document counts, most common first (ties in
+    /// name order).
+    pub categories: Vec<(String, usize)>,
+}
+
+impl CorpusSummary {
+    /// Summarize a loaded corpus.
+    #[must_use]
+    pub fn of(corpus: &Corpus) -> Self {
+        // Categories differing only in case are counted together, under the
+        // spelling seen first
+        let mut counts: HashMap<String, (&str, usize)> = HashMap::new();
+        for doc in corpus.documents() {
+            counts
+                .entry(label_key(&doc.category))
+                .or_insert((&doc.category, 0))
+                .1 += 1;
+        }
+
+        let mut categories: Vec<(String, usize)> = counts
+            .into_values()
+            .map(|(category, count)| (category.to_string(), count))
+            .collect();
+        categories.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        Self {
+            name: corpus.name(),
+            document_count: corpus.documents().len(),
+            categories,
+        }
+    }
+}
+
+/// Summarize every configured corpus that can be loaded.
+///
+/// Corpora that are missing or fail to load are skipped.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails.
+pub fn summarize_corpora() -> anyhow::Result<Vec<CorpusSummary>> {
+    let config = load_config()?;
+
+    Ok(config
+        .corpus
+        .paths
+        .iter()
+        .filter_map(|path_str| Corpus::load(&expand_tilde(path_str)).ok())
+        .map(|corpus| CorpusSummary::of(&corpus))
+        .collect())
+}
+
+/// Information about a document with resolved path.
+///
+/// Used for list and add results. The path is absolute (resolved from corpus root).
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentInfo {
+    /// Human-readable document title.
+    pub title: String,
+    /// Category for grouping (e.g., "aws", "rust").
+    pub category: String,
+    /// Tags for additional classification.
+    pub tags: Vec<String>,
+    /// Absolute path to the document file.
+    pub path: PathBuf,
+    /// Number of words in the document, if known.
+    pub word_count: Option<usize>,
+    /// MIME type of the document body (e.g., "text/markdown").
+    pub content_type: String,
+}
+
+/// A link found in a document and the document it leads to.
+#[derive(Debug, Clone)]
+pub struct LinkStatus {
+    /// Full path of the document containing the link.
+    pub source: PathBuf,
+    /// The link as written.
+    pub link: Link,
+    /// Manifest path of the linked document, or `None` if the link is broken.
+    pub resolved: Option<PathBuf>,
+}
+
+/// Links checked across all configured corpora.
+#[derive(Debug, Clone, Default)]
+pub struct LinkOutcome {
+    /// Every link found, valid or broken.
+    pub links: Vec<LinkStatus>,
+    /// Corpora or documents that couldn't be read.
+    pub warnings: Vec<String>,
+}
+
+/// Parse a document's links and resolve each against its corpus.
+fn document_links(
+    corpus: &Corpus,
+    storage: &LocalStorageBackend,
+    doc: &Document,
+) -> anyhow::Result<Vec<LinkStatus>> {
+    let content = storage.read_document(&doc.path)?;
+    Ok(links::extract_links(&content)
+        .into_iter()
+        .map(|link| LinkStatus {
+            source: corpus.resolve_document_path(doc),
+            resolved: links::resolve_link(corpus, &doc.path, &link),
+            link,
+        })
+        .collect())
+}
+
+/// List the `[[wikilinks]]` and markdown links in a document and whether
+/// each resolves to a document in the same corpus.
+///
+/// # Arguments
+///
+/// * `doc_path` - Relative path to the document (e.g., "notes/index.md")
+///
+/// # Errors
+///
+/// Returns an error if the document is not found in any corpus, the path
+/// attempts traversal, or the document cannot be read.
+pub fn links(doc_path: &str) -> anyhow::Result<Vec<LinkStatus>> {
+    let config = load_config()?;
+
+    if doc_path.contains("..") {
+        anyhow::bail!("Invalid document path: contains '..' component");
+    }
+
+    for path_str in &config.corpus.paths {
+        let corpus_path = expand_tilde(path_str);
+
+        if !corpus_path.exists() {
+            continue;
+        }
+
+        if let Ok(corpus) = Corpus::load(&corpus_path)
+            && let Some(doc) = corpus.find_document(Path::new(doc_path))
+        {
+            validate_path_within_root(&corpus.root, &doc.path)?;
+            let storage = LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest)?;
+            return document_links(&corpus, &storage, doc);
+        }
+    }
+
+    anyhow::bail!("Document not found: {doc_path}")
+}
+
+/// Check the links in every document of every configured corpus.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, or if nothing could be checked
+/// and some corpus or document failed to load.
+pub fn check_all_links() -> anyhow::Result<LinkOutcome> {
+    let config = load_config()?;
+    let mut outcome = LinkOutcome::default();
+    let mut checked = 0;
+
+    for path_str in &config.corpus.paths {
+        let path = expand_tilde(path_str);
+
+        if !path.exists() {
+            continue;
+        }
+
+        let corpus = match Corpus::load(&path) {
+            Ok(corpus) => corpus,
+            Err(e) => {
+                outcome
+                    .warnings
+                    .push(format!("Load {}: {e}", path.display()));
+                continue;
+            }
+        };
+        let storage = LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest)?;
+
+        for doc in corpus.documents() {
+            match document_links(&corpus, &storage, doc) {
+                Ok(statuses) => {
+                    checked += 1;
+                    outcome.links.extend(statuses);
+                }
+                Err(e) => outcome.warnings.push(format!(
+                    "Read {}: {e}",
+                    corpus.resolve_document_path(doc).display()
+                )),
+            }
+        }
+    }
+
+    if checked == 0 && !outcome.warnings.is_empty() {
+        anyhow::bail!("Link check failed:\n  {}", outcome.warnings.join("\n  "));
+    }
+
+    Ok(outcome)
+}
+
+/// Manifest issues found across all configured corpora.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyOutcome {
+    /// Each issue with the root of the corpus it was found in.
+    pub issues: Vec<(PathBuf, ValidationIssue)>,
+    /// Number of issues `--fix` resolved.
+    pub fixed: usize,
+    /// Corpora whose manifest couldn't be found, read, or parsed, with the
+    /// manifest path and the error.
+    pub load_errors: Vec<(PathBuf, String)>,
+    /// Issues that couldn't be fixed.
+    pub warnings: Vec<String>,
+}
+
+/// Check every configured corpus with [`Corpus::validate`] and
+/// [`Corpus::check_files`], optionally fixing what they find.
+///
+/// Missing files and duplicate manifest entries are only reported.
+///
+/// # Arguments
+///
+/// * `fix` - How to resolve category mismatches; `None` only reports them
+/// * `fix_permissions` - Make unreadable files readable by their owner
+///   (Unix only; elsewhere they are reported with a warning)
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, or if fixing a corpus's
+/// manifest fails (including because it is locked).
+pub fn verify(fix: Option<CategoryFix>, fix_permissions: bool) -> anyhow::Result<VerifyOutcome> {
+    let config = load_config()?;
+    let mut outcome = VerifyOutcome::default();
+
+    for path_str in &config.corpus.paths {
+        let path = expand_tilde(path_str);
+
+        if !path.exists() {
+            continue;
+        }
+
+        let mut corpus = match Corpus::load(&path) {
+            Ok(corpus) => corpus,
+            Err(e) => {
+                outcome
+                    .load_errors
+                    .push((e.path().to_path_buf(), e.to_string()));
+                continue;
+            }
+        };
+
+        let mut issues = corpus.validate();
+        // Under the date layout, directories don't follow categories
+        if config.corpus.layout == DocumentLayout::Date {
+            issues.retain(|issue| !matches!(issue, ValidationIssue::CategoryMismatch { .. }));
+        }
+        issues.extend(corpus.check_files());
+        let mismatches: Vec<&ValidationIssue> = issues
+            .iter()
+            .filter(|issue| matches!(issue, ValidationIssue::CategoryMismatch { .. }))
+            .collect();
+
+        if let Some(fix) = fix
+            && !mismatches.is_empty()
+        {
+            ensure_unlocked(&corpus.root)?;
+            let mut fixed = 0;
+            for issue in mismatches {
+                match fix_issue(&mut corpus, issue, fix) {
+                    Ok(()) => fixed += 1,
+                    Err(e) => outcome.warnings.push(format!(
+                        "Fix {}: {e}",
+                        corpus.root.join(issue.path()).display()
+                    )),
+                }
+            }
+            // A failed fix leaves the manifest as it was, so only rewrite it
+            // if something changed
+            if fixed > 0 {
+                LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest)?
+                    .with_manifest_format(config.storage.manifest_format)
+                    .write_manifest(&corpus.manifest)?;
+            }
+            outcome.fixed += fixed;
+        }
+
+        if fix_permissions {
+            for issue in &issues {
+                if let ValidationIssue::Unreadable { path, .. } = issue {
+                    let full_path = corpus.root.join(path);
+                    match make_readable(&full_path) {
+                        Ok(()) => outcome.fixed += 1,
+                        Err(e) => outcome
+                            .warnings
+                            .push(format!("Fix {}: {e}", full_path.display())),
+                    }
+                }
+            }
+        }
+
+        outcome
+            .issues
+            .extend(issues.into_iter().map(|issue| (corpus.root.clone(), issue)));
+    }
+
+    Ok(outcome)
+}
+
+/// Let a file's owner read it.
+#[cfg(unix)]
+fn make_readable(path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o400);
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+/// Let a file's owner read it.
+#[cfg(not(unix))]
+fn make_readable(_path: &Path) -> anyhow::Result<()> {
+    anyhow::bail!("fixing permissions is only supported on Unix; check the file's access settings")
+}
+
+/// Resolve a category mismatch in `corpus`'s manifest, moving the
+/// document's file if `fix` calls for it. The caller writes the manifest.
+fn fix_issue(corpus: &mut Corpus, issue: &ValidationIssue, fix: CategoryFix) -> anyhow::Result<()> {
+    let ValidationIssue::CategoryMismatch { path, category } = issue else {
+        anyhow::bail!("Only category mismatches can be fixed with --fix");
+    };
+    let root = corpus.root.clone();
+    let doc = corpus
+        .manifest
+        .documents
+        .iter_mut()
+        .find(|doc| &doc.path == path)
+        .ok_or_else(|| anyhow::anyhow!("Document not found: {}", path.display()))?;
+
+    match fix {
+        CategoryFix::Move => {
+            let file_name = path
+                .file_name()
+                .ok_or_else(|| anyhow::anyhow!("Invalid document path: {}", path.display()))?;
+            let target = Path::new(category).join(file_name);
+            validate_path_within_root(&root, &target)?;
+            if root.join(&target).exists() {
+                anyhow::bail!("{} already exists", target.display());
+            }
+
+            std::fs::create_dir_all(root.join(category))?;
+            std::fs::rename(root.join(path), root.join(&target))?;
+            doc.aliases.push(path.clone());
+            doc.path = target;
+        }
+        CategoryFix::Category => {
+            let directory = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("File is in the corpus root; use `--fix move` instead")
+                })?;
+            doc.category = directory.to_string_lossy().replace('\\', "/");
+        }
+    }
+    Ok(())
+}
+
+/// Manifest changes made by [`reindex_manifest`].
+#[derive(Debug, Clone, Default)]
+pub struct SyncOutcome {
+    /// Full paths of files given new manifest entries.
+    pub added: Vec<PathBuf>,
+    /// Full paths of entries dropped because their file no longer exists.
+    pub removed: Vec<PathBuf>,
+    /// Files that couldn't be added, and corpora that couldn't be synced.
+    pub warnings: Vec<String>,
+}
+
+/// Reconcile each configured corpus's manifest with the markdown files on
+/// disk.
+///
+/// Files the manifest doesn't list get an entry titled after their first
+/// `# ` heading (or their file name), categorized by their first directory
+/// (or a matching `[[corpus.category_rules]]` entry), with no tags. Entries
+/// whose file is gone are dropped. Entries for files that still exist are
+/// kept as they are. Files directly in the corpus root have no category and
+/// are only reported.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, or if a corpus that needs
+/// changes is locked or its manifest cannot be written.
+pub fn reindex_manifest() -> anyhow::Result<SyncOutcome> {
+    let config = load_config()?;
+    let resolver = ConfigRulesResolver::new(config.corpus.category_rules.clone());
+    let mut outcome = SyncOutcome::default();
+
+    for path_str in &config.corpus.paths {
+        let root = expand_tilde(path_str);
+
+        if !root.is_dir() {
+            continue;
+        }
+
+        let storage = LocalStorageBackend::new(root.clone())
+            .with_manifest_format(config.storage.manifest_format);
+        let mut manifest = match storage.read_manifest() {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                outcome.warnings.push(e.to_string());
+                continue;
+            }
+        };
+
+        let mut files = Vec::new();
+        if let Err(e) = markdown_files(&root, &root, &mut files) {
+            outcome
+                .warnings
+                .push(format!("Read {}: {e}", root.display()));
+            continue;
+        }
+        files.sort();
+
+        let before = manifest.documents.len();
+        let mut removed = Vec::new();
+        manifest.documents.retain(|doc| {
+            let exists = root.join(&doc.path).exists();
+            if !exists {
+                removed.push(root.join(&doc.path));
+            }
+            exists
+        });
+
+        let mut added = Vec::new();
+        for path in files {
+            if manifest.find_document_index(&path).is_some() {
+                continue;
+            }
+            match untracked_document(&root, path, &resolver) {
+                Ok(document) => {
+                    added.push(root.join(&document.path));
+                    manifest.documents.push(document);
+                }
+                Err(e) => outcome.warnings.push(e.to_string()),
+            }
+        }
+
+        if added.is_empty() && manifest.documents.len() == before {
+            continue;
+        }
+        ensure_unlocked(&root)?;
+        LocalStorageBackend::for_manifest(root.clone(), &manifest)?
+            .with_manifest_format(config.storage.manifest_format)
+            .write_manifest(&manifest)?;
+
+        outcome.added.extend(added);
+        outcome.removed.extend(removed);
+    }
+
+    Ok(outcome)
+}
+
+/// Collect the markdown files under `dir`, relative to `root`, skipping the
+/// paths kvault ignores. Symlinked directories aren't followed.
+fn markdown_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        if is_ignored_corpus_path(relative) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            markdown_files(root, &path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            files.push(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// A manifest entry for a file found on disk, at `path` relative to `root`.
+fn untracked_document(
+    root: &Path,
+    path: PathBuf,
+    resolver: &ConfigRulesResolver,
+) -> anyhow::Result<Document> {
+    let full_path = root.join(&path);
+    let Some(category) = resolver.resolve(&path) else {
+        anyhow::bail!(
+            "Skipped {}: not in a category directory",
+            full_path.display()
+        );
+    };
+    validate_identifier(&category, "Category")
+        .map_err(|e| anyhow::anyhow!("Skipped {}: {e}", full_path.display()))?;
+
+    let content = std::fs::read_to_string(&full_path)
+        .map_err(|e| anyhow::anyhow!("Skipped {}: {e}", full_path.display()))?;
+    let title = first_heading(&content).map_or_else(|| humanize_title(&path), str::to_string);
+
+    Ok(Document {
+        content_type: Some(infer_content_type(&path).to_string()),
+        path,
+        title,
+        category,
+        tags: Vec::new(),
+        encrypted: false,
+        word_count: Some(count_words(&content)),
+        aliases: Vec::new(),
+    })
+}
+
+/// The text of the first `# ` heading in `content`, if any.
+fn first_heading(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .filter_map(|line| line.strip_prefix("# "))
+        .map(str::trim)
+        .find(|heading| !heading.is_empty())
+}
+
+/// Words per shingle when comparing documents for similarity.
+#[cfg(feature = "similarity")]
+const SHINGLE_WORDS: usize = 3;
+
+/// A group of documents with the same or similar content.
+#[derive(Debug, Clone)]
+pub struct DuplicateCluster {
+    /// Full paths of the documents, in manifest order. The first is the one
+    /// `--merge` keeps.
+    pub paths: Vec<PathBuf>,
+    /// Whether the contents are identical, rather than merely similar.
+    pub exact: bool,
+}
+
+/// Duplicate clusters found across all configured corpora.
+#[derive(Debug, Clone, Default)]
+pub struct DuplicatesOutcome {
+    /// Clusters within each corpus; documents in different corpora are
+    /// never grouped together.
+    pub clusters: Vec<DuplicateCluster>,
+    /// Corpora or documents that couldn't be read.
+    pub warnings: Vec<String>,
+}
+
+/// Find documents with duplicate content in each configured corpus.
+///
+/// Documents with identical content always form a cluster. With
+/// `similarity` (requires the `similarity` feature), documents whose word
+/// shingles have at least that Jaccard similarity are also clustered. One
+/// document per identical-content group is compared against every other,
+/// so this is slower on large corpora.
+///
+/// With `merge`, each identical-content cluster is collapsed into its first
+/// document: the others' paths (and aliases) become its aliases, their tags
+/// are added to it, and they are deleted. Similar clusters are only reported.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, `similarity` is not in
+/// `(0, 1]` or the feature is disabled, or merging fails to update a corpus
+/// (including because it is locked).
+pub fn find_duplicates(similarity: Option<f64>, merge: bool) -> anyhow::Result<DuplicatesOutcome> {
+    if let Some(threshold) = similarity
+        && !(threshold > 0.0 && threshold <= 1.0)
+    {
+        anyhow::bail!("Similarity threshold must be in (0, 1], got {threshold}");
+    }
+    #[cfg(not(feature = "similarity"))]
+    if similarity.is_some() {
+        anyhow::bail!("Similarity clustering requires the `similarity` feature");
+    }
+
+    let config = load_config()?;
+    let mut outcome = DuplicatesOutcome::default();
+
+    for path_str in &config.corpus.paths {
+        let path = expand_tilde(path_str);
+
+        if !path.exists() {
+            continue;
+        }
+
+        let corpus = match Corpus::load(&path) {
+            Ok(corpus) => corpus,
+            Err(e) => {
+                outcome
+                    .warnings
+                    .push(format!("Load {}: {e}", path.display()));
+                continue;
+            }
+        };
+        let storage = LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest)?
+            .with_manifest_format(config.storage.manifest_format);
+
+        let mut contents = Vec::new();
+        for (index, doc) in corpus.documents().iter().enumerate() {
+            match storage.read_document(&doc.path) {
+                Ok(content) => contents.push((index, content)),
+                Err(e) => outcome.warnings.push(format!(
+                    "Read {}: {e}",
+                    corpus.resolve_document_path(doc).display()
+                )),
+            }
+        }
+
+        let exact = exact_duplicate_groups(&contents);
+        #[cfg_attr(not(feature = "similarity"), allow(unused_mut))]
+        let mut clusters: Vec<(Vec<usize>, bool)> =
+            exact.iter().map(|group| (group.clone(), true)).collect();
+        #[cfg(feature = "similarity")]
+        if let Some(threshold) = similarity {
+            clusters.extend(
+                similar_groups(&contents, &exact, threshold)
+                    .into_iter()
+                    .map(|group| (group, false)),
+            );
+        }
+
+        outcome
+            .clusters
+            .extend(clusters.iter().map(|(indices, exact)| {
+                DuplicateCluster {
+                    paths: indices
+                        .iter()
+                        .map(|&i| corpus.resolve_document_path(&corpus.manifest.documents[i]))
+                        .collect(),
+                    exact: *exact,
+                }
+            }));
+
+        if merge && !exact.is_empty() {
+            ensure_unlocked(&corpus.root)?;
+            merge_duplicate_groups(corpus.manifest, &storage, &exact)?;
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Group manifest indices of documents with identical content, keeping
+/// only groups of two or more, each in manifest order.
+fn exact_duplicate_groups(contents: &[(usize, String)]) -> Vec<Vec<usize>> {
+    let mut by_content: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, content) in contents {
+        by_content.entry(content).or_default().push(*index);
+    }
+
+    let mut groups: Vec<Vec<usize>> = by_content
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect();
+    groups.sort_unstable();
+    groups
+}
+
+/// Cluster documents whose shingle sets have Jaccard similarity of at least
+/// `threshold`, skipping all but the first member of each exact group.
+///
+/// Clusters are connected components: A~B and B~C puts A, B, and C together.
+#[cfg(feature = "similarity")]
+fn similar_groups(
+    contents: &[(usize, String)],
+    exact: &[Vec<usize>],
+    threshold: f64,
+) -> Vec<Vec<usize>> {
+    let skipped: HashSet<usize> = exact
+        .iter()
+        .flat_map(|group| &group[1..])
+        .copied()
+        .collect();
+    let shingled: Vec<(usize, HashSet<String>)> = contents
+        .iter()
+        .filter(|(index, _)| !skipped.contains(index))
+        .map(|(index, content)| (*index, shingles(content)))
+        .collect();
+
+    // Union-find over positions in `shingled`
+    let mut parent: Vec<usize> = (0..shingled.len()).collect();
+
+    for a in 0..shingled.len() {
+        for b in a + 1..shingled.len() {
+            if jaccard(&shingled[a].1, &shingled[b].1) >= threshold {
+                let (root_a, root_b) = (root(&mut parent, a), root(&mut parent, b));
+                parent[root_b.max(root_a)] = root_a.min(root_b);
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (position, (index, _)) in shingled.iter().enumerate() {
+        let group = root(&mut parent, position);
+        groups.entry(group).or_default().push(*index);
+    }
+
+    let mut groups: Vec<Vec<usize>> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect();
+    groups.sort_unstable();
+    groups
+}
+
+/// Union-find root of `i`, halving the path on the way.
+#[cfg(feature = "similarity")]
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Lowercased runs of [`SHINGLE_WORDS`] consecutive words. Documents shorter
+/// than that yield a single shingle of all their words.
+#[cfg(feature = "similarity")]
+fn shingles(content: &str) -> HashSet<String> {
+    let words: Vec<String> = content.split_whitespace().map(str::to_lowercase).collect();
+    if words.len() < SHINGLE_WORDS {
+        return HashSet::from([words.join(" ")]);
+    }
+    words
+        .windows(SHINGLE_WORDS)
+        .map(|window| window.join(" "))
+        .collect()
+}
+
+/// Size of the intersection over size of the union; 1.0 for two empty sets.
+#[cfg(feature = "similarity")]
+#[allow(clippy::cast_precision_loss)]
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Collapse each group into its first document and delete the rest.
+///
+/// The manifest is written before any file is deleted, so a failed delete
+/// leaves only a stray file rather than a dangling manifest entry.
+fn merge_duplicate_groups(
+    mut manifest: Manifest,
+    storage: &LocalStorageBackend,
+    groups: &[Vec<usize>],
+) -> anyhow::Result<()> {
+    let mut removed = Vec::new();
+
+    for group in groups {
+        let (keep, others) = (group[0], &group[1..]);
+        for &other in others {
+            let doc = manifest.documents[other].clone();
+            let canonical = &mut manifest.documents[keep];
+            canonical.aliases.push(doc.path.clone());
+            canonical.aliases.extend(doc.aliases);
+            for tag in doc.tags {
+                if !canonical.tags.iter().any(|t| labels_match(t, &tag)) {
+                    canonical.tags.push(tag);
+                }
+            }
+            removed.push((other, doc.path));
+        }
+    }
+
+    let removed_indices: HashSet<usize> = removed.iter().map(|(index, _)| *index).collect();
+    let mut index = 0;
+    manifest.documents.retain(|_| {
+        let keep = !removed_indices.contains(&index);
+        index += 1;
+        keep
+    });
+    storage.write_manifest(&manifest)?;
+
+    for (_, path) in removed {
+        storage.delete_document(&path)?;
+    }
+    Ok(())
+}
+
+/// Lock a corpus so commands refuse to modify it.
+///
+/// Writes the [`LOCK_FILE`] sentinel in the corpus
+/// root. Unlike config settings, the lock travels with the corpus. Rebuilding
+/// the search index is still allowed, since it only derives from documents.
+///
+/// # Arguments
+///
+/// * `corpus_path` - Corpus root; defaults to the first configured corpus
+///
+/// # Returns
+///
+/// The locked corpus root. Locking an already locked corpus succeeds.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, no corpus is configured, the
+/// corpus directory doesn't exist, or the sentinel can't be written.
+pub fn lock(corpus_path: Option<&str>) -> anyhow::Result<PathBuf> {
+    let root = lock_target(corpus_path)?;
+    std::fs::write(root.join(LOCK_FILE), "")
+        .map_err(|e| anyhow::anyhow!("Failed to lock {}: {e}", root.display()))?;
+    Ok(root)
+}
+
+/// Remove a corpus's lock, allowing changes again.
+///
+/// # Arguments
+///
+/// * `corpus_path` - Corpus root; defaults to the first configured corpus
+///
+/// # Returns
+///
+/// The unlocked corpus root. Unlocking a corpus that isn't locked succeeds.
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, no corpus is configured, the
+/// corpus directory doesn't exist, or the sentinel can't be removed.
+pub fn unlock(corpus_path: Option<&str>) -> anyhow::Result<PathBuf> {
+    let root = lock_target(corpus_path)?;
+    match std::fs::remove_file(root.join(LOCK_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            anyhow::bail!("Failed to unlock {}: {e}", root.display())
+        }
+        _ => Ok(root),
+    }
+}
+
+/// Resolve the corpus root for `lock`/`unlock`.
+fn lock_target(corpus_path: Option<&str>) -> anyhow::Result<PathBuf> {
+    let root = if let Some(path) = corpus_path {
+        expand_tilde(path)
+    } else {
+        let config = load_config()?;
+        let path = config
+            .corpus
+            .paths
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No corpus path configured"))?;
+        expand_tilde(path)
+    };
+
+    if !root.is_dir() {
+        anyhow::bail!("Corpus not found: {}", root.display());
+    }
+    Ok(root)
+}
+
+/// Refuse to modify a locked corpus.
+fn ensure_unlocked(root: &Path) -> anyhow::Result<()> {
+    if is_locked(root) {
+        anyhow::bail!(
+            "Corpus is locked: {} (run `kvault unlock` to allow changes)",
+            root.display()
+        );
+    }
+    Ok(())
+}
+
+/// Add a new document to the knowledge corpus.
+///
+/// # Arguments
+///
+/// * `title` - Human-readable document title
+/// * `content` - Document content (markdown)
+/// * `category` - Category for grouping (e.g., "aws", "rust")
+/// * `tags` - Optional tags for classification
+/// * `reject_duplicate_titles` - Fail, rather than warn on stderr, if the
+///   category already has a document with the same title
+/// * `corpus` - Configured corpus to add to, by path or directory name;
+///   may be omitted when only one corpus is configured
+///
+/// # Returns
+///
+/// Information about the created document including its path.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - No corpus path is configured
+/// - `corpus` is omitted with several corpora configured, or doesn't select
+///   exactly one of them
+/// - Title or category contain invalid characters
+/// - The corpus is locked
+/// - Document already exists
+/// - `reject_duplicate_titles` is set and the title is already used in the
+///   category
+/// - The corpus is on a case-insensitive filesystem and the category or path
+///   differs only in case from an existing document's
+/// - Storage operations fail
+pub fn add(
+    title: &str,
+    content: &str,
+    category: &str,
+    tags: Vec<String>,
+    reject_duplicate_titles: bool,
+    corpus: Option<&str>,
+) -> anyhow::Result<DocumentInfo> {
+    add_document(
+        title,
+        Some(content),
+        category,
+        tags,
+        reject_duplicate_titles,
+        corpus,
+    )
+}
+
+/// Record a file already in the corpus as a document, without writing it.
+///
+/// The file must be where [`add`] would write it (`<category>/<slug>.md`
+/// unless `[corpus] layout` says otherwise). It is recorded as unencrypted,
+/// since kvault didn't write it.
+///
+/// # Arguments
+///
+/// See [`add`].
+///
+/// # Errors
+///
+/// Returns an error if the file doesn't exist, is already in the manifest,
+/// or [`add`] would fail for any reason but the file existing.
+pub fn add_manifest_only(
+    title: &str,
+    category: &str,
+    tags: Vec<String>,
+    reject_duplicate_titles: bool,
+    corpus: Option<&str>,
+) -> anyhow::Result<DocumentInfo> {
+    add_document(title, None, category, tags, reject_duplicate_titles, corpus)
+}
+
+/// Add a document, writing `content` to a new file, or recording the
+/// existing file if `content` is `None`.
+fn add_document(
+    title: &str,
+    content: Option<&str>,
+    category: &str,
+    tags: Vec<String>,
+    reject_duplicate_titles: bool,
+    corpus: Option<&str>,
+) -> anyhow::Result<DocumentInfo> {
+    // Validate inputs before any file operations
+    validate_document_fields(title, category, &tags)?;
+
+    let config = load_config()?;
+    let root = add_target(&config, corpus)?;
+    ensure_unlocked(&root)?;
+    let (storage, mut manifest) = add_storage(&config, &root)?;
+    let category = existing_category(&manifest, category).to_string();
+    let category = category.as_str();
+    let doc_path = new_document_path(
+        &root,
+        &manifest,
+        config.corpus.layout,
+        title,
+        category,
+        reject_duplicate_titles,
+    )?;
+
+    let word_count = if let Some(content) = content {
+        if storage.exists(&doc_path) {
+            anyhow::bail!("Document already exists: {}", doc_path.display());
+        }
+        storage.write_document(&doc_path, content)?;
+        Some(count_words(content))
+    } else {
+        if !storage.exists(&doc_path) {
+            anyhow::bail!(
+                "File not found: {} (--manifest-only records an existing file)",
+                root.join(&doc_path).display()
+            );
+        }
+        if manifest.find_document_index(&doc_path).is_some() {
+            anyhow::bail!("Document already in manifest: {}", doc_path.display());
+        }
+        // Binary files are recorded without a word count
+        std::fs::read_to_string(root.join(&doc_path))
+            .ok()
+            .map(|text| count_words(&text))
+    };
+
+    let document = Document {
+        path: doc_path.clone(),
+        title: title.to_string(),
+        category: category.to_string(),
+        tags: tags.clone(),
+        encrypted: config.corpus.encrypted && content.is_some(),
+        word_count,
+        aliases: vec![],
+        content_type: Some(infer_content_type(&doc_path).to_string()),
+    };
+
+    storage.append_document(&mut manifest, document)?;
+
+    #[cfg(feature = "ranked")]
+    if config.search.auto_index_on_add {
+        index_added(
+            &Corpus {
+                root: root.clone(),
+                manifest,
+            },
+            &doc_path,
+        );
+    } else {
+        // Make the document findable by ranked search before the next reindex
+        overlay::record(&root, &doc_path);
+    }
+
+    Ok(DocumentInfo {
+        title: title.to_string(),
+        category: category.to_string(),
+        tags,
+        path: root.join(&doc_path),
+        word_count,
+        content_type: infer_content_type(&doc_path).to_string(),
+    })
+}
+
+/// Check a new document's title, category, and tags.
+fn validate_document_fields(title: &str, category: &str, tags: &[String]) -> anyhow::Result<()> {
+    if title.is_empty() {
+        anyhow::bail!("Title cannot be empty");
+    }
+    if title.len() > MAX_INPUT_LENGTH {
+        anyhow::bail!(
+            "Title too long: {} chars (max {MAX_INPUT_LENGTH})",
+            title.len()
+        );
+    }
+
+    validate_identifier(category, "Category")?;
+
+    for tag in tags {
+        if !tag.is_empty() {
+            validate_identifier(tag, "Tag")?;
+        }
+    }
+    Ok(())
+}
+
+/// Open the storage and read the manifest of the corpus at `root` for
+/// adding documents, encrypting them if the config asks for it.
+fn add_storage(config: &Config, root: &Path) -> anyhow::Result<(LocalStorageBackend, Manifest)> {
+    let format = config.storage.manifest_format;
+    let storage = LocalStorageBackend::new(root.to_path_buf()).with_manifest_format(format);
 
     let mut manifest = storage.read_manifest()?;
 
-    let slug = slugify(title);
-    let doc_path = PathBuf::from(category).join(format!("{slug}.md"));
+    let storage = if config.corpus.encrypted {
+        encrypted_storage(root, &mut manifest, format)?
+    } else {
+        storage
+    };
+    Ok((storage, manifest))
+}
+
+/// The path a new document titled `title` gets in `category` under
+/// `layout`, after checking it doesn't clash with `manifest`.
+///
+/// A duplicate title in the category is a warning on stderr, or an error if
+/// `reject_duplicate_titles` is set.
+fn new_document_path(
+    root: &Path,
+    manifest: &Manifest,
+    layout: DocumentLayout,
+    title: &str,
+    category: &str,
+    reject_duplicate_titles: bool,
+) -> anyhow::Result<PathBuf> {
+    let slug = slugify(title);
+    let dir = match layout {
+        DocumentLayout::Category => PathBuf::from(category),
+        DocumentLayout::Date => {
+            let (year, month, day) = today_civil();
+            PathBuf::from(format!("{year:04}"))
+                .join(format!("{month:02}"))
+                .join(format!("{day:02}"))
+        }
+    };
+    let doc_path = dir.join(format!("{slug}.md"));
+
+    // Validate the constructed path is safe
+    validate_path_within_root(root, &doc_path)?;
+
+    if is_case_insensitive_fs(root)
+        && let Some(collision) = find_case_collision(manifest, category, &doc_path)
+    {
+        anyhow::bail!("{collision} on this case-insensitive filesystem");
+    }
+
+    if let Some(existing) = find_duplicate_title(manifest, title, category) {
+        let message = format!(
+            "A document titled '{}' already exists in category '{category}': {}",
+            existing.title,
+            existing.path.display()
+        );
+        if reject_duplicate_titles {
+            anyhow::bail!(message);
+        }
+        eprintln!("Warning: {message}");
+    }
+    Ok(doc_path)
+}
+
+/// Resolve the corpus root `add` writes to.
+///
+/// `corpus` names a configured corpus by path, or by its directory name if
+/// that is unique. Without it, the only configured corpus is used; with
+/// several, guessing could put the document somewhere unexpected, so it's an
+/// error.
+fn add_target(config: &Config, corpus: Option<&str>) -> anyhow::Result<PathBuf> {
+    let configured: Vec<PathBuf> = config
+        .corpus
+        .paths
+        .iter()
+        .map(|p| expand_tilde(p))
+        .collect();
+    let list = || {
+        configured
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n  ")
+    };
+
+    let Some(selection) = corpus else {
+        return match configured.as_slice() {
+            [] => anyhow::bail!("No corpus path configured"),
+            [root] => Ok(root.clone()),
+            _ => anyhow::bail!(
+                "Several corpora are configured; choose one with --corpus (a path or \
+                 directory name):\n  {}",
+                list()
+            ),
+        };
+    };
+
+    let canonical =
+        |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let wanted = canonical(&expand_tilde(selection));
+    if let Some(root) = configured.iter().find(|root| canonical(root) == wanted) {
+        return Ok(root.clone());
+    }
+
+    let named: Vec<&PathBuf> = configured
+        .iter()
+        .filter(|root| root.file_name().is_some_and(|name| name == selection))
+        .collect();
+    match named.as_slice() {
+        [root] => Ok((*root).clone()),
+        [] => anyhow::bail!(
+            "Not a configured corpus: {selection}\nConfigured corpora:\n  {}",
+            list()
+        ),
+        _ => anyhow::bail!(
+            "Corpus name '{selection}' matches several corpora; use its path:\n  {}",
+            named
+                .iter()
+                .map(|root| root.display().to_string())
+                .collect::<Vec<_>>()
+                .join("\n  ")
+        ),
+    }
+}
+
+/// Add a newly added document to its corpus's ranked search index.
+///
+/// Corpora without an index are left alone. If indexing fails the document
+/// is still added, so a warning is printed and the document is recorded as
+/// pending instead (see [`overlay`]).
+#[cfg(feature = "ranked")]
+fn index_added(corpus: &Corpus, doc_path: &Path) {
+    if !TantivyBackend::index_exists(corpus) {
+        return;
+    }
+
+    let documents: Vec<&Document> = corpus
+        .documents()
+        .iter()
+        .filter(|doc| doc.path == doc_path)
+        .collect();
+    let indexed = TantivyBackend::open_for_corpus(corpus, IndexMode::ReadWrite)
+        .and_then(|backend| backend.update_documents(corpus, &documents));
+
+    if let Err(e) = indexed {
+        eprintln!(
+            "Warning: Could not index {}: {e} (run `kvault index` to update the index)",
+            doc_path.display()
+        );
+        overlay::record(&corpus.root, doc_path);
+    }
+}
+
+/// Check whether the filesystem holding `root` ignores case in file names.
+///
+/// Looks up an existing entry of `root` (e.g., the manifest) by its
+/// uppercase name, so nothing is written. Returns `false` if `root` has no
+/// entry to check with, since collisions only matter once there are files
+/// to collide with.
+fn is_case_insensitive_fs(root: &Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return false;
+    };
+    let names: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+
+    names
+        .iter()
+        .map(|name| name.to_uppercase())
+        .find(|upper| !names.contains(upper))
+        .is_some_and(|upper| root.join(upper).exists())
+}
+
+/// `category` as the manifest already spells it, so adding to `aws` when
+/// documents are filed under `AWS` joins that category and its directory
+/// instead of starting a second one.
+fn existing_category<'a>(manifest: &'a Manifest, category: &'a str) -> &'a str {
+    manifest
+        .documents
+        .iter()
+        .map(|doc| doc.category.as_str())
+        .find(|existing| labels_match(existing, category))
+        .unwrap_or(category)
+}
+
+/// Find an existing document whose category or path equals the new one's
+/// except for case, which would share a directory or file on a
+/// case-insensitive filesystem.
+///
+/// # Returns
+///
+/// A description of the first collision found, or `None`.
+fn find_case_collision(manifest: &Manifest, category: &str, doc_path: &Path) -> Option<String> {
+    let new_path = doc_path.to_string_lossy();
+
+    manifest.documents.iter().find_map(|doc| {
+        let path = doc.path.to_string_lossy();
+        if path != new_path && path.to_lowercase() == new_path.to_lowercase() {
+            return Some(format!(
+                "Document path '{new_path}' collides with existing '{path}'"
+            ));
+        }
+
+        (doc.category != category && doc.category.to_lowercase() == category.to_lowercase()).then(
+            || {
+                format!(
+                    "Category '{category}' collides with existing category '{}'",
+                    doc.category
+                )
+            },
+        )
+    })
+}
+
+/// Find an existing document in `category` whose title matches `title`,
+/// ignoring case and surrounding whitespace.
+fn find_duplicate_title<'a>(
+    manifest: &'a Manifest,
+    title: &str,
+    category: &str,
+) -> Option<&'a Document> {
+    let title = title.trim().to_lowercase();
+    manifest.documents.iter().find(|doc| {
+        labels_match(&doc.category, category) && doc.title.trim().to_lowercase() == title
+    })
+}
+
+/// A complete document to add, as accepted by `add --stdin-json`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DocumentRecord {
+    /// Human-readable document title.
+    pub title: String,
+    /// Category for grouping (e.g., "aws", "rust").
+    pub category: String,
+    /// Optional tags for additional classification.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Document content (markdown).
+    pub content: String,
+}
+
+/// Add a document described by a JSON [`DocumentRecord`].
+///
+/// Fields are validated as for [`add`], which also selects the `corpus`;
+/// tags are trimmed and empty tags dropped.
+///
+/// # Errors
+///
+/// Returns an error if the JSON is not a valid record, the content is empty,
+/// or [`add`] fails.
+pub fn add_json(
+    json: &str,
+    reject_duplicate_titles: bool,
+    corpus: Option<&str>,
+) -> anyhow::Result<DocumentInfo> {
+    let record: DocumentRecord =
+        serde_json::from_str(json).map_err(|e| anyhow::anyhow!("Invalid document JSON: {e}"))?;
+
+    if record.content.trim().is_empty() {
+        anyhow::bail!("Content cannot be empty");
+    }
+
+    add(
+        &record.title,
+        &record.content,
+        &record.category,
+        record_tags(&record.tags),
+        reject_duplicate_titles,
+        corpus,
+    )
+}
+
+/// Add the documents described by JSON lines of [`DocumentRecord`]s, all or
+/// none, with [`add_batch`]. Blank lines are skipped.
+///
+/// # Errors
+///
+/// Returns an error if a line is not a valid record, or [`add_batch`] fails.
+pub fn add_jsonl(
+    jsonl: &str,
+    reject_duplicate_titles: bool,
+    corpus: Option<&str>,
+) -> anyhow::Result<Vec<DocumentInfo>> {
+    let records = jsonl
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| anyhow::anyhow!("Document {}: Invalid document JSON: {e}", i + 1))
+        })
+        .collect::<anyhow::Result<Vec<DocumentRecord>>>()?;
+    add_batch(&records, reject_duplicate_titles, corpus)
+}
+
+/// A record's tags, trimmed, without empty ones.
+fn record_tags(tags: &[String]) -> Vec<String> {
+    tags.iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Add several documents as one transaction: either all are added, or the
+/// corpus is left as it was.
+///
+/// Every record is checked as for [`add_json`] before anything is written,
+/// including against the others in the batch. The documents are then written
+/// to hidden temporary files beside their destinations, renamed into place,
+/// and recorded with a single manifest write. If any step fails, every file
+/// written for the batch is removed.
+///
+/// # Errors
+///
+/// Returns an error, naming the first failing record by its position, if a
+/// record is invalid or clashes with an existing document or an earlier
+/// record; or if writing the documents or the manifest fails.
+pub fn add_batch(
+    records: &[DocumentRecord],
+    reject_duplicate_titles: bool,
+    corpus: Option<&str>,
+) -> anyhow::Result<Vec<DocumentInfo>> {
+    let in_record = |i: usize| move |e: anyhow::Error| anyhow::anyhow!("Document {}: {e}", i + 1);
+
+    for (i, record) in records.iter().enumerate() {
+        if record.content.trim().is_empty() {
+            return Err(in_record(i)(anyhow::anyhow!("Content cannot be empty")));
+        }
+        validate_document_fields(&record.title, &record.category, &record_tags(&record.tags))
+            .map_err(in_record(i))?;
+    }
+
+    let config = load_config()?;
+    let root = add_target(&config, corpus)?;
+    ensure_unlocked(&root)?;
+    let (storage, manifest) = add_storage(&config, &root)?;
+
+    // Check each record against the manifest as it will be with the
+    // records before it
+    let mut staged = manifest.clone();
+    for (i, record) in records.iter().enumerate() {
+        let category = existing_category(&staged, &record.category).to_string();
+        let doc_path = new_document_path(
+            &root,
+            &staged,
+            config.corpus.layout,
+            &record.title,
+            &category,
+            reject_duplicate_titles,
+        )
+        .map_err(in_record(i))?;
+        if storage.exists(&doc_path) || staged.find_document_index(&doc_path).is_some() {
+            return Err(in_record(i)(anyhow::anyhow!(
+                "Document already exists: {}",
+                doc_path.display()
+            )));
+        }
+
+        staged.documents.push(Document {
+            path: doc_path.clone(),
+            title: record.title.clone(),
+            category,
+            tags: record_tags(&record.tags),
+            encrypted: config.corpus.encrypted,
+            word_count: Some(count_words(&record.content)),
+            aliases: vec![],
+            content_type: Some(infer_content_type(&doc_path).to_string()),
+        });
+    }
+
+    let added = &staged.documents[manifest.documents.len()..];
+    // Directories the batch will create, deepest first, to remove on failure
+    let mut new_dirs: Vec<PathBuf> = added
+        .iter()
+        .flat_map(|doc| doc.path.ancestors().skip(1))
+        .filter(|dir| !dir.as_os_str().is_empty() && !root.join(dir).exists())
+        .map(Path::to_path_buf)
+        .collect();
+    new_dirs.sort();
+    new_dirs.dedup();
+    new_dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+
+    let mut written = Vec::new();
+    if let Err(e) = write_batch(&storage, records, added, &staged, &mut written) {
+        for path in &written {
+            let _ = std::fs::remove_file(root.join(path));
+        }
+        for dir in &new_dirs {
+            let _ = std::fs::remove_dir(root.join(dir));
+        }
+        return Err(e);
+    }
+
+    let updated = Corpus {
+        root: root.clone(),
+        manifest: staged,
+    };
+    let added = &updated.manifest.documents[manifest.documents.len()..];
+    #[cfg(feature = "ranked")]
+    for doc in added {
+        if config.search.auto_index_on_add {
+            index_added(&updated, &doc.path);
+        } else {
+            overlay::record(&root, &doc.path);
+        }
+    }
+
+    Ok(added
+        .iter()
+        .map(|doc| DocumentInfo {
+            title: doc.title.clone(),
+            category: doc.category.clone(),
+            tags: doc.tags.clone(),
+            path: updated.resolve_document_path(doc),
+            word_count: doc.word_count,
+            content_type: doc.resolved_content_type().to_string(),
+        })
+        .collect())
+}
+
+/// Write a batch's documents into place and then its manifest.
+///
+/// `written` collects the path of every file created, so the caller can
+/// remove them if this fails.
+fn write_batch(
+    storage: &LocalStorageBackend,
+    records: &[DocumentRecord],
+    documents: &[Document],
+    manifest: &Manifest,
+    written: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    for (record, doc) in records.iter().zip(documents) {
+        let name = doc.path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = doc.path.with_file_name(format!(".{name}.tmp"));
+        written.push(temp.clone());
+        storage.write_document(&temp, &record.content)?;
+    }
+
+    let root = storage.root();
+    for (path, doc) in written.iter_mut().zip(documents) {
+        std::fs::rename(root.join(&*path), root.join(&doc.path))?;
+        path.clone_from(&doc.path);
+    }
+
+    storage.write_manifest(manifest)?;
+    Ok(())
+}
+
+/// Create a storage backend that encrypts new documents.
+///
+/// Generates the corpus encryption salt and key check value on first use and
+/// writes them to the manifest, so later appends don't need to rewrite the
+/// manifest header. The passphrase must pass the key check, so documents
+/// are never sealed under a different key than the rest of the corpus.
+/// Corpora encrypted before key checks were recorded get one once the
+/// passphrase decrypts one of their documents.
+#[cfg(feature = "crypto")]
+fn encrypted_storage(
+    root: &Path,
+    manifest: &mut Manifest,
+    format: ManifestFormat,
+) -> anyhow::Result<LocalStorageBackend> {
+    use crate::storage::crypto::{self, Cipher};
+
+    let (salt, is_new) = if let Some(salt) = &manifest.encryption_salt {
+        (crypto::decode_salt(salt)?, false)
+    } else {
+        let salt = Cipher::generate_salt();
+        manifest.encryption_salt = Some(crypto::encode_salt(&salt));
+        (salt, true)
+    };
+
+    let cipher = Cipher::from_env(&salt)?;
+    let record_check = if let Some(check) = &manifest.encryption_check {
+        cipher.verify_key_check(check)?;
+        false
+    } else {
+        let sealed = manifest
+            .documents
+            .iter()
+            .filter(|doc| doc.encrypted)
+            .filter_map(|doc| std::fs::read(root.join(&doc.path)).ok())
+            .find(|data| crypto::is_encrypted(data));
+        if let Some(sealed) = sealed {
+            cipher.decrypt(&sealed)?;
+        }
+        manifest.encryption_check = Some(cipher.key_check()?);
+        true
+    };
+
+    let storage =
+        LocalStorageBackend::with_cipher(root.to_path_buf(), cipher).with_manifest_format(format);
+    if is_new || record_check {
+        storage.write_manifest(manifest)?;
+    }
+    Ok(storage)
+}
+
+/// Encrypted corpora are unavailable without the `crypto` feature.
+#[cfg(not(feature = "crypto"))]
+fn encrypted_storage(
+    _root: &Path,
+    _manifest: &mut Manifest,
+    _format: ManifestFormat,
+) -> anyhow::Result<LocalStorageBackend> {
+    anyhow::bail!("Encrypted corpora require kvault built with the `crypto` feature")
+}
+
+/// Storage for the corpus configured under `[corpus.s3]`, if any.
+///
+/// # Errors
+///
+/// Returns an error if the S3 client cannot be set up.
+#[cfg(feature = "s3")]
+pub fn s3_storage(config: &Config) -> anyhow::Result<Option<Box<dyn StorageBackend>>> {
+    let Some(s3) = &config.corpus.s3 else {
+        return Ok(None);
+    };
+    let storage = S3StorageBackend::new(s3)?.with_manifest_format(config.storage.manifest_format);
+    Ok(Some(Box::new(storage)))
+}
+
+/// S3 corpora are unavailable without the `s3` feature.
+///
+/// # Errors
+///
+/// Returns an error if `[corpus.s3]` is configured.
+#[cfg(not(feature = "s3"))]
+pub fn s3_storage(config: &Config) -> anyhow::Result<Option<Box<dyn StorageBackend>>> {
+    if config.corpus.s3.is_some() {
+        anyhow::bail!("S3 storage requires kvault built with the `s3` feature");
+    }
+    Ok(None)
+}
+
+/// Remove a document: drop its manifest entry and delete its file.
+///
+/// The manifest is rewritten first, so a failed delete leaves only a stray
+/// file rather than a dangling manifest entry. A ranked search index keeps
+/// the document until the next `kvault index`.
+///
+/// # Arguments
+///
+/// * `doc_path` - Relative path to the document (e.g., "aws/lambda-patterns.md")
+///
+/// # Returns
+///
+/// The removed document's metadata.
+///
+/// # Errors
+///
+/// Returns an error if the path escapes a corpus root, the document is not
+/// found in any corpus, its corpus is locked, or the manifest cannot be
+/// written or the file deleted.
+pub fn remove(doc_path: &str) -> anyhow::Result<DocumentInfo> {
+    let config = load_config()?;
+
+    for path_str in &config.corpus.paths {
+        let root = expand_tilde(path_str);
+
+        if !root.exists() {
+            continue;
+        }
+        validate_path_within_root(&root, Path::new(doc_path))?;
+
+        let storage = LocalStorageBackend::new(root.clone())
+            .with_manifest_format(config.storage.manifest_format);
+        let Ok(mut manifest) = storage.read_manifest() else {
+            continue;
+        };
+
+        let Some(index) = manifest.find_document_index(Path::new(doc_path)) else {
+            continue;
+        };
+
+        ensure_unlocked(&root)?;
+        // The entry may name the file differently than `doc_path` does
+        validate_path_within_root(&root, &manifest.documents[index].path)?;
+        let doc = manifest.documents.remove(index);
+
+        storage.write_manifest(&manifest)?;
+        if storage.exists(&doc.path) {
+            storage.delete_document(&doc.path)?;
+        }
+
+        return Ok(DocumentInfo {
+            content_type: doc.resolved_content_type().to_string(),
+            path: root.join(&doc.path),
+            title: doc.title,
+            category: doc.category,
+            tags: doc.tags,
+            word_count: doc.word_count,
+        });
+    }
+
+    anyhow::bail!("Document not found: {doc_path}")
+}
+
+/// Replace a document's content, leaving its manifest entry as it is apart
+/// from a cached word count, which is refreshed.
+///
+/// Encrypted documents are written encrypted again, which requires
+/// `KVAULT_PASSPHRASE`. A ranked search index keeps the old content until
+/// the next `kvault index`.
+///
+/// # Arguments
+///
+/// * `doc_path` - Relative path to the document (e.g., "aws/lambda-patterns.md")
+/// * `content` - The new content
+///
+/// # Returns
+///
+/// The document's metadata, with the new content's word count.
+///
+/// # Errors
+///
+/// Returns an error if the content is empty, the path escapes a corpus root,
+/// the document is not found in any corpus, its corpus is locked, or the
+/// file cannot be written.
+pub fn update(doc_path: &str, content: &str) -> anyhow::Result<DocumentInfo> {
+    if content.trim().is_empty() {
+        anyhow::bail!("Content cannot be empty");
+    }
+
+    let config = load_config()?;
+    let format = config.storage.manifest_format;
+
+    for path_str in &config.corpus.paths {
+        let root = expand_tilde(path_str);
+
+        if !root.exists() {
+            continue;
+        }
+        validate_path_within_root(&root, Path::new(doc_path))?;
+
+        let storage = LocalStorageBackend::new(root.clone()).with_manifest_format(format);
+        let Ok(mut manifest) = storage.read_manifest() else {
+            continue;
+        };
+
+        let Some(index) = manifest.find_document_index(Path::new(doc_path)) else {
+            continue;
+        };
+        let doc = manifest.documents[index].clone();
+
+        ensure_unlocked(&root)?;
+        // The entry may name the file differently than `doc_path` does
+        validate_path_within_root(&root, &doc.path)?;
+
+        let storage = if doc.encrypted {
+            encrypted_storage(&root, &mut manifest, format)?
+        } else {
+            storage
+        };
+        storage.write_document(&doc.path, content)?;
+
+        // Refresh a cached word count; entries without one are counted when
+        // listed
+        let word_count = count_words(content);
+        if doc.word_count.is_some_and(|cached| cached != word_count) {
+            manifest.documents[index].word_count = Some(word_count);
+            storage.write_manifest(&manifest)?;
+        }
+
+        // Let ranked searches in this process find the new content
+        #[cfg(feature = "ranked")]
+        overlay::record(&root, &doc.path);
+
+        return Ok(DocumentInfo {
+            content_type: doc.resolved_content_type().to_string(),
+            path: root.join(&doc.path),
+            title: doc.title,
+            category: doc.category,
+            tags: doc.tags,
+            word_count: Some(word_count),
+        });
+    }
+
+    anyhow::bail!("Document not found: {doc_path}")
+}
+
+/// Add and remove tags on an existing document's manifest entry, writing
+/// the manifest once.
+///
+/// Tags already present are not added again, tags the document doesn't have
+/// are ignored, and a tag in both lists ends up removed. The document content
+/// is not touched.
+///
+/// # Arguments
+///
+/// * `doc_path` - Relative path to the document (e.g., "aws/lambda-patterns.md")
+/// * `add` - Tags to add
+/// * `remove` - Tags to remove
+///
+/// # Returns
+///
+/// The document's updated metadata.
+///
+/// # Errors
+///
+/// Returns an error if a tag to add is invalid, the document is not found in
+/// any corpus, its corpus is locked, or the manifest cannot be written.
+pub fn change_tags(
+    doc_path: &str,
+    add: &[String],
+    remove: &[String],
+) -> anyhow::Result<DocumentInfo> {
+    for tag in add {
+        validate_identifier(tag, "Tag")?;
+    }
+
+    update_tags(doc_path, |existing| {
+        for tag in add {
+            if !existing.iter().any(|t| labels_match(t, tag)) {
+                existing.push(tag.clone());
+            }
+        }
+        existing.retain(|t| !remove.iter().any(|tag| labels_match(t, tag)));
+    })
+}
+
+/// Locate a document's manifest entry, apply `edit` to its tags, and rewrite
+/// the manifest.
+fn update_tags(
+    doc_path: &str,
+    edit: impl FnOnce(&mut Vec<String>),
+) -> anyhow::Result<DocumentInfo> {
+    let config = load_config()?;
+
+    for path_str in &config.corpus.paths {
+        let root = expand_tilde(path_str);
+
+        if !root.exists() {
+            continue;
+        }
+
+        let storage = LocalStorageBackend::new(root.clone())
+            .with_manifest_format(config.storage.manifest_format);
+        let Ok(mut manifest) = storage.read_manifest() else {
+            continue;
+        };
+
+        let Some(index) = manifest.find_document_index(Path::new(doc_path)) else {
+            continue;
+        };
+        let doc = &mut manifest.documents[index];
+
+        ensure_unlocked(&root)?;
+        edit(&mut doc.tags);
+
+        let info = DocumentInfo {
+            title: doc.title.clone(),
+            category: doc.category.clone(),
+            tags: doc.tags.clone(),
+            path: root.join(&doc.path),
+            word_count: doc.word_count,
+            content_type: doc.resolved_content_type().to_string(),
+        };
+
+        // The index holds the old tags until the next reindex
+        #[cfg(feature = "ranked")]
+        overlay::record(&root, &doc.path);
+
+        storage.write_manifest(&manifest)?;
+
+        return Ok(info);
+    }
+
+    anyhow::bail!("Document not found: {doc_path}")
+}
+
+/// Render a configured template as the content for a new document.
+///
+/// Substitutes `{{title}}` with the document title and `{{date}}` with
+/// today's date (UTC, `YYYY-MM-DD`).
+///
+/// # Errors
+///
+/// Returns an error if config loading fails, the template name isn't
+/// configured, or the template file can't be read.
+pub fn render_template(name: &str, title: &str) -> anyhow::Result<String> {
+    let config = load_config()?;
+
+    let Some(path) = config.templates.get(name) else {
+        anyhow::bail!("Unknown template: {name}");
+    };
+
+    let path = expand_tilde(path);
+    let template = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read template {}: {e}", path.display()))?;
+
+    Ok(apply_template(&template, title, &today()))
+}
+
+/// Substitute template placeholders.
+fn apply_template(template: &str, title: &str, date: &str) -> String {
+    template
+        .replace("{{title}}", title)
+        .replace("{{date}}", date)
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`.
+fn today() -> String {
+    let (year, month, day) = today_civil();
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Today's UTC date as (year, month, day).
+fn today_civil() -> (u64, u64, u64) {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    civil_from_days(secs / 86_400)
+}
+
+/// Convert days since the Unix epoch to a (year, month, day) civil date.
+///
+/// Uses Howard Hinnant's `civil_from_days` algorithm, restricted to dates
+/// on or after 1970-01-01.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Categories assigned round-robin to synthetic documents.
+const SYNTHETIC_CATEGORIES: [&str; 4] = ["aws", "rust", "devops", "python"];
+
+/// Vocabulary synthetic document bodies are drawn from.
+const SYNTHETIC_WORDS: [&str; 32] = [
+    "lambda",
+    "function",
+    "deploy",
+    "cluster",
+    "error",
+    "result",
+    "option",
+    "trait",
+    "async",
+    "await",
+    "bucket",
+    "queue",
+    "retry",
+    "timeout",
+    "config",
+    "secret",
+    "index",
+    "query",
+    "cache",
+    "latency",
+    "throughput",
+    "pipeline",
+    "container",
+    "service",
+    "handler",
+    "request",
+    "response",
+    "schema",
+    "migration",
+    "rollback",
+    "metric",
+    "alert",
+];
+
+/// Words per synthetic document body.
+const SYNTHETIC_BODY_WORDS: usize = 200;
+
+/// Generate a corpus of `count` synthetic documents under `root` and load it.
+///
+/// Content is deterministic for a given `count`: document `i` is
+/// `{category}/doc-{i}.md`, titled `Synthetic Document {i}`, with a body of
+/// words drawn pseudo-randomly from a fixed vocabulary (which includes
+/// `lambda`). Used by benchmarks and tests that need a corpus of a given size.
+///
+/// # Errors
+///
+/// Returns an error if the documents or manifest cannot be written.
+pub fn generate_synthetic_corpus(root: &Path, count: usize) -> anyhow::Result<Corpus> {
+    let storage = LocalStorageBackend::new(root.to_path_buf());
+    let mut manifest = Manifest::empty();
+
+    // xorshift64: fast, dependency-free, and reproducible across runs
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut next_word = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        SYNTHETIC_WORDS[usize::try_from(state % SYNTHETIC_WORDS.len() as u64).unwrap_or(0)]
+    };
+
+    for i in 0..count {
+        let category = SYNTHETIC_CATEGORIES[i % SYNTHETIC_CATEGORIES.len()];
+        let title = format!("Synthetic Document {i}");
+        let path = PathBuf::from(category).join(format!("doc-{i}.md"));
+
+        let mut content = format!("# {title}\n");
+        for line in 0..SYNTHETIC_BODY_WORDS / 10 {
+            content.push('\n');
+            for word in 0..10 {
+                if word > 0 {
+                    content.push(' ');
+                }
+                content.push_str(next_word());
+            }
+            if line % 5 == 4 {
+                content.push('\n');
+            }
+        }
+
+        storage.write_document(&path, &content)?;
+        manifest.documents.push(Document {
+            path,
+            title,
+            category: category.to_string(),
+            tags: vec![category.to_string()],
+            encrypted: false,
+            word_count: Some(count_words(&content)),
+            aliases: vec![],
+            content_type: None,
+        });
+    }
+
+    storage.write_manifest(&manifest)?;
+    Ok(Corpus::load(root)?)
+}
+
+/// Count whitespace-separated words in document content.
+fn count_words(content: &str) -> usize {
+    content.split_whitespace().count()
+}
+
+/// Convert a title to a URL-safe slug.
+fn slugify(title: &str) -> String {
+    title
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod slugify_tests {
+        use super::*;
+
+        #[test]
+        fn simple_title() {
+            assert_eq!(slugify("Hello World"), "hello-world");
+        }
+
+        #[test]
+        fn title_with_special_chars() {
+            assert_eq!(
+                slugify("AWS Lambda: Best Practices!"),
+                "aws-lambda-best-practices"
+            );
+        }
+
+        #[test]
+        fn title_with_numbers() {
+            assert_eq!(slugify("Top 10 Rust Tips"), "top-10-rust-tips");
+        }
+
+        #[test]
+        fn title_with_multiple_spaces() {
+            assert_eq!(slugify("Hello    World"), "hello-world");
+        }
+
+        #[test]
+        fn empty_title() {
+            assert_eq!(slugify(""), "");
+        }
+
+        #[test]
+        fn unicode_title() {
+            // Unicode alphanumeric chars are preserved
+            assert_eq!(slugify("Café"), "café");
+        }
+    }
+
+    mod validate_identifier_tests {
+        use super::*;
+
+        #[test]
+        fn valid_identifier() {
+            assert!(validate_identifier("aws", "Category").is_ok());
+            assert!(validate_identifier("rust-tips", "Category").is_ok());
+            assert!(validate_identifier("my_category", "Category").is_ok());
+            assert!(validate_identifier("Category123", "Category").is_ok());
+        }
+
+        #[test]
+        fn empty_identifier() {
+            let result = validate_identifier("", "Category");
+            assert!(result.is_err());
+            assert!(result.unwrap_err().to_string().contains("cannot be empty"));
+        }
+
+        #[test]
+        fn identifier_with_invalid_chars() {
+            let result = validate_identifier("aws/lambda", "Category");
+            assert!(result.is_err());
+            assert!(
+                result
+                    .unwrap_err()
+                    .to_string()
+                    .contains("invalid character")
+            );
+        }
+
+        #[test]
+        fn identifier_starting_with_hyphen() {
+            let result = validate_identifier("-invalid", "Category");
+            assert!(result.is_err());
+            assert!(result.unwrap_err().to_string().contains("must start with"));
+        }
+
+        #[test]
+        fn identifier_with_spaces() {
+            let result = validate_identifier("my category", "Category");
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn identifier_too_long() {
+            let long_value = "a".repeat(MAX_INPUT_LENGTH + 1);
+            let result = validate_identifier(&long_value, "Category");
+            assert!(result.is_err());
+            assert!(result.unwrap_err().to_string().contains("too long"));
+        }
+
+        #[test]
+        fn identifier_with_path_traversal() {
+            let result = validate_identifier("../etc", "Category");
+            assert!(result.is_err());
+        }
+    }
+
+    mod compare_results_tests {
+        use super::*;
+        use crate::search::Snippet;
+
+        fn result(title: &str, path: &str, line_number: usize, score: Option<f32>) -> SearchResult {
+            SearchResult {
+                path: PathBuf::from(path),
+                title: title.to_string(),
+                category: "test".to_string(),
+                tags: vec![],
+                snippet: Some(Snippet {
+                    text: String::new(),
+                    highlights: vec![],
+                    line: Some(line_number),
+                }),
+                score,
+                hit_count: None,
+                explanation: None,
+            }
+        }
+
+        fn sorted_titles(mut results: Vec<SearchResult>) -> Vec<String> {
+            results.sort_by(compare_results);
+            results.into_iter().map(|r| r.title).collect()
+        }
+
+        #[test]
+        fn equal_scores_sort_by_title_regardless_of_input_order() {
+            let a = result("Alpha", "/c/z.md", 1, Some(1.5));
+            let b = result("Beta", "/c/a.md", 1, Some(1.5));
+            let top = result("Zed", "/c/m.md", 1, Some(2.0));
+
+            let forward = sorted_titles(vec![a.clone(), b.clone(), top.clone()]);
+            let reverse = sorted_titles(vec![b, top, a]);
+
+            assert_eq!(forward, ["Zed", "Alpha", "Beta"]);
+            assert_eq!(forward, reverse);
+        }
+
+        #[test]
+        fn equal_titles_sort_by_path() {
+            let mut results = [
+                result("Same", "/c/b.md", 1, Some(1.0)),
+                result("Same", "/c/a.md", 1, Some(1.0)),
+            ];
+            results.sort_by(compare_results);
+            assert_eq!(results[0].path, PathBuf::from("/c/a.md"));
+        }
+
+        #[test]
+        fn unscored_results_follow_cross_corpus_order() {
+            let per_corpus = || {
+                vec![
+                    (
+                        PathBuf::from("/z"),
+                        vec![
+                            result("Delta", "/z/d.md", 1, None),
+                            result("Bravo", "/z/b.md", 1, None),
+                        ],
+                    ),
+                    (
+                        PathBuf::from("/a"),
+                        vec![
+                            result("Charlie", "/a/c.md", 1, None),
+                            result("Alpha", "/a/a.md", 1, None),
+                        ],
+                    ),
+                ]
+            };
+            let titles = |order| -> Vec<String> {
+                merge_results(per_corpus(), order)
+                    .into_iter()
+                    .map(|r| r.title)
+                    .collect()
+            };
+
+            assert_eq!(
+                titles(CrossOrder::ConfigOrder),
+                ["Bravo", "Delta", "Alpha", "Charlie"]
+            );
+            assert_eq!(
+                titles(CrossOrder::Title),
+                ["Alpha", "Bravo", "Charlie", "Delta"]
+            );
+            assert_eq!(
+                titles(CrossOrder::Corpus),
+                ["Alpha", "Charlie", "Bravo", "Delta"]
+            );
+        }
+
+        #[test]
+        fn dedupe_keeps_first_corpus_copy() {
+            let mut per_corpus = vec![
+                (
+                    PathBuf::from("/a"),
+                    vec![
+                        result("Doc", "/a/notes/doc.md", 1, None),
+                        result("Doc", "/a/notes/doc.md", 4, None),
+                    ],
+                ),
+                (
+                    PathBuf::from("/b"),
+                    vec![
+                        result("Doc", "/b/notes/doc.md", 1, None),
+                        result("Renamed", "/b/notes/doc.md", 2, None),
+                    ],
+                ),
+            ];
+            dedupe_results(&mut per_corpus);
+
+            assert_eq!(per_corpus[0].1.len(), 2);
+            let titles: Vec<&str> = per_corpus[1].1.iter().map(|r| r.title.as_str()).collect();
+            assert_eq!(titles, ["Renamed"]);
+        }
+
+        #[test]
+        fn scored_results_ignore_cross_corpus_order() {
+            let per_corpus = vec![
+                (
+                    PathBuf::from("/a"),
+                    vec![result("Low", "/a/l.md", 1, Some(1.0))],
+                ),
+                (
+                    PathBuf::from("/b"),
+                    vec![result("High", "/b/h.md", 1, Some(2.0))],
+                ),
+            ];
+            let titles: Vec<String> = merge_results(per_corpus, CrossOrder::ConfigOrder)
+                .into_iter()
+                .map(|r| r.title)
+                .collect();
+            assert_eq!(titles, ["High", "Low"]);
+        }
+
+        #[test]
+        fn scores_are_normalized_per_corpus_before_merging() {
+            // The small corpus's best match scores low only because its
+            // index gives the term a low IDF
+            let per_corpus = vec![
+                (
+                    PathBuf::from("/large"),
+                    vec![
+                        result("Large One", "/large/1.md", 1, Some(12.0)),
+                        result("Large Two", "/large/2.md", 1, Some(9.0)),
+                        result("Large Three", "/large/3.md", 1, Some(6.0)),
+                    ],
+                ),
+                (
+                    PathBuf::from("/small"),
+                    vec![
+                        result("Small Best", "/small/1.md", 1, Some(3.0)),
+                        result("Small Other", "/small/2.md", 1, Some(1.5)),
+                    ],
+                ),
+            ];
+            let merged = merge_results(per_corpus, CrossOrder::ConfigOrder);
+            let titles: Vec<&str> = merged.iter().map(|r| r.title.as_str()).collect();
+
+            assert_eq!(
+                titles,
+                [
+                    "Large One",
+                    "Small Best",
+                    "Large Two",
+                    "Large Three",
+                    "Small Other"
+                ]
+            );
+            assert_eq!(merged[1].score, Some(1.0));
+            assert_eq!(merged[2].score, Some(0.75));
+        }
+
+        #[test]
+        fn single_corpus_keeps_raw_scores() {
+            let mut explained = result("Doc", "/a/d.md", 1, Some(4.0));
+            explained.explanation = Some("4.0000 total".to_string());
+            let per_corpus = vec![
+                (PathBuf::from("/a"), vec![explained]),
+                (
+                    PathBuf::from("/b"),
+                    vec![result("Plain", "/b/p.md", 1, None)],
+                ),
+            ];
+
+            let merged = merge_results(per_corpus, CrossOrder::ConfigOrder);
+            assert_eq!(merged[0].score, Some(4.0));
+            assert_eq!(merged[0].explanation.as_deref(), Some("4.0000 total"));
+        }
+
+        #[test]
+        fn normalized_explanations_show_the_division() {
+            let mut top = result("Top", "/a/t.md", 1, Some(4.0));
+            top.explanation = Some("4.0000 total\n  2.0000 part".to_string());
+            let mut per_corpus = vec![
+                (PathBuf::from("/a"), vec![top]),
+                (
+                    PathBuf::from("/b"),
+                    vec![result("Other", "/b/o.md", 1, Some(1.0))],
+                ),
+            ];
+
+            normalize_scores(&mut per_corpus);
+            assert_eq!(
+                per_corpus[0].1[0].explanation.as_deref(),
+                Some(
+                    "1.0000 normalized: divided by the corpus's top score 4.0000\n  \
+                     4.0000 total\n    2.0000 part"
+                )
+            );
+        }
+
+        #[test]
+        fn sole_weak_match_scores_one() {
+            let mut per_corpus = vec![
+                (
+                    PathBuf::from("/a"),
+                    vec![
+                        result("Strong", "/a/s.md", 1, Some(8.0)),
+                        result("Weaker", "/a/w.md", 1, Some(2.0)),
+                    ],
+                ),
+                (
+                    PathBuf::from("/b"),
+                    vec![result("Weak", "/b/w.md", 1, Some(0.5))],
+                ),
+            ];
+
+            normalize_scores(&mut per_corpus);
+            let scores: Vec<_> = per_corpus
+                .iter()
+                .flat_map(|(_, results)| results.iter().map(|result| result.score))
+                .collect();
+            assert_eq!(scores, [Some(1.0), Some(0.25), Some(1.0)]);
+        }
+
+        #[test]
+        fn unscored_matches_keep_line_order_within_document() {
+            let mut results = [
+                result("Doc", "/c/doc.md", 7, None),
+                result("Doc", "/c/doc.md", 2, None),
+            ];
+            results.sort_by(compare_results);
+            assert_eq!(
+                results.map(|result| result.line_number()),
+                [Some(2), Some(7)]
+            );
+        }
+    }
+
+    mod truncate_content_tests {
+        use super::*;
+
+        #[test]
+        fn short_content_unchanged() {
+            assert_eq!(truncate_content("hello".to_string(), 10), "hello");
+            assert_eq!(truncate_content("hello".to_string(), 5), "hello");
+        }
+
+        #[test]
+        fn long_content_truncated_with_marker() {
+            let content = "a".repeat(1000);
+            let truncated = truncate_content(content, 100);
+            assert_eq!(truncated, format!("{}{TRUNCATION_MARKER}", "a".repeat(100)));
+        }
+
+        #[test]
+        fn cut_backs_off_to_char_boundary() {
+            // "é" is two bytes; a limit of 2 lands inside it
+            let truncated = truncate_content("aébc".to_string(), 2);
+            assert_eq!(truncated, format!("a{TRUNCATION_MARKER}"));
+        }
+
+        #[test]
+        fn zero_limit_keeps_only_marker() {
+            assert_eq!(truncate_content("abc".to_string(), 0), TRUNCATION_MARKER);
+        }
+    }
+
+    mod case_collision_tests {
+        use super::*;
+
+        fn manifest() -> Manifest {
+            let mut manifest = Manifest::empty();
+            manifest.documents.push(Document {
+                path: PathBuf::from("aws/lambda.md"),
+                title: "Lambda".to_string(),
+                category: "aws".to_string(),
+                tags: vec![],
+                encrypted: false,
+                word_count: None,
+                aliases: vec![],
+                content_type: None,
+            });
+            manifest
+        }
+
+        #[test]
+        fn duplicate_title_matches_within_category_only() {
+            let manifest = manifest();
+            let existing = &manifest.documents[0];
+
+            let found = find_duplicate_title(
+                &manifest,
+                &existing.title.to_uppercase(),
+                &existing.category,
+            );
+            assert_eq!(found.map(|doc| &doc.path), Some(&existing.path));
+            assert!(find_duplicate_title(&manifest, &existing.title, "elsewhere").is_none());
+            assert!(
+                find_duplicate_title(&manifest, "Something Else", &existing.category).is_none()
+            );
+        }
+
+        #[test]
+        fn category_differing_in_case_collides() {
+            let collision =
+                find_case_collision(&manifest(), "AWS", Path::new("AWS/s3.md")).unwrap();
+            assert!(collision.contains("Category 'AWS' collides with existing category 'aws'"));
+        }
+
+        #[test]
+        fn path_differing_in_case_collides() {
+            let collision =
+                find_case_collision(&manifest(), "aws", Path::new("aws/Lambda.md")).unwrap();
+            assert!(collision.contains("'aws/Lambda.md' collides with existing 'aws/lambda.md'"));
+        }
+
+        #[test]
+        fn same_or_unrelated_names_do_not_collide() {
+            assert!(find_case_collision(&manifest(), "aws", Path::new("aws/s3.md")).is_none());
+            assert!(find_case_collision(&manifest(), "rust", Path::new("rust/a.md")).is_none());
+        }
 
-    // Validate the constructed path is safe
-    validate_path_within_root(&root, &doc_path)?;
+        #[test]
+        fn case_check_writes_nothing() {
+            let temp_dir = tempfile::TempDir::new().unwrap();
+            assert!(!is_case_insensitive_fs(temp_dir.path()));
+            assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
 
-    if storage.exists(&doc_path) {
-        anyhow::bail!("Document already exists: {}", doc_path.display());
+            std::fs::write(temp_dir.path().join("manifest.json"), "{}").unwrap();
+            assert_eq!(
+                is_case_insensitive_fs(temp_dir.path()),
+                temp_dir.path().join("MANIFEST.JSON").exists()
+            );
+            assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+        }
     }
 
-    storage.write_document(&doc_path, content)?;
+    mod duplicates_tests {
+        use super::*;
 
-    let document = Document {
-        path: doc_path.clone(),
-        title: title.to_string(),
-        category: category.to_string(),
-        tags: tags.clone(),
-    };
+        fn contents(texts: &[&str]) -> Vec<(usize, String)> {
+            texts
+                .iter()
+                .enumerate()
+                .map(|(index, text)| (index, (*text).to_string()))
+                .collect()
+        }
 
-    manifest.documents.push(document);
-    storage.write_manifest(&manifest)?;
+        #[test]
+        fn groups_identical_content_in_manifest_order() {
+            let contents = contents(&["a", "b", "a", "c", "b", "a"]);
+            assert_eq!(
+                exact_duplicate_groups(&contents),
+                vec![vec![0, 2, 5], vec![1, 4]]
+            );
+        }
 
-    Ok(DocumentInfo {
-        title: title.to_string(),
-        category: category.to_string(),
-        tags,
-        path: root.join(&doc_path),
-    })
-}
+        #[test]
+        fn unique_content_has_no_groups() {
+            assert!(exact_duplicate_groups(&contents(&["a", "b"])).is_empty());
+        }
 
-/// Convert a title to a URL-safe slug.
-fn slugify(title: &str) -> String {
-    title
-        .to_lowercase()
-        .chars()
-        .map(|c| if c.is_alphanumeric() { c } else { '-' })
-        .collect::<String>()
-        .split('-')
-        .filter(|s| !s.is_empty())
-        .collect::<Vec<_>>()
-        .join("-")
-}
+        #[cfg(feature = "similarity")]
+        #[test]
+        fn clusters_similar_content_transitively() {
+            let contents = contents(&[
+                "the quick brown fox jumps over the lazy dog",
+                "the quick brown fox jumps over the lazy cat",
+                "the quick brown fox jumps over the lazy dog",
+                "completely unrelated text about rust lifetimes",
+            ]);
+            let exact = exact_duplicate_groups(&contents);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+            assert_eq!(similar_groups(&contents, &exact, 0.7), vec![vec![0, 1]]);
+            assert!(similar_groups(&contents, &exact, 0.9).is_empty());
+        }
 
-    mod slugify_tests {
+        #[cfg(feature = "similarity")]
+        #[test]
+        fn jaccard_ignores_case() {
+            let a = shingles("One two three four");
+            let b = shingles("one TWO three five");
+            assert!((jaccard(&a, &b) - 1.0 / 3.0).abs() < f64::EPSILON);
+            assert!((jaccard(&shingles("x"), &shingles("X")) - 1.0).abs() < f64::EPSILON);
+        }
+    }
+
+    mod line_range_tests {
         use super::*;
 
-        #[test]
-        fn simple_title() {
-            assert_eq!(slugify("Hello World"), "hello-world");
+        const CONTENT: &str = "one\ntwo\nthree\nfour\nfive\n";
+
+        fn range(s: &str) -> LineRange {
+            s.parse().unwrap()
         }
 
         #[test]
-        fn title_with_special_chars() {
-            assert_eq!(
-                slugify("AWS Lambda: Best Practices!"),
-                "aws-lambda-best-practices"
-            );
+        fn parses_start_end() {
+            assert_eq!(range("2:4"), LineRange { start: 2, end: 4 });
+            assert_eq!(range(" 3 : 3 "), LineRange { start: 3, end: 3 });
         }
 
         #[test]
-        fn title_with_numbers() {
-            assert_eq!(slugify("Top 10 Rust Tips"), "top-10-rust-tips");
+        fn rejects_invalid_ranges() {
+            for s in ["4", "0:2", "4:2", "a:2", "1:", ":3", "-1:2"] {
+                assert!(s.parse::<LineRange>().is_err(), "{s}");
+            }
         }
 
         #[test]
-        fn title_with_multiple_spaces() {
-            assert_eq!(slugify("Hello    World"), "hello-world");
+        fn selects_mid_range() {
+            assert_eq!(select_lines(CONTENT, range("2:4")), "two\nthree\nfour\n");
+            assert_eq!(select_lines(CONTENT, range("3:3")), "three\n");
         }
 
         #[test]
-        fn empty_title() {
-            assert_eq!(slugify(""), "");
+        fn clamps_end_past_last_line() {
+            assert_eq!(select_lines(CONTENT, range("4:100")), "four\nfive\n");
+            assert_eq!(select_lines("a\nb", range("2:9")), "b");
         }
 
         #[test]
-        fn unicode_title() {
-            // Unicode alphanumeric chars are preserved
-            assert_eq!(slugify("Café"), "café");
+        fn start_past_last_line_is_empty() {
+            assert_eq!(select_lines(CONTENT, range("6:9")), "");
         }
     }
 
-    mod validate_identifier_tests {
+    mod template_tests {
         use super::*;
 
         #[test]
-        fn valid_identifier() {
-            assert!(validate_identifier("aws", "Category").is_ok());
-            assert!(validate_identifier("rust-tips", "Category").is_ok());
-            assert!(validate_identifier("my_category", "Category").is_ok());
-            assert!(validate_identifier("Category123", "Category").is_ok());
+        fn substitutes_placeholders() {
+            let rendered = apply_template(
+                "# {{title}}\n\nCreated {{date}}. {{title}} notes.",
+                "Lambda",
+                "2024-01-02",
+            );
+            assert_eq!(rendered, "# Lambda\n\nCreated 2024-01-02. Lambda notes.");
         }
 
         #[test]
-        fn empty_identifier() {
-            let result = validate_identifier("", "Category");
-            assert!(result.is_err());
-            assert!(result.unwrap_err().to_string().contains("cannot be empty"));
+        fn leaves_unknown_placeholders() {
+            assert_eq!(apply_template("{{author}}", "T", "D"), "{{author}}");
         }
 
         #[test]
-        fn identifier_with_invalid_chars() {
-            let result = validate_identifier("aws/lambda", "Category");
-            assert!(result.is_err());
-            assert!(
-                result
-                    .unwrap_err()
-                    .to_string()
-                    .contains("invalid character")
-            );
+        fn civil_dates() {
+            assert_eq!(civil_from_days(0), (1970, 1, 1));
+            assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+            assert_eq!(civil_from_days(19_723), (2024, 1, 1));
         }
+    }
 
-        #[test]
-        fn identifier_starting_with_hyphen() {
-            let result = validate_identifier("-invalid", "Category");
-            assert!(result.is_err());
-            assert!(result.unwrap_err().to_string().contains("must start with"));
+    mod generate_index_tests {
+        use super::*;
+
+        fn info(title: &str, category: &str, path: &str) -> DocumentInfo {
+            DocumentInfo {
+                title: title.to_string(),
+                category: category.to_string(),
+                tags: vec![],
+                path: PathBuf::from(path),
+                word_count: None,
+                content_type: "text/markdown".to_string(),
+            }
         }
 
         #[test]
-        fn identifier_with_spaces() {
-            let result = validate_identifier("my category", "Category");
-            assert!(result.is_err());
+        fn groups_by_category_and_sorts_by_title() {
+            let documents = [
+                info("Zebra", "rust", "/vault/rust/zebra.md"),
+                info("Lambda", "aws", "/vault/aws/lambda.md"),
+                info("Alpha", "rust", "/other/rust/alpha notes.md"),
+            ];
+            let markdown = render_index(&documents, Path::new("/vault")).unwrap();
+            assert_eq!(
+                markdown,
+                "# Index\n\
+                 \n## aws\n\n\
+                 - [Lambda](<aws/lambda.md>)\n\
+                 \n## rust\n\n\
+                 - [Alpha](<../other/rust/alpha notes.md>)\n\
+                 - [Zebra](<rust/zebra.md>)\n"
+            );
         }
 
         #[test]
-        fn identifier_too_long() {
-            let long_value = "a".repeat(MAX_INPUT_LENGTH + 1);
-            let result = validate_identifier(&long_value, "Category");
-            assert!(result.is_err());
-            assert!(result.unwrap_err().to_string().contains("too long"));
+        fn groups_categories_ignoring_case() {
+            let documents = [
+                info("Lambda", "AWS", "/vault/AWS/lambda.md"),
+                info("Billing", "aws", "/vault/aws/billing.md"),
+            ];
+            let markdown = render_index(&documents, Path::new("/vault")).unwrap();
+            assert_eq!(
+                markdown,
+                "# Index\n\
+                 \n## AWS\n\n\
+                 - [Billing](<aws/billing.md>)\n\
+                 - [Lambda](<AWS/lambda.md>)\n"
+            );
         }
 
         #[test]
-        fn identifier_with_path_traversal() {
-            let result = validate_identifier("../etc", "Category");
-            assert!(result.is_err());
+        fn escapes_link_text() {
+            let documents = [info(
+                r"Arrays [a] (and \ slices)",
+                "rust",
+                "/vault/rust/arrays.md",
+            )];
+            let markdown = render_index(&documents, Path::new("/vault")).unwrap();
+            assert!(
+                markdown.contains(r"- [Arrays \[a\] \(and \\ slices\)](<rust/arrays.md>)"),
+                "{markdown}"
+            );
         }
     }
 
@@ -647,4 +4350,342 @@ mod tests {
             );
         }
     }
+
+    mod frontmatter_tests {
+        use super::*;
+
+        #[test]
+        fn full_frontmatter_is_parsed_and_stripped() {
+            let content = "---\ntitle: Cold Starts\ncategory: aws\ntags:\n  - lambda\n  - \" latency \"\nauthor: me\n---\n\n# Cold Starts\n";
+
+            let (frontmatter, body) = split_frontmatter(content).unwrap();
+
+            assert_eq!(
+                frontmatter,
+                Frontmatter {
+                    title: Some("Cold Starts".to_string()),
+                    category: Some("aws".to_string()),
+                    tags: vec!["lambda".to_string(), "latency".to_string()],
+                }
+            );
+            assert_eq!(body, "# Cold Starts\n");
+        }
+
+        #[test]
+        fn partial_frontmatter_leaves_fields_unset() {
+            let content =
+                "---\r\ntitle: Borrowing\r\ntags: ownership, lifetimes\r\n...\r\nBody\r\n";
+
+            let (frontmatter, body) = split_frontmatter(content).unwrap();
+
+            assert_eq!(frontmatter.title.as_deref(), Some("Borrowing"));
+            assert_eq!(frontmatter.category, None);
+            assert_eq!(frontmatter.tags, ["ownership", "lifetimes"]);
+            assert_eq!(body, "Body\r\n");
+        }
+
+        #[test]
+        fn content_without_frontmatter_is_unchanged() {
+            for content in [
+                "# Title\n\n---\n\nAfter a rule.\n",
+                "--- not a delimiter\ntitle: x\n---\n",
+                "---\ntitle: Unterminated\n",
+                "",
+            ] {
+                let (frontmatter, body) = split_frontmatter(content).unwrap();
+                assert_eq!(frontmatter, Frontmatter::default());
+                assert_eq!(body, content);
+            }
+
+            let (frontmatter, body) = split_frontmatter("---\n---\nBody").unwrap();
+            assert_eq!(frontmatter, Frontmatter::default());
+            assert_eq!(body, "Body");
+        }
+
+        #[test]
+        fn body_between_rules_is_not_frontmatter() {
+            for content in [
+                "---\nIntro paragraph.\n---\nMore text.\n",
+                "---\n- first point\n- second point\n---\n",
+                "---\nSee the notes below: they matter.\n---\nNotes.\n",
+            ] {
+                let (frontmatter, body) = split_frontmatter(content).unwrap();
+                assert_eq!(frontmatter, Frontmatter::default());
+                assert_eq!(body, content);
+            }
+        }
+
+        #[test]
+        fn invalid_frontmatter_is_an_error() {
+            let err = split_frontmatter("---\ntitle: [unclosed\n---\nBody").unwrap_err();
+            assert!(err.to_string().contains("Invalid frontmatter"));
+        }
+    }
+
+    mod backend_for_tests {
+        use super::*;
+
+        fn corpus(temp_dir: &tempfile::TempDir) -> Corpus {
+            Corpus {
+                root: temp_dir.path().to_path_buf(),
+                manifest: Manifest::empty(),
+            }
+        }
+
+        fn name(corpus: &Corpus, backend: Backend, rg: &RipgrepBackend) -> &'static str {
+            backend_for(corpus, backend, rg, &SearchOptions::default(), usize::MAX)
+                .unwrap()
+                .name()
+        }
+
+        #[test]
+        fn uses_ripgrep_without_index() {
+            let temp_dir = tempfile::TempDir::new().unwrap();
+            let corpus = corpus(&temp_dir);
+            let rg = RipgrepBackend::new();
+
+            assert_eq!(name(&corpus, Backend::Ripgrep, &rg), "ripgrep");
+            assert_eq!(name(&corpus, Backend::Auto, &rg), "ripgrep");
+        }
+
+        #[cfg(feature = "ranked")]
+        #[test]
+        fn ranked_requires_index() {
+            let temp_dir = tempfile::TempDir::new().unwrap();
+            let corpus = corpus(&temp_dir);
+            let rg = RipgrepBackend::new();
+
+            let error = backend_for(
+                &corpus,
+                Backend::Ranked,
+                &rg,
+                &SearchOptions::default(),
+                usize::MAX,
+            )
+            .err()
+            .unwrap();
+            assert!(error.to_string().contains("No index found"));
+        }
+
+        /// A ripgrep backend running a script in `dir` that only reports a
+        /// version, so choosing a backend doesn't depend on `rg` in PATH.
+        #[cfg(all(feature = "ranked", unix))]
+        fn fake_ripgrep(dir: &Path) -> RipgrepBackend {
+            use std::os::unix::fs::PermissionsExt;
+
+            let script = dir.join("fake-rg");
+            std::fs::write(&script, "#!/bin/sh\necho 'ripgrep 14.0.0'\n").unwrap();
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+            RipgrepBackend::with_binary(script)
+        }
+
+        #[cfg(all(feature = "ranked", unix))]
+        #[test]
+        fn uses_index_when_built() {
+            let temp_dir = tempfile::TempDir::new().unwrap();
+            let corpus = corpus(&temp_dir);
+            TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite)
+                .unwrap()
+                .index_corpus(&corpus)
+                .unwrap();
+            let bin_dir = tempfile::TempDir::new().unwrap();
+            let rg = fake_ripgrep(bin_dir.path());
+
+            assert_eq!(name(&corpus, Backend::Ranked, &rg), "ranked");
+            assert_eq!(name(&corpus, Backend::Auto, &rg), "ranked");
+            assert_eq!(name(&corpus, Backend::Ripgrep, &rg), "ripgrep");
+
+            // Only ripgrep can search within a file list
+            let within = SearchOptions {
+                within: Some(vec![]),
+                ..Default::default()
+            };
+            let backend = backend_for(&corpus, Backend::Auto, &rg, &within, usize::MAX).unwrap();
+            assert_eq!(backend.name(), "ripgrep");
+
+            // Missing ripgrep falls back to the index
+            let missing = RipgrepBackend::with_binary(temp_dir.path().join("no-such-rg"));
+            assert_eq!(name(&corpus, Backend::Ripgrep, &missing), "ranked");
+        }
+    }
+
+    mod add_target_tests {
+        use super::*;
+
+        fn config(paths: &[&str]) -> Config {
+            let mut config = Config::default();
+            config.corpus.paths = paths.iter().map(ToString::to_string).collect();
+            config
+        }
+
+        fn error(config: &Config, corpus: Option<&str>) -> String {
+            add_target(config, corpus).unwrap_err().to_string()
+        }
+
+        #[test]
+        fn defaults_only_to_a_single_corpus() {
+            assert_eq!(
+                add_target(&config(&["/kb/notes"]), None).unwrap(),
+                PathBuf::from("/kb/notes")
+            );
+            assert!(error(&config(&[]), None).contains("No corpus path configured"));
+            assert!(
+                error(&config(&["/a/notes", "/b/work"]), None)
+                    .contains("Several corpora are configured")
+            );
+        }
+
+        #[test]
+        fn selects_by_path_or_unique_name() {
+            let config = config(&["/a/notes", "/b/notes", "/c/work"]);
+
+            assert_eq!(
+                add_target(&config, Some("work")).unwrap(),
+                PathBuf::from("/c/work")
+            );
+            assert_eq!(
+                add_target(&config, Some("/b/notes")).unwrap(),
+                PathBuf::from("/b/notes")
+            );
+            assert!(error(&config, Some("notes")).contains("matches several corpora"));
+            assert!(error(&config, Some("/d/other")).contains("Not a configured corpus"));
+        }
+    }
+
+    mod check_query_length_tests {
+        use super::*;
+
+        #[test]
+        fn rejects_short_queries_after_trimming() {
+            let error = check_query_length(" x ", 2).unwrap_err();
+            assert!(error.to_string().contains("--force"));
+            assert!(check_query_length("\u{e9}", 2).is_err());
+            assert!(check_query_length("ok", 2).is_ok());
+        }
+
+        #[test]
+        fn allows_empty_queries_and_zero_minimum() {
+            assert!(check_query_length("", 2).is_ok());
+            assert!(check_query_length("  ", 2).is_ok());
+            assert!(check_query_length("x", 0).is_ok());
+        }
+    }
+
+    mod normalize_query_tests {
+        use super::*;
+
+        #[test]
+        fn trims_and_collapses_whitespace() {
+            assert_eq!(
+                normalize_query("  lambda \t cold\n start "),
+                "lambda cold start"
+            );
+            assert_eq!(normalize_query(" \t "), "");
+        }
+
+        #[test]
+        fn straightens_curly_quotes() {
+            assert_eq!(
+                normalize_query("  \u{201C}don\u{2019}t panic\u{201D}"),
+                "\"don't panic\""
+            );
+        }
+
+        #[test]
+        fn applies_only_when_configured() {
+            let mut config = Config::default();
+            assert_eq!(
+                prepare_query(&config, " \u{2018}x\u{2019} "),
+                " \u{2018}x\u{2019} "
+            );
+
+            config.search.normalize_query = true;
+            assert_eq!(prepare_query(&config, " \u{2018}x\u{2019} "), "'x'");
+        }
+    }
+
+    mod tag_cooccurrence_tests {
+        use super::*;
+
+        fn tagged(path: &str, tags: &[&str]) -> DocumentInfo {
+            DocumentInfo {
+                title: path.to_string(),
+                category: "notes".to_string(),
+                tags: tags.iter().map(ToString::to_string).collect(),
+                path: PathBuf::from(path),
+                word_count: None,
+                content_type: "text/markdown".to_string(),
+            }
+        }
+
+        fn pair(first: &str, second: &str, count: usize) -> ((String, String), usize) {
+            ((first.to_string(), second.to_string()), count)
+        }
+
+        #[test]
+        fn counts_shared_pairs_most_frequent_first() {
+            let documents = [
+                tagged("a.md", &["lambda", "aws"]),
+                tagged("b.md", &["aws", "lambda", "python"]),
+                tagged("c.md", &["rust"]),
+            ];
+            assert_eq!(
+                count_tag_pairs(&documents),
+                [
+                    pair("aws", "lambda", 2),
+                    pair("aws", "python", 1),
+                    pair("lambda", "python", 1),
+                ]
+            );
+        }
+
+        #[test]
+        fn ignores_case_and_repeated_tags() {
+            let documents = [
+                tagged("a.md", &["AWS", "lambda", "aws"]),
+                tagged("b.md", &["aws", "Lambda"]),
+            ];
+            assert_eq!(count_tag_pairs(&documents), [pair("AWS", "lambda", 2)]);
+        }
+    }
+
+    mod reindex_manifest_tests {
+        use super::*;
+
+        #[test]
+        fn first_heading_skips_other_lines_and_empty_headings() {
+            assert_eq!(
+                first_heading("intro\n## Sub\n#  \n# Title \n# Later"),
+                Some("Title")
+            );
+            assert_eq!(first_heading("#Tight\nplain"), None);
+        }
+
+        #[test]
+        fn markdown_files_skip_ignored_paths() {
+            let temp_dir = tempfile::TempDir::new().unwrap();
+            let root = temp_dir.path();
+            std::fs::create_dir_all(root.join("aws/lambda")).unwrap();
+            std::fs::create_dir_all(root.join(".index")).unwrap();
+            for file in [
+                "aws/lambda/cold-starts.md",
+                "aws/notes.txt",
+                ".index/meta.md",
+                "top.md",
+            ] {
+                std::fs::write(root.join(file), "x").unwrap();
+            }
+
+            let mut files = Vec::new();
+            markdown_files(root, root, &mut files).unwrap();
+            files.sort();
+            assert_eq!(
+                files,
+                [
+                    PathBuf::from("aws/lambda/cold-starts.md"),
+                    PathBuf::from("top.md")
+                ]
+            );
+        }
+    }
 }
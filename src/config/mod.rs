@@ -1,36 +1,268 @@
 //! Configuration loading for kvault.
 
+use std::collections::HashMap;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use directories::{BaseDirs, ProjectDirs};
 use serde::Deserialize;
 
+use crate::corpus::category::CategoryRule;
+use crate::corpus::{DocumentLayout, ManifestFormat};
+use crate::search::SnippetStrategy;
+
 /// Environment variable to override config file location.
 pub const KVAULT_CONFIG_ENV: &str = "KVAULT_CONFIG";
 
-/// Top-level configuration loaded from config.toml.
-#[derive(Debug, Default, Deserialize)]
+/// Environment variable selecting a named profile from the config.
+pub const KVAULT_PROFILE_ENV: &str = "KVAULT_PROFILE";
+
+/// Held by tests that set [`KVAULT_CONFIG_ENV`], since every test in the
+/// binary shares the environment.
+#[cfg(test)]
+pub(crate) static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Profile selected with `--profile`, which takes precedence over
+/// `KVAULT_PROFILE`.
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Select the profile [`Config::load`] applies for the rest of the process.
+///
+/// Only the first call has any effect.
+pub fn set_profile(name: String) {
+    let _ = PROFILE.set(name);
+}
+
+/// Config file names probed in the default config directory, in order.
+/// TOML is the canonical format and wins if several exist.
+const CONFIG_FILE_NAMES: [&str; 4] = ["config.toml", "config.yaml", "config.yml", "config.json"];
+
+/// Default number of corpus paths beyond which a warning is printed.
+pub const DEFAULT_WARN_CORPORA: usize = 50;
+
+/// Default number of corpus paths beyond which loading fails.
+pub const DEFAULT_MAX_CORPORA: usize = 500;
+
+/// Default minimum length, in characters, of a search query.
+pub const DEFAULT_MIN_QUERY_LENGTH: usize = 2;
+
+/// Default document count from which `--backend auto` suggests building an
+/// index for an unindexed corpus.
+pub const DEFAULT_INDEX_HINT_DOCUMENTS: usize = 1000;
+
+/// Default language of ranked search indexes, whose words are stemmed and
+/// stopwords dropped by its rules.
+pub const DEFAULT_INDEX_LANGUAGE: &str = "en";
+
+/// Config file syntax, chosen by file extension.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// TOML (default, and used for unrecognized extensions).
+    #[default]
+    Toml,
+    /// YAML (`.yaml` or `.yml`).
+    Yaml,
+    /// JSON (`.json`).
+    Json,
+}
+
+impl ConfigFormat {
+    /// Pick the format for a config file from its extension.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            Some("json") => Self::Json,
+            _ => Self::Toml,
+        }
+    }
+}
+
+/// Top-level configuration loaded from config.toml (or YAML/JSON).
+#[derive(Debug, Default, PartialEq, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub corpus: CorpusConfig,
+    #[serde(default)]
+    pub search: SearchConfig,
+    #[serde(default)]
+    pub list: ListConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// Named templates for new documents, mapping name to file path.
+    #[serde(default)]
+    pub templates: HashMap<String, String>,
+    /// Named alternative settings, selected with `--profile` or
+    /// `KVAULT_PROFILE`.
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// Settings under `[profiles.<name>]`. Each section present replaces the
+/// corresponding top-level section when the profile is selected.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct Profile {
+    /// Replaces `[corpus]` (e.g., a separate set of `paths`).
+    pub corpus: Option<CorpusConfig>,
+    /// Replaces `[search]`.
+    pub search: Option<SearchConfig>,
 }
 
 /// Configuration for knowledge corpus locations.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Deserialize)]
 pub struct CorpusConfig {
     #[serde(default = "default_corpus_paths")]
     pub paths: Vec<String>,
+    /// Encrypt newly added documents at rest (requires the `crypto` feature).
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Directories `add` puts new documents in: `category` (default) or
+    /// `date`.
+    #[serde(default)]
+    pub layout: DocumentLayout,
+    /// Warn when more than this many corpus paths are configured.
+    #[serde(default = "default_warn_corpora")]
+    pub warn_corpora: usize,
+    /// Refuse to load a config with more than this many corpus paths.
+    #[serde(default = "default_max_corpora")]
+    pub max_corpora: usize,
+    /// Resolve relative corpus paths against the config file's directory
+    /// instead of the current working directory.
+    #[serde(default)]
+    pub paths_relative_to_config: bool,
+    /// Categories for files the manifest doesn't list, by directory; files
+    /// no rule covers are in their first directory's category.
+    #[serde(default)]
+    pub category_rules: Vec<CategoryRule>,
+    /// Bucket to keep the corpus in (requires the `s3` feature).
+    pub s3: Option<S3Config>,
+    /// Ranked search index settings.
+    #[serde(default)]
+    pub index: IndexConfig,
+}
+
+/// Settings under `[corpus.index]`.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct IndexConfig {
+    /// Two-letter code of the language whose stemming and stopwords apply
+    /// to titles and bodies (default [`DEFAULT_INDEX_LANGUAGE`]).
+    pub language: Option<String>,
+}
+
+impl IndexConfig {
+    /// The language new indexes are built for.
+    #[must_use]
+    pub fn language(&self) -> &str {
+        self.language.as_deref().unwrap_or(DEFAULT_INDEX_LANGUAGE)
+    }
+}
+
+/// Settings under `[corpus.s3]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct S3Config {
+    /// Bucket name.
+    pub bucket: String,
+    /// Key prefix the corpus is stored under (e.g., `team/kb`); empty for
+    /// the bucket root.
+    #[serde(default)]
+    pub prefix: String,
+    /// AWS region (default: from the environment or AWS profile).
+    pub region: Option<String>,
+}
+
+/// Configuration for search backends.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct SearchConfig {
+    /// Path to the ripgrep binary (defaults to `rg` in PATH).
+    pub ripgrep_path: Option<String>,
+    /// Globs for paths to leave out of ripgrep searches (e.g., `archive/**`),
+    /// in addition to the manifest and index, which are always excluded.
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
+    /// How ranked results pick their snippet: `best` (default), `first`,
+    /// or `prefix`.
+    #[serde(default)]
+    pub snippet_strategy: SnippetStrategy,
+    /// Index each document added with `add` straight away, if its corpus
+    /// has a ranked search index, instead of waiting for `kvault index`.
+    #[serde(default)]
+    pub auto_index_on_add: bool,
+    /// Tidy queries before searching: trim them, collapse runs of
+    /// whitespace, and replace curly quotes with straight ones, so text
+    /// pasted from documents or chat still matches literally.
+    #[serde(default)]
+    pub normalize_query: bool,
+    /// Under `--backend auto`, corpora without an index are searched with
+    /// ripgrep; from this many documents, a note on stderr suggests
+    /// building one (default [`DEFAULT_INDEX_HINT_DOCUMENTS`]).
+    pub index_hint_documents: Option<usize>,
+    /// Reject queries shorter than this many characters, after trimming,
+    /// since they match almost everything (default
+    /// [`DEFAULT_MIN_QUERY_LENGTH`]; `0` allows any length).
+    pub min_query_length: Option<usize>,
+    /// Maximum number of results when `search` is run without `--limit`
+    /// (default 10).
+    pub default_limit: Option<usize>,
+    /// Category to search when `search` is run without `--category`.
+    pub default_category: Option<String>,
+}
+
+impl SearchConfig {
+    /// The document count from which unindexed corpora get an index hint.
+    #[must_use]
+    pub fn index_hint_documents(&self) -> usize {
+        self.index_hint_documents
+            .unwrap_or(DEFAULT_INDEX_HINT_DOCUMENTS)
+    }
+
+    /// The shortest query searched without `--force`.
+    #[must_use]
+    pub fn min_query_length(&self) -> usize {
+        self.min_query_length.unwrap_or(DEFAULT_MIN_QUERY_LENGTH)
+    }
+}
+
+/// Configuration for `kvault list`.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct ListConfig {
+    /// Category to list when `list` is run without `--category`.
+    pub default_category: Option<String>,
+}
+
+/// Configuration for how corpora are stored.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct StorageConfig {
+    /// Manifest file format for writes: `json` (default) or `jsonl`.
+    /// Either format is read regardless of this setting.
+    #[serde(default)]
+    pub manifest_format: ManifestFormat,
 }
 
 fn default_corpus_paths() -> Vec<String> {
     vec!["~/.kvault".to_string()]
 }
 
+fn default_warn_corpora() -> usize {
+    DEFAULT_WARN_CORPORA
+}
+
+fn default_max_corpora() -> usize {
+    DEFAULT_MAX_CORPORA
+}
+
 impl Default for CorpusConfig {
     fn default() -> Self {
         Self {
             paths: default_corpus_paths(),
+            encrypted: false,
+            layout: DocumentLayout::default(),
+            warn_corpora: DEFAULT_WARN_CORPORA,
+            max_corpora: DEFAULT_MAX_CORPORA,
+            paths_relative_to_config: false,
+            category_rules: Vec::new(),
+            s3: None,
+            index: IndexConfig::default(),
         }
     }
 }
@@ -38,26 +270,163 @@ impl Default for CorpusConfig {
 impl Config {
     /// Load config from ~/.config/kvault/config.toml, or return defaults.
     ///
+    /// See [`Config::config_path`] for where the file is looked up.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the config file exists but cannot be read or parsed.
+    /// Returns an error if the config file exists but cannot be read or parsed,
+    /// or if it lists more corpus paths than `corpus.max_corpora`.
     pub fn load() -> anyhow::Result<Self> {
         if let Some(path) = Self::config_path()
             && path.exists()
         {
-            let contents = std::fs::read_to_string(&path)?;
-            let config: Config = toml::from_str(&contents)?;
-            return Ok(config);
+            return Self::load_from(&path);
         }
 
         Ok(Config::default())
     }
 
+    /// Load config from a specific file, parsed according to its extension,
+    /// with the selected profile (if any) applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, names an
+    /// unknown profile, or lists more corpus paths than `corpus.max_corpora`.
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut config = Self::parse(&contents, ConfigFormat::from_path(path))
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", path.display()))?;
+        if let Some(profile) = Self::selected_profile() {
+            config = config.with_profile(&profile)?;
+        }
+        if config.corpus.paths_relative_to_config
+            && let Some(base) = path.parent()
+        {
+            config.resolve_relative_paths(base);
+        }
+        if let Some(warning) = config.check_corpus_count()? {
+            eprintln!("Warning: {warning}");
+        }
+        config.check_exclude_globs()?;
+        Ok(config)
+    }
+
+    /// Parse config file contents in the given format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the contents are not a valid config in `format`.
+    pub fn parse(contents: &str, format: ConfigFormat) -> anyhow::Result<Self> {
+        Ok(match format {
+            ConfigFormat::Toml => toml::from_str(contents)?,
+            ConfigFormat::Yaml => serde_yaml_ng::from_str(contents)?,
+            ConfigFormat::Json => serde_json::from_str(contents)?,
+        })
+    }
+
+    /// The profile chosen with `--profile`, else `KVAULT_PROFILE`.
+    #[must_use]
+    pub fn selected_profile() -> Option<String> {
+        PROFILE
+            .get()
+            .cloned()
+            .or_else(|| env::var(KVAULT_PROFILE_ENV).ok())
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Replace the top-level sections with those set in profile `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no such profile is configured.
+    pub fn with_profile(mut self, name: &str) -> anyhow::Result<Self> {
+        let Some(profile) = self.profiles.remove(name) else {
+            let mut known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            anyhow::bail!(
+                "Unknown profile: {name} (configured: {})",
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            );
+        };
+
+        if let Some(corpus) = profile.corpus {
+            self.corpus = corpus;
+        }
+        if let Some(search) = profile.search {
+            self.search = search;
+        }
+        Ok(self)
+    }
+
+    /// Rewrite relative corpus paths to be relative to `base`.
+    ///
+    /// Absolute paths and paths starting with `~` are left unchanged.
+    fn resolve_relative_paths(&mut self, base: &Path) {
+        for path in &mut self.corpus.paths {
+            if path.starts_with('~') || Path::new(path.as_str()).is_absolute() {
+                continue;
+            }
+            *path = base.join(path.as_str()).to_string_lossy().into_owned();
+        }
+    }
+
+    /// Check the number of configured corpus paths against the limits.
+    ///
+    /// # Returns
+    ///
+    /// A warning message if the count exceeds `corpus.warn_corpora`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the count exceeds `corpus.max_corpora`.
+    pub fn check_corpus_count(&self) -> anyhow::Result<Option<String>> {
+        let count = self.corpus.paths.len();
+
+        if count > self.corpus.max_corpora {
+            anyhow::bail!(
+                "{count} corpus paths configured (max {}); check corpus.paths in your config",
+                self.corpus.max_corpora
+            );
+        }
+
+        if count > self.corpus.warn_corpora {
+            return Ok(Some(format!(
+                "{count} corpus paths configured (more than {}); commands may be slow",
+                self.corpus.warn_corpora
+            )));
+        }
+
+        Ok(None)
+    }
+
+    /// Check `search.exclude_globs` for basic glob syntax errors.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first glob that is empty, starts with `!`
+    /// (which would re-include paths rather than exclude them), or has
+    /// unbalanced `[]` or `{}`.
+    pub fn check_exclude_globs(&self) -> anyhow::Result<()> {
+        for glob in &self.search.exclude_globs {
+            if let Err(reason) = validate_glob(glob) {
+                anyhow::bail!("Invalid search.exclude_globs entry '{glob}': {reason}");
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the config file path.
     ///
     /// Checks in order:
     /// 1. `KVAULT_CONFIG` environment variable (if set)
-    /// 2. Default location: `~/.config/kvault/config.toml` (or platform equivalent)
+    /// 2. Default location: `~/.config/kvault/config.toml` (or platform
+    ///    equivalent), falling back to `config.yaml`, `config.yml`, then
+    ///    `config.json` in the same directory
     #[must_use]
     pub fn config_path() -> Option<PathBuf> {
         // Check environment variable first
@@ -66,8 +435,53 @@ impl Config {
         }
 
         // Fall back to default platform-specific location
-        ProjectDirs::from("", "", "kvault").map(|dirs| dirs.config_dir().join("config.toml"))
+        let dir = ProjectDirs::from("", "", "kvault")?
+            .config_dir()
+            .to_path_buf();
+        let path = CONFIG_FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| dir.join(CONFIG_FILE_NAMES[0]));
+        Some(path)
+    }
+}
+
+/// Check a glob for the syntax errors ripgrep would reject, plus a leading
+/// `!`, which is added automatically for exclusions.
+fn validate_glob(glob: &str) -> Result<(), &'static str> {
+    if glob.trim().is_empty() {
+        return Err("glob is empty");
+    }
+    if glob.starts_with('!') {
+        return Err("globs are already exclusions; remove the leading '!'");
+    }
+
+    let mut in_class = false;
+    let mut alternates = 0usize;
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' if !in_class => in_class = true,
+            ']' if in_class => in_class = false,
+            '{' if !in_class => alternates += 1,
+            '}' if !in_class => {
+                alternates = alternates.checked_sub(1).ok_or("unmatched '}'")?;
+            }
+            _ => {}
+        }
+    }
+
+    if in_class {
+        return Err("unclosed '['");
+    }
+    if alternates > 0 {
+        return Err("unclosed '{'");
     }
+    Ok(())
 }
 
 /// Expand ~ to the user's home directory.
@@ -84,6 +498,7 @@ pub fn expand_tilde(path: &str) -> PathBuf {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::PoisonError;
 
     #[test]
     fn expand_tilde_with_home() {
@@ -125,11 +540,275 @@ mod tests {
         assert!(!config.corpus.paths.is_empty());
     }
 
+    fn config_with_paths(count: usize) -> Config {
+        Config {
+            corpus: CorpusConfig {
+                paths: (0..count).map(|i| format!("/corpus/{i}")).collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resolve_relative_paths_against_base() {
+        let mut config = Config {
+            corpus: CorpusConfig {
+                paths: vec![
+                    "notes".to_string(),
+                    "../shared".to_string(),
+                    "~/.kvault".to_string(),
+                    "/abs/corpus".to_string(),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        config.resolve_relative_paths(Path::new("/home/me/.config/kvault"));
+
+        assert_eq!(
+            config.corpus.paths,
+            [
+                "/home/me/.config/kvault/notes",
+                "/home/me/.config/kvault/../shared",
+                "~/.kvault",
+                "/abs/corpus",
+            ]
+        );
+    }
+
+    #[test]
+    fn corpus_count_within_limit_is_silent() {
+        let config = config_with_paths(DEFAULT_WARN_CORPORA);
+        assert!(config.check_corpus_count().unwrap().is_none());
+    }
+
+    #[test]
+    fn corpus_count_over_soft_limit_warns() {
+        let config = config_with_paths(DEFAULT_WARN_CORPORA + 1);
+        let warning = config.check_corpus_count().unwrap().unwrap();
+        assert!(warning.contains("51 corpus paths"));
+    }
+
+    #[test]
+    fn corpus_count_over_hard_cap_errors() {
+        let mut config = config_with_paths(11);
+        config.corpus.max_corpora = 10;
+        assert!(config.check_corpus_count().is_err());
+    }
+
+    #[test]
+    fn validate_glob_accepts_common_patterns() {
+        for glob in ["*.tmp", "archive/**", "[abc].md", "*.{tmp,bak}", "a\\[b"] {
+            assert!(validate_glob(glob).is_ok(), "{glob}");
+        }
+    }
+
+    #[test]
+    fn validate_glob_rejects_malformed_patterns() {
+        for glob in ["", "  ", "!manifest.json", "[abc", "*.{tmp", "a}b"] {
+            assert!(validate_glob(glob).is_err(), "{glob}");
+        }
+    }
+
+    #[test]
+    fn check_exclude_globs_names_bad_entry() {
+        let mut config = Config::default();
+        config.search.exclude_globs = vec!["*.tmp".to_string(), "!manifest.json".to_string()];
+
+        let err = config.check_exclude_globs().unwrap_err().to_string();
+        assert!(err.contains("'!manifest.json'"));
+    }
+
+    /// The same settings in each supported syntax.
+    const EQUIVALENT_CONFIGS: [(&str, &str); 4] = [
+        (
+            "config.toml",
+            "[corpus]\npaths = [\"/notes\", \"/work\"]\nencrypted = true\n\n\
+             [search]\nexclude_globs = [\"archive/**\"]\n\n\
+             [storage]\nmanifest_format = \"jsonl\"\n\n\
+             [templates]\nmeeting = \"/templates/meeting.md\"\n",
+        ),
+        (
+            "config.yaml",
+            "corpus:\n  paths: [/notes, /work]\n  encrypted: true\n\
+             search:\n  exclude_globs: [\"archive/**\"]\n\
+             storage:\n  manifest_format: jsonl\n\
+             templates:\n  meeting: /templates/meeting.md\n",
+        ),
+        (
+            "config.yml",
+            "corpus:\n  paths:\n    - /notes\n    - /work\n  encrypted: true\n\
+             search:\n  exclude_globs:\n    - \"archive/**\"\n\
+             storage:\n  manifest_format: jsonl\n\
+             templates:\n  meeting: /templates/meeting.md\n",
+        ),
+        (
+            "config.json",
+            r#"{
+                "corpus": {"paths": ["/notes", "/work"], "encrypted": true},
+                "search": {"exclude_globs": ["archive/**"]},
+                "storage": {"manifest_format": "jsonl"},
+                "templates": {"meeting": "/templates/meeting.md"}
+            }"#,
+        ),
+    ];
+
+    #[test]
+    fn config_formats_load_identically() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let configs: Vec<Config> = EQUIVALENT_CONFIGS
+            .iter()
+            .map(|(name, contents)| {
+                let path = temp_dir.path().join(name);
+                std::fs::write(&path, contents).unwrap();
+                Config::load_from(&path).unwrap()
+            })
+            .collect();
+
+        assert_eq!(configs[0].corpus.paths, vec!["/notes", "/work"]);
+        assert!(configs[0].corpus.encrypted);
+        assert_eq!(configs[0].storage.manifest_format, ManifestFormat::Jsonl);
+        for config in &configs[1..] {
+            assert_eq!(config, &configs[0]);
+        }
+    }
+
+    const TWO_PROFILES: &str = "[corpus]\npaths = [\"/default\"]\n\n\
+        [search]\nexclude_globs = [\"drafts/**\"]\n\n\
+        [profiles.work.corpus]\npaths = [\"/work/kb\", \"/work/shared\"]\n\n\
+        [profiles.personal.corpus]\npaths = [\"/home/notes\"]\nencrypted = true\n\n\
+        [profiles.personal.search]\nripgrep_path = \"/opt/rg\"\n";
+
+    #[test]
+    fn profile_replaces_its_sections() {
+        let config = Config::parse(TWO_PROFILES, ConfigFormat::Toml).unwrap();
+        assert_eq!(config.corpus.paths, vec!["/default"]);
+
+        let work = Config::parse(TWO_PROFILES, ConfigFormat::Toml)
+            .unwrap()
+            .with_profile("work")
+            .unwrap();
+        assert_eq!(work.corpus.paths, vec!["/work/kb", "/work/shared"]);
+        // No [profiles.work.search], so the top-level search settings stay
+        assert_eq!(work.search.exclude_globs, vec!["drafts/**"]);
+
+        let personal = config.with_profile("personal").unwrap();
+        assert_eq!(personal.corpus.paths, vec!["/home/notes"]);
+        assert!(personal.corpus.encrypted);
+        assert_eq!(personal.search.ripgrep_path.as_deref(), Some("/opt/rg"));
+        assert!(personal.search.exclude_globs.is_empty());
+    }
+
+    #[test]
+    fn unknown_profile_lists_configured_ones() {
+        let config = Config::parse(TWO_PROFILES, ConfigFormat::Toml).unwrap();
+        let err = config.with_profile("home").unwrap_err().to_string();
+        assert_eq!(err, "Unknown profile: home (configured: personal, work)");
+    }
+
+    #[test]
+    fn config_format_from_extension() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a/config.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.yaml")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.yml")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.json")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("kvaultrc")),
+            ConfigFormat::Toml
+        );
+    }
+
+    #[test]
+    fn parse_error_names_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.yaml");
+        std::fs::write(&path, "corpus: [unclosed").unwrap();
+
+        let err = Config::load_from(&path).unwrap_err().to_string();
+        assert!(err.contains("config.yaml"), "{err}");
+    }
+
+    #[test]
+    fn storage_manifest_format_parses() {
+        let config: Config = toml::from_str("[storage]\nmanifest_format = \"jsonl\"").unwrap();
+        assert_eq!(config.storage.manifest_format, ManifestFormat::Jsonl);
+
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.storage.manifest_format, ManifestFormat::Json);
+    }
+
+    #[test]
+    fn search_snippet_strategy_parses() {
+        let config: Config = toml::from_str("[search]\nsnippet_strategy = \"prefix\"").unwrap();
+        assert_eq!(config.search.snippet_strategy, SnippetStrategy::Prefix);
+
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.search.snippet_strategy, SnippetStrategy::Best);
+
+        assert!(toml::from_str::<Config>("[search]\nsnippet_strategy = \"last\"").is_err());
+    }
+
+    #[test]
+    fn list_and_search_defaults_parse() {
+        let config: Config = toml::from_str(
+            "[search]\ndefault_limit = 25\ndefault_category = \"aws\"\n\n[list]\ndefault_category = \"rust\"",
+        )
+        .unwrap();
+        assert_eq!(config.search.default_limit, Some(25));
+        assert_eq!(config.search.default_category.as_deref(), Some("aws"));
+        assert_eq!(config.list.default_category.as_deref(), Some("rust"));
+
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.search.default_limit, None);
+        assert_eq!(config.search.default_category, None);
+        assert_eq!(config.list.default_category, None);
+    }
+
+    #[test]
+    fn s3_section_parses() {
+        let config: Config =
+            toml::from_str("[corpus.s3]\nbucket = \"team-kb\"\nregion = \"eu-west-1\"").unwrap();
+        assert_eq!(
+            config.corpus.s3,
+            Some(S3Config {
+                bucket: "team-kb".to_string(),
+                prefix: String::new(),
+                region: Some("eu-west-1".to_string()),
+            })
+        );
+        assert_eq!(Config::default().corpus.s3, None);
+    }
+
+    #[test]
+    fn index_language_defaults_to_english() {
+        assert_eq!(Config::default().corpus.index.language(), "en");
+
+        let config: Config = toml::from_str("[corpus.index]\nlanguage = \"de\"").unwrap();
+        assert_eq!(config.corpus.index.language(), "de");
+    }
+
     #[test]
     fn config_path_respects_env_var() {
         let test_path = "/custom/config/path.toml";
+        let _lock = ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
 
-        // SAFETY: Test is single-threaded and we restore the env var after
+        // SAFETY: Other tests setting the variable hold `ENV_LOCK`, and we
+        // restore it after
         unsafe {
             std::env::set_var(KVAULT_CONFIG_ENV, test_path);
         }
@@ -145,6 +824,8 @@ mod tests {
 
     #[test]
     fn config_path_falls_back_to_default() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
         // Ensure env var is not set
         unsafe {
             std::env::remove_var(KVAULT_CONFIG_ENV);
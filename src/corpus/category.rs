@@ -0,0 +1,115 @@
+//! Categories for files the manifest doesn't describe.
+//!
+//! Documents in the manifest carry their own category. Other files a search
+//! turns up get one from their path, through a [`CategoryResolver`]: by
+//! default [`FirstComponentResolver`], or [`ConfigRulesResolver`] when
+//! `[[corpus.category_rules]]` are configured. Category filters then match
+//! these files too, so `--category aws` can return files outside the
+//! manifest.
+
+use std::path::{Component, Path};
+
+use serde::Deserialize;
+
+/// Maps a file path to a category.
+pub trait CategoryResolver: Send + Sync {
+    /// The category for the file at `path`, relative to the corpus root, or
+    /// `None` if the path doesn't determine one.
+    fn resolve(&self, path: &Path) -> Option<String>;
+}
+
+/// Category from the file's first directory: `aws/lambda/cold-starts.md` is
+/// in `aws`. Files in the corpus root have none.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FirstComponentResolver;
+
+impl CategoryResolver for FirstComponentResolver {
+    fn resolve(&self, path: &Path) -> Option<String> {
+        let mut components = path.components();
+        let first = components.next()?;
+        // The file name itself isn't a directory
+        components.next()?;
+        match first {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        }
+    }
+}
+
+/// A `[[corpus.category_rules]]` entry: files under `prefix` belong to
+/// `category`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CategoryRule {
+    /// Directory, relative to the corpus root (e.g., `work/aws`).
+    pub prefix: String,
+    /// Category for files under it.
+    pub category: String,
+}
+
+/// Categories from configured rules, tried in order; paths no rule covers
+/// fall back to [`FirstComponentResolver`].
+#[derive(Debug, Clone, Default)]
+pub struct ConfigRulesResolver {
+    rules: Vec<CategoryRule>,
+}
+
+impl ConfigRulesResolver {
+    /// Create a resolver from rules in the order they should be tried.
+    #[must_use]
+    pub fn new(rules: Vec<CategoryRule>) -> Self {
+        Self { rules }
+    }
+}
+
+impl CategoryResolver for ConfigRulesResolver {
+    fn resolve(&self, path: &Path) -> Option<String> {
+        self.rules
+            .iter()
+            .find(|rule| path.starts_with(rule.prefix.trim_end_matches('/')))
+            .map(|rule| rule.category.clone())
+            .or_else(|| FirstComponentResolver.resolve(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(prefix: &str, category: &str) -> CategoryRule {
+        CategoryRule {
+            prefix: prefix.to_string(),
+            category: category.to_string(),
+        }
+    }
+
+    #[test]
+    fn first_component_is_the_category() {
+        let resolver = FirstComponentResolver;
+        let resolve = |path: &str| resolver.resolve(Path::new(path));
+
+        assert_eq!(resolve("aws/lambda.md").as_deref(), Some("aws"));
+        assert_eq!(resolve("aws/lambda/cold-starts.md").as_deref(), Some("aws"));
+        assert_eq!(resolve("2024/01/02/notes.md").as_deref(), Some("2024"));
+        assert_eq!(resolve("notes.md"), None);
+        assert_eq!(resolve(""), None);
+        assert_eq!(resolve("/abs/notes.md"), None);
+    }
+
+    #[test]
+    fn config_rules_match_in_order_then_fall_back() {
+        let resolver = ConfigRulesResolver::new(vec![
+            rule("work/aws", "aws"),
+            rule("work/", "work"),
+            rule("inbox", "unsorted"),
+        ]);
+        let resolve = |path: &str| resolver.resolve(Path::new(path));
+
+        assert_eq!(resolve("work/aws/lambda.md").as_deref(), Some("aws"));
+        assert_eq!(resolve("work/notes.md").as_deref(), Some("work"));
+        assert_eq!(resolve("inbox/today.md").as_deref(), Some("unsorted"));
+        // Prefixes match whole directories
+        assert_eq!(resolve("inboxes/today.md").as_deref(), Some("inboxes"));
+        assert_eq!(resolve("rust/errors.md").as_deref(), Some("rust"));
+        assert_eq!(resolve("notes.md"), None);
+    }
+}
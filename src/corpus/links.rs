@@ -0,0 +1,280 @@
+//! Links between corpus documents.
+//!
+//! Documents can refer to each other with `[[wikilinks]]` (matched against
+//! manifest paths, file names, and titles) or markdown `[text](path.md)`
+//! links (resolved relative to the linking document). External URLs and
+//! anything inside fenced code blocks are ignored.
+
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use super::Corpus;
+
+/// The syntax a link was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// `[[target]]`, `[[target|alias]]`, or `[[target#heading]]`.
+    Wiki,
+    /// `[text](target)`.
+    Markdown,
+}
+
+/// A link found in a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// How the link was written.
+    pub kind: LinkKind,
+    /// Link target with any alias and `#fragment` removed.
+    pub target: String,
+    /// 1-indexed line the link appears on.
+    pub line: usize,
+}
+
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LinkKind::Wiki => write!(f, "[[{}]]", self.target),
+            LinkKind::Markdown => write!(f, "({})", self.target),
+        }
+    }
+}
+
+/// Extract internal links from markdown content, in document order.
+#[must_use]
+pub fn extract_links(content: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut in_code_block = false;
+
+    for (index, line) in content.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(start) = rest.find('[') {
+            let after = &rest[start..];
+            let parsed = if after.starts_with("[[") {
+                parse_wikilink(after)
+            } else if rest[..start].ends_with('!') {
+                // Image, not a link; skip its alt text
+                parse_markdown_link(after).map(|(_, len)| (None, len))
+            } else {
+                parse_markdown_link(after)
+            };
+
+            let Some((link, len)) = parsed else {
+                rest = &after[1..];
+                continue;
+            };
+            if let Some((kind, target)) = link {
+                links.push(Link {
+                    kind,
+                    target,
+                    line: index + 1,
+                });
+            }
+            rest = &after[len..];
+        }
+    }
+
+    links
+}
+
+/// A parsed link (or `None` for links to ignore) and the bytes it spans.
+type Parsed = Option<(Option<(LinkKind, String)>, usize)>;
+
+/// Parse `[[target|alias#heading]]` at the start of `s`.
+fn parse_wikilink(s: &str) -> Parsed {
+    let end = s.find("]]")?;
+    let inner = &s[2..end];
+    let target = inner.split(['|', '#']).next().unwrap_or_default().trim();
+    let link = (!target.is_empty()).then(|| (LinkKind::Wiki, target.to_string()));
+    Some((link, end + 2))
+}
+
+/// Parse `[text](target "title")` at the start of `s`.
+fn parse_markdown_link(s: &str) -> Parsed {
+    let close = s.find(']')?;
+    let rest = s[close + 1..].strip_prefix('(')?;
+    let end = rest.find(')')?;
+    let len = close + 2 + end + 1;
+
+    let target = rest[..end]
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches('<')
+        .trim_end_matches('>');
+    let target = target.split('#').next().unwrap_or_default();
+
+    if target.is_empty() || target.contains("://") || target.starts_with("mailto:") {
+        return Some((None, len));
+    }
+    Some((Some((LinkKind::Markdown, target.to_string())), len))
+}
+
+/// Resolve a link from the document at `source` (relative to the corpus
+/// root) to the manifest path of the document it points to.
+///
+/// Wikilinks match, in order, a manifest path or alias (with or without
+/// `.md`), a file name without extension, then a title; the last two ignore
+/// case. Markdown links are resolved relative to `source`'s directory, or to
+/// the corpus root if they start with `/`, and may also name an alias.
+///
+/// # Returns
+///
+/// The linked document's path, or `None` if the link is broken.
+#[must_use]
+pub fn resolve_link(corpus: &Corpus, source: &Path, link: &Link) -> Option<PathBuf> {
+    match link.kind {
+        LinkKind::Wiki => resolve_wikilink(corpus, &link.target),
+        LinkKind::Markdown => {
+            let target = match link.target.strip_prefix('/') {
+                Some(absolute) => PathBuf::from(absolute),
+                None => source.parent().unwrap_or(Path::new("")).join(&link.target),
+            };
+            let target = normalize(&target)?;
+            corpus.find_document(&target).map(|doc| doc.path.clone())
+        }
+    }
+}
+
+fn resolve_wikilink(corpus: &Corpus, target: &str) -> Option<PathBuf> {
+    let docs = corpus.documents();
+
+    // Also tries `target` with `.md` added
+    let by_path = corpus.find_document(Path::new(target));
+    let by_stem = || {
+        docs.iter().find(|doc| {
+            doc.path
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().eq_ignore_ascii_case(target))
+        })
+    };
+    let by_title = || {
+        docs.iter()
+            .find(|doc| doc.title.to_lowercase() == target.to_lowercase())
+    };
+
+    by_path
+        .or_else(by_stem)
+        .or_else(by_title)
+        .map(|doc| doc.path.clone())
+}
+
+/// Collapse `.` and `..` components. Returns `None` if the path climbs
+/// above its starting point.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus::{Document, Manifest};
+
+    fn corpus() -> Corpus {
+        let document = |path: &str, title: &str| Document {
+            path: PathBuf::from(path),
+            title: title.to_string(),
+            category: "notes".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        };
+        let mut manifest = Manifest::empty();
+        manifest.documents = vec![
+            document("notes/other-note.md", "Other Note"),
+            document("rust/error-handling.md", "Error Handling"),
+        ];
+        Corpus {
+            root: PathBuf::from("/corpus"),
+            manifest,
+        }
+    }
+
+    fn resolve(target: &str, kind: LinkKind) -> Option<PathBuf> {
+        let link = Link {
+            kind,
+            target: target.to_string(),
+            line: 1,
+        };
+        resolve_link(&corpus(), Path::new("notes/index.md"), &link)
+    }
+
+    #[test]
+    fn extracts_wiki_and_markdown_links() {
+        let content = "See [[other-note]] and [[Error Handling|errors]].\n\
+                       Also [the guide](../rust/error-handling.md#result \"Guide\").\n\
+                       ![diagram](diagram.png) and [site](https://example.com).\n\
+                       ```\n[[not-a-link]]\n```\n\
+                       [[missing#section]]";
+        let links = extract_links(content);
+
+        let summary: Vec<_> = links
+            .iter()
+            .map(|link| (link.kind, link.target.as_str(), link.line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (LinkKind::Wiki, "other-note", 1),
+                (LinkKind::Wiki, "Error Handling", 1),
+                (LinkKind::Markdown, "../rust/error-handling.md", 2),
+                (LinkKind::Wiki, "missing", 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_unclosed_brackets() {
+        assert!(extract_links("[[open and [half](").is_empty());
+    }
+
+    #[test]
+    fn resolves_wikilinks_by_path_stem_and_title() {
+        let expected = Some(PathBuf::from("notes/other-note.md"));
+        assert_eq!(resolve("notes/other-note", LinkKind::Wiki), expected);
+        assert_eq!(resolve("Other-Note", LinkKind::Wiki), expected);
+        assert_eq!(resolve("other note", LinkKind::Wiki), expected);
+        assert_eq!(resolve("missing", LinkKind::Wiki), None);
+    }
+
+    #[test]
+    fn resolves_markdown_links_relative_to_source() {
+        let expected = Some(PathBuf::from("rust/error-handling.md"));
+        assert_eq!(
+            resolve("../rust/error-handling.md", LinkKind::Markdown),
+            expected
+        );
+        assert_eq!(
+            resolve("/rust/error-handling.md", LinkKind::Markdown),
+            expected
+        );
+        assert_eq!(
+            resolve("./other-note.md", LinkKind::Markdown),
+            Some(PathBuf::from("notes/other-note.md"))
+        );
+        assert_eq!(resolve("error-handling.md", LinkKind::Markdown), None);
+        assert_eq!(resolve("../../outside.md", LinkKind::Markdown), None);
+    }
+}
@@ -1,28 +1,170 @@
 //! Knowledge corpus management and manifest parsing.
 
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+pub mod category;
+pub mod links;
+
 /// Errors that can occur when loading a corpus.
 #[derive(Debug, Error)]
 pub enum CorpusError {
     #[error("Manifest not found at {0}")]
     ManifestNotFound(PathBuf),
 
-    #[error("Failed to read manifest: {0}")]
-    ReadError(#[from] std::io::Error),
+    #[error("Failed to read manifest {}: {source}", path.display())]
+    ReadError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Failed to parse manifest {}: {source}", path.display())]
+    ParseError {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+impl CorpusError {
+    /// The manifest file the error is about (for `ManifestNotFound`, where
+    /// it was expected).
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::ManifestNotFound(path)
+            | Self::ReadError { path, .. }
+            | Self::ParseError { path, .. } => path,
+        }
+    }
+}
+
+/// File name of a manifest stored as a single JSON document.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// File name of a manifest stored as JSON lines.
+pub const MANIFEST_JSONL_FILE: &str = "manifest.jsonl";
+
+/// Content type of documents that don't record one.
+pub const DEFAULT_CONTENT_TYPE: &str = "text/markdown";
+
+/// Sentinel file that marks a corpus as locked against writes. It lives in
+/// the corpus root, so the lock travels with the corpus.
+pub const LOCK_FILE: &str = ".locked";
+
+/// Directory under the corpus root holding the Tantivy search index.
+pub const INDEX_DIR: &str = ".index";
 
-    #[error("Failed to parse manifest: {0}")]
-    ParseError(#[from] serde_json::Error),
+/// Directories under a corpus root that never hold documents.
+const IGNORED_DIRS: [&str; 2] = [INDEX_DIR, ".git"];
+
+/// Ignore files read by ripgrep and git.
+const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", ".rgignore"];
+
+/// Globs matching the same paths as [`is_ignored_corpus_path`], for tools
+/// like ripgrep that walk a corpus themselves. Keep the two in step.
+pub const IGNORED_CORPUS_GLOBS: [&str; 11] = [
+    INDEX_DIR,
+    ".git",
+    MANIFEST_FILE,
+    MANIFEST_JSONL_FILE,
+    LOCK_FILE,
+    ".gitignore",
+    ".ignore",
+    ".rgignore",
+    ".*.tmp",
+    "*.bak",
+    "*~",
+];
+
+/// Whether a path in a corpus belongs to kvault or another tool rather than
+/// being a document.
+///
+/// Covers the index and `.git` directories and everything under them, the
+/// manifest in either format, the lock sentinel, ignore files, staged writes
+/// (`.<name>.tmp`), and backups (`*.bak`, `*~`). Every operation that walks
+/// a corpus directory skips these paths.
+///
+/// # Arguments
+///
+/// * `path` - Path relative to the corpus root
+#[must_use]
+pub fn is_ignored_corpus_path(path: &Path) -> bool {
+    if path
+        .components()
+        .any(|component| IGNORED_DIRS.iter().any(|dir| component.as_os_str() == *dir))
+    {
+        return true;
+    }
+
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    [MANIFEST_FILE, MANIFEST_JSONL_FILE, LOCK_FILE].contains(&name)
+        || IGNORE_FILES.contains(&name)
+        || (name.starts_with('.') && extension == Some("tmp"))
+        || extension == Some("bak")
+        || name.ends_with('~')
+}
+
+/// On-disk representation of a corpus manifest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestFormat {
+    /// A single pretty-printed JSON object in `manifest.json` (default).
+    /// Every change rewrites the whole file.
+    #[default]
+    Json,
+    /// `manifest.jsonl`: a header line with the version and encryption salt,
+    /// then one `Document` per line. New documents are appended.
+    Jsonl,
+}
+
+impl ManifestFormat {
+    /// The manifest file name for this format.
+    #[must_use]
+    pub const fn file_name(self) -> &'static str {
+        match self {
+            Self::Json => MANIFEST_FILE,
+            Self::Jsonl => MANIFEST_JSONL_FILE,
+        }
+    }
+
+    /// Find the manifest file in a corpus root, whichever format it uses.
+    ///
+    /// `manifest.json` takes precedence if both files exist.
+    #[must_use]
+    pub fn detect(root: &Path) -> Option<(PathBuf, Self)> {
+        [Self::Json, Self::Jsonl].into_iter().find_map(|format| {
+            let path = root.join(format.file_name());
+            path.exists().then_some((path, format))
+        })
+    }
+}
+
+/// Where `add` puts new documents within a corpus.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentLayout {
+    /// `<category>/<slug>.md` (default).
+    #[default]
+    Category,
+    /// `<YYYY>/<MM>/<DD>/<slug>.md` for the day the document is added, with
+    /// the category recorded only in the manifest.
+    Date,
 }
 
 /// A knowledge document with metadata.
 ///
 /// Stored in manifest.json. The path is relative to the corpus root.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Document {
     /// Path relative to corpus root (e.g., "aws/lambda-patterns.md").
     pub path: PathBuf,
@@ -33,14 +175,132 @@ pub struct Document {
     /// Optional tags for additional classification.
     #[serde(default)]
     pub tags: Vec<String>,
+    /// Whether the document body is encrypted at rest.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypted: bool,
+    /// Cached number of whitespace-separated words in the body, recorded
+    /// when the document is written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word_count: Option<usize>,
+    /// Former paths of documents merged into this one, which links and
+    /// `get` still resolve.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<PathBuf>,
+    /// MIME type of the body (e.g., "text/markdown", "text/csv"), inferred
+    /// from the file extension when the document is added. Absent means
+    /// [`DEFAULT_CONTENT_TYPE`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
 }
 
 /// The manifest.json structure listing all documents in a corpus.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Manifest {
+    /// Manifest format version (currently "1").
     pub version: String,
+    /// Display name for the corpus (e.g., "Team runbooks"). Defaults to the
+    /// corpus directory name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Documents in the corpus.
     #[serde(default)]
     pub documents: Vec<Document>,
+    /// Hex-encoded salt for deriving the document encryption key.
+    /// Present only for corpora containing encrypted documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption_salt: Option<String>,
+    /// Hex-encoded constant sealed with the encryption key, which shows
+    /// whether a passphrase derives the right key. Absent from corpora
+    /// encrypted before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption_check: Option<String>,
+}
+
+/// A manifest inconsistency found by [`Corpus::validate`], or a problem
+/// with a document's file found by [`Corpus::check_files`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// A document's file isn't under its category's directory (e.g.,
+    /// `category: aws` with `path: rust/foo.md`), so it sits with another
+    /// category's files.
+    CategoryMismatch {
+        /// Document path, relative to the corpus root.
+        path: PathBuf,
+        /// Category declared in the manifest.
+        category: String,
+    },
+    /// A document's file doesn't exist.
+    MissingFile {
+        /// Document path, relative to the corpus root.
+        path: PathBuf,
+    },
+    /// A document's file exists but can't be opened, typically because of
+    /// its permissions (e.g., after syncing from another machine).
+    Unreadable {
+        /// Document path, relative to the corpus root.
+        path: PathBuf,
+        /// Why opening the file failed.
+        error: String,
+    },
+    /// Several manifest entries list the same file.
+    DuplicatePath {
+        /// Document path, relative to the corpus root.
+        path: PathBuf,
+        /// How many entries list it.
+        count: usize,
+    },
+}
+
+impl ValidationIssue {
+    /// Path of the document the issue is about.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::CategoryMismatch { path, .. }
+            | Self::MissingFile { path }
+            | Self::Unreadable { path, .. }
+            | Self::DuplicatePath { path, .. } => path,
+        }
+    }
+
+    /// A stable `snake_case` name for the kind of issue, for
+    /// machine-readable reports.
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::CategoryMismatch { .. } => "category_mismatch",
+            Self::MissingFile { .. } => "missing_file",
+            Self::Unreadable { .. } => "unreadable",
+            Self::DuplicatePath { .. } => "duplicate_path",
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CategoryMismatch { path, category } => write!(
+                f,
+                "category '{category}' doesn't match directory '{}'",
+                path.parent().unwrap_or(Path::new("")).display()
+            ),
+            Self::MissingFile { .. } => write!(f, "file is missing"),
+            Self::Unreadable { error, .. } => write!(f, "file can't be read: {error}"),
+            Self::DuplicatePath { count, .. } => {
+                write!(f, "listed {count} times in the manifest")
+            }
+        }
+    }
+}
+
+/// How `verify --fix` resolves a [`ValidationIssue::CategoryMismatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CategoryFix {
+    /// Move the file into its category's directory, keeping the old path
+    /// as an alias.
+    Move,
+    /// Set the category to the file's directory.
+    Category,
 }
 
 /// A loaded knowledge corpus with its root path and manifest.
@@ -51,22 +311,36 @@ pub struct Corpus {
 }
 
 impl Corpus {
-    /// Load a corpus from a directory containing manifest.json.
+    /// Load a corpus from a directory containing manifest.json or
+    /// manifest.jsonl.
     ///
     /// # Errors
     ///
-    /// Returns `CorpusError::ManifestNotFound` if manifest.json doesn't exist.
+    /// Returns `CorpusError::ManifestNotFound` if neither manifest exists.
     /// Returns `CorpusError::ReadError` if the file cannot be read.
     /// Returns `CorpusError::ParseError` if the JSON is invalid.
     pub fn load(root: &Path) -> Result<Self, CorpusError> {
-        let manifest_path = root.join("manifest.json");
-
-        if !manifest_path.exists() {
-            return Err(CorpusError::ManifestNotFound(manifest_path));
-        }
+        let Some((manifest_path, format)) = ManifestFormat::detect(root) else {
+            return Err(CorpusError::ManifestNotFound(root.join(MANIFEST_FILE)));
+        };
 
-        let contents = fs::read_to_string(&manifest_path)?;
-        let manifest: Manifest = serde_json::from_str(&contents)?;
+        let file = fs::File::open(&manifest_path).map_err(|source| CorpusError::ReadError {
+            path: manifest_path.clone(),
+            source,
+        })?;
+        let manifest = Manifest::from_reader(BufReader::new(file), format).map_err(|e| {
+            if e.is_io() {
+                CorpusError::ReadError {
+                    path: manifest_path.clone(),
+                    source: e.into(),
+                }
+            } else {
+                CorpusError::ParseError {
+                    path: manifest_path.clone(),
+                    source: e,
+                }
+            }
+        })?;
 
         Ok(Self {
             root: root.to_path_buf(),
@@ -83,6 +357,94 @@ impl Corpus {
     pub fn documents(&self) -> &[Document] {
         &self.manifest.documents
     }
+
+    /// Check the manifest for documents whose file isn't under their
+    /// category's directory, then for files listed more than once.
+    ///
+    /// A document may sit in a subdirectory of its category (`aws/lambda/x.md`
+    /// in `aws`), but not elsewhere, including the corpus root.
+    #[must_use]
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mismatches = self
+            .documents()
+            .iter()
+            .filter(|doc| !doc.path.starts_with(&doc.category))
+            .map(|doc| ValidationIssue::CategoryMismatch {
+                path: doc.path.clone(),
+                category: doc.category.clone(),
+            });
+
+        let mut counts: HashMap<&Path, usize> = HashMap::new();
+        for doc in self.documents() {
+            *counts.entry(&doc.path).or_default() += 1;
+        }
+        // Each duplicated path is reported once, where it first appears
+        let duplicates = self.documents().iter().filter_map(move |doc| {
+            let count = counts.remove(doc.path.as_path())?;
+            (count > 1).then(|| ValidationIssue::DuplicatePath {
+                path: doc.path.clone(),
+                count,
+            })
+        });
+
+        mismatches.chain(duplicates).collect()
+    }
+
+    /// Check that every document's file exists and can be opened.
+    ///
+    /// Unlike [`Self::validate`], this reads the filesystem.
+    #[must_use]
+    pub fn check_files(&self) -> Vec<ValidationIssue> {
+        self.documents()
+            .iter()
+            .filter_map(|doc| {
+                let error = fs::File::open(self.resolve_document_path(doc)).err()?;
+                let path = doc.path.clone();
+                Some(if error.kind() == std::io::ErrorKind::NotFound {
+                    ValidationIssue::MissingFile { path }
+                } else {
+                    ValidationIssue::Unreadable {
+                        path,
+                        error: error.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Find a document by its manifest path or one of its aliases.
+    ///
+    /// See [`Manifest::find_document_index`] for how paths are matched.
+    #[must_use]
+    pub fn find_document(&self, path: &Path) -> Option<&Document> {
+        self.manifest
+            .find_document_index(path)
+            .map(|index| &self.manifest.documents[index])
+    }
+
+    /// The corpus display name: the manifest's `name`, or else the root
+    /// directory name.
+    #[must_use]
+    pub fn name(&self) -> String {
+        self.manifest.name.clone().unwrap_or_else(|| {
+            self.root.file_name().map_or_else(
+                || self.root.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            )
+        })
+    }
+}
+
+/// First line of a JSONL manifest, holding everything but the documents.
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestHeader {
+    version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption_salt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption_check: Option<String>,
 }
 
 impl Manifest {
@@ -90,7 +452,573 @@ impl Manifest {
     pub fn empty() -> Self {
         Self {
             version: "1".to_string(),
+            name: None,
             documents: vec![],
+            encryption_salt: None,
+            encryption_check: None,
+        }
+    }
+
+    /// Index of the document with `path` as its manifest path or an alias.
+    ///
+    /// Paths are matched as written, so an entry without an extension (e.g.,
+    /// `notes/todo`) names exactly that file. Only if nothing matches is the
+    /// path retried with `.md` added, or removed if it has it, so
+    /// `notes/todo` and `notes/todo.md` each find the other's document.
+    #[must_use]
+    pub fn find_document_index(&self, path: &Path) -> Option<usize> {
+        let find = |path: &Path| {
+            let documents = &self.documents;
+            documents
+                .iter()
+                .position(|doc| doc.path == path)
+                .or_else(|| {
+                    documents
+                        .iter()
+                        .position(|doc| doc.aliases.iter().any(|a| a == path))
+                })
+        };
+
+        find(path).or_else(|| find(&toggle_markdown_extension(path)))
+    }
+
+    /// Parse manifest file contents stored in the given format.
+    ///
+    /// Blank lines in a JSONL manifest are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the contents are not valid for the format.
+    pub fn parse(contents: &str, format: ManifestFormat) -> Result<Self, serde_json::Error> {
+        Self::from_reader(contents.as_bytes(), format)
+    }
+
+    /// Parse a manifest while reading it, without first loading the whole
+    /// file into memory.
+    ///
+    /// Blank lines in a JSONL manifest are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails (see [`serde_json::Error::is_io`])
+    /// or the contents are not valid for the format.
+    pub fn from_reader(
+        reader: impl BufRead,
+        format: ManifestFormat,
+    ) -> Result<Self, serde_json::Error> {
+        if format == ManifestFormat::Json {
+            return serde_json::from_reader(reader);
+        }
+
+        let mut lines = reader
+            .lines()
+            .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()));
+        let header: ManifestHeader = match lines.next() {
+            Some(line) => serde_json::from_str(&line.map_err(serde_json::Error::io)?)?,
+            None => return Ok(Self::empty()),
+        };
+
+        let documents = lines
+            .map(|line| serde_json::from_str(&line.map_err(serde_json::Error::io)?))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            version: header.version,
+            name: header.name,
+            documents,
+            encryption_salt: header.encryption_salt,
+            encryption_check: header.encryption_check,
+        })
+    }
+
+    /// Serialize the manifest in the given format.
+    ///
+    /// JSONL output ends with a newline so documents can be appended with
+    /// [`Document::to_jsonl`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn encode(&self, format: ManifestFormat) -> Result<String, serde_json::Error> {
+        if format == ManifestFormat::Json {
+            return serde_json::to_string_pretty(self);
+        }
+
+        let header = ManifestHeader {
+            version: self.version.clone(),
+            name: self.name.clone(),
+            encryption_salt: self.encryption_salt.clone(),
+            encryption_check: self.encryption_check.clone(),
+        };
+        let mut output = serde_json::to_string(&header)?;
+        output.push('\n');
+        for doc in &self.documents {
+            output.push_str(&doc.to_jsonl()?);
+        }
+        Ok(output)
+    }
+}
+
+impl Document {
+    /// The document's content type, or [`DEFAULT_CONTENT_TYPE`] if the
+    /// manifest doesn't record one.
+    #[must_use]
+    pub fn resolved_content_type(&self) -> &str {
+        self.content_type.as_deref().unwrap_or(DEFAULT_CONTENT_TYPE)
+    }
+
+    /// Serialize as a single newline-terminated JSONL manifest line.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn to_jsonl(&self) -> Result<String, serde_json::Error> {
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        Ok(line)
+    }
+}
+
+/// Whether two categories or tags are the same. They are stored as typed,
+/// so `AWS` displays as `AWS`, but compared ignoring case.
+#[must_use]
+pub fn labels_match(a: &str, b: &str) -> bool {
+    a == b || label_key(a) == label_key(b)
+}
+
+/// Key under which labels that [`labels_match`] share a group, e.g. when
+/// counting documents per category.
+#[must_use]
+pub fn label_key(label: &str) -> String {
+    label.to_lowercase()
+}
+
+/// Infer a content type (MIME type) from a file's extension.
+///
+/// Unknown or missing extensions yield [`DEFAULT_CONTENT_TYPE`].
+#[must_use]
+pub fn infer_content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("txt" | "text") => "text/plain",
+        Some("csv") => "text/csv",
+        Some("tsv") => "text/tab-separated-values",
+        Some("json") => "application/json",
+        Some("yaml" | "yml") => "application/yaml",
+        Some("toml") => "application/toml",
+        Some("html" | "htm") => "text/html",
+        Some("rs") => "text/x-rust",
+        Some("py") => "text/x-python",
+        Some("go") => "text/x-go",
+        Some("c" | "h") => "text/x-c",
+        Some("java") => "text/x-java",
+        Some("js" | "mjs") => "text/javascript",
+        Some("ts") => "text/x-typescript",
+        Some("sh" | "bash") => "text/x-shellscript",
+        Some("sql") => "application/sql",
+        _ => DEFAULT_CONTENT_TYPE,
+    }
+}
+
+/// Whether a content type is source code, whose identifiers should be kept
+/// whole when indexed rather than split like prose.
+#[must_use]
+pub fn is_code_content_type(content_type: &str) -> bool {
+    content_type.starts_with("text/x-")
+        || matches!(
+            content_type,
+            "text/javascript" | "application/json" | "application/sql"
+        )
+}
+
+/// Whether the corpus at `root` is locked against writes.
+#[must_use]
+pub fn is_locked(root: &Path) -> bool {
+    root.join(LOCK_FILE).exists()
+}
+
+/// A file's stem as-is (e.g., `aws-lambda` for `notes/aws-lambda.md`), for
+/// showing the on-disk name instead of the manifest title.
+#[must_use]
+pub fn file_stem_title(path: &Path) -> String {
+    path.file_stem().map_or_else(
+        || "Unknown".to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+/// `path` with a `.md` extension removed if it has one, otherwise added.
+///
+/// The extension is appended rather than replaced, so `notes/v1.2` becomes
+/// `notes/v1.2.md`.
+fn toggle_markdown_extension(path: &Path) -> PathBuf {
+    if path.extension().is_some_and(|ext| ext == "md") {
+        return path.with_extension("");
+    }
+    let mut toggled = path.as_os_str().to_owned();
+    toggled.push(".md");
+    PathBuf::from(toggled)
+}
+
+/// Derive a display title from a file path with no better title available.
+///
+/// Converts the file stem to title case, treating `-` and `_` as word
+/// separators (e.g., `my-cool_note.txt` becomes `My Cool Note`).
+#[must_use]
+pub fn humanize_title(path: &Path) -> String {
+    let Some(stem) = path.file_stem() else {
+        return "Unknown".to_string();
+    };
+
+    let title = stem
+        .to_string_lossy()
+        .split(['-', '_', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if title.is_empty() {
+        "Unknown".to_string()
+    } else {
+        title
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(path: &str) -> Document {
+        Document {
+            path: PathBuf::from(path),
+            title: "Title".to_string(),
+            category: "cat".to_string(),
+            tags: vec!["a".to_string()],
+            encrypted: false,
+            word_count: Some(3),
+            aliases: vec![],
+            content_type: None,
+        }
+    }
+
+    #[test]
+    fn load_errors_name_the_manifest() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let manifest_path = temp_dir.path().join(MANIFEST_FILE);
+        std::fs::write(&manifest_path, "not valid json").unwrap();
+
+        let error = Corpus::load(temp_dir.path()).unwrap_err();
+        assert!(matches!(error, CorpusError::ParseError { .. }));
+        assert_eq!(error.path(), manifest_path);
+        assert!(error.to_string().starts_with(&format!(
+            "Failed to parse manifest {}: ",
+            manifest_path.display()
+        )));
+
+        // A manifest that is a directory can't be read
+        std::fs::remove_file(&manifest_path).unwrap();
+        std::fs::create_dir(&manifest_path).unwrap();
+        let error = Corpus::load(temp_dir.path()).unwrap_err();
+        assert!(matches!(error, CorpusError::ReadError { .. }));
+        assert!(
+            error
+                .to_string()
+                .contains(&manifest_path.display().to_string())
+        );
+    }
+
+    #[test]
+    fn validate_flags_documents_outside_their_category() {
+        let mut manifest = Manifest::empty();
+        manifest.documents = vec![
+            document("cat/one.md"),
+            document("cat/nested/two.md"),
+            document("other/three.md"),
+            document("catalog/four.md"),
+            document("five.md"),
+        ];
+        let corpus = Corpus {
+            root: PathBuf::from("/corpus"),
+            manifest,
+        };
+
+        let issues = corpus.validate();
+        let paths: Vec<&Path> = issues.iter().map(ValidationIssue::path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("other/three.md"),
+                Path::new("catalog/four.md"),
+                Path::new("five.md"),
+            ]
+        );
+        assert_eq!(
+            issues[0].to_string(),
+            "category 'cat' doesn't match directory 'other'"
+        );
+    }
+
+    #[test]
+    fn validate_flags_paths_listed_more_than_once() {
+        let mut manifest = Manifest::empty();
+        manifest.documents = vec![
+            document("cat/one.md"),
+            document("cat/two.md"),
+            document("cat/one.md"),
+            document("cat/one.md"),
+        ];
+        let corpus = Corpus {
+            root: PathBuf::from("/corpus"),
+            manifest,
+        };
+
+        let issues = corpus.validate();
+        assert_eq!(
+            issues,
+            [ValidationIssue::DuplicatePath {
+                path: PathBuf::from("cat/one.md"),
+                count: 3,
+            }]
+        );
+        assert_eq!(issues[0].kind(), "duplicate_path");
+        assert_eq!(issues[0].to_string(), "listed 3 times in the manifest");
+    }
+
+    #[test]
+    fn check_files_reports_missing_and_unreadable_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("cat")).unwrap();
+        std::fs::write(temp_dir.path().join("cat/one.md"), "One").unwrap();
+        // A file where a directory is expected can't be opened either
+        std::fs::write(temp_dir.path().join("cat/two.md"), "Two").unwrap();
+
+        let mut manifest = Manifest::empty();
+        manifest.documents = vec![
+            document("cat/one.md"),
+            document("cat/missing.md"),
+            document("cat/two.md/three.md"),
+        ];
+        let corpus = Corpus {
+            root: temp_dir.path().to_path_buf(),
+            manifest,
+        };
+
+        let issues = corpus.check_files();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(
+            issues[0],
+            ValidationIssue::MissingFile {
+                path: PathBuf::from("cat/missing.md")
+            }
+        );
+        assert_eq!(issues[0].to_string(), "file is missing");
+        assert!(
+            matches!(&issues[1], ValidationIssue::Unreadable { path, .. }
+            if path == Path::new("cat/two.md/three.md"))
+        );
+        assert!(issues[1].to_string().starts_with("file can't be read: "));
+    }
+
+    #[test]
+    fn find_document_matches_extension_less_paths() {
+        let mut manifest = Manifest::empty();
+        manifest.documents = vec![
+            document("notes/todo"),
+            document("notes/todo.txt"),
+            document("notes/guide.md"),
+            document("notes/v1.2"),
+        ];
+
+        let find = |path: &str| {
+            manifest
+                .find_document_index(Path::new(path))
+                .map(|index| manifest.documents[index].path.clone())
+        };
+        assert_eq!(find("notes/todo"), Some(PathBuf::from("notes/todo")));
+        assert_eq!(find("notes/todo.md"), Some(PathBuf::from("notes/todo")));
+        assert_eq!(
+            find("notes/todo.txt"),
+            Some(PathBuf::from("notes/todo.txt"))
+        );
+        assert_eq!(find("notes/guide"), Some(PathBuf::from("notes/guide.md")));
+        assert_eq!(find("notes/v1.2.md"), Some(PathBuf::from("notes/v1.2")));
+        assert_eq!(find("notes/missing"), None);
+    }
+
+    #[test]
+    fn jsonl_manifest_round_trips() {
+        let mut manifest = Manifest::empty();
+        manifest.name = Some("Notes".to_string());
+        manifest.encryption_salt = Some("00ff".to_string());
+        manifest.documents = vec![document("cat/one.md"), document("cat/two.md")];
+
+        let contents = manifest.encode(ManifestFormat::Jsonl).unwrap();
+        assert_eq!(contents.lines().count(), 3);
+        assert!(contents.ends_with('\n'));
+
+        let parsed = Manifest::parse(&contents, ManifestFormat::Jsonl).unwrap();
+        assert_eq!(parsed.version, "1");
+        assert_eq!(parsed.name.as_deref(), Some("Notes"));
+        assert_eq!(parsed.encryption_salt.as_deref(), Some("00ff"));
+        assert_eq!(parsed.documents.len(), 2);
+        assert_eq!(parsed.documents[1].path, PathBuf::from("cat/two.md"));
+    }
+
+    #[test]
+    fn jsonl_manifest_ignores_blank_lines() {
+        let contents = format!(
+            "{{\"version\":\"1\"}}\n\n{}\n",
+            document("cat/one.md").to_jsonl().unwrap()
+        );
+        let parsed = Manifest::parse(&contents, ManifestFormat::Jsonl).unwrap();
+        assert_eq!(parsed.documents.len(), 1);
+        assert!(parsed.encryption_salt.is_none());
+    }
+
+    #[test]
+    fn empty_jsonl_manifest_parses_as_empty() {
+        let parsed = Manifest::parse("", ManifestFormat::Jsonl).unwrap();
+        assert!(parsed.documents.is_empty());
+    }
+
+    #[test]
+    fn jsonl_manifest_rejects_invalid_lines() {
+        let contents = "{\"version\":\"1\"}\nnot json\n";
+        assert!(Manifest::parse(contents, ManifestFormat::Jsonl).is_err());
+    }
+
+    #[test]
+    fn content_type_inferred_from_extension() {
+        assert_eq!(infer_content_type(Path::new("notes/a.md")), "text/markdown");
+        assert_eq!(infer_content_type(Path::new("data/sales.CSV")), "text/csv");
+        assert_eq!(infer_content_type(Path::new("src/main.rs")), "text/x-rust");
+        assert_eq!(
+            infer_content_type(Path::new("notes/README")),
+            DEFAULT_CONTENT_TYPE
+        );
+        assert_eq!(
+            infer_content_type(Path::new("a.unknown")),
+            DEFAULT_CONTENT_TYPE
+        );
+
+        assert!(is_code_content_type("text/x-rust"));
+        assert!(!is_code_content_type("text/csv"));
+        assert!(!is_code_content_type(DEFAULT_CONTENT_TYPE));
+    }
+
+    #[test]
+    fn content_type_round_trips_through_manifest() {
+        let mut csv = document("data/sales.csv");
+        csv.content_type = Some("text/csv".to_string());
+        let mut manifest = Manifest::empty();
+        manifest.documents = vec![csv, document("cat/one.md")];
+
+        for format in [ManifestFormat::Json, ManifestFormat::Jsonl] {
+            let contents = manifest.encode(format).unwrap();
+            // Unknown types aren't written out
+            assert_eq!(contents.matches("content_type").count(), 1);
+
+            let parsed = Manifest::parse(&contents, format).unwrap();
+            assert_eq!(
+                parsed.documents[0].content_type.as_deref(),
+                Some("text/csv")
+            );
+            assert_eq!(parsed.documents[0].resolved_content_type(), "text/csv");
+            assert_eq!(parsed.documents[1].content_type, None);
+            assert_eq!(parsed.documents[1].resolved_content_type(), "text/markdown");
+        }
+    }
+
+    #[test]
+    fn corpus_name_defaults_to_directory() {
+        let mut corpus = Corpus {
+            root: PathBuf::from("/home/me/work-notes"),
+            manifest: Manifest::empty(),
+        };
+        assert_eq!(corpus.name(), "work-notes");
+
+        corpus.manifest.name = Some("Work".to_string());
+        assert_eq!(corpus.name(), "Work");
+    }
+
+    #[test]
+    fn humanize_title_from_stem() {
+        assert_eq!(
+            humanize_title(Path::new("my-cool_note.txt")),
+            "My Cool Note"
+        );
+        assert_eq!(
+            humanize_title(Path::new("notes/aws-lambda.md")),
+            "Aws Lambda"
+        );
+    }
+
+    #[test]
+    fn humanize_title_preserves_inner_case() {
+        assert_eq!(humanize_title(Path::new("readme_IAM.txt")), "Readme IAM");
+    }
+
+    #[test]
+    fn humanize_title_collapses_separators() {
+        assert_eq!(humanize_title(Path::new("--a__b-.txt")), "A B");
+    }
+
+    #[test]
+    fn humanize_title_without_words() {
+        assert_eq!(humanize_title(Path::new("___.txt")), "Unknown");
+        assert_eq!(humanize_title(Path::new("")), "Unknown");
+    }
+
+    #[test]
+    fn ignored_corpus_paths_cover_special_files() {
+        for path in [
+            ".index",
+            ".index/meta.json",
+            ".git/HEAD",
+            "notes/.git/config",
+            "manifest.json",
+            "manifest.jsonl",
+            ".locked",
+            ".gitignore",
+            "notes/.ignore",
+            ".rgignore",
+            ".manifest.json.tmp",
+            "notes/.lambda.md.tmp",
+            "manifest.json.bak",
+            "notes/lambda.md~",
+        ] {
+            assert!(is_ignored_corpus_path(Path::new(path)), "{path}");
+        }
+    }
+
+    #[test]
+    fn ignored_corpus_paths_leave_documents_alone() {
+        for path in [
+            "notes/lambda.md",
+            "index/notes.md",
+            "notes/git.md",
+            "notes/manifest.md",
+            "notes/tmp.md",
+            "notes/lambda.tmp",
+            "2024/01/02/backup.md",
+        ] {
+            assert!(!is_ignored_corpus_path(Path::new(path)), "{path}");
+        }
+    }
+
+    #[test]
+    fn ignored_corpus_globs_agree_with_helper() {
+        for glob in IGNORED_CORPUS_GLOBS {
+            let example = glob.replace('*', "example");
+            assert!(is_ignored_corpus_path(Path::new(&example)), "{glob}");
         }
     }
 }
@@ -9,6 +9,7 @@
 //! - [`commands`] - High-level operations (search, list, add, get)
 //! - [`corpus`] - Document and manifest types
 //! - [`search`] - Search backend trait and implementations
+//! - [`query`] - Structured metadata queries over manifest fields
 //! - [`storage`] - Storage backend trait and implementations
 //! - [`config`] - Configuration loading
 //! - [`cli`] - Command-line interface definitions
@@ -17,8 +18,12 @@ pub mod cli;
 pub mod commands;
 pub mod config;
 pub mod corpus;
+pub mod query;
 pub mod search;
 pub mod storage;
 
 #[cfg(feature = "mcp")]
 pub mod mcp;
+
+#[cfg(feature = "tui")]
+pub mod tui;
@@ -1,119 +1,1013 @@
-use std::io::Read;
+use std::collections::HashSet;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
-use kvault::cli::{Cli, Commands};
-use kvault::commands;
+use kvault::cli::{
+    AddArgs, Backend, CategoryFix, Cli, Commands, GraphFormat, HighlightMode, OutputFormat,
+    SearchArgs, category_match, output_format,
+};
+use kvault::commands::{self, DocumentInfo, ListCategory, ListOutcome};
+use kvault::corpus::DEFAULT_CONTENT_TYPE;
+use kvault::search::format::{Layout, PlainFormatter, ResultFormatter};
+#[cfg(feature = "ranked")]
+use kvault::search::tantivy::Rebuild;
+use kvault::search::{SearchOptions, SearchResult, SnippetStrategy};
+
+/// Exit code used by `--fail-on-empty` when there are no results.
+const EXIT_NO_RESULTS: i32 = 3;
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    if let Some(profile) = cli.profile {
+        kvault::config::set_profile(profile);
+    }
 
     match cli.command {
-        Some(Commands::Search {
+        Some(Commands::Search(args)) => search(args),
+        Some(Commands::List {
+            json_schema: true, ..
+        }) => {
+            println!("{}", commands::manifest_schema()?);
+            Ok(())
+        }
+        Some(Commands::List {
+            category,
+            all_categories,
+            category_prefix,
+            tags,
+            word_count,
+            dedupe,
+            broken,
+            limit,
+            offset,
+            format,
+            json,
+            fail_on_empty,
+            ..
+        }) => {
+            let format = output_format(format, json);
+            let outcome = commands::list(
+                ListCategory::from_flags(category.as_deref(), all_categories),
+                category_match(category_prefix),
+                &tags,
+                word_count,
+                dedupe,
+                broken,
+            )?;
+            print_list(&outcome, word_count, (offset, limit), format, fail_on_empty)
+        }
+        Some(Commands::Query { expr }) => query(&expr),
+        Some(Commands::Add(args)) => add(args),
+        Some(Commands::Get {
+            path,
+            line_range,
+            limit_bytes,
+            resolve_links,
+        }) => {
+            let content = commands::get(&path, line_range, limit_bytes, resolve_links)?;
+            print!("{content}");
+            Ok(())
+        }
+        Some(Commands::Links {
+            check_all: true, ..
+        }) => check_all_links(),
+        // Clap requires a path unless --check-all is given
+        Some(Commands::Links { path, .. }) => links(&path.unwrap_or_default()),
+        Some(Commands::Remove { path }) => remove(&path),
+        Some(Commands::Update { path, file }) => update(&path, file),
+        Some(Commands::Tag { path, add, remove }) => tag(&path, &add, &remove),
+        Some(Commands::TagGraph { format }) => tag_graph(format),
+        #[cfg(feature = "similarity")]
+        Some(Commands::Duplicates { merge, similarity }) => duplicates(similarity, merge),
+        #[cfg(not(feature = "similarity"))]
+        Some(Commands::Duplicates { merge }) => duplicates(None, merge),
+        Some(Commands::Verify {
+            fix,
+            fix_permissions,
+            ci: false,
+        }) => verify(fix, fix_permissions),
+        Some(Commands::Verify { ci: true, .. }) => verify_ci(),
+        Some(Commands::Sync) => sync(),
+        Some(Commands::GenIndex { output }) => gen_index(&output),
+        Some(Commands::Lock { corpus }) => set_locked(corpus.as_deref(), true),
+        Some(Commands::Unlock { corpus }) => set_locked(corpus.as_deref(), false),
+        #[cfg(feature = "ranked")]
+        Some(Commands::Index {
+            compact,
+            resume,
+            full,
+            force,
+        }) => index(compact, rebuild_mode(resume, full), force),
+        #[cfg(feature = "ranked")]
+        Some(Commands::TermStats { term }) => term_stats(&term),
+        #[cfg(feature = "ranked")]
+        Some(Commands::Compare {
             query,
             limit,
             category,
-            case_sensitive,
-            backend,
-            fuzzy,
-        }) => {
-            // Validate fuzzy parameter
-            if let Some(distance) = fuzzy
-                && distance > 2
-            {
-                anyhow::bail!("Fuzzy edit distance must be 0-2, got {distance}");
-            }
+            all_categories,
+        }) => compare(&query, limit, category, all_categories),
+        #[cfg(feature = "mcp")]
+        Some(Commands::Serve { allowed_categories }) => {
+            tokio::runtime::Runtime::new()?.block_on(kvault::mcp::serve(allowed_categories))
+        }
+        None => {
+            Cli::parse_from(["kvault", "--help"]);
+            Ok(())
+        }
+    }
+}
 
-            let results =
-                commands::search(&query, limit, category, case_sensitive, backend, fuzzy)?;
+/// Exit with [`EXIT_NO_RESULTS`] if `--fail-on-empty` was given and nothing
+/// was found. Output has already been printed, so only the status changes.
+fn exit_if_empty(fail_on_empty: bool, empty: bool) {
+    if fail_on_empty && empty {
+        std::process::exit(EXIT_NO_RESULTS);
+    }
+}
 
-            if results.is_empty() {
-                println!("No matches found for '{query}'");
-                return Ok(());
-            }
+/// Read content for a new document from a file, stdin, or a template.
+///
+/// A template is used only when stdin is a terminal or provides no content.
+fn read_content(
+    file: Option<String>,
+    template: Option<String>,
+    title: &str,
+) -> anyhow::Result<String> {
+    if let Some(path) = file {
+        return std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read file {path}: {e}"));
+    }
 
-            for result in &results {
-                let score_str = result
-                    .score
-                    .map(|s| format!(" (score: {s:.2})"))
-                    .unwrap_or_default();
-                println!(
-                    "{}: {} (line {}){score_str}",
-                    result.title,
-                    result.path.display(),
-                    result.line_number
-                );
-                println!("  {}", result.matched_line);
-            }
+    let mut buf = String::new();
+    if template.is_none() || !std::io::stdin().is_terminal() {
+        std::io::stdin().read_to_string(&mut buf)?;
+    }
 
-            println!("\n{} result(s) found", results.len());
-            Ok(())
+    match template {
+        Some(name) if buf.trim().is_empty() => commands::render_template(&name, title),
+        _ => Ok(buf),
+    }
+}
+
+/// Add a document from flags and stdin (or a file), or from JSON on stdin.
+fn add(args: AddArgs) -> anyhow::Result<()> {
+    let AddArgs {
+        title,
+        category,
+        tags,
+        file,
+        template,
+        stdin_json,
+        stdin_jsonl,
+        no_duplicate_titles,
+        manifest_only,
+        corpus,
+    } = args;
+    let corpus = corpus.as_deref();
+
+    let results = if stdin_jsonl {
+        let mut jsonl = String::new();
+        std::io::stdin().read_to_string(&mut jsonl)?;
+        commands::add_jsonl(&jsonl, no_duplicate_titles, corpus)?
+    } else if stdin_json {
+        let mut json = String::new();
+        std::io::stdin().read_to_string(&mut json)?;
+        vec![commands::add_json(&json, no_duplicate_titles, corpus)?]
+    } else if manifest_only {
+        vec![commands::add_manifest_only(
+            &title.unwrap_or_default(),
+            &category.unwrap_or_default(),
+            commands::parse_tags(tags),
+            no_duplicate_titles,
+            corpus,
+        )?]
+    } else {
+        // With no file or piped content there is no frontmatter to fall
+        // back on, and reading stdin would wait on the terminal
+        if file.is_none()
+            && std::io::stdin().is_terminal()
+            && (title.is_none() || category.is_none())
+        {
+            anyhow::bail!(
+                "Specify --title and --category, or give content with them in its \
+                frontmatter (--file or piped stdin)"
+            );
         }
-        Some(Commands::List { category }) => {
-            let documents = commands::list(category.as_deref())?;
+        let content = read_content(file, template, title.as_deref().unwrap_or_default())?;
+        // Flags override the frontmatter, which isn't stored
+        let (frontmatter, content) = commands::split_frontmatter(&content)?;
+        let Some(title) = title.or(frontmatter.title) else {
+            anyhow::bail!("Specify --title, or set `title` in the content's frontmatter");
+        };
+        let Some(category) = category.or(frontmatter.category) else {
+            anyhow::bail!("Specify --category, or set `category` in the content's frontmatter");
+        };
 
-            if documents.is_empty() {
-                println!("No documents found.");
-                return Ok(());
-            }
+        if content.trim().is_empty() {
+            anyhow::bail!("Content cannot be empty");
+        }
+
+        let tags = if tags.is_some() {
+            commands::parse_tags(tags)
+        } else {
+            frontmatter.tags
+        };
+        vec![commands::add(
+            &title,
+            content,
+            &category,
+            tags,
+            no_duplicate_titles,
+            corpus,
+        )?]
+    };
+
+    for result in results {
+        println!("Added: {}", result.title);
+        println!("  Category: {}", result.category);
+        println!("  Path: {}", result.path.display());
+    }
+
+    Ok(())
+}
 
-            for doc in &documents {
-                let tags = if doc.tags.is_empty() {
-                    String::new()
+/// Print listed documents in the requested format.
+///
+/// `page` is the `--offset` and `--limit` window; when either is given, text
+/// output ends with a "Showing X-Y of Z" footer.
+fn print_list(
+    outcome: &ListOutcome,
+    word_count: bool,
+    page: (usize, Option<usize>),
+    format: OutputFormat,
+    fail_on_empty: bool,
+) -> anyhow::Result<()> {
+    let (offset, limit) = page;
+    let total = outcome.documents.len();
+    let start = offset.min(total);
+    let end = limit.map_or(total, |limit| start.saturating_add(limit).min(total));
+    let documents = &outcome.documents[start..end];
+
+    print_warnings(&outcome.warnings);
+    match format {
+        OutputFormat::Text => {
+            print_documents(documents, word_count);
+            if (offset > 0 || limit.is_some()) && total > 0 {
+                if documents.is_empty() {
+                    println!("\nShowing none of {total}");
                 } else {
-                    format!(" [{}]", doc.tags.join(", "))
-                };
-                println!("{}: {}{tags}", doc.category, doc.title);
-                println!("  {}", doc.path.display());
+                    println!("\nShowing {}-{end} of {total}", start + 1);
+                }
+            }
+        }
+        OutputFormat::Csv => write_documents_csv(documents)?,
+        OutputFormat::Paths => {
+            for doc in documents {
+                println!("{}", doc.path.display());
             }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(documents)?),
+    }
+    exit_if_empty(fail_on_empty, documents.is_empty());
+    Ok(())
+}
 
-            Ok(())
+/// Print each link in a document and where it leads.
+fn links(path: &str) -> anyhow::Result<()> {
+    for status in commands::links(path)? {
+        match status.resolved {
+            Some(target) => println!(
+                "line {}: {} -> {}",
+                status.link.line,
+                status.link,
+                target.display()
+            ),
+            None => println!("line {}: {} (broken)", status.link.line, status.link),
         }
-        Some(Commands::Add {
-            title,
-            category,
-            tags,
-            file,
-        }) => {
-            let content = if let Some(path) = file {
-                std::fs::read_to_string(&path)
-                    .map_err(|e| anyhow::anyhow!("Failed to read file {path}: {e}"))?
-            } else {
-                let mut buf = String::new();
-                std::io::stdin().read_to_string(&mut buf)?;
-                buf
-            };
+    }
+    Ok(())
+}
+
+/// Report broken links across all corpora, failing if there are any.
+fn check_all_links() -> anyhow::Result<()> {
+    let outcome = commands::check_all_links()?;
+    print_warnings(&outcome.warnings);
+
+    let broken: Vec<_> = outcome
+        .links
+        .iter()
+        .filter(|status| status.resolved.is_none())
+        .collect();
+    for status in &broken {
+        println!(
+            "{}:{}: {}",
+            status.source.display(),
+            status.link.line,
+            status.link
+        );
+    }
+
+    if !broken.is_empty() {
+        anyhow::bail!(
+            "{} broken link(s) out of {}",
+            broken.len(),
+            outcome.links.len()
+        );
+    }
+    println!("All {} link(s) resolve", outcome.links.len());
+    Ok(())
+}
+
+/// Report manifest and file issues, or what `--fix` and `--fix-permissions`
+/// changed. Fails if any issue is left unresolved.
+fn verify(fix: Option<CategoryFix>, fix_permissions: bool) -> anyhow::Result<()> {
+    let outcome = commands::verify(fix, fix_permissions)?;
+    let warnings: Vec<String> = outcome
+        .load_errors
+        .iter()
+        .map(|(_, error)| error.clone())
+        .chain(outcome.warnings.iter().cloned())
+        .collect();
+    print_warnings(&warnings);
+
+    for (root, issue) in &outcome.issues {
+        println!("{}: {issue}", root.join(issue.path()).display());
+    }
+
+    let total = outcome.issues.len();
+    if (fix.is_some() || fix_permissions) && total > 0 {
+        println!("Fixed {} of {total} issue(s)", outcome.fixed);
+    }
+    if outcome.fixed < total {
+        anyhow::bail!("{} issue(s) found", total - outcome.fixed);
+    }
+    if total == 0 {
+        println!("No issues found");
+    }
+    Ok(())
+}
+
+fn query(expr: &str) -> anyhow::Result<()> {
+    let outcome = commands::query_metadata(expr)?;
+    print_warnings(&outcome.warnings);
+    print_documents(&outcome.documents, false);
+    Ok(())
+}
+
+fn sync() -> anyhow::Result<()> {
+    let outcome = commands::reindex_manifest()?;
+    for warning in &outcome.warnings {
+        eprintln!("Warning: {warning}");
+    }
+
+    for path in &outcome.added {
+        println!("Added: {}", path.display());
+    }
+    for path in &outcome.removed {
+        println!("Removed: {}", path.display());
+    }
+    println!(
+        "{} added, {} removed",
+        outcome.added.len(),
+        outcome.removed.len()
+    );
+    Ok(())
+}
+
+/// Print every problem `verify` finds, including manifests that fail to
+/// load, as one JSON object per line, failing if there are any.
+fn verify_ci() -> anyhow::Result<()> {
+    let outcome = commands::verify(None, false)?;
+
+    for (path, error) in &outcome.load_errors {
+        let report = serde_json::json!({"kind": "manifest_error", "path": path, "message": error});
+        println!("{report}");
+    }
+    for (root, issue) in &outcome.issues {
+        let report = serde_json::json!({
+            "kind": issue.kind(),
+            "path": root.join(issue.path()),
+            "message": issue.to_string(),
+        });
+        println!("{report}");
+    }
+
+    let total = outcome.load_errors.len() + outcome.issues.len();
+    if total > 0 {
+        anyhow::bail!("{total} issue(s) found");
+    }
+    Ok(())
+}
 
-            if content.trim().is_empty() {
-                anyhow::bail!("Content cannot be empty");
+/// Print duplicate clusters, one path per line, or what merging removed.
+fn duplicates(similarity: Option<f64>, merge: bool) -> anyhow::Result<()> {
+    let outcome = commands::find_duplicates(similarity, merge)?;
+    print_warnings(&outcome.warnings);
+
+    if outcome.clusters.is_empty() {
+        println!("No duplicates found");
+        return Ok(());
+    }
+
+    for cluster in &outcome.clusters {
+        let Some((canonical, others)) = cluster.paths.split_first() else {
+            continue;
+        };
+        let kind = if cluster.exact {
+            "identical"
+        } else {
+            "similar"
+        };
+        println!("{} ({kind}):", canonical.display());
+        for path in others {
+            if merge && cluster.exact {
+                println!("  merged {}", path.display());
+            } else {
+                println!("  {}", path.display());
             }
+        }
+    }
+    Ok(())
+}
 
-            let tag_list = commands::parse_tags(tags);
+/// How `kvault index` builds each index: resumed, from scratch, or (`None`)
+/// incrementally.
+#[cfg(feature = "ranked")]
+fn rebuild_mode(resume: bool, full: bool) -> Option<Rebuild> {
+    if resume {
+        Some(Rebuild::Resume)
+    } else if full {
+        Some(Rebuild::Full)
+    } else {
+        None
+    }
+}
 
-            let result = commands::add(&title, &content, &category, tag_list)?;
+/// Update, rebuild (optionally resuming), or compact every corpus's search
+/// index.
+#[cfg(feature = "ranked")]
+fn index(compact: bool, rebuild: Option<Rebuild>, force: bool) -> anyhow::Result<()> {
+    if compact {
+        println!("Compacting search index...");
+        let count = commands::compact_all()?;
+        println!("\nCompacted {count} corpus(es)");
+        return Ok(());
+    }
 
-            println!("Added: {}", result.title);
-            println!("  Category: {}", result.category);
-            println!("  Path: {}", result.path.display());
+    println!("Building search index...");
+    let count = commands::index_all(rebuild, force)?;
+    println!("\nIndexed {count} corpus(es)");
+    Ok(())
+}
 
-            Ok(())
+/// Print a term's index statistics for each indexed corpus.
+#[cfg(feature = "ranked")]
+fn term_stats(term: &str) -> anyhow::Result<()> {
+    for (name, stats) in commands::term_stats(term)? {
+        println!("{name}: {}", stats.term);
+        println!("  Documents: {} of {}", stats.doc_freq, stats.num_docs);
+        println!("  Occurrences: {}", stats.term_freq);
+        println!("  IDF: {:.3}", stats.idf());
+    }
+    Ok(())
+}
+
+/// Print each backend's results for a query, then the documents only one
+/// of them found.
+#[cfg(feature = "ranked")]
+fn compare(
+    query: &str,
+    limit: Option<usize>,
+    category: Option<String>,
+    all_categories: bool,
+) -> anyhow::Result<()> {
+    let options = SearchOptions {
+        limit,
+        category,
+        all_categories,
+        ..SearchOptions::default()
+    };
+    let comparison = commands::compare_backends(query, &options)?;
+    let formatter = plain_formatter(None, false, false, None);
+
+    for (name, outcome) in [
+        ("ripgrep", &comparison.ripgrep),
+        ("ranked", &comparison.ranked),
+    ] {
+        print_warnings(&outcome.warnings);
+        println!("== {name} ==");
+        if outcome.results.is_empty() {
+            println!("No matches found for '{query}'");
+        } else {
+            println!("{}", formatter.format(&outcome.results));
         }
-        Some(Commands::Get { path }) => {
-            let content = commands::get(&path)?;
-            print!("{content}");
-            Ok(())
+        println!();
+    }
+
+    println!("== differences ==");
+    let only_ripgrep = comparison.only_ripgrep();
+    let only_ranked = comparison.only_ranked();
+    if only_ripgrep.is_empty() && only_ranked.is_empty() {
+        println!("Both backends found the same documents");
+    }
+    for (name, paths) in [("ripgrep", only_ripgrep), ("ranked", only_ranked)] {
+        for path in paths {
+            println!("Only {name}: {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Print how often tags appear together, as text, DOT, or JSON.
+fn tag_graph(format: GraphFormat) -> anyhow::Result<()> {
+    let pairs = commands::tag_cooccurrence()?;
+    match format {
+        GraphFormat::Text => {
+            if pairs.is_empty() {
+                println!("No documents share tags");
+            }
+            for ((first, second), count) in &pairs {
+                println!("{first}, {second}: {count}");
+            }
+        }
+        GraphFormat::Dot => {
+            println!("graph tags {{");
+            for ((first, second), count) in &pairs {
+                println!(
+                    "  {} -- {} [weight={count}, label={count}];",
+                    dot_id(first),
+                    dot_id(second)
+                );
+            }
+            println!("}}");
+        }
+        GraphFormat::Json => {
+            let pairs: Vec<_> = pairs
+                .iter()
+                .map(|(tags, count)| serde_json::json!({"tags": [tags.0, tags.1], "count": count}))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&pairs)?);
         }
+    }
+    Ok(())
+}
+
+/// A quoted Graphviz identifier.
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Write the corpus index and report how many documents it lists.
+fn gen_index(output: &Path) -> anyhow::Result<()> {
+    let outcome = commands::generate_index(output)?;
+    print_warnings(&outcome.warnings);
+    println!(
+        "Wrote index of {} document(s) to {}",
+        outcome.documents.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Lock or unlock a corpus and print which one.
+fn set_locked(corpus: Option<&str>, locked: bool) -> anyhow::Result<()> {
+    if locked {
+        let root = commands::lock(corpus)?;
+        println!("Locked: {}", root.display());
+    } else {
+        let root = commands::unlock(corpus)?;
+        println!("Unlocked: {}", root.display());
+    }
+    Ok(())
+}
+
+/// Delete a document and print what was removed.
+fn remove(path: &str) -> anyhow::Result<()> {
+    let info = commands::remove(path)?;
+    println!("Removed: {}", info.title);
+    println!("  Path: {}", info.path.display());
+    Ok(())
+}
+
+/// Replace a document's content from stdin (or a file).
+fn update(path: &str, file: Option<String>) -> anyhow::Result<()> {
+    let content = read_content(file, None, "")?;
+    let info = commands::update(path, &content)?;
+    println!("Updated: {}", info.title);
+    println!("  Path: {}", info.path.display());
+    Ok(())
+}
+
+/// Add and/or remove tags on a document and print its updated tags.
+fn tag(path: &str, add: &[String], remove: &[String]) -> anyhow::Result<()> {
+    let add = commands::parse_tags(Some(add.join(",")));
+    let remove = commands::parse_tags(Some(remove.join(",")));
+
+    if add.is_empty() && remove.is_empty() {
+        anyhow::bail!("Specify tags to change with --add and/or --remove");
+    }
+    let info = commands::change_tags(path, &add, &remove)?;
+
+    println!("Updated: {}", info.title);
+    println!("  Tags: {}", info.tags.join(", "));
+    Ok(())
+}
+
+/// Run a search and print the results.
+fn search(args: SearchArgs) -> anyhow::Result<()> {
+    let SearchArgs {
+        query,
+        queries_from_stdin: _,
+        #[cfg(feature = "tui")]
+        interactive,
+        limit,
+        max_per_file,
+        group,
+        rank,
+        category,
+        all_categories,
+        category_prefix,
+        tags,
+        case_sensitive,
+        backend,
+        match_mode,
+        fuzzy,
         #[cfg(feature = "ranked")]
-        Some(Commands::Index) => {
-            println!("Building search index...");
-            let count = commands::index_all()?;
-            println!("\nIndexed {count} corpus(es)");
-            Ok(())
+        suggest,
+        highlight,
+        no_color,
+        format,
+        json,
+        fail_on_empty,
+        explain,
+        dedupe,
+        raw,
+        within,
+        include_metadata,
+        export_to,
+        force,
+        no_header,
+        no_footer,
+        separator,
+        order,
+    } = args;
+
+    check_search_args(fuzzy, max_per_file)?;
+    #[cfg(feature = "ranked")]
+    check_backend_args(backend, suggest)?;
+    let format = output_format(format, json);
+    let within = within.as_deref().map(read_path_list).transpose()?;
+
+    let options = SearchOptions {
+        limit,
+        max_per_file,
+        group_by_document: group,
+        score_matches: rank,
+        category,
+        all_categories,
+        category_match: category_match(category_prefix),
+        tags,
+        case_sensitive,
+        fuzzy,
+        match_mode,
+        explain,
+        cross_corpus_order: order,
+        dedupe,
+        raw_titles: raw,
+        within,
+        include_metadata,
+        allow_short_query: force,
+        // Set from `[search]` with the default limit and category by
+        // `commands::search`
+        snippet_strategy: SnippetStrategy::default(),
+    };
+    #[cfg(feature = "tui")]
+    if interactive {
+        return search_interactive(query.unwrap_or_default(), backend, &options);
+    }
+
+    let highlight = highlight.or(no_color.then_some(HighlightMode::None));
+    let formatter = plain_formatter(highlight, no_header, no_footer, separator);
+    let Some(query) = query else {
+        return search_stdin_queries(backend, &options, format, &formatter, fail_on_empty);
+    };
+
+    let outcome = commands::search(&query, backend, &options)?;
+    print_warnings(&outcome.warnings);
+    let results = outcome.results;
+
+    if let Some(dest) = export_to {
+        export_results(&results, &dest)?;
+    }
+
+    if !matches!(format, OutputFormat::Text) {
+        return print_results_as(&results, format, fail_on_empty);
+    }
+
+    if results.is_empty() {
+        #[cfg(feature = "ranked")]
+        if suggest
+            && fuzzy.is_none()
+            && let Some(suggestion) = commands::suggest(&query, backend, &options)?
+        {
+            println!("No matches for '{query}'. Did you mean '{suggestion}'?");
+            exit_if_empty(fail_on_empty, true);
+            return Ok(());
         }
-        #[cfg(feature = "mcp")]
-        Some(Commands::Serve) => tokio::runtime::Runtime::new()?.block_on(kvault::mcp::serve()),
-        None => {
-            Cli::parse_from(["kvault", "--help"]);
-            Ok(())
+
+        println!("No matches found for '{query}'");
+        exit_if_empty(fail_on_empty, true);
+        return Ok(());
+    }
+
+    println!("{}", formatter.format(&results));
+    Ok(())
+}
+
+/// Search as the query is typed and print the document picked with Enter.
+#[cfg(feature = "tui")]
+fn search_interactive(
+    query: String,
+    backend: Backend,
+    options: &SearchOptions,
+) -> anyhow::Result<()> {
+    let picked = kvault::tui::run_in_terminal(query, |query| {
+        Ok(commands::search(query, backend, options)?.results)
+    })?;
+
+    if let Some(result) = picked {
+        print!("{}", commands::read_result(&result)?);
+    }
+    Ok(())
+}
+
+/// Print search results in a non-text `format`.
+fn print_results_as(
+    results: &[SearchResult],
+    format: OutputFormat,
+    fail_on_empty: bool,
+) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Csv => write_results_csv(results)?,
+        OutputFormat::Paths => print_result_paths(results),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(results)?),
+    }
+    exit_if_empty(fail_on_empty, results.is_empty());
+    Ok(())
+}
+
+/// The formatter for text search results. Highlighting defaults to ANSI
+/// when stdout is a terminal and `NO_COLOR` is unset or empty.
+fn plain_formatter(
+    highlight: Option<HighlightMode>,
+    no_header: bool,
+    no_footer: bool,
+    separator: Option<String>,
+) -> PlainFormatter {
+    let highlight = highlight.unwrap_or_else(|| {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        if std::io::stdout().is_terminal() && !no_color {
+            HighlightMode::Ansi
+        } else {
+            HighlightMode::None
+        }
+    });
+
+    PlainFormatter::new(highlight).with_layout(Layout {
+        header: !no_header,
+        footer: !no_footer,
+        separator,
+    })
+}
+
+/// Search for each line of stdin and print the results grouped by query,
+/// as text under a `== <query> ==` header or as a JSON array of
+/// `{query, results}` (with an `error` for queries that failed). Blank
+/// lines are skipped.
+///
+/// A failing query is reported under its header without stopping the
+/// others; the command then fails once all of them have been printed.
+fn search_stdin_queries(
+    backend: Backend,
+    options: &SearchOptions,
+    format: OutputFormat,
+    formatter: &PlainFormatter,
+    fail_on_empty: bool,
+) -> anyhow::Result<()> {
+    #[derive(serde::Serialize)]
+    struct QueryResults<'a> {
+        query: &'a str,
+        results: &'a [SearchResult],
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    }
+
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    let queries: Vec<&str> = input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    let json = match format {
+        OutputFormat::Text => false,
+        OutputFormat::Json => true,
+        OutputFormat::Csv | OutputFormat::Paths => {
+            anyhow::bail!("--queries-from-stdin supports text and json output only");
+        }
+    };
+
+    let batch = commands::search_many(&queries, backend, options)?;
+    print_warnings(&batch.warnings);
+    for outcome in batch.outcomes.iter().flatten() {
+        print_warnings(&outcome.warnings);
+    }
+
+    if json {
+        let groups: Vec<QueryResults> = queries
+            .iter()
+            .zip(&batch.outcomes)
+            .map(|(query, outcome)| QueryResults {
+                query,
+                results: outcome.as_ref().map_or(&[], |outcome| &outcome.results),
+                error: outcome.as_ref().err().map(ToString::to_string),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+    } else {
+        for (i, (query, outcome)) in queries.iter().zip(&batch.outcomes).enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("== {query} ==");
+            match outcome {
+                Ok(outcome) if outcome.results.is_empty() => {
+                    println!("No matches found for '{query}'");
+                }
+                Ok(outcome) => println!("{}", formatter.format(&outcome.results)),
+                Err(e) => eprintln!("Error: {e}"),
+            }
+        }
+    }
+
+    let failed = batch
+        .outcomes
+        .iter()
+        .filter(|outcome| outcome.is_err())
+        .count();
+    if failed > 0 {
+        anyhow::bail!("{failed} of {} queries failed", queries.len());
+    }
+    exit_if_empty(
+        fail_on_empty,
+        batch
+            .outcomes
+            .iter()
+            .flatten()
+            .all(|outcome| outcome.results.is_empty()),
+    );
+    Ok(())
+}
+
+/// Read a `--within` file list: one path per line, blank lines ignored.
+fn read_path_list(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read file {}: {e}", path.display()))?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Print document listings in the human-readable list format.
+fn print_documents(documents: &[DocumentInfo], word_count: bool) {
+    if documents.is_empty() {
+        println!("No documents found.");
+        return;
+    }
+
+    for doc in documents {
+        let tags = if doc.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", doc.tags.join(", "))
+        };
+        let words = match doc.word_count {
+            Some(count) if word_count => format!(" ({count} words)"),
+            _ => String::new(),
+        };
+        // Markdown is the norm; flag anything else so it isn't read as prose
+        let content_type = if doc.content_type == DEFAULT_CONTENT_TYPE {
+            String::new()
+        } else {
+            format!(" <{}>", doc.content_type)
+        };
+        println!("{}: {}{tags}{words}{content_type}", doc.category, doc.title);
+        println!("  {}", doc.path.display());
+    }
+}
+
+/// Print warnings that didn't stop a command (failed corpora, broken
+/// links, unreadable documents) to stderr.
+fn print_warnings(warnings: &[String]) {
+    if warnings.is_empty() {
+        return;
+    }
+
+    eprintln!("Warning:\n  {}", warnings.join("\n  "));
+}
+
+/// Column headers shared by CSV output for search results and listings.
+const CSV_HEADER: [&str; 6] = ["title", "category", "tags", "path", "line", "score"];
+
+/// Reject search argument values clap can't rule out by itself.
+fn check_search_args(fuzzy: Option<u8>, max_per_file: Option<usize>) -> anyhow::Result<()> {
+    if let Some(distance) = fuzzy
+        && distance > 2
+    {
+        anyhow::bail!("Fuzzy edit distance must be 0-2, got {distance}");
+    }
+
+    if max_per_file == Some(0) {
+        anyhow::bail!("--max-per-file must be at least 1");
+    }
+    Ok(())
+}
+
+/// Reject flags that only one backend supports when the other is requested.
+#[cfg(feature = "ranked")]
+fn check_backend_args(backend: Backend, suggest: bool) -> anyhow::Result<()> {
+    if suggest && matches!(backend, Backend::Ripgrep) {
+        anyhow::bail!("ripgrep does not support --suggest; use --backend ranked or auto");
+    }
+    Ok(())
+}
+
+/// Copy the documents behind `results` into a new corpus at `dest`,
+/// reporting on stderr so it doesn't mix with the results.
+fn export_results(results: &[SearchResult], dest: &Path) -> anyhow::Result<()> {
+    let corpus = commands::export_results_to_corpus(results, dest)?;
+    eprintln!(
+        "Exported {} documents to {}",
+        corpus.documents().len(),
+        dest.display()
+    );
+    Ok(())
+}
+
+/// Print the path of each document with a result, once, in result order.
+fn print_result_paths(results: &[SearchResult]) {
+    let mut seen = HashSet::new();
+    for result in results {
+        if seen.insert(&result.path) {
+            println!("{}", result.path.display());
         }
     }
 }
+
+/// Write search results to stdout as CSV.
+fn write_results_csv(results: &[SearchResult]) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    writer.write_record(CSV_HEADER)?;
+    for result in results {
+        writer.write_record([
+            result.title.as_str(),
+            result.category.as_str(),
+            &result.tags.join(";"),
+            &result.path.display().to_string(),
+            &result
+                .line_number()
+                .map(|line| line.to_string())
+                .unwrap_or_default(),
+            &result.score.map(|s| s.to_string()).unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write document listings to stdout as CSV.
+///
+/// The `line` and `score` columns are left empty.
+fn write_documents_csv(documents: &[DocumentInfo]) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    writer.write_record(CSV_HEADER)?;
+    for doc in documents {
+        writer.write_record([
+            doc.title.as_str(),
+            doc.category.as_str(),
+            &doc.tags.join(";"),
+            &doc.path.display().to_string(),
+            "",
+            "",
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
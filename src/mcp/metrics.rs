@@ -0,0 +1,121 @@
+//! Usage counters for the MCP server.
+//!
+//! Plain atomics rather than a metrics framework: tool handlers bump them and
+//! the `get_metrics` tool reports a snapshot.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Counters shared by every clone of a server.
+#[derive(Debug, Default)]
+pub struct ServerMetrics {
+    searches: AtomicU64,
+    adds: AtomicU64,
+    errors: AtomicU64,
+    search_micros: AtomicU64,
+}
+
+/// Point-in-time copy of [`ServerMetrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Searches run, successful or not.
+    pub searches: u64,
+    /// Documents added successfully.
+    pub adds: u64,
+    /// Tool calls that returned an error.
+    pub errors: u64,
+    /// Mean search time, or zero before the first search.
+    pub average_search_latency: Duration,
+}
+
+impl ServerMetrics {
+    /// Count a search that took `elapsed`.
+    pub fn record_search(&self, elapsed: Duration) {
+        self.searches.fetch_add(1, Ordering::Relaxed);
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.search_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    /// Count a successfully added document.
+    pub fn record_add(&self) {
+        self.adds.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a tool call's result as an error if it is one, passing it
+    /// through unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns `result`'s error, if any.
+    pub fn track<T, E>(&self, result: Result<T, E>) -> Result<T, E> {
+        if result.is_err() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Read all counters.
+    #[must_use]
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let searches = self.searches.load(Ordering::Relaxed);
+        let average_micros = self
+            .search_micros
+            .load(Ordering::Relaxed)
+            .checked_div(searches)
+            .unwrap_or_default();
+
+        MetricsSnapshot {
+            searches,
+            adds: self.adds.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            average_search_latency: Duration::from_micros(average_micros),
+        }
+    }
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "searches: {}\nadds: {}\nerrors: {}\naverage search latency: {:.1} ms",
+            self.searches,
+            self.adds,
+            self.errors,
+            self.average_search_latency.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_averages_search_latency() {
+        let metrics = ServerMetrics::default();
+        assert_eq!(metrics.snapshot().average_search_latency, Duration::ZERO);
+
+        metrics.record_search(Duration::from_millis(10));
+        metrics.record_search(Duration::from_millis(30));
+        metrics.record_add();
+        let _ = metrics.track::<(), _>(Err("failed"));
+        let _ = metrics.track::<_, ()>(Ok(()));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(
+            snapshot,
+            MetricsSnapshot {
+                searches: 2,
+                adds: 1,
+                errors: 1,
+                average_search_latency: Duration::from_millis(20),
+            }
+        );
+        assert!(
+            snapshot
+                .to_string()
+                .contains("average search latency: 20.0 ms")
+        );
+    }
+}
@@ -2,8 +2,13 @@
 //!
 //! Exposes kvault functionality as MCP tools for AI editors.
 
+mod metrics;
+
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Instant;
 
 use rmcp::{
     ServerHandler, ServiceExt,
@@ -16,13 +21,20 @@ use rmcp::{
 };
 use serde::Deserialize;
 
-use crate::cli::DEFAULT_SEARCH_LIMIT;
-use crate::commands;
+use crate::cli::Backend;
+use crate::commands::{self, CorpusSummary, ListCategory, SearchOutcome};
+use crate::corpus;
+use crate::search::format::{Layout, MarkdownFormatter, ResultFormatter};
+use crate::search::{CategoryMatch, SearchOptions};
+
+pub use metrics::{MetricsSnapshot, ServerMetrics};
 
 /// Parameters for `search_knowledge` tool.
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct SearchParams {
-    #[schemars(description = "The search query")]
+    #[schemars(
+        description = "The search query; `title:`, `category:`, and `tags:` tokens filter on those fields"
+    )]
     pub query: String,
     #[schemars(description = "Maximum number of results (default: 10)")]
     pub limit: Option<usize>,
@@ -30,6 +42,12 @@ pub struct SearchParams {
     pub category: Option<String>,
     #[schemars(description = "Use case-sensitive matching (default: false)")]
     pub case_sensitive: Option<bool>,
+    #[schemars(description = "Start each result with its `## title` heading (default: true)")]
+    pub header: Option<bool>,
+    #[schemars(description = "End with the `*N result(s) found*` count (default: true)")]
+    pub footer: Option<bool>,
+    #[schemars(description = "Line to insert between results (e.g., '---')")]
+    pub separator: Option<String>,
 }
 
 /// Parameters for `list_knowledge` tool.
@@ -44,6 +62,12 @@ pub struct ListParams {
 pub struct GetParams {
     #[schemars(description = "Document path (e.g., 'aws/lambda-patterns.md')")]
     pub path: String,
+    #[schemars(
+        description = "Only return lines START:END, 1-indexed and inclusive (e.g., '40:60')"
+    )]
+    pub line_range: Option<String>,
+    #[schemars(description = "Truncate content to at most this many bytes (default: unlimited)")]
+    pub max_bytes: Option<usize>,
 }
 
 /// Parameters for `add_knowledge` tool.
@@ -57,12 +81,45 @@ pub struct AddParams {
     pub category: String,
     #[schemars(description = "Comma-separated tags")]
     pub tags: Option<String>,
+    #[schemars(
+        description = "Corpus to add to, by path or directory name (required when several are configured)"
+    )]
+    pub corpus: Option<String>,
+}
+
+/// Parameters for `remove_knowledge` tool.
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct RemoveParams {
+    #[schemars(description = "Document path (e.g., 'aws/lambda-patterns.md')")]
+    pub path: String,
+}
+
+/// Parameters for `update_knowledge` tool.
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct UpdateParams {
+    #[schemars(description = "Document path (e.g., 'aws/lambda-patterns.md')")]
+    pub path: String,
+    #[schemars(description = "New document content (markdown), replacing the old")]
+    pub content: String,
 }
 
+/// Server instructions describing the available tools.
+const BASE_INSTRUCTIONS: &str = "kvault provides searchable access to a knowledge corpus. \
+    Use search_knowledge to find documents, list_knowledge to browse, \
+    get_document to read full contents, add_knowledge to save new documents, \
+    update_knowledge to revise one, and remove_knowledge to delete one. get_metrics reports server usage counters.";
+
+/// Maximum categories listed per corpus in the server instructions.
+const MAX_INSTRUCTION_CATEGORIES: usize = 10;
+
 /// MCP server exposing kvault tools.
 #[derive(Clone)]
 pub struct KvaultServer {
     tool_router: ToolRouter<Self>,
+    instructions: String,
+    metrics: Arc<ServerMetrics>,
+    /// Categories tools may touch; `None` allows all.
+    allowed_categories: Option<Arc<HashSet<String>>>,
 }
 
 impl Default for KvaultServer {
@@ -75,49 +132,114 @@ impl Default for KvaultServer {
 impl KvaultServer {
     #[must_use]
     pub fn new() -> Self {
+        Self::with_instructions(BASE_INSTRUCTIONS.to_string())
+    }
+
+    /// Create a server that sends `instructions` to clients on initialization.
+    #[must_use]
+    pub fn with_instructions(instructions: String) -> Self {
         Self {
             tool_router: Self::tool_router(),
+            instructions,
+            metrics: Arc::default(),
+            allowed_categories: None,
         }
     }
 
+    /// Restrict every tool to documents in `categories`: other documents are
+    /// hidden from search, list, and get, and can't be added. An empty list
+    /// allows all categories.
+    #[must_use]
+    pub fn with_allowed_categories(mut self, categories: Vec<String>) -> Self {
+        // Kept lowercased, since categories compare ignoring case
+        self.allowed_categories = (!categories.is_empty()).then(|| {
+            Arc::new(
+                categories
+                    .into_iter()
+                    .map(|category| category.to_lowercase())
+                    .collect(),
+            )
+        });
+        self
+    }
+
+    fn is_allowed(&self, category: &str) -> bool {
+        self.allowed_categories
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(&category.to_lowercase()))
+    }
+
+    /// Usage counters accumulated since the server was created.
+    #[must_use]
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
     #[tool(description = "Search the knowledge corpus for documents matching a query")]
     async fn search_knowledge(
         &self,
         Parameters(params): Parameters<SearchParams>,
     ) -> Result<CallToolResult, McpError> {
-        let limit = params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
         let case_sensitive = params.case_sensitive.unwrap_or(false);
+        let layout = Layout {
+            header: params.header.unwrap_or(true),
+            footer: params.footer.unwrap_or(true),
+            separator: params.separator,
+        };
 
-        match commands::search(&params.query, limit, params.category, case_sensitive) {
-            Ok(results) => {
-                if results.is_empty() {
-                    return Ok(CallToolResult::success(vec![Content::text(format!(
-                        "No matches found for '{}'",
-                        params.query
-                    ))]));
-                }
+        if let Some(category) = &params.category
+            && !self.is_allowed(category)
+        {
+            return self.metrics.track(Err(category_not_allowed(category)));
+        }
+        // Without a category, filter to the allowed ones before limiting
+        let scoped = params.category.is_none() && self.allowed_categories.is_some();
 
-                let mut output = String::new();
-                for result in &results {
-                    let _ = write!(
-                        output,
-                        "## {}\n**File:** {}\n**Line {}:** {}\n\n",
-                        result.title,
-                        result.path.display(),
-                        result.line_number,
-                        result.matched_line
-                    );
-                }
-                let _ = write!(output, "*{} result(s) found*", results.len());
+        let options = SearchOptions {
+            limit: if scoped {
+                Some(usize::MAX)
+            } else {
+                params.limit
+            },
+            category: params.category,
+            case_sensitive,
+            ..Default::default()
+        };
 
-                Ok(CallToolResult::success(vec![Content::text(output)]))
+        let started = Instant::now();
+        let outcome = commands::search(&params.query, Backend::default(), &options).and_then(
+            |mut outcome| {
+                if scoped {
+                    let limit = match params.limit {
+                        Some(limit) => limit,
+                        None => commands::default_search_limit()?,
+                    };
+                    outcome
+                        .results
+                        .retain(|result| self.is_allowed(&result.category));
+                    outcome.results.truncate(limit);
+                }
+                Ok(outcome)
+            },
+        );
+        let result = match outcome {
+            Ok(outcome) if outcome.results.is_empty() => {
+                Ok(CallToolResult::success(vec![Content::text(format!(
+                    "No matches found for '{}'",
+                    params.query
+                ))]))
             }
+            Ok(outcome) => Ok(CallToolResult::success(vec![Content::text(
+                format_search_results(&outcome, layout),
+            )])),
             Err(e) => Err(McpError {
                 code: ErrorCode::INTERNAL_ERROR,
                 message: Cow::from(format!("Search failed: {e}")),
                 data: None,
             }),
-        }
+        };
+        self.metrics.record_search(started.elapsed());
+        self.metrics.track(result)
     }
 
     #[tool(description = "List all documents in the knowledge corpus")]
@@ -125,8 +247,23 @@ impl KvaultServer {
         &self,
         Parameters(params): Parameters<ListParams>,
     ) -> Result<CallToolResult, McpError> {
-        match commands::list(params.category.as_deref()) {
-            Ok(documents) => {
+        if let Some(category) = &params.category
+            && !self.is_allowed(category)
+        {
+            return self.metrics.track(Err(category_not_allowed(category)));
+        }
+
+        let result = match commands::list(
+            ListCategory::from_flags(params.category.as_deref(), false),
+            CategoryMatch::Exact,
+            &[],
+            false,
+            false,
+            false,
+        ) {
+            Ok(outcome) => {
+                let mut documents = outcome.documents;
+                documents.retain(|doc| self.is_allowed(&doc.category));
                 if documents.is_empty() {
                     return Ok(CallToolResult::success(vec![Content::text(
                         "No documents found.".to_string(),
@@ -149,6 +286,7 @@ impl KvaultServer {
                         doc.path.display()
                     );
                 }
+                write_warnings(&mut output, &outcome.warnings);
 
                 Ok(CallToolResult::success(vec![Content::text(output)]))
             }
@@ -157,7 +295,8 @@ impl KvaultServer {
                 message: Cow::from(format!("List failed: {e}")),
                 data: None,
             }),
-        }
+        };
+        self.metrics.track(result)
     }
 
     #[tool(description = "Get the full contents of a document by its path")]
@@ -165,14 +304,44 @@ impl KvaultServer {
         &self,
         Parameters(params): Parameters<GetParams>,
     ) -> Result<CallToolResult, McpError> {
-        match commands::get(&params.path) {
+        let line_range = match params.line_range.as_deref().map(str::parse).transpose() {
+            Ok(range) => range,
+            Err(e) => {
+                return self.metrics.track(Err(McpError {
+                    code: ErrorCode::INVALID_PARAMS,
+                    message: Cow::from(format!("Invalid line_range: {e}")),
+                    data: None,
+                }));
+            }
+        };
+
+        // Documents outside the allowed categories look like they don't exist
+        if self.allowed_categories.is_some() {
+            let hidden = match commands::document_info(&params.path) {
+                Ok(info) => !self.is_allowed(&info.category),
+                Err(_) => false,
+            };
+            if hidden {
+                return self.metrics.track(Err(McpError {
+                    code: ErrorCode::INTERNAL_ERROR,
+                    message: Cow::from(format!(
+                        "Failed to get document: Document not found: {}",
+                        params.path
+                    )),
+                    data: None,
+                }));
+            }
+        }
+
+        let result = match commands::get(&params.path, line_range, params.max_bytes, false) {
             Ok(content) => Ok(CallToolResult::success(vec![Content::text(content)])),
             Err(e) => Err(McpError {
                 code: ErrorCode::INTERNAL_ERROR,
                 message: Cow::from(format!("Failed to get document: {e}")),
                 data: None,
             }),
-        }
+        };
+        self.metrics.track(result)
     }
 
     #[tool(description = "Add a new document to the knowledge corpus")]
@@ -180,10 +349,24 @@ impl KvaultServer {
         &self,
         Parameters(params): Parameters<AddParams>,
     ) -> Result<CallToolResult, McpError> {
+        if !self.is_allowed(&params.category) {
+            return self
+                .metrics
+                .track(Err(category_not_allowed(&params.category)));
+        }
+
         let tag_list = commands::parse_tags(params.tags);
 
-        match commands::add(&params.title, &params.content, &params.category, tag_list) {
+        let result = match commands::add(
+            &params.title,
+            &params.content,
+            &params.category,
+            tag_list,
+            false,
+            params.corpus.as_deref(),
+        ) {
             Ok(result) => {
+                self.metrics.record_add();
                 let output = format!(
                     "Added document:\n- **Title:** {}\n- **Category:** {}\n- **Path:** {}",
                     result.title,
@@ -197,7 +380,154 @@ impl KvaultServer {
                 message: Cow::from(format!("Failed to add document: {e}")),
                 data: None,
             }),
+        };
+        self.metrics.track(result)
+    }
+
+    #[tool(description = "Replace the content of a document in the knowledge corpus")]
+    async fn update_knowledge(
+        &self,
+        Parameters(params): Parameters<UpdateParams>,
+    ) -> Result<CallToolResult, McpError> {
+        // Documents outside the allowed categories look like they don't exist
+        if self.allowed_categories.is_some()
+            && commands::document_info(&params.path)
+                .is_ok_and(|info| !self.is_allowed(&info.category))
+        {
+            return self.metrics.track(Err(McpError {
+                code: ErrorCode::INTERNAL_ERROR,
+                message: Cow::from(format!(
+                    "Failed to update document: Document not found: {}",
+                    params.path
+                )),
+                data: None,
+            }));
         }
+
+        let result = match commands::update(&params.path, &params.content) {
+            Ok(info) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Updated document:\n- **Title:** {}\n- **Path:** {}",
+                info.title,
+                info.path.display()
+            ))])),
+            Err(e) => Err(McpError {
+                code: ErrorCode::INTERNAL_ERROR,
+                message: Cow::from(format!("Failed to update document: {e}")),
+                data: None,
+            }),
+        };
+        self.metrics.track(result)
+    }
+
+    #[tool(description = "Delete a document from the knowledge corpus by its path")]
+    async fn remove_knowledge(
+        &self,
+        Parameters(params): Parameters<RemoveParams>,
+    ) -> Result<CallToolResult, McpError> {
+        // Documents outside the allowed categories look like they don't exist
+        if self.allowed_categories.is_some()
+            && commands::document_info(&params.path)
+                .is_ok_and(|info| !self.is_allowed(&info.category))
+        {
+            return self.metrics.track(Err(McpError {
+                code: ErrorCode::INTERNAL_ERROR,
+                message: Cow::from(format!(
+                    "Failed to remove document: Document not found: {}",
+                    params.path
+                )),
+                data: None,
+            }));
+        }
+
+        let result = match commands::remove(&params.path) {
+            Ok(info) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Removed document:\n- **Title:** {}\n- **Path:** {}",
+                info.title,
+                info.path.display()
+            ))])),
+            Err(e) => Err(McpError {
+                code: ErrorCode::INTERNAL_ERROR,
+                message: Cow::from(format!("Failed to remove document: {e}")),
+                data: None,
+            }),
+        };
+        self.metrics.track(result)
+    }
+
+    #[tool(description = "Report server usage: searches, adds, errors, and average search latency")]
+    async fn get_metrics(&self) -> Result<CallToolResult, McpError> {
+        Ok(CallToolResult::success(vec![Content::text(
+            self.metrics().to_string(),
+        )]))
+    }
+}
+
+/// Error for a category outside the server's allowed categories.
+fn category_not_allowed(category: &str) -> McpError {
+    McpError {
+        code: ErrorCode::INVALID_PARAMS,
+        message: Cow::from(format!("Category not allowed: {category}")),
+        data: None,
+    }
+}
+
+/// Format search results as markdown, bolding matched text.
+fn format_search_results(outcome: &SearchOutcome, layout: Layout) -> String {
+    let mut output = MarkdownFormatter::new(layout).format(&outcome.results);
+    write_warnings(&mut output, &outcome.warnings);
+    output
+}
+
+/// Build server instructions that tell the client what each corpus covers.
+fn build_instructions(summaries: &[CorpusSummary]) -> String {
+    let mut output = BASE_INSTRUCTIONS.to_string();
+    if summaries.is_empty() {
+        return output;
+    }
+
+    output.push_str("\n\nAvailable corpora:");
+    for summary in summaries {
+        let categories = summary
+            .categories
+            .iter()
+            .take(MAX_INSTRUCTION_CATEGORIES)
+            .map(|(category, count)| format!("{category} ({count})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = write!(
+            output,
+            "\n- {} ({} documents)",
+            summary.name, summary.document_count
+        );
+        if !categories.is_empty() {
+            let _ = write!(output, ": {categories}");
+        }
+    }
+    output
+}
+
+/// Drop categories outside `allowed` (compared ignoring case) from each
+/// summary, recounting its documents from those left.
+fn restrict_summaries(summaries: &mut [CorpusSummary], allowed: &[String]) {
+    for summary in summaries {
+        summary.categories.retain(|(category, _)| {
+            allowed
+                .iter()
+                .any(|allowed| corpus::labels_match(allowed, category))
+        });
+        summary.document_count = summary.categories.iter().map(|(_, count)| count).sum();
+    }
+}
+
+/// Append per-corpus warnings to a tool's text output.
+fn write_warnings(output: &mut String, warnings: &[String]) {
+    if warnings.is_empty() {
+        return;
+    }
+
+    let _ = write!(output, "\n\n**Warnings:**\n");
+    for warning in warnings {
+        let _ = writeln!(output, "- {warning}");
     }
 }
 
@@ -205,12 +535,7 @@ impl KvaultServer {
 impl ServerHandler for KvaultServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
-            instructions: Some(
-                "kvault provides searchable access to a knowledge corpus. \
-                Use search_knowledge to find documents, list_knowledge to browse, \
-                get_document to read full contents, and add_knowledge to save new documents."
-                    .into(),
-            ),
+            instructions: Some(self.instructions.clone()),
             capabilities: ServerCapabilities::builder().enable_tools().build(),
             ..Default::default()
         }
@@ -219,12 +544,261 @@ impl ServerHandler for KvaultServer {
 
 /// Start the MCP server with stdio transport.
 ///
+/// The server instructions summarize the configured corpora and their top
+/// categories as of startup, so clients know what is searchable.
+///
+/// # Arguments
+///
+/// * `allowed_categories` - Categories the tools are limited to (see
+///   [`KvaultServer::with_allowed_categories`]); empty allows all
+///
 /// # Errors
 ///
 /// Returns an error if the server fails to start or encounters a fatal error.
-pub async fn serve() -> anyhow::Result<()> {
-    let server = KvaultServer::new();
+pub async fn serve(allowed_categories: Vec<String>) -> anyhow::Result<()> {
+    let mut summaries = commands::summarize_corpora()?;
+    if !allowed_categories.is_empty() {
+        restrict_summaries(&mut summaries, &allowed_categories);
+    }
+
+    let server = KvaultServer::with_instructions(build_instructions(&summaries))
+        .with_allowed_categories(allowed_categories);
     let service = server.serve(stdio()).await?;
     service.waiting().await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ENV_LOCK, KVAULT_CONFIG_ENV};
+    use crate::search::{SearchResult, Snippet};
+    use std::ops::Range;
+    use std::path::PathBuf;
+    use std::sync::{MutexGuard, PoisonError};
+
+    #[test]
+    fn search_results_bold_matched_terms() {
+        let outcome = SearchOutcome {
+            results: vec![SearchResult {
+                path: PathBuf::from("/corpus/aws/lambda.md"),
+                title: "Lambda Patterns".to_string(),
+                category: "aws".to_string(),
+                tags: vec![],
+                snippet: Some(Snippet {
+                    text: "Best practices for lambda functions.".to_string(),
+                    highlights: vec![Range { start: 19, end: 25 }],
+                    line: Some(3),
+                }),
+                score: None,
+                hit_count: None,
+                explanation: None,
+            }],
+            warnings: vec![],
+        };
+
+        let output = format_search_results(&outcome, Layout::default());
+        assert!(output.contains("**Line 3:** Best practices for **lambda** functions."));
+        assert!(output.contains("*1 result(s) found*"));
+    }
+
+    #[test]
+    fn instructions_list_corpus_categories() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let manifest = r#"{"version": "1", "documents": [
+            {"path": "rust/a.md", "title": "A", "category": "rust"},
+            {"path": "rust/b.md", "title": "B", "category": "rust"},
+            {"path": "aws/c.md", "title": "C", "category": "aws"}
+        ]}"#;
+        std::fs::write(temp_dir.path().join("manifest.json"), manifest).unwrap();
+        let corpus = crate::corpus::Corpus::load(temp_dir.path()).unwrap();
+
+        let instructions = build_instructions(&[CorpusSummary::of(&corpus)]);
+
+        assert!(instructions.starts_with(BASE_INSTRUCTIONS));
+        assert!(instructions.contains(&format!(
+            "- {} (3 documents): rust (2), aws (1)",
+            corpus.name()
+        )));
+    }
+
+    #[test]
+    fn instructions_count_categories_ignoring_case() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let manifest = r#"{"version": "1", "documents": [
+            {"path": "AWS/a.md", "title": "A", "category": "AWS"},
+            {"path": "aws/b.md", "title": "B", "category": "aws"},
+            {"path": "rust/c.md", "title": "C", "category": "rust"}
+        ]}"#;
+        std::fs::write(temp_dir.path().join("manifest.json"), manifest).unwrap();
+        let corpus = crate::corpus::Corpus::load(temp_dir.path()).unwrap();
+
+        let instructions = build_instructions(&[CorpusSummary::of(&corpus)]);
+
+        assert!(instructions.contains("(3 documents): AWS (2), rust (1)"));
+    }
+
+    #[test]
+    fn instructions_restrict_categories_ignoring_case() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let manifest = r#"{"version": "1", "documents": [
+            {"path": "aws/a.md", "title": "A", "category": "aws"},
+            {"path": "aws/b.md", "title": "B", "category": "aws"},
+            {"path": "rust/c.md", "title": "C", "category": "rust"}
+        ]}"#;
+        std::fs::write(temp_dir.path().join("manifest.json"), manifest).unwrap();
+        let corpus = crate::corpus::Corpus::load(temp_dir.path()).unwrap();
+
+        let mut summaries = [CorpusSummary::of(&corpus)];
+        restrict_summaries(&mut summaries, &["AWS".to_string()]);
+        let instructions = build_instructions(&summaries);
+
+        assert!(instructions.contains("(2 documents): aws (2)"));
+        assert!(!instructions.contains("rust"));
+    }
+
+    /// A temporary corpus with one AWS document, and a config listing it
+    /// that `KVAULT_CONFIG` points at until dropped.
+    struct TestConfig {
+        _dir: tempfile::TempDir,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl TestConfig {
+        fn new() -> Self {
+            let lock = ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+            let dir = tempfile::TempDir::new().unwrap();
+            let corpus = dir.path().join("corpus");
+            std::fs::create_dir_all(corpus.join("aws")).unwrap();
+            std::fs::write(
+                corpus.join("aws/lambda.md"),
+                "# Lambda\n\nCold starts in lambda functions.\n",
+            )
+            .unwrap();
+            std::fs::write(
+                corpus.join("manifest.json"),
+                r#"{"version": "1", "documents": [
+                    {"path": "aws/lambda.md", "title": "Lambda", "category": "aws"}
+                ]}"#,
+            )
+            .unwrap();
+            let config_path = dir.path().join("config.toml");
+            std::fs::write(
+                &config_path,
+                format!("[corpus]\npaths = [\"{}\"]\n", corpus.display()),
+            )
+            .unwrap();
+
+            // SAFETY: Other tests setting the variable hold `ENV_LOCK`
+            unsafe {
+                std::env::set_var(KVAULT_CONFIG_ENV, &config_path);
+            }
+            Self {
+                _dir: dir,
+                _lock: lock,
+            }
+        }
+    }
+
+    impl Drop for TestConfig {
+        fn drop(&mut self) {
+            // SAFETY: As in `new`, `ENV_LOCK` is still held
+            unsafe {
+                std::env::remove_var(KVAULT_CONFIG_ENV);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_calls_update_metrics() {
+        let _config = TestConfig::new();
+        let server = KvaultServer::new();
+        let search = || SearchParams {
+            query: "lambda".to_string(),
+            limit: Some(1),
+            category: None,
+            case_sensitive: None,
+            header: None,
+            footer: None,
+            separator: None,
+        };
+
+        let _ = server.search_knowledge(Parameters(search())).await;
+        let _ = server.search_knowledge(Parameters(search())).await;
+        let invalid = server
+            .get_document(Parameters(GetParams {
+                path: "a.md".to_string(),
+                line_range: Some("9:1".to_string()),
+                max_bytes: None,
+            }))
+            .await;
+        assert!(invalid.is_err());
+
+        let snapshot = server.metrics();
+        assert_eq!(snapshot.searches, 2);
+        assert_eq!(snapshot.adds, 0);
+        assert!(snapshot.errors >= 1);
+
+        // Clones share counters, as rmcp clones the server per session
+        assert_eq!(server.clone().metrics().searches, 2);
+
+        let report = server.get_metrics().await.unwrap();
+        let text = format!("{:?}", report.content);
+        assert!(text.contains("searches: 2"), "{text}");
+    }
+
+    #[tokio::test]
+    async fn allowed_categories_reject_other_categories() {
+        let _config = TestConfig::new();
+        let server = KvaultServer::new().with_allowed_categories(vec!["aws".to_string()]);
+
+        let added = server
+            .add_knowledge(Parameters(AddParams {
+                title: "Secret".to_string(),
+                content: "Hidden.".to_string(),
+                category: "private".to_string(),
+                tags: None,
+                corpus: None,
+            }))
+            .await;
+        assert!(
+            added
+                .unwrap_err()
+                .message
+                .contains("Category not allowed: private")
+        );
+
+        let listed = server
+            .list_knowledge(Parameters(ListParams {
+                category: Some("private".to_string()),
+            }))
+            .await;
+        assert!(listed.is_err());
+
+        let removed = server
+            .remove_knowledge(Parameters(RemoveParams {
+                path: "../outside.md".to_string(),
+            }))
+            .await;
+        assert!(removed.is_err());
+
+        let updated = server
+            .update_knowledge(Parameters(UpdateParams {
+                path: "aws/lambda.md".to_string(),
+                content: " ".to_string(),
+            }))
+            .await;
+        assert!(
+            updated
+                .unwrap_err()
+                .message
+                .contains("Content cannot be empty")
+        );
+        assert_eq!(server.metrics().errors, 4);
+    }
+
+    #[test]
+    fn instructions_without_corpora_are_base() {
+        assert_eq!(build_instructions(&[]), BASE_INSTRUCTIONS);
+    }
+}
@@ -0,0 +1,416 @@
+//! Structured metadata queries over manifest fields.
+//!
+//! Parses a tiny boolean expression language and evaluates it against
+//! [`Document`] metadata, independent of any search backend.
+//!
+//! # Grammar
+//!
+//! ```text
+//! expr       := and_expr ("OR" and_expr)*
+//! and_expr   := primary ("AND" primary)*
+//! primary    := "(" expr ")" | comparison
+//! comparison := field op value
+//! field      := "category" | "tag" | "title"
+//! op         := "=" | "!=" | "~"
+//! value      := word | "quoted string"
+//! ```
+//!
+//! `=` and `!=` compare exactly, `~` is a case-insensitive substring match.
+//! For `tag`, a comparison holds if any of the document's tags satisfies it
+//! (`!=` holds if none equal the value). `AND`/`OR` are case-insensitive
+//! and `AND` binds tighter than `OR`.
+
+use thiserror::Error;
+
+use crate::corpus::{Document, labels_match};
+
+/// Errors that can occur when parsing a metadata query.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum QueryError {
+    #[error("Empty query expression")]
+    Empty,
+
+    #[error("Unterminated quoted string in query")]
+    UnterminatedString,
+
+    #[error("Unknown field '{0}' (expected category, tag, or title)")]
+    UnknownField(String),
+
+    #[error("Expected {expected}, found {found}")]
+    Unexpected { expected: String, found: String },
+}
+
+/// A manifest field that can be queried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Category,
+    Tag,
+    Title,
+}
+
+/// A comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// Exact equality.
+    Eq,
+    /// Exact inequality.
+    NotEq,
+    /// Case-insensitive substring match.
+    Contains,
+}
+
+/// A parsed metadata query expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Compare { field: Field, op: Op, value: String },
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Parse a query expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QueryError` if the expression is empty or malformed.
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Err(QueryError::Empty);
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+
+        if let Some(token) = parser.peek() {
+            return Err(QueryError::Unexpected {
+                expected: "AND, OR, or end of query".to_string(),
+                found: token.describe(),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    /// Returns true if the document's metadata satisfies this expression.
+    #[must_use]
+    pub fn matches(&self, doc: &Document) -> bool {
+        match self {
+            Self::Compare { field, op, value } => match field {
+                Field::Category => compare_label(&doc.category, *op, value),
+                Field::Title => compare(&doc.title, *op, value),
+                Field::Tag => match op {
+                    Op::NotEq => !doc.tags.iter().any(|t| labels_match(t, value)),
+                    _ => doc.tags.iter().any(|t| compare_label(t, *op, value)),
+                },
+            },
+            Self::And(left, right) => left.matches(doc) && right.matches(doc),
+            Self::Or(left, right) => left.matches(doc) || right.matches(doc),
+        }
+    }
+}
+
+/// Compare a category or tag, which ignores case (see [`labels_match`]).
+fn compare_label(actual: &str, op: Op, value: &str) -> bool {
+    match op {
+        Op::Eq => labels_match(actual, value),
+        Op::NotEq => !labels_match(actual, value),
+        Op::Contains => compare(actual, op, value),
+    }
+}
+
+fn compare(actual: &str, op: Op, value: &str) -> bool {
+    match op {
+        Op::Eq => actual == value,
+        Op::NotEq => actual != value,
+        Op::Contains => actual.to_lowercase().contains(&value.to_lowercase()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    Op(Op),
+    Word(String),
+    Quoted(String),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Self::LParen => "'('".to_string(),
+            Self::RParen => "')'".to_string(),
+            Self::Op(Op::Eq) => "'='".to_string(),
+            Self::Op(Op::NotEq) => "'!='".to_string(),
+            Self::Op(Op::Contains) => "'~'".to_string(),
+            Self::Word(w) => format!("'{w}'"),
+            Self::Quoted(q) => format!("\"{q}\""),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Self::Word(w) if w.eq_ignore_ascii_case(keyword))
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '=' => {
+                chars.next();
+                tokens.push(Token::Op(Op::Eq));
+            }
+            '~' => {
+                chars.next();
+                tokens.push(Token::Op(Op::Contains));
+            }
+            '!' => {
+                chars.next();
+                if chars.next_if_eq(&'=').is_none() {
+                    return Err(QueryError::Unexpected {
+                        expected: "'=' after '!'".to_string(),
+                        found: chars
+                            .peek()
+                            .map_or_else(|| "end of query".to_string(), |c| format!("'{c}'")),
+                    });
+                }
+                tokens.push(Token::Op(Op::NotEq));
+            }
+            '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => return Err(QueryError::UnterminatedString),
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()=!~\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn describe_next(&self) -> String {
+        self.peek()
+            .map_or_else(|| "end of query".to_string(), Token::describe)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.parse_and()?;
+        while self.peek().is_some_and(|t| t.is_keyword("OR")) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.parse_primary()?;
+        while self.peek().is_some_and(|t| t.is_keyword("AND")) {
+            self.pos += 1;
+            let right = self.parse_primary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, QueryError> {
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.parse_or()?;
+            if self.peek() != Some(&Token::RParen) {
+                return Err(QueryError::Unexpected {
+                    expected: "')'".to_string(),
+                    found: self.describe_next(),
+                });
+            }
+            self.pos += 1;
+            return Ok(expr);
+        }
+
+        let field = match self.next() {
+            Some(Token::Word(word)) => match word.to_lowercase().as_str() {
+                "category" => Field::Category,
+                "tag" | "tags" => Field::Tag,
+                "title" => Field::Title,
+                _ => return Err(QueryError::UnknownField(word)),
+            },
+            other => {
+                return Err(QueryError::Unexpected {
+                    expected: "a field name".to_string(),
+                    found: other.map_or_else(|| "end of query".to_string(), |t| t.describe()),
+                });
+            }
+        };
+
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            other => {
+                return Err(QueryError::Unexpected {
+                    expected: "an operator (=, !=, ~)".to_string(),
+                    found: other.map_or_else(|| "end of query".to_string(), |t| t.describe()),
+                });
+            }
+        };
+
+        let value = match self.next() {
+            Some(Token::Word(value) | Token::Quoted(value)) => value,
+            other => {
+                return Err(QueryError::Unexpected {
+                    expected: "a value".to_string(),
+                    found: other.map_or_else(|| "end of query".to_string(), |t| t.describe()),
+                });
+            }
+        };
+
+        Ok(Expr::Compare { field, op, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn doc(title: &str, category: &str, tags: &[&str]) -> Document {
+        Document {
+            path: PathBuf::from(format!("{category}/doc.md")),
+            title: title.to_string(),
+            category: category.to_string(),
+            tags: tags.iter().map(ToString::to_string).collect(),
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        }
+    }
+
+    #[test]
+    fn parse_simple_comparison() {
+        let expr = Expr::parse("category = aws").unwrap();
+        assert_eq!(
+            expr,
+            Expr::Compare {
+                field: Field::Category,
+                op: Op::Eq,
+                value: "aws".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let expr = Expr::parse("tag = a OR tag = b AND category = c").unwrap();
+        assert!(matches!(expr, Expr::Or(_, ref right) if matches!(**right, Expr::And(_, _))));
+    }
+
+    #[test]
+    fn matches_category_and_tag() {
+        let expr = Expr::parse("tag = lambda AND category = aws").unwrap();
+        assert!(expr.matches(&doc("Lambda", "aws", &["aws", "lambda"])));
+        assert!(!expr.matches(&doc("Lambda", "rust", &["lambda"])));
+        assert!(!expr.matches(&doc("Other", "aws", &["ec2"])));
+    }
+
+    #[test]
+    fn matches_parenthesized_or() {
+        let expr = Expr::parse("(category = aws OR category = rust) AND tag != draft").unwrap();
+        assert!(expr.matches(&doc("A", "rust", &["errors"])));
+        assert!(!expr.matches(&doc("B", "rust", &["draft"])));
+        assert!(!expr.matches(&doc("C", "go", &[])));
+    }
+
+    #[test]
+    fn matches_quoted_title_and_contains() {
+        let expr = Expr::parse(r#"title = "Lambda Patterns""#).unwrap();
+        assert!(expr.matches(&doc("Lambda Patterns", "aws", &[])));
+
+        let expr = Expr::parse("title ~ LAMBDA").unwrap();
+        assert!(expr.matches(&doc("Lambda Patterns", "aws", &[])));
+    }
+
+    #[test]
+    fn keywords_are_case_insensitive() {
+        assert!(Expr::parse("category = aws and tag = lambda").is_ok());
+    }
+
+    #[test]
+    fn empty_query_errors() {
+        assert_eq!(Expr::parse("   "), Err(QueryError::Empty));
+    }
+
+    #[test]
+    fn unknown_field_errors() {
+        assert_eq!(
+            Expr::parse("author = me"),
+            Err(QueryError::UnknownField("author".to_string()))
+        );
+    }
+
+    #[test]
+    fn missing_value_errors() {
+        let err = Expr::parse("category =").unwrap_err();
+        assert!(err.to_string().contains("Expected a value"));
+    }
+
+    #[test]
+    fn unbalanced_parens_error() {
+        let err = Expr::parse("(category = aws").unwrap_err();
+        assert!(err.to_string().contains("Expected ')'"));
+    }
+
+    #[test]
+    fn unterminated_string_errors() {
+        assert_eq!(
+            Expr::parse(r#"title = "open"#),
+            Err(QueryError::UnterminatedString)
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Inline `field:value` targeting within search queries.
+//!
+//! A query like `title:lambda category:aws serverless` restricts results to
+//! documents whose title contains `lambda` and whose category is `aws`, then
+//! searches for `serverless`. Recognized fields are `title:`, `content:`,
+//! `category:`, and `tags:` (or `tag:`); values may be double-quoted to
+//! include spaces. Any other `word:word` token is left in the query text.
+
+use crate::corpus::labels_match;
+
+/// A field that can be targeted inline in a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryField {
+    /// Document title.
+    Title,
+    /// Document body; `content:x` is the same as plain `x`.
+    Content,
+    /// Document category.
+    Category,
+    /// Document tags.
+    Tags,
+}
+
+impl QueryField {
+    /// Split a query token into a recognized field and its value.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the token has no recognized `field:` prefix or an empty value.
+    #[must_use]
+    pub fn parse_token(token: &str) -> Option<(Self, &str)> {
+        let (name, value) = token.split_once(':')?;
+        let field = match name {
+            "title" => Self::Title,
+            "content" => Self::Content,
+            "category" => Self::Category,
+            "tags" | "tag" => Self::Tags,
+            _ => return None,
+        };
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        (!value.is_empty()).then_some((field, value))
+    }
+}
+
+/// A query split into field filters and free text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldQuery {
+    /// Query text to match against document bodies, including `content:`
+    /// values. The original query if it has no field filters.
+    pub text: String,
+    /// Words or phrases the title must contain.
+    pub title: Vec<String>,
+    /// Categories the document must be in.
+    pub category: Vec<String>,
+    /// Tags the document must have.
+    pub tags: Vec<String>,
+}
+
+impl FieldQuery {
+    /// Split `query` into field filters and text.
+    #[must_use]
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        let mut text = Vec::new();
+        let mut has_fields = false;
+
+        for token in tokens(query) {
+            let field = QueryField::parse_token(token);
+            has_fields |= field.is_some();
+            match field {
+                Some((QueryField::Title, value)) => parsed.title.push(value.to_string()),
+                Some((QueryField::Content, value)) => text.push(value),
+                Some((QueryField::Category, value)) => parsed.category.push(value.to_string()),
+                Some((QueryField::Tags, value)) => parsed.tags.push(value.to_string()),
+                None => text.push(token),
+            }
+        }
+
+        // Leave plain queries untouched, including their spacing
+        parsed.text = if has_fields {
+            text.join(" ")
+        } else {
+            query.to_string()
+        };
+        parsed
+    }
+
+    /// Also require each of `tags`, as `tags:` tokens would.
+    #[must_use]
+    pub fn with_tags(mut self, tags: &[String]) -> Self {
+        self.tags.extend(tags.iter().cloned());
+        self
+    }
+
+    /// Whether the query has any title, category, or tag filter.
+    #[must_use]
+    pub fn has_filters(&self) -> bool {
+        !self.title.is_empty() || !self.category.is_empty() || !self.tags.is_empty()
+    }
+
+    /// Whether a document's metadata passes every filter.
+    ///
+    /// Titles match by substring, ignoring case unless `case_sensitive`;
+    /// categories and tags must match whole, ignoring case.
+    #[must_use]
+    pub fn matches(
+        &self,
+        title: &str,
+        category: &str,
+        tags: &[String],
+        case_sensitive: bool,
+    ) -> bool {
+        let title_contains = |word: &String| {
+            if case_sensitive {
+                title.contains(word.as_str())
+            } else {
+                title.to_lowercase().contains(&word.to_lowercase())
+            }
+        };
+
+        self.title.iter().all(title_contains)
+            && self.category.iter().all(|c| labels_match(c, category))
+            && self
+                .tags
+                .iter()
+                .all(|tag| tags.iter().any(|t| labels_match(t, tag)))
+    }
+}
+
+/// Split a query on whitespace, keeping double-quoted spans together.
+fn tokens(query: &str) -> impl Iterator<Item = &str> {
+    let mut rest = query.trim_start();
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let mut in_quotes = false;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| {
+                if c == '"' {
+                    in_quotes = !in_quotes;
+                }
+                c.is_whitespace() && !in_quotes
+            })
+            .map_or(rest.len(), |(i, _)| i);
+
+        let token = &rest[..end];
+        rest = rest[end..].trim_start();
+        Some(token)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_filters_from_text() {
+        let query = FieldQuery::parse(r#"title:lambda category:aws tags:"cold start" serverless"#);
+        assert_eq!(
+            query,
+            FieldQuery {
+                text: "serverless".to_string(),
+                title: vec!["lambda".to_string()],
+                category: vec!["aws".to_string()],
+                tags: vec!["cold start".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn content_values_and_unknown_fields_stay_in_text() {
+        let query = FieldQuery::parse("content:borrow std::io http://example.com");
+        assert!(!query.has_filters());
+        assert_eq!(query.text, "borrow std::io http://example.com");
+
+        // Plain queries are untouched
+        assert_eq!(FieldQuery::parse("  two  spaces ").text, "  two  spaces ");
+        assert_eq!(FieldQuery::parse("title:").text, "title:");
+    }
+
+    #[test]
+    fn matches_metadata() {
+        let query = FieldQuery::parse("title:Lambda category:aws tag:serverless");
+        let tags = vec!["serverless".to_string()];
+
+        assert!(query.matches("AWS Lambda Patterns", "aws", &tags, false));
+        assert!(!query.matches("AWS lambda patterns", "aws", &tags, true));
+        assert!(!query.matches("AWS Lambda Patterns", "rust", &tags, false));
+        assert!(!query.matches("AWS Lambda Patterns", "aws", &[], false));
+    }
+}
@@ -0,0 +1,305 @@
+//! Rendering search results as text.
+//!
+//! The CLI and MCP server each pick a [`ResultFormatter`]; library consumers
+//! can implement the trait to present results their own way.
+
+use std::fmt::Write;
+
+use crate::search::{HighlightMode, SearchResult};
+
+/// Renders a set of search results as a single string.
+pub trait ResultFormatter {
+    /// Format `results`, including a trailing result count.
+    fn format(&self, results: &[SearchResult]) -> String;
+}
+
+/// Which parts of the output a formatter writes, for embedding results in
+/// other documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    /// Start each result with a header naming its title (default: true).
+    pub header: bool,
+    /// End with the number of results (default: true).
+    pub footer: bool,
+    /// Line written between consecutive results (default: none).
+    pub separator: Option<String>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            header: true,
+            footer: true,
+            separator: None,
+        }
+    }
+}
+
+impl Layout {
+    /// Write the separator line (and `spacing` after it) before every
+    /// result but the first.
+    fn separate(&self, output: &mut String, index: usize, spacing: &str) {
+        if let Some(separator) = &self.separator
+            && index > 0
+        {
+            let _ = write!(output, "{separator}\n{spacing}");
+        }
+    }
+
+    /// Append `footer`, or without a footer drop the trailing blank lines
+    /// so the output still ends at its last line of text.
+    fn finish(&self, mut output: String, footer: &str) -> String {
+        if self.footer {
+            output.push_str(footer);
+        } else {
+            output.truncate(output.trim_end_matches('\n').len());
+        }
+        output
+    }
+}
+
+/// Plain text output, as printed by the CLI.
+///
+/// Each result is a `title: path (line N)` header followed by the indented
+/// matched line, highlighted with the configured mode, and its score
+/// explanation (if any) indented below that. Without headers, matched lines
+/// are not indented.
+#[derive(Debug, Clone, Default)]
+pub struct PlainFormatter {
+    /// How matched text is marked up.
+    pub highlight: HighlightMode,
+    /// Which parts of the output are written.
+    pub layout: Layout,
+}
+
+impl PlainFormatter {
+    /// Create a plain formatter that highlights matches with `highlight`.
+    #[must_use]
+    pub fn new(highlight: HighlightMode) -> Self {
+        Self {
+            highlight,
+            layout: Layout::default(),
+        }
+    }
+
+    /// Use `layout` instead of the default layout.
+    #[must_use]
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+impl ResultFormatter for PlainFormatter {
+    fn format(&self, results: &[SearchResult]) -> String {
+        let indent = if self.layout.header { "  " } else { "" };
+        let mut output = String::new();
+        for (index, result) in results.iter().enumerate() {
+            self.layout.separate(&mut output, index, "");
+            if self.layout.header {
+                let hits = result
+                    .hit_count
+                    .map(|n| format!(" ({n} hit(s))"))
+                    .unwrap_or_default();
+                let score = result
+                    .score
+                    .map(|s| format!(" (score: {s:.2})"))
+                    .unwrap_or_default();
+                let location = result.line_number().map_or_else(
+                    || "title or tags".to_string(),
+                    |line| format!("line {line}"),
+                );
+                let _ = writeln!(
+                    output,
+                    "{}: {} ({location}){hits}{score}",
+                    result.title,
+                    result.path.display(),
+                );
+            }
+            let _ = writeln!(
+                output,
+                "{indent}{}",
+                self.highlight
+                    .apply(result.matched_line(), result.match_ranges())
+            );
+            if let Some(explanation) = &result.explanation {
+                for line in explanation.lines() {
+                    let _ = writeln!(output, "{indent}  {line}");
+                }
+            }
+        }
+        self.layout
+            .finish(output, &format!("\n{} result(s) found", results.len()))
+    }
+}
+
+/// Markdown output with a heading per result and bold matches.
+///
+/// A separator, if any, is followed by a blank line, so `---` renders as a
+/// horizontal rule.
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownFormatter {
+    /// Which parts of the output are written.
+    pub layout: Layout,
+}
+
+impl MarkdownFormatter {
+    /// Create a markdown formatter with the given layout.
+    #[must_use]
+    pub const fn new(layout: Layout) -> Self {
+        Self { layout }
+    }
+}
+
+impl ResultFormatter for MarkdownFormatter {
+    fn format(&self, results: &[SearchResult]) -> String {
+        let mut output = String::new();
+        for (index, result) in results.iter().enumerate() {
+            self.layout.separate(&mut output, index, "\n");
+            if self.layout.header {
+                let _ = writeln!(output, "## {}", result.title);
+            }
+            let location = result.line_number().map_or_else(
+                || "Title or tags".to_string(),
+                |line| format!("Line {line}"),
+            );
+            let _ = write!(
+                output,
+                "**File:** {}\n**{location}:** {}\n\n",
+                result.path.display(),
+                HighlightMode::Markdown.apply(result.matched_line(), result.match_ranges())
+            );
+        }
+        self.layout
+            .finish(output, &format!("*{} result(s) found*", results.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::search::Snippet;
+
+    fn sample_results() -> Vec<SearchResult> {
+        vec![
+            SearchResult {
+                path: PathBuf::from("/corpus/aws/lambda.md"),
+                title: "Lambda Patterns".to_string(),
+                category: "aws".to_string(),
+                tags: vec![],
+                snippet: Some(Snippet {
+                    text: "Use lambda layers.".to_string(),
+                    highlights: vec![Range { start: 4, end: 10 }],
+                    line: Some(3),
+                }),
+                score: None,
+                hit_count: None,
+                explanation: None,
+            },
+            SearchResult {
+                path: PathBuf::from("/corpus/rust/errors.md"),
+                title: "Error Handling".to_string(),
+                category: "rust".to_string(),
+                tags: vec![],
+                snippet: Some(Snippet {
+                    text: "Wrap errors with context.".to_string(),
+                    highlights: vec![],
+                    line: Some(7),
+                }),
+                score: Some(1.5),
+                hit_count: None,
+                explanation: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn plain_formatter_output() {
+        let output = PlainFormatter::default().format(&sample_results());
+        assert_eq!(
+            output,
+            "Lambda Patterns: /corpus/aws/lambda.md (line 3)\n  Use lambda layers.\n\
+             Error Handling: /corpus/rust/errors.md (line 7) (score: 1.50)\n  Wrap errors with context.\n\
+             \n2 result(s) found"
+        );
+    }
+
+    #[test]
+    fn plain_formatter_applies_highlight() {
+        let output = PlainFormatter::new(HighlightMode::Ansi).format(&sample_results()[..1]);
+        assert!(output.contains("  Use \x1b[1;31mlambda\x1b[0m layers.\n"));
+    }
+
+    #[test]
+    fn plain_formatter_indents_explanation() {
+        let mut results = sample_results();
+        results[1].explanation = Some("1.5000 total\n  1.5000 part".to_string());
+
+        let output = PlainFormatter::default().format(&results[1..]);
+        assert!(
+            output.contains("  Wrap errors with context.\n    1.5000 total\n      1.5000 part\n")
+        );
+    }
+
+    #[test]
+    fn markdown_formatter_output() {
+        let output = MarkdownFormatter::default().format(&sample_results());
+        assert_eq!(
+            output,
+            "## Lambda Patterns\n**File:** /corpus/aws/lambda.md\n**Line 3:** Use **lambda** layers.\n\n\
+             ## Error Handling\n**File:** /corpus/rust/errors.md\n**Line 7:** Wrap errors with context.\n\n\
+             *2 result(s) found*"
+        );
+    }
+
+    #[test]
+    fn formatters_handle_empty_results() {
+        assert_eq!(PlainFormatter::default().format(&[]), "\n0 result(s) found");
+        assert_eq!(
+            MarkdownFormatter::default().format(&[]),
+            "*0 result(s) found*"
+        );
+    }
+
+    #[test]
+    fn layout_drops_header_and_footer() {
+        let layout = Layout {
+            header: false,
+            footer: false,
+            separator: None,
+        };
+
+        let output = PlainFormatter::default()
+            .with_layout(layout.clone())
+            .format(&sample_results());
+        assert_eq!(output, "Use lambda layers.\nWrap errors with context.");
+
+        let output = MarkdownFormatter::new(layout).format(&sample_results());
+        assert_eq!(
+            output,
+            "**File:** /corpus/aws/lambda.md\n**Line 3:** Use **lambda** layers.\n\n\
+             **File:** /corpus/rust/errors.md\n**Line 7:** Wrap errors with context."
+        );
+    }
+
+    #[test]
+    fn layout_separates_results() {
+        let layout = Layout {
+            separator: Some("---".to_string()),
+            ..Layout::default()
+        };
+
+        let output = PlainFormatter::default()
+            .with_layout(layout.clone())
+            .format(&sample_results());
+        assert!(output.contains("  Use lambda layers.\n---\nError Handling:"));
+        assert_eq!(output.matches("---").count(), 1);
+
+        let output = MarkdownFormatter::new(layout).format(&sample_results());
+        assert!(output.contains("layers.\n\n---\n\n## Error Handling"));
+        assert!(output.ends_with("context.\n\n*2 result(s) found*"));
+    }
+}
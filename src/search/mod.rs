@@ -1,41 +1,352 @@
 //! Search backend trait and types.
 
+pub mod fields;
+pub mod format;
 pub mod ripgrep;
 
+#[cfg(feature = "ranked")]
+pub mod overlay;
 #[cfg(feature = "ranked")]
 pub mod tantivy;
 
+use std::ops::Range;
 use std::path::PathBuf;
 
+use serde::{Deserialize, Serialize};
+
 use crate::corpus::Corpus;
 
+/// How a query is matched against document text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MatchMode {
+    /// Literal text anywhere, including inside longer words (default).
+    #[default]
+    Substring,
+    /// Literal text only as a whole word.
+    Word,
+    /// Query is a regular expression.
+    Regex,
+}
+
+/// How unscored results from several corpora are ordered.
+///
+/// Scored (ranked) results are always ordered by score across corpora.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CrossOrder {
+    /// Corpora in config order, each sorted by title then path (default).
+    #[default]
+    ConfigOrder,
+    /// All results by title then path, regardless of corpus.
+    Title,
+    /// Corpora ordered by root path, each sorted by title then path.
+    Corpus,
+}
+
+/// How a category filter is compared with document categories.
+///
+/// Categories may be nested with slashes (e.g., `aws/lambda`), and are
+/// compared ignoring case.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CategoryMatch {
+    /// The category must equal the filter (default).
+    #[default]
+    Exact,
+    /// The category must equal the filter or be nested under it, so `aws`
+    /// matches `aws` and `aws/lambda` but not `aws-legacy`.
+    Prefix,
+}
+
+impl CategoryMatch {
+    /// Whether a document's `category` passes the `filter`.
+    #[must_use]
+    pub fn matches(self, filter: &str, category: &str) -> bool {
+        let (filter, category) = (filter.to_lowercase(), category.to_lowercase());
+        match self {
+            Self::Exact => category == filter,
+            Self::Prefix => category
+                .strip_prefix(filter.trim_end_matches('/'))
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/')),
+        }
+    }
+}
+
+/// How ranked results choose the body text shown for each match
+/// (`[search] snippet_strategy`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnippetStrategy {
+    /// The highest-scoring fragment around query terms (default).
+    #[default]
+    Best,
+    /// The first line containing a query term.
+    First,
+    /// The start of the document, whitespace collapsed.
+    Prefix,
+}
+
+/// How matched text is marked up when displaying search results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum HighlightMode {
+    /// Plain text.
+    #[default]
+    None,
+    /// Bold red ANSI escapes, for terminals.
+    Ansi,
+    /// `**bold**`, for markdown renderers.
+    Markdown,
+}
+
+impl HighlightMode {
+    /// Wrap each matched byte range of `line` in this mode's markers.
+    ///
+    /// Ranges must be sorted; ranges that overlap a previous one, fall outside
+    /// the line, or split a UTF-8 character are ignored.
+    #[must_use]
+    pub fn apply(self, line: &str, ranges: &[Range<usize>]) -> String {
+        let (open, close) = match self {
+            Self::None => return line.to_string(),
+            Self::Ansi => ("\x1b[1;31m", "\x1b[0m"),
+            Self::Markdown => ("**", "**"),
+        };
+
+        let mut output = String::with_capacity(line.len());
+        let mut pos = 0;
+        for range in ranges {
+            let valid = range.start >= pos
+                && range.start < range.end
+                && line.is_char_boundary(range.start)
+                && line.is_char_boundary(range.end);
+            if !valid {
+                continue;
+            }
+
+            output.push_str(&line[pos..range.start]);
+            output.push_str(open);
+            output.push_str(&line[range.clone()]);
+            output.push_str(close);
+            pos = range.end;
+        }
+        output.push_str(&line[pos..]);
+        output
+    }
+}
+
 /// Options for filtering and limiting search results.
 #[derive(Debug, Clone, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct SearchOptions {
-    /// Maximum number of results to return.
+    /// Maximum number of results to return. Backends return every match
+    /// when unset; [`crate::commands::search`] first fills it from
+    /// `[search] default_limit` (default 10).
     pub limit: Option<usize>,
-    /// Filter results to this category only.
+    /// Maximum number of results from any one document, within `limit`.
+    /// Rejected by backends returning one result per document (e.g.,
+    /// Tantivy).
+    pub max_per_file: Option<usize>,
+    /// Report each document once, on its first match, with the number of
+    /// matches in [`SearchResult::hit_count`]. Rejected by backends
+    /// returning one result per document (e.g., Tantivy).
+    pub group_by_document: bool,
+    /// Score each match by how often its document matched and return the
+    /// best-scoring documents first. Only supported by backends that
+    /// otherwise leave results unscored (e.g., ripgrep), which then read
+    /// every match instead of stopping at `limit`.
+    pub score_matches: bool,
+    /// Filter results to this category only. [`crate::commands::search`]
+    /// fills it from `[search] default_category` when unset, unless
+    /// `all_categories` is set.
     pub category: Option<String>,
+    /// Search every category even if `[search] default_category` is set
+    /// (applied by [`crate::commands::search`], not by backends).
+    pub all_categories: bool,
+    /// How `category` is compared with each document's category.
+    pub category_match: CategoryMatch,
+    /// Only return documents that have every one of these tags (compared
+    /// whole, ignoring case), as with `tags:` in the query.
+    pub tags: Vec<String>,
     /// Use case-sensitive matching (default is case-insensitive).
     pub case_sensitive: bool,
     /// Fuzzy search edit distance (0-2). None means exact matching.
     /// Only used by backends that support fuzzy search (e.g., Tantivy).
     pub fuzzy: Option<u8>,
+    /// How the query is matched. Term-based backends (e.g., Tantivy) always
+    /// match whole words and only reject `Regex`.
+    pub match_mode: MatchMode,
+    /// Attach a description of how each result was scored.
+    pub explain: bool,
+    /// How unscored results from different corpora are merged. Only used
+    /// when searching several corpora.
+    pub cross_corpus_order: CrossOrder,
+    /// Title results with the file stem instead of the manifest title, to
+    /// spot manifest entries that have drifted from their files.
+    pub raw_titles: bool,
+    /// How snippets are chosen. Only used by backends that build snippets
+    /// from whole documents (e.g., Tantivy); ripgrep always shows the
+    /// matching line.
+    pub snippet_strategy: SnippetStrategy,
+    /// Drop results for a document already found in an earlier configured
+    /// corpus under the same relative path and title. Only used when
+    /// searching several corpora.
+    pub dedupe: bool,
+    /// Only search these files (as in [`SearchResult::path`]; relative paths
+    /// resolve against the working directory), e.g. the results of an
+    /// earlier search; `None` searches whole corpora.
+    pub within: Option<Vec<PathBuf>>,
+    /// Also match the query against each document's manifest title and
+    /// tags. Only supported by backends that otherwise search bodies alone
+    /// (e.g., ripgrep); documents matching only there are reported without a
+    /// line number.
+    pub include_metadata: bool,
+    /// Search even if the query is shorter than `[search] min_query_length`
+    /// (checked by [`crate::commands::search`], not by backends).
+    pub allow_short_query: bool,
+}
+
+/// Features a search backend supports, used to reject unsupported options
+/// up front instead of silently ignoring them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct BackendCapabilities {
+    /// Approximate matching within an edit distance (`SearchOptions::fuzzy`).
+    pub supports_fuzzy: bool,
+    /// Quoted phrase syntax within a query.
+    pub supports_phrase: bool,
+    /// Relevance scores on results (`SearchResult::score`).
+    pub supports_scoring: bool,
+    /// Filtering results by tag.
+    pub supports_tag_filter: bool,
+    /// Regular expression queries (`MatchMode::Regex`).
+    pub supports_regex: bool,
+    /// Searching only a given set of files (`SearchOptions::within`).
+    pub supports_within: bool,
+    /// Capping results per document (`SearchOptions::max_per_file`).
+    pub supports_max_per_file: bool,
+    /// Folding matches into one result per document
+    /// (`SearchOptions::group_by_document`).
+    pub supports_group_by_document: bool,
+    /// Scoring matches by document on request
+    /// (`SearchOptions::score_matches`).
+    pub supports_score_matches: bool,
+    /// Matching titles and tags on request
+    /// (`SearchOptions::include_metadata`).
+    pub supports_include_metadata: bool,
+}
+
+impl BackendCapabilities {
+    /// Check that the requested options are supported.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Backend name used in the error message
+    /// * `options` - Requested search options
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first unsupported option.
+    pub fn check(&self, backend: &str, options: &SearchOptions) -> anyhow::Result<()> {
+        if options.fuzzy.is_some() && !self.supports_fuzzy {
+            anyhow::bail!("{backend} does not support fuzzy search");
+        }
+
+        if options.match_mode == MatchMode::Regex && !self.supports_regex {
+            anyhow::bail!("{backend} does not support regex search");
+        }
+
+        if !options.tags.is_empty() && !self.supports_tag_filter {
+            anyhow::bail!("{backend} does not support tag filtering");
+        }
+
+        if options.within.is_some() && !self.supports_within {
+            anyhow::bail!("{backend} does not support searching within a file list");
+        }
+
+        if options.max_per_file.is_some() && !self.supports_max_per_file {
+            anyhow::bail!("{backend} does not support limiting results per file");
+        }
+
+        if options.group_by_document && !self.supports_group_by_document {
+            anyhow::bail!("{backend} does not support grouping matches by document");
+        }
+
+        if options.score_matches && !self.supports_score_matches {
+            anyhow::bail!("{backend} does not support ranking matches");
+        }
+
+        if options.include_metadata && !self.supports_include_metadata {
+            anyhow::bail!(
+                "{backend} does not support --include-metadata; it already searches titles"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// A piece of document text representing a search result, with the
+/// matched text marked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Snippet {
+    /// The matching line (trimmed), or a fragment of the body.
+    pub text: String,
+    /// Byte ranges of the matched text within `text`, in order. Empty when
+    /// the backend doesn't report match positions.
+    pub highlights: Vec<Range<usize>>,
+    /// Line the text starts on (1-indexed), or `None` for a match in the
+    /// document's title or tags (see `SearchOptions::include_metadata`).
+    pub line: Option<usize>,
 }
 
 /// A single search result with match context.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SearchResult {
     /// Absolute path to the matched file.
     pub path: PathBuf,
     /// Document title from manifest, or filename if not in manifest.
     pub title: String,
-    /// The line containing the match (trimmed).
-    pub matched_line: String,
-    /// Line number where the match occurred (1-indexed).
-    pub line_number: usize,
+    /// Document category from manifest; for files not in the manifest, from
+    /// their path (see [`crate::corpus::category`]), or "unknown".
+    pub category: String,
+    /// Document tags from manifest.
+    pub tags: Vec<String>,
+    /// The matched text, or `None` if no body text represents the result
+    /// (e.g., it matched field filters only, or its document is encrypted).
+    pub snippet: Option<Snippet>,
     /// Relevance score (populated by ranking backends like Tantivy).
     pub score: Option<f32>,
+    /// Matches found in the document, when its matches were grouped into
+    /// this one result (see `SearchOptions::group_by_document`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hit_count: Option<usize>,
+    /// How the result was scored, one component per line. Only populated
+    /// when `SearchOptions::explain` is set.
+    pub explanation: Option<String>,
+}
+
+impl SearchResult {
+    /// The snippet text, or the title for results without a snippet.
+    #[must_use]
+    pub fn matched_line(&self) -> &str {
+        self.snippet
+            .as_ref()
+            .map_or(&self.title, |snippet| &snippet.text)
+    }
+
+    /// Byte ranges to highlight within [`Self::matched_line`].
+    #[must_use]
+    pub fn match_ranges(&self) -> &[Range<usize>] {
+        self.snippet
+            .as_ref()
+            .map_or(&[], |snippet| &snippet.highlights)
+    }
+
+    /// The snippet's line, 1 for results without a snippet, or `None` for
+    /// a match in the document's title or tags.
+    #[must_use]
+    pub fn line_number(&self) -> Option<usize> {
+        self.snippet
+            .as_ref()
+            .map_or(Some(1), |snippet| snippet.line)
+    }
 }
 
 /// Trait for search backends (ripgrep, tantivy, etc.).
@@ -61,4 +372,151 @@ pub trait SearchBackend: Send + Sync {
 
     /// Returns true if this backend requires indexing before search.
     fn needs_indexing(&self) -> bool;
+
+    /// Describe the search features this backend supports.
+    fn capabilities(&self) -> BackendCapabilities;
+
+    /// Name of the backend as given to `--backend`, for error messages.
+    fn name(&self) -> &'static str;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn category_prefix_matches_nested_categories() {
+        assert!(CategoryMatch::Exact.matches("aws", "aws"));
+        assert!(!CategoryMatch::Exact.matches("aws", "aws/lambda"));
+
+        assert!(CategoryMatch::Prefix.matches("aws", "aws"));
+        assert!(CategoryMatch::Prefix.matches("aws", "aws/lambda"));
+        assert!(CategoryMatch::Prefix.matches("aws/", "aws/lambda/layers"));
+        assert!(CategoryMatch::Exact.matches("aws", "AWS"));
+        assert!(CategoryMatch::Prefix.matches("AWS", "aws/Lambda"));
+        assert!(!CategoryMatch::Prefix.matches("aws", "aws-legacy"));
+        assert!(!CategoryMatch::Prefix.matches("aws/lambda", "aws"));
+    }
+
+    #[test]
+    fn check_rejects_unsupported_fuzzy() {
+        let options = SearchOptions {
+            fuzzy: Some(1),
+            ..Default::default()
+        };
+
+        let err = BackendCapabilities::default()
+            .check("ripgrep", &options)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "ripgrep does not support fuzzy search");
+
+        let fuzzy = BackendCapabilities {
+            supports_fuzzy: true,
+            ..Default::default()
+        };
+        assert!(fuzzy.check("tantivy", &options).is_ok());
+    }
+
+    #[test]
+    fn check_rejects_unsupported_regex() {
+        let options = SearchOptions {
+            match_mode: MatchMode::Regex,
+            ..Default::default()
+        };
+
+        let err = BackendCapabilities::default()
+            .check("ranked", &options)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "ranked does not support regex search");
+    }
+
+    #[test]
+    fn check_rejects_unsupported_within() {
+        let options = SearchOptions {
+            within: Some(vec![PathBuf::from("/corpus/aws/lambda.md")]),
+            ..Default::default()
+        };
+
+        let err = BackendCapabilities::default()
+            .check("ranked", &options)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ranked does not support searching within a file list"
+        );
+    }
+
+    #[test]
+    fn check_rejects_unsupported_result_shaping() {
+        let caps = BackendCapabilities::default();
+
+        let options = SearchOptions {
+            max_per_file: Some(2),
+            ..Default::default()
+        };
+        let err = caps.check("ranked", &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ranked does not support limiting results per file"
+        );
+
+        let options = SearchOptions {
+            group_by_document: true,
+            ..Default::default()
+        };
+        let err = caps.check("ranked", &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ranked does not support grouping matches by document"
+        );
+
+        let options = SearchOptions {
+            score_matches: true,
+            ..Default::default()
+        };
+        let err = caps.check("ranked", &options).unwrap_err();
+        assert_eq!(err.to_string(), "ranked does not support ranking matches");
+
+        let options = SearchOptions {
+            include_metadata: true,
+            ..Default::default()
+        };
+        let err = caps.check("ranked", &options).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("does not support --include-metadata")
+        );
+    }
+
+    #[test]
+    fn highlight_modes() {
+        let line = "use lambda or Lambda";
+        let ranges = [4..10, 14..20];
+
+        assert_eq!(HighlightMode::None.apply(line, &ranges), line);
+        assert_eq!(
+            HighlightMode::Markdown.apply(line, &ranges),
+            "use **lambda** or **Lambda**"
+        );
+        assert_eq!(
+            HighlightMode::Ansi.apply(line, &ranges),
+            "use \x1b[1;31mlambda\x1b[0m or \x1b[1;31mLambda\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn highlight_skips_invalid_ranges() {
+        // Out of bounds, overlapping, and mid-character ranges are ignored
+        let ranges = [0..1, 0..2, 2..3, 5..99];
+        assert_eq!(HighlightMode::Markdown.apply("aéz", &ranges), "**a**éz");
+    }
+
+    #[test]
+    fn check_accepts_plain_options() {
+        assert!(
+            BackendCapabilities::default()
+                .check("ripgrep", &SearchOptions::default())
+                .is_ok()
+        );
+    }
 }
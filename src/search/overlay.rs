@@ -0,0 +1,233 @@
+//! Near-real-time ranked search over documents not yet in the index.
+//!
+//! Documents added or retagged by this process are recorded as pending until
+//! their corpus is reindexed. Ranked searches index the pending documents
+//! into a small in-memory Tantivy index and merge its hits with the on-disk
+//! index, so fresh content is findable before the next `kvault index`.
+//!
+//! Scores from the two indexes are computed over different document sets, so
+//! they are comparable only roughly; ordering across them is best-effort.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+
+use crate::config::DEFAULT_INDEX_LANGUAGE;
+use crate::corpus::{Corpus, Document};
+use crate::search::tantivy::TantivyBackend;
+use crate::search::{BackendCapabilities, SearchBackend, SearchOptions, SearchResult};
+
+/// Pending document paths (relative to the corpus root), keyed by corpus root.
+static PENDING: LazyLock<Mutex<HashMap<PathBuf, HashSet<PathBuf>>>> = LazyLock::new(Mutex::default);
+
+fn pending_map() -> MutexGuard<'static, HashMap<PathBuf, HashSet<PathBuf>>> {
+    // The map holds no invariants a panic could break
+    PENDING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Record that a document changed since its corpus was last indexed.
+pub fn record(root: &Path, doc_path: &Path) {
+    pending_map()
+        .entry(root.to_path_buf())
+        .or_default()
+        .insert(doc_path.to_path_buf());
+}
+
+/// Forget pending documents for a corpus, e.g. after it has been reindexed.
+pub fn clear(root: &Path) {
+    pending_map().remove(root);
+}
+
+/// Manifest entries for a corpus's pending documents.
+///
+/// Paths no longer in the manifest are skipped.
+#[must_use]
+pub fn pending_documents(corpus: &Corpus) -> Vec<&Document> {
+    let map = pending_map();
+    let Some(paths) = map.get(&corpus.root) else {
+        return Vec::new();
+    };
+
+    corpus
+        .documents()
+        .iter()
+        .filter(|doc| paths.contains(&doc.path))
+        .collect()
+}
+
+/// A Tantivy index searched together with its corpus's pending documents.
+pub struct OverlayBackend {
+    index: TantivyBackend,
+}
+
+impl OverlayBackend {
+    /// Wrap an on-disk index.
+    #[must_use]
+    pub fn new(index: TantivyBackend) -> Self {
+        Self { index }
+    }
+}
+
+impl SearchBackend for OverlayBackend {
+    fn search(
+        &self,
+        query: &str,
+        corpus: &Corpus,
+        options: &SearchOptions,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        search(&self.index, query, corpus, options)
+    }
+
+    fn index(&self, corpus: &Corpus) -> anyhow::Result<()> {
+        self.index.index(corpus)?;
+        clear(&corpus.root);
+        Ok(())
+    }
+
+    fn needs_indexing(&self) -> bool {
+        true
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        self.index.capabilities()
+    }
+
+    fn name(&self) -> &'static str {
+        self.index.name()
+    }
+}
+
+/// Search the on-disk index and the corpus's pending documents together.
+///
+/// Index hits for pending documents are dropped in favour of the overlay's,
+/// since the index holds a stale copy (or none).
+///
+/// # Errors
+///
+/// Returns an error if either search fails or the overlay cannot be built.
+pub fn search(
+    index: &TantivyBackend,
+    query: &str,
+    corpus: &Corpus,
+    options: &SearchOptions,
+) -> anyhow::Result<Vec<SearchResult>> {
+    let pending = pending_documents(corpus);
+    if pending.is_empty() {
+        return index.search(query, corpus, options);
+    }
+
+    let language = index.language();
+    let overlay = TantivyBackend::in_memory(language.as_deref().unwrap_or(DEFAULT_INDEX_LANGUAGE))?;
+    overlay.index_documents(corpus, &pending)?;
+
+    let stale: HashSet<PathBuf> = pending
+        .iter()
+        .map(|doc| corpus.resolve_document_path(doc))
+        .collect();
+
+    let mut results: Vec<SearchResult> = index
+        .search(query, corpus, options)?
+        .into_iter()
+        .filter(|result| !stale.contains(&result.path))
+        .collect();
+    results.extend(overlay.search(query, corpus, options)?);
+
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    results.truncate(options.limit.unwrap_or(10));
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus::Manifest;
+    use crate::search::tantivy::IndexMode;
+    use tempfile::TempDir;
+
+    fn write_document(corpus: &mut Corpus, path: &str, title: &str, content: &str) {
+        let full_path = corpus.root.join(path);
+        std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+        std::fs::write(full_path, content).unwrap();
+        corpus
+            .manifest
+            .documents
+            .retain(|doc| doc.path != Path::new(path));
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from(path),
+            title: title.to_string(),
+            category: "notes".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+    }
+
+    fn indexed_corpus(temp_dir: &TempDir) -> (Corpus, TantivyBackend) {
+        let mut corpus = Corpus {
+            root: temp_dir.path().to_path_buf(),
+            manifest: Manifest::empty(),
+        };
+        write_document(
+            &mut corpus,
+            "notes/old.md",
+            "Old",
+            "Ownership and borrowing.",
+        );
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+        (corpus, backend)
+    }
+
+    #[test]
+    fn pending_document_is_found_before_reindex() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut corpus, backend) = indexed_corpus(&temp_dir);
+
+        write_document(&mut corpus, "notes/new.md", "New", "Fresh lifetimes notes.");
+        assert!(
+            backend
+                .search("lifetimes", &corpus, &SearchOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+
+        record(&corpus.root, Path::new("notes/new.md"));
+        let results = search(&backend, "lifetimes", &corpus, &SearchOptions::default()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "New");
+
+        clear(&corpus.root);
+        assert!(pending_documents(&corpus).is_empty());
+    }
+
+    #[test]
+    fn overlay_replaces_stale_index_hits() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut corpus, backend) = indexed_corpus(&temp_dir);
+
+        write_document(
+            &mut corpus,
+            "notes/old.md",
+            "Old",
+            "Rewritten: ownership only.",
+        );
+        record(&corpus.root, Path::new("notes/old.md"));
+
+        assert!(
+            search(&backend, "borrowing", &corpus, &SearchOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+        let results = search(&backend, "ownership", &corpus, &SearchOptions::default()).unwrap();
+        assert_eq!(results.len(), 1);
+
+        clear(&corpus.root);
+    }
+}
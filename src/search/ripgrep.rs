@@ -1,29 +1,92 @@
 //! Ripgrep-based search backend.
 
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::process::Command;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
 
 use serde::Deserialize;
 
-use crate::corpus::{Corpus, Document};
-use crate::search::{SearchBackend, SearchOptions, SearchResult};
+use crate::corpus::category::{CategoryResolver, FirstComponentResolver};
+use crate::corpus::{
+    Corpus, Document, IGNORED_CORPUS_GLOBS, file_stem_title, humanize_title, is_ignored_corpus_path,
+};
+use crate::search::fields::FieldQuery;
+use crate::search::{
+    BackendCapabilities, MatchMode, SearchBackend, SearchOptions, SearchResult, Snippet,
+};
 
 /// Maximum allowed query length to prevent abuse.
 const MAX_QUERY_LENGTH: usize = 1000;
 
+/// Score a query term in a document's title adds, in matching lines.
+const TITLE_WEIGHT: f32 = 3.0;
+
+/// Matches read before ripgrep is stopped when scoring. Scores only count
+/// these, so a query matching more lines ranks the ones ripgrep found first.
+const MAX_SCORED_MATCHES: usize = 10_000;
+
+/// Binary looked up in PATH when no explicit ripgrep path is configured.
+const DEFAULT_BINARY: &str = "rg";
+
 /// Search backend using ripgrep for fast text search.
 ///
-/// Uses `--fixed-strings` mode to treat queries as literal text rather than
-/// regex patterns, preventing regex denial-of-service attacks and unexpected behavior.
-#[derive(Default)]
-pub struct RipgrepBackend;
+/// Unless `MatchMode::Regex` is requested, uses `--fixed-strings` mode to treat
+/// queries as literal text rather than regex patterns, preventing unexpected
+/// behavior. ripgrep's regex engine runs in linear time, so explicit regex
+/// queries are not a denial-of-service risk.
+///
+/// A literal query of several words matches files containing every word,
+/// on any lines; wrapped in double quotes, it matches as one phrase.
+#[derive(Clone)]
+pub struct RipgrepBackend {
+    binary: PathBuf,
+    excludes: Vec<String>,
+    categories: Arc<dyn CategoryResolver>,
+}
+
+impl Default for RipgrepBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl RipgrepBackend {
-    /// Create a new ripgrep search backend.
+    /// Create a new ripgrep search backend using `rg` from PATH.
     #[must_use]
     pub fn new() -> Self {
-        Self
+        Self::with_binary(DEFAULT_BINARY)
+    }
+
+    /// Create a ripgrep search backend that runs the given binary.
+    #[must_use]
+    pub fn with_binary(binary: impl Into<PathBuf>) -> Self {
+        Self {
+            binary: binary.into(),
+            excludes: Vec::new(),
+            categories: Arc::new(FirstComponentResolver),
+        }
+    }
+
+    /// Give matches in files the manifest doesn't list categories from
+    /// `resolver` (default: [`FirstComponentResolver`]). Files it has no
+    /// category for are in `unknown`.
+    #[must_use]
+    pub fn with_category_resolver(mut self, resolver: impl CategoryResolver + 'static) -> Self {
+        self.categories = Arc::new(resolver);
+        self
+    }
+
+    /// Also exclude paths matching these globs (without a leading `!`).
+    ///
+    /// The built-in exclusions for the manifest and index always apply and
+    /// take precedence over these.
+    #[must_use]
+    pub fn with_excludes(mut self, globs: impl IntoIterator<Item = String>) -> Self {
+        self.excludes.extend(globs);
+        self
     }
 
     /// Check if ripgrep is available in PATH.
@@ -32,17 +95,80 @@ impl RipgrepBackend {
     ///
     /// Returns an error with install instructions if ripgrep is not found.
     pub fn check_available() -> anyhow::Result<()> {
-        match Command::new("rg").arg("--version").output() {
+        Self::new().check()
+    }
+
+    /// Check if this backend's ripgrep binary can be run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with install instructions if the binary is not found.
+    pub fn check(&self) -> anyhow::Result<()> {
+        match Command::new(&self.binary).arg("--version").output() {
             Ok(output) if output.status.success() => Ok(()),
             _ => anyhow::bail!(
-                "ripgrep not found\n\n\
+                "ripgrep not found ({})\n\n\
                 Install ripgrep:\n  \
                 brew install ripgrep    # macOS\n  \
                 cargo install ripgrep   # any platform\n  \
-                apt install ripgrep     # Debian/Ubuntu"
+                apt install ripgrep     # Debian/Ubuntu",
+                self.binary.display()
             ),
         }
     }
+
+    /// The ripgrep command searching for `patterns`, up to the paths to
+    /// search. A match of any pattern is reported; with several patterns,
+    /// which files match all of them is left to the caller, so matches per
+    /// file aren't capped. Nor are they when every match is scored or
+    /// counted towards its document's hits.
+    fn command(&self, patterns: &[&str], options: &SearchOptions) -> Command {
+        let mut cmd = Command::new(&self.binary);
+        cmd.arg("--json");
+
+        match options.match_mode {
+            // Use fixed-strings to treat query as literal text, not regex.
+            MatchMode::Substring => {
+                cmd.arg("--fixed-strings");
+            }
+            MatchMode::Word => {
+                cmd.arg("--fixed-strings").arg("--word-regexp");
+            }
+            MatchMode::Regex => {}
+        }
+
+        // Later globs take precedence in ripgrep, so built-in exclusions go
+        // last where no configured glob can override them
+        let excludes = self.excludes.iter().map(String::as_str);
+        for glob in excludes.chain(IGNORED_CORPUS_GLOBS) {
+            cmd.arg("--glob").arg(format!("!{glob}"));
+        }
+
+        // No file can contribute more than the overall limit either
+        if patterns.len() == 1 && !options.score_matches && !options.group_by_document {
+            let per_file = match (options.max_per_file, options.limit) {
+                (Some(max), Some(limit)) => max.min(limit),
+                (max, limit) => max.or(limit).unwrap_or(100),
+            };
+            cmd.arg("--max-count").arg(per_file.to_string());
+        }
+
+        // Parallel traversal finishes files in a different order on each
+        // run; sorting by path makes which matches fall within the limit,
+        // and their order, reproducible. It also makes ripgrep search on a
+        // single thread, the price of not sorting again after parsing
+        cmd.arg("--sort").arg("path");
+
+        // Case-insensitive by default, unless --case-sensitive is specified
+        if !options.case_sensitive {
+            cmd.arg("--ignore-case");
+        }
+
+        for pattern in patterns {
+            cmd.arg("--regexp").arg(pattern);
+        }
+        cmd
+    }
 }
 
 impl SearchBackend for RipgrepBackend {
@@ -52,7 +178,7 @@ impl SearchBackend for RipgrepBackend {
         corpus: &Corpus,
         options: &SearchOptions,
     ) -> anyhow::Result<Vec<SearchResult>> {
-        Self::check_available()?;
+        self.check()?;
 
         // Validate query to prevent abuse
         if query.is_empty() {
@@ -72,32 +198,112 @@ impl SearchBackend for RipgrepBackend {
             anyhow::bail!("Query contains invalid characters");
         }
 
-        let mut cmd = Command::new("rg");
-        cmd.arg("--json")
-            // Use fixed-strings to treat query as literal text, not regex.
-            // This prevents ReDoS attacks and unexpected regex behavior.
-            .arg("--fixed-strings")
-            // Exclude manifest.json from search results
-            .arg("--glob")
-            .arg("!manifest.json")
-            .arg("--max-count")
-            .arg(options.limit.unwrap_or(100).to_string());
+        // Files of this corpus to search instead of its whole root, named
+        // as results name them so the manifest lookups below find them
+        let options = &SearchOptions {
+            within: options
+                .within
+                .as_ref()
+                .map(|paths| paths_in_corpus(corpus, paths)),
+            ..options.clone()
+        };
+        if options.within.as_ref().is_some_and(Vec::is_empty) {
+            return Ok(vec![]);
+        }
 
-        // Case-insensitive by default, unless --case-sensitive is specified
-        if !options.case_sensitive {
-            cmd.arg("--ignore-case");
+        // `title:`/`category:`/`tags:` filter on the manifest; only the
+        // remaining text is passed to ripgrep
+        let fields = FieldQuery::parse(query).with_tags(&options.tags);
+        if fields.has_filters() && fields.text.trim().is_empty() {
+            return Ok(metadata_matches(corpus, &fields, options));
+        }
+
+        let patterns = match options.match_mode {
+            MatchMode::Regex => vec![fields.text.as_str()],
+            MatchMode::Substring | MatchMode::Word => literal_patterns(&fields.text),
+        };
+        if patterns.is_empty() {
+            return Ok(vec![]);
+        }
+        // Files must match every word, which the per-file and overall
+        // limits can only be applied after
+        let all_terms = patterns.len() > 1;
+        // Scores and hit counts need every match of a document, so reading
+        // only stops at the limit when neither is wanted
+        let read_limit = if options.score_matches {
+            MAX_SCORED_MATCHES
+        } else if all_terms || options.group_by_document {
+            usize::MAX
+        } else {
+            options.limit.unwrap_or(usize::MAX)
+        };
+
+        let mut cmd = self.command(&patterns, options);
+        cmd.arg("--"); // End of options, paths follow
+        match &options.within {
+            Some(paths) => cmd.args(paths),
+            None => cmd.arg(&corpus.root),
+        };
+
+        let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+
+        // Drain stderr on its own thread so a chatty ripgrep can't block on a
+        // full pipe while we're reading stdout
+        let mut stderr = child.stderr.take();
+        let stderr_reader = std::thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(stderr) = stderr.as_mut() {
+                let _ = stderr.read_to_string(&mut text);
+            }
+            text
+        });
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("ripgrep stdout was not captured"))?;
+        let mut stdout = BufReader::new(stdout);
+        let no_output = stdout.fill_buf()?.is_empty();
+        let mut results = parse_ripgrep_output(
+            stdout,
+            corpus,
+            self.categories.as_ref(),
+            &fields,
+            options,
+            read_limit,
+        )?;
+
+        // The rest of the output would be discarded anyway, so stop ripgrep
+        // rather than wait for it to scan everything
+        if results.len() >= read_limit {
+            let _ = child.kill();
         }
+        let status = child.wait()?;
+        if all_terms {
+            results = require_all_terms(results, &patterns, options);
+        }
+        let stderr = stderr_reader.join().unwrap_or_default();
 
-        let output = cmd
-            .arg("--") // End of options, query follows
-            .arg(query)
-            .arg(&corpus.root)
-            .output()?;
+        // Exit code 2 with no output means ripgrep itself failed (e.g., an
+        // invalid regex); 1 just means nothing matched
+        if status.code() == Some(2) && no_output {
+            anyhow::bail!("ripgrep failed: {}", stderr.trim());
+        }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let results = parse_ripgrep_output(&stdout, corpus, options);
+        if options.include_metadata {
+            results.extend(metadata_text_matches(corpus, &fields, options, &results));
+            results.sort_by(|a, b| {
+                a.path
+                    .cmp(&b.path)
+                    .then(a.line_number().cmp(&b.line_number()))
+            });
+        }
 
-        Ok(results)
+        Ok(if options.score_matches {
+            rank_matches(results, &patterns, options)
+        } else {
+            limit_matches(results, options)
+        })
     }
 
     fn index(&self, _corpus: &Corpus) -> anyhow::Result<()> {
@@ -108,12 +314,33 @@ impl SearchBackend for RipgrepBackend {
     fn needs_indexing(&self) -> bool {
         false
     }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        // Matches are reported line by line, scored on request by how often
+        // their document matched
+        BackendCapabilities {
+            supports_scoring: true,
+            supports_tag_filter: true,
+            supports_regex: true,
+            supports_within: true,
+            supports_max_per_file: true,
+            supports_group_by_document: true,
+            supports_score_matches: true,
+            supports_include_metadata: true,
+            ..Default::default()
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "ripgrep"
+    }
 }
 
 /// Parsed match from ripgrep JSON output.
 struct RgMatch {
     path: PathBuf,
     matched_line: String,
+    match_ranges: Vec<Range<usize>>,
     line_number: usize,
 }
 
@@ -129,6 +356,14 @@ struct RgMatchData {
     path: Option<RgText>,
     lines: Option<RgText>,
     line_number: Option<usize>,
+    #[serde(default)]
+    submatches: Vec<RgSubmatch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RgSubmatch {
+    start: usize,
+    end: usize,
 }
 
 #[derive(Debug, Deserialize)]
@@ -145,58 +380,821 @@ fn parse_rg_line(line: &str) -> Option<RgMatch> {
     }
 
     let data = msg.data?;
+    let line = data.lines?.text;
+    let matched_line = line.trim();
+
+    // Submatch offsets are relative to the untrimmed line
+    let leading = line.len() - line.trim_start().len();
+    let match_ranges = data
+        .submatches
+        .iter()
+        .map(|m| {
+            m.start.saturating_sub(leading)..m.end.saturating_sub(leading).min(matched_line.len())
+        })
+        .filter(|r| r.start < r.end)
+        .collect();
+
     Some(RgMatch {
         path: PathBuf::from(&data.path?.text),
-        matched_line: data.lines?.text.trim().to_string(),
+        matched_line: matched_line.to_string(),
+        match_ranges,
         line_number: data.line_number?,
     })
 }
 
-fn parse_ripgrep_output(
-    output: &str,
+/// Results for a query made only of field filters: one per document whose
+/// metadata passes them, matched on its title.
+fn metadata_matches(
     corpus: &Corpus,
+    fields: &FieldQuery,
+    options: &SearchOptions,
+) -> Vec<SearchResult> {
+    corpus
+        .documents()
+        .iter()
+        .filter(|doc| {
+            options
+                .category
+                .as_ref()
+                .is_none_or(|cat| options.category_match.matches(cat, &doc.category))
+                && fields.matches(&doc.title, &doc.category, &doc.tags, options.case_sensitive)
+                && options
+                    .within
+                    .as_ref()
+                    .is_none_or(|paths| paths.contains(&corpus.resolve_document_path(doc)))
+        })
+        .take(options.limit.unwrap_or(usize::MAX))
+        .map(|doc| SearchResult {
+            path: corpus.resolve_document_path(doc),
+            title: if options.raw_titles {
+                file_stem_title(&doc.path)
+            } else {
+                doc.title.clone()
+            },
+            category: doc.category.clone(),
+            tags: doc.tags.clone(),
+            snippet: None,
+            score: None,
+            hit_count: None,
+            explanation: options
+                .explain
+                .then(|| "unscored: document metadata matches the field filters".to_string()),
+        })
+        .collect()
+}
+
+/// The files among `paths` inside `corpus`, sorted, as `corpus.root` joined
+/// with their path relative to it, like [`SearchResult::path`].
+///
+/// Paths are compared in absolute form, so relative ones resolve against the
+/// working directory. Globs don't apply to paths named on ripgrep's command
+/// line, so ignored paths are dropped here.
+fn paths_in_corpus(corpus: &Corpus, paths: &[PathBuf]) -> Vec<PathBuf> {
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let root = absolute(&corpus.root);
+    let mut found: Vec<PathBuf> = paths
+        .iter()
+        .filter_map(|path| {
+            let relative = absolute(path).strip_prefix(&root).ok()?.to_path_buf();
+            (!is_ignored_corpus_path(&relative)).then(|| corpus.root.join(relative))
+        })
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Results without a line for documents whose title or tags contain the query
+/// text but whose body has no match in `body_results`.
+///
+/// The title is reported if it matches, otherwise the tag list. Matching
+/// follows `options.match_mode` and `options.case_sensitive`, except that
+/// regex queries never match metadata.
+fn metadata_text_matches(
+    corpus: &Corpus,
+    fields: &FieldQuery,
+    options: &SearchOptions,
+    body_results: &[SearchResult],
+) -> Vec<SearchResult> {
+    let query = fields.text.trim();
+    let mut results = Vec::new();
+
+    for doc in corpus.documents() {
+        let path = corpus.resolve_document_path(doc);
+        let eligible = options
+            .category
+            .as_ref()
+            .is_none_or(|cat| options.category_match.matches(cat, &doc.category))
+            && fields.matches(&doc.title, &doc.category, &doc.tags, options.case_sensitive)
+            && options
+                .within
+                .as_ref()
+                .is_none_or(|paths| paths.contains(&path))
+            && !body_results.iter().any(|result| result.path == path);
+        if !eligible {
+            continue;
+        }
+
+        let matched = find_term(&doc.title, query, options)
+            .map(|ranges| (doc.title.clone(), ranges))
+            .or_else(|| tag_matches(&doc.tags, query, options));
+        let Some((text, highlights)) = matched else {
+            continue;
+        };
+
+        results.push(SearchResult {
+            path,
+            title: if options.raw_titles {
+                file_stem_title(&doc.path)
+            } else {
+                doc.title.clone()
+            },
+            category: doc.category.clone(),
+            tags: doc.tags.clone(),
+            snippet: Some(Snippet {
+                text,
+                highlights,
+                line: None,
+            }),
+            score: None,
+            hit_count: None,
+            explanation: options
+                .explain
+                .then(|| "unscored: query matches the document's title or tags".to_string()),
+        });
+    }
+
+    results
+}
+
+/// A `tags: a, b` line and the ranges of the query within it, if any tag
+/// contains the query.
+fn tag_matches(
+    tags: &[String],
+    query: &str,
+    options: &SearchOptions,
+) -> Option<(String, Vec<Range<usize>>)> {
+    let mut line = "tags: ".to_string();
+    let mut ranges = Vec::new();
+    let mut matched = false;
+
+    for (index, tag) in tags.iter().enumerate() {
+        if index > 0 {
+            line.push_str(", ");
+        }
+        if let Some(found) = find_term(tag, query, options) {
+            matched = true;
+            let offset = line.len();
+            ranges.extend(found.into_iter().map(|r| r.start + offset..r.end + offset));
+        }
+        line.push_str(tag);
+    }
+
+    matched.then_some((line, ranges))
+}
+
+/// The patterns ripgrep searches for in a substring or word query: the text
+/// between double quotes as one phrase, or otherwise each word on its own.
+fn literal_patterns(text: &str) -> Vec<&str> {
+    let text = text.trim();
+    if let Some(phrase) = text
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        && !phrase.trim().is_empty()
+    {
+        return vec![phrase];
+    }
+    text.split_whitespace().collect()
+}
+
+/// Keep the matches in files where every term matched on some line.
+fn require_all_terms(
+    results: Vec<SearchResult>,
+    terms: &[&str],
+    options: &SearchOptions,
+) -> Vec<SearchResult> {
+    let mut found: HashMap<PathBuf, HashSet<usize>> = HashMap::new();
+    for result in &results {
+        let line = result.matched_line();
+        found.entry(result.path.clone()).or_default().extend(
+            terms
+                .iter()
+                .enumerate()
+                .filter(|(_, term)| find_term(line, term, options).is_some())
+                .map(|(i, _)| i),
+        );
+    }
+
+    results
+        .into_iter()
+        .filter(|result| found[&result.path].len() == terms.len())
+        .collect()
+}
+
+/// Score each match by its document, order them best first, and apply the
+/// limits as [`limit_matches`] does.
+///
+/// A document scores one point per matching line, plus [`TITLE_WEIGHT`]
+/// for each of `terms` in its title. Every match in a document shares its
+/// score, so matches stay grouped by document and, since `results` must be
+/// ordered by path and then line number, in line order; equal scores keep
+/// path order.
+fn rank_matches(
+    mut results: Vec<SearchResult>,
+    terms: &[&str],
     options: &SearchOptions,
 ) -> Vec<SearchResult> {
+    let mut lines: HashMap<PathBuf, usize> = HashMap::new();
+    for result in &results {
+        // Metadata matches have no line
+        if result.line_number().is_some() {
+            *lines.entry(result.path.clone()).or_default() += 1;
+        }
+    }
+
+    for result in &mut results {
+        let lines = lines.get(&result.path).copied().unwrap_or_default();
+        let title_terms = terms
+            .iter()
+            .filter(|term| find_term(&result.title, term, options).is_some())
+            .count();
+        #[allow(clippy::cast_precision_loss)]
+        let score = lines as f32 + TITLE_WEIGHT * title_terms as f32;
+        result.score = Some(score);
+        result.explanation = options.explain.then(|| {
+            format!(
+                "{score} = {lines} matching line(s) + {TITLE_WEIGHT} x {title_terms} query term(s) in the title"
+            )
+        });
+    }
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    limit_matches(results, options)
+}
+
+/// Apply `options.group_by_document`, `options.max_per_file`, and
+/// `options.limit` to `results`, keeping their order.
+///
+/// Grouping keeps a document's first match, counting all of them in its
+/// `hit_count`. As in [`rank_matches`], a metadata match (without a line) only counts
+/// for a document none of whose lines matched.
+fn limit_matches(mut results: Vec<SearchResult>, options: &SearchOptions) -> Vec<SearchResult> {
+    if options.group_by_document {
+        let body_matches: HashSet<PathBuf> = results
+            .iter()
+            .filter(|result| result.line_number().is_some())
+            .map(|result| result.path.clone())
+            .collect();
+        results.retain(|result| {
+            result.line_number().is_some() || !body_matches.contains(&result.path)
+        });
+
+        let mut hits: HashMap<PathBuf, usize> = HashMap::new();
+        for result in &results {
+            *hits.entry(result.path.clone()).or_default() += 1;
+        }
+        for result in &mut results {
+            result.hit_count = hits.get(&result.path).copied();
+        }
+    }
+
+    let max_per_file = if options.group_by_document {
+        1
+    } else {
+        options.max_per_file.unwrap_or(usize::MAX)
+    };
+    let mut per_file: HashMap<PathBuf, usize> = HashMap::new();
+    results
+        .into_iter()
+        .filter(|result| {
+            let count = per_file.entry(result.path.clone()).or_default();
+            *count += 1;
+            *count <= max_per_file
+        })
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// Byte ranges of `query` within `text`, matched as ripgrep would in
+/// substring or word mode. Used for titles and tags ripgrep doesn't see, and
+/// to check which terms a matched line contains.
+///
+/// # Returns
+///
+/// `None` if the query doesn't occur, is empty, or is a regex. The ranges
+/// are empty if ignoring case changed the text's byte length.
+fn find_term(text: &str, query: &str, options: &SearchOptions) -> Option<Vec<Range<usize>>> {
+    if query.is_empty() || options.match_mode == MatchMode::Regex {
+        return None;
+    }
+
+    let (haystack, needle) = if options.case_sensitive {
+        (text.to_string(), query.to_string())
+    } else {
+        (text.to_lowercase(), query.to_lowercase())
+    };
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let ranges: Vec<Range<usize>> = haystack
+        .match_indices(&needle)
+        .map(|(start, m)| start..start + m.len())
+        .filter(|r| {
+            options.match_mode != MatchMode::Word
+                || !(haystack[..r.start].chars().next_back().is_some_and(is_word)
+                    || haystack[r.end..].chars().next().is_some_and(is_word))
+        })
+        .collect();
+
+    if ranges.is_empty() {
+        return None;
+    }
+    // Offsets into the lowercased text are only valid for the original if
+    // lowercasing kept every character's length
+    Some(if haystack.len() == text.len() {
+        ranges
+    } else {
+        Vec::new()
+    })
+}
+
+/// Read ripgrep JSON output line by line, converting matches into results
+/// until `read_limit` have been collected. Files the manifest doesn't list
+/// are categorized by `categories`. Matches in documents failing the
+/// query's field filters are skipped; the limits in `options` are left to
+/// [`limit_matches`].
+///
+/// Results keep the order ripgrep printed them in: by path and then line
+/// number, since the command always passes `--sort path`.
+fn parse_ripgrep_output(
+    output: impl BufRead,
+    corpus: &Corpus,
+    categories: &dyn CategoryResolver,
+    fields: &FieldQuery,
+    options: &SearchOptions,
+    read_limit: usize,
+) -> std::io::Result<Vec<SearchResult>> {
     let doc_map: HashMap<PathBuf, &Document> = corpus
         .documents()
         .iter()
         .map(|d| (corpus.resolve_document_path(d), d))
         .collect();
 
-    let mut results: Vec<SearchResult> = output
-        .lines()
-        .filter_map(parse_rg_line)
-        .filter_map(|m| {
-            let (title, category) = doc_map.get(&m.path).map_or_else(
+    let mut results = Vec::new();
+
+    for line in output.split(b'\n') {
+        if results.len() >= read_limit {
+            break;
+        }
+
+        let line = line?;
+        let result = parse_rg_line(&String::from_utf8_lossy(&line)).and_then(|m| {
+            let (title, category, tags) = doc_map.get(&m.path).map_or_else(
                 || {
-                    let title = m.path.file_stem().map_or_else(
-                        || "Unknown".to_string(),
-                        |s| s.to_string_lossy().to_string(),
-                    );
-                    (title, "unknown".to_string())
+                    let category = m
+                        .path
+                        .strip_prefix(&corpus.root)
+                        .ok()
+                        .and_then(|relative| categories.resolve(relative))
+                        .unwrap_or_else(|| "unknown".to_string());
+                    (humanize_title(&m.path), category, Vec::new())
                 },
-                |doc| (doc.title.clone(), doc.category.clone()),
+                |doc| (doc.title.clone(), doc.category.clone(), doc.tags.clone()),
             );
 
             if let Some(ref cat) = options.category
-                && &category != cat
+                && !options.category_match.matches(cat, &category)
             {
                 return None;
             }
+            if !fields.matches(&title, &category, &tags, options.case_sensitive) {
+                return None;
+            }
+
+            let title = if options.raw_titles {
+                file_stem_title(&m.path)
+            } else {
+                title
+            };
+
+            let explanation = options.explain.then(|| {
+                format!(
+                    "unscored: ripgrep match on line {}; results are ordered by title, then path",
+                    m.line_number
+                )
+            });
 
             Some(SearchResult {
                 path: m.path,
                 title,
-                matched_line: m.matched_line,
-                line_number: m.line_number,
+                category,
+                tags,
+                snippet: Some(Snippet {
+                    text: m.matched_line,
+                    highlights: m.match_ranges,
+                    line: Some(m.line_number),
+                }),
                 score: None,
+                hit_count: None,
+                explanation,
             })
-        })
-        .collect();
+        });
+        results.extend(result);
+    }
+
+    Ok(results)
+}
 
-    if let Some(limit) = options.limit {
-        results.truncate(limit);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rg_line_adjusts_ranges_for_trim() {
+        let line = r#"{"type":"match","data":{"path":{"text":"/c/a.md"},"lines":{"text":"  use lambda here\n"},"line_number":3,"submatches":[{"match":{"text":"lambda"},"start":6,"end":12}]}}"#;
+
+        let m = parse_rg_line(line).unwrap();
+        assert_eq!(m.matched_line, "use lambda here");
+        assert_eq!(m.match_ranges, vec![Range { start: 4, end: 10 }]);
+        assert_eq!(&m.matched_line[m.match_ranges[0].clone()], "lambda");
     }
 
-    results
+    #[test]
+    fn find_term_follows_match_mode() {
+        let options = |match_mode, case_sensitive| SearchOptions {
+            case_sensitive,
+            match_mode,
+            ..Default::default()
+        };
+
+        let substring = options(MatchMode::Substring, false);
+        assert_eq!(
+            find_term("AWS Lambda", "lambda", &substring),
+            Some(vec![Range { start: 4, end: 10 }])
+        );
+        assert_eq!(
+            find_term("prelambda", "lambda", &substring),
+            Some(vec![Range { start: 3, end: 9 }])
+        );
+        assert_eq!(
+            find_term("AWS Lambda", "lambda", &options(MatchMode::Substring, true)),
+            None
+        );
+        assert_eq!(
+            find_term("prelambda", "lambda", &options(MatchMode::Word, false)),
+            None
+        );
+        assert_eq!(
+            find_term("lambda", "lamb.a", &options(MatchMode::Regex, false)),
+            None
+        );
+    }
+
+    fn rg_match(path: &str, line_number: usize) -> String {
+        format!(
+            r#"{{"type":"match","data":{{"path":{{"text":"{path}"}},"lines":{{"text":"lambda\n"}},"line_number":{line_number},"submatches":[]}}}}"#
+        )
+    }
+
+    fn corpus() -> Corpus {
+        Corpus {
+            root: PathBuf::from("/c"),
+            manifest: crate::corpus::Manifest::empty(),
+        }
+    }
+
+    #[test]
+    fn parse_output_raw_titles_use_file_stem() {
+        let mut corpus = corpus();
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("notes/aws-lambda.md"),
+            title: "AWS Lambda Guide".to_string(),
+            category: "aws".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+        let output = rg_match("/c/notes/aws-lambda.md", 1) + "\n";
+        let title = |raw_titles| {
+            let options = SearchOptions {
+                raw_titles,
+                ..Default::default()
+            };
+            parse_ripgrep_output(
+                output.as_bytes(),
+                &corpus,
+                &FirstComponentResolver,
+                &FieldQuery::default(),
+                &options,
+                usize::MAX,
+            )
+            .unwrap()
+            .remove(0)
+            .title
+        };
+
+        assert_eq!(title(false), "AWS Lambda Guide");
+        assert_eq!(title(true), "aws-lambda");
+    }
+
+    /// Unranked results for ripgrep `output` in the test corpus.
+    fn parse(output: &str) -> Vec<SearchResult> {
+        parse_ripgrep_output(
+            output.as_bytes(),
+            &corpus(),
+            &FirstComponentResolver,
+            &FieldQuery::default(),
+            &SearchOptions::default(),
+            usize::MAX,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_output_stops_at_read_limit() {
+        let output: String = (1..=5)
+            .map(|n| rg_match("/c/a.md", n) + "\n")
+            .collect::<Vec<_>>()
+            .concat();
+
+        let results = parse_ripgrep_output(
+            output.as_bytes(),
+            &corpus(),
+            &FirstComponentResolver,
+            &FieldQuery::default(),
+            &SearchOptions::default(),
+            3,
+        )
+        .unwrap();
+        assert_eq!(results.len(), 3);
+    }
+
+    #[test]
+    fn limit_keeps_order_without_scores() {
+        let output: String = [rg_match("/c/a.md", 1), rg_match("/c/a.md", 2)]
+            .into_iter()
+            .chain([rg_match("/c/b.md", 1)])
+            .map(|line| line + "\n")
+            .collect::<Vec<_>>()
+            .concat();
+        let options = SearchOptions {
+            limit: Some(2),
+            ..Default::default()
+        };
+
+        let results = limit_matches(parse(&output), &options);
+        let found: Vec<_> = results
+            .iter()
+            .map(|r| (r.path.display().to_string(), r.line_number(), r.score))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("/c/a.md".to_string(), Some(1), None),
+                ("/c/a.md".to_string(), Some(2), None)
+            ]
+        );
+    }
+
+    #[test]
+    fn rank_caps_matches_per_file() {
+        let output: String = (1..=5)
+            .map(|n| rg_match("/c/a.md", n))
+            .chain([rg_match("/c/b.md", 1), rg_match("/c/b.md", 2)])
+            .map(|line| line + "\n")
+            .collect::<Vec<_>>()
+            .concat();
+        let options = SearchOptions {
+            max_per_file: Some(2),
+            ..Default::default()
+        };
+
+        let results = rank_matches(parse(&output), &[], &options);
+        let found: Vec<_> = results
+            .iter()
+            .map(|r| (r.path.to_string_lossy().into_owned(), r.line_number()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("/c/a.md".to_string(), Some(1)),
+                ("/c/a.md".to_string(), Some(2)),
+                ("/c/b.md".to_string(), Some(1)),
+                ("/c/b.md".to_string(), Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rank_applies_global_limit_with_per_file_cap() {
+        let output: String = [1, 2, 3]
+            .into_iter()
+            .flat_map(|n| [rg_match("/c/a.md", n), rg_match("/c/b.md", n)])
+            .map(|line| line + "\n")
+            .collect::<Vec<_>>()
+            .concat();
+        let options = SearchOptions {
+            limit: Some(3),
+            max_per_file: Some(1),
+            ..Default::default()
+        };
+
+        let results = rank_matches(parse(&output), &[], &options);
+        assert_eq!(results.len(), 2);
+        assert_ne!(results[0].path, results[1].path);
+    }
+
+    #[test]
+    fn parse_rg_line_ignores_non_matches() {
+        assert!(parse_rg_line(r#"{"type":"summary","data":{}}"#).is_none());
+    }
+
+    #[test]
+    fn literal_patterns_split_words_unless_quoted() {
+        assert_eq!(literal_patterns("lambda"), ["lambda"]);
+        assert_eq!(
+            literal_patterns("  lambda   cold start "),
+            ["lambda", "cold", "start"]
+        );
+        assert_eq!(
+            literal_patterns("\"lambda cold start\""),
+            ["lambda cold start"]
+        );
+        assert_eq!(literal_patterns("\"lambda"), ["\"lambda"]);
+        assert_eq!(literal_patterns("\"\""), ["\"\""]);
+        assert!(literal_patterns("   ").is_empty());
+    }
+
+    fn line_result(path: &str, line: usize, text: &str) -> SearchResult {
+        SearchResult {
+            path: PathBuf::from(path),
+            title: path.to_string(),
+            category: "unknown".to_string(),
+            tags: vec![],
+            snippet: Some(Snippet {
+                text: text.to_string(),
+                highlights: vec![],
+                line: Some(line),
+            }),
+            score: None,
+            hit_count: None,
+            explanation: None,
+        }
+    }
+
+    /// A match in the title or tags, which has no line.
+    fn metadata_result(path: &str, text: &str) -> SearchResult {
+        let mut result = line_result(path, 1, text);
+        if let Some(snippet) = &mut result.snippet {
+            snippet.line = None;
+        }
+        result
+    }
+
+    #[test]
+    fn require_all_terms_keeps_files_matching_every_term() {
+        let results = vec![
+            line_result("/c/a.md", 1, "Lambda functions"),
+            line_result("/c/a.md", 4, "avoid a cold start"),
+            line_result("/c/a.md", 9, "more on Lambda"),
+            line_result("/c/b.md", 2, "lambda only"),
+            line_result("/c/c.md", 3, "lambda cold"),
+        ];
+        let lines = |options: &SearchOptions| {
+            let terms = ["lambda", "cold"];
+            rank_matches(
+                require_all_terms(results.clone(), &terms, options),
+                &terms,
+                options,
+            )
+            .iter()
+            .map(|r| (r.path.display().to_string(), r.line_number()))
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            lines(&SearchOptions::default()),
+            [
+                ("/c/a.md".to_string(), Some(1)),
+                ("/c/a.md".to_string(), Some(4)),
+                ("/c/a.md".to_string(), Some(9)),
+                ("/c/c.md".to_string(), Some(3)),
+            ]
+        );
+        assert_eq!(
+            lines(&SearchOptions {
+                max_per_file: Some(1),
+                limit: Some(2),
+                ..Default::default()
+            }),
+            [
+                ("/c/a.md".to_string(), Some(1)),
+                ("/c/c.md".to_string(), Some(3))
+            ]
+        );
+        assert!(
+            lines(&SearchOptions {
+                case_sensitive: true,
+                ..Default::default()
+            })
+            .iter()
+            .all(|(path, _)| path == "/c/c.md")
+        );
+    }
+
+    #[test]
+    fn rank_scores_lines_and_title_terms() {
+        let mut titled = line_result("/c/b.md", 5, "lambda");
+        titled.title = "Lambda Notes".to_string();
+        let results = vec![
+            line_result("/c/a.md", 1, "lambda"),
+            line_result("/c/a.md", 2, "lambda"),
+            titled,
+            metadata_result("/c/c.md", "tags: lambda"),
+        ];
+        let options = SearchOptions {
+            explain: true,
+            ..Default::default()
+        };
+
+        let ranked = rank_matches(results, &["lambda"], &options);
+        let scores: Vec<_> = ranked
+            .iter()
+            .map(|r| (r.path.display().to_string(), r.line_number(), r.score))
+            .collect();
+        assert_eq!(
+            scores,
+            [
+                ("/c/b.md".to_string(), Some(5), Some(4.0)),
+                ("/c/a.md".to_string(), Some(1), Some(2.0)),
+                ("/c/a.md".to_string(), Some(2), Some(2.0)),
+                ("/c/c.md".to_string(), None, Some(0.0)),
+            ]
+        );
+        assert_eq!(
+            ranked[0].explanation.as_deref(),
+            Some("4 = 1 matching line(s) + 3 x 1 query term(s) in the title")
+        );
+    }
+
+    #[test]
+    fn rank_groups_matches_by_document() {
+        let output: String = [rg_match("/c/a.md", 2), rg_match("/c/a.md", 7)]
+            .into_iter()
+            .chain([rg_match("/c/b.md", 4)])
+            .map(|line| line + "\n")
+            .collect::<Vec<_>>()
+            .concat();
+        let options = SearchOptions {
+            group_by_document: true,
+            ..Default::default()
+        };
+
+        let results = rank_matches(parse(&output), &[], &options);
+        let grouped: Vec<_> = results
+            .iter()
+            .map(|r| (r.path.display().to_string(), r.line_number(), r.hit_count))
+            .collect();
+        assert_eq!(
+            grouped,
+            [
+                ("/c/a.md".to_string(), Some(2), Some(2)),
+                ("/c/b.md".to_string(), Some(4), Some(1)),
+            ]
+        );
+
+        let ungrouped = rank_matches(parse(&output), &[], &SearchOptions::default());
+        assert_eq!(ungrouped.len(), 3);
+        assert!(ungrouped.iter().all(|r| r.hit_count.is_none()));
+    }
+
+    #[test]
+    fn group_prefers_body_lines_to_metadata() {
+        let results = vec![
+            metadata_result("/c/a.md", "tags: lambda"),
+            line_result("/c/a.md", 3, "lambda"),
+            line_result("/c/a.md", 8, "lambda"),
+            metadata_result("/c/b.md", "tags: lambda"),
+        ];
+        let options = SearchOptions {
+            group_by_document: true,
+            ..Default::default()
+        };
+
+        let grouped: Vec<_> = limit_matches(results, &options)
+            .iter()
+            .map(|r| (r.path.display().to_string(), r.line_number(), r.hit_count))
+            .collect();
+        assert_eq!(
+            grouped,
+            [
+                ("/c/a.md".to_string(), Some(3), Some(2)),
+                ("/c/b.md".to_string(), None, Some(1)),
+            ]
+        );
+    }
 }
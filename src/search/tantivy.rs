@@ -3,23 +3,102 @@
 //! Provides ranked search results using the Tantivy full-text search engine.
 //! Supports fuzzy matching for typo-tolerant queries.
 
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
 
-use tantivy::collector::TopDocs;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tantivy::collector::{DocSetCollector, TopDocs};
 use tantivy::directory::MmapDirectory;
-use tantivy::query::{BooleanQuery, FuzzyTermQuery, Occur, QueryParser, TermQuery};
-use tantivy::schema::{FAST, Field, STORED, STRING, Schema, TEXT, Value};
-use tantivy::{Index, IndexReader, IndexSettings, IndexWriter, ReloadPolicy, Term};
-
-use crate::corpus::Corpus;
-use crate::search::{SearchBackend, SearchOptions, SearchResult};
-
-/// Default index directory name within corpus root.
-const INDEX_DIR: &str = ".index";
+use tantivy::postings::Postings;
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, Explanation, FuzzyTermQuery, Occur, QueryParser,
+    RangeQuery, TermQuery,
+};
+use tantivy::schema::{
+    FAST, Field, FieldType, IndexRecordOption, STORED, STRING, Schema, TEXT, TextFieldIndexing,
+    TextOptions, Value,
+};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::tokenizer::{
+    Language, LowerCaser, RegexTokenizer, RemoveLongFilter, SimpleTokenizer, Stemmer,
+    StopWordFilter, TextAnalyzer,
+};
+use tantivy::{
+    DocSet, Index, IndexReader, IndexSettings, IndexWriter, ReloadPolicy, Searcher, TERMINATED,
+    Term,
+};
+
+use crate::config::DEFAULT_INDEX_LANGUAGE;
+use crate::corpus::{
+    Corpus, Document, INDEX_DIR, ManifestFormat, file_stem_title, is_code_content_type,
+};
+use crate::search::fields::{FieldQuery, QueryField};
+use crate::search::{
+    BackendCapabilities, CategoryMatch, SearchBackend, SearchOptions, SearchResult, Snippet,
+    SnippetStrategy,
+};
+use crate::storage::StorageBackend;
+use crate::storage::local::LocalStorageBackend;
 
 /// Default heap size for index writer (50MB).
 const WRITER_HEAP_SIZE: usize = 50_000_000;
 
+/// Progress marker file within the index directory.
+const PROGRESS_FILE: &str = "progress.json";
+
+/// File within the index directory holding the fingerprint of the corpus
+/// as of the last complete indexing run.
+const FINGERPRINT_FILE: &str = "fingerprint";
+
+/// Documents indexed between commits (and progress marker updates) when
+/// indexing resumably.
+pub const COMMIT_BATCH_SIZE: usize = 500;
+
+/// Maximum edit distance considered when suggesting query corrections.
+const SUGGEST_DISTANCE: u8 = 2;
+
+/// Score multiplier for query words matched in a title rather than a body.
+const TITLE_BOOST: f32 = 3.0;
+
+/// Maximum length of a `best` or `prefix` snippet, in characters.
+const SNIPPET_CHARS: usize = 160;
+
+/// Name of the tokenizer for source-code bodies.
+const CODE_TOKENIZER: &str = "code";
+
+/// Name of the tokenizer for the unstemmed words suggestions come from.
+const SPELLING_TOKENIZER: &str = "spelling";
+
+/// Prefix of the stemming tokenizer's name for titles and prose bodies,
+/// followed by the language code (e.g., `stem_en`).
+const STEM_TOKENIZER_PREFIX: &str = "stem_";
+
+/// Language codes accepted for `[corpus.index] language`, with the
+/// stemming rules each selects.
+const LANGUAGES: [(&str, Language); 18] = [
+    ("ar", Language::Arabic),
+    ("da", Language::Danish),
+    ("de", Language::German),
+    ("el", Language::Greek),
+    ("en", Language::English),
+    ("es", Language::Spanish),
+    ("fi", Language::Finnish),
+    ("fr", Language::French),
+    ("hu", Language::Hungarian),
+    ("it", Language::Italian),
+    ("nl", Language::Dutch),
+    ("no", Language::Norwegian),
+    ("pt", Language::Portuguese),
+    ("ro", Language::Romanian),
+    ("ru", Language::Russian),
+    ("sv", Language::Swedish),
+    ("ta", Language::Tamil),
+    ("tr", Language::Turkish),
+];
+
 /// Index mode controls whether the backend can write to the index.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IndexMode {
@@ -29,14 +108,151 @@ pub enum IndexMode {
     ReadOnly,
 }
 
+/// Segment counts reported by [`TantivyBackend::compact`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionReport {
+    /// Number of searchable segments before compaction.
+    pub segments_before: usize,
+    /// Number of searchable segments after compaction.
+    pub segments_after: usize,
+}
+
+/// Whether [`TantivyBackend::index_corpus_resumable`] starts over or
+/// continues an interrupted run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rebuild {
+    /// Clear the index and index every document (default).
+    #[default]
+    Full,
+    /// Skip documents an interrupted run already committed.
+    Resume,
+}
+
+/// Outcome of [`TantivyBackend::index_corpus_resumable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexProgress {
+    /// Documents processed by this run (including unreadable ones skipped
+    /// with a warning).
+    pub indexed: usize,
+    /// Documents skipped because an earlier run already indexed them.
+    pub skipped: usize,
+    /// Whether every document is now indexed.
+    pub complete: bool,
+}
+
+/// Outcome of [`TantivyBackend::index_incremental`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncrementalReport {
+    /// New or modified documents processed by this run (including
+    /// unreadable ones skipped with a warning).
+    pub indexed: usize,
+    /// Indexed documents no longer in the manifest, now deleted.
+    pub removed: usize,
+    /// Documents whose indexed copy was already current.
+    pub unchanged: usize,
+}
+
+/// Index-wide statistics for one term, from [`TantivyBackend::term_stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermStats {
+    /// The term as indexed (lowercased and stemmed).
+    pub term: String,
+    /// Documents containing the term in their title or body.
+    pub doc_freq: u64,
+    /// Occurrences of the term across those documents.
+    pub term_freq: u64,
+    /// Documents in the index.
+    pub num_docs: u64,
+}
+
+impl TermStats {
+    /// BM25 inverse document frequency for a term in `doc_freq` of `num_docs`
+    /// documents. Rarer terms get higher values and so contribute more to a
+    /// result's score.
+    ///
+    /// This uses Tantivy's formula, but Tantivy scores each field with that
+    /// field's own document frequency, while `doc_freq` counts documents with
+    /// the term in any field. The value is a guide, not the exact weight.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn idf(&self) -> f64 {
+        let doc_freq = self.doc_freq as f64;
+        let num_docs = self.num_docs as f64;
+        (1.0 + (num_docs - doc_freq + 0.5) / (doc_freq + 0.5)).ln()
+    }
+}
+
+/// Progress of an unfinished indexing run, persisted in the index directory.
+///
+/// Documents are indexed in manifest order, so the count plus the last path
+/// identify where to continue.
+#[derive(Debug, Serialize, Deserialize)]
+struct ProgressMarker {
+    /// Number of leading manifest documents already committed.
+    completed: usize,
+    /// Path of the last committed document.
+    last_path: PathBuf,
+    /// Manifest size when the run started.
+    total: usize,
+}
+
+impl ProgressMarker {
+    fn new(documents: &[Document], completed: usize) -> Self {
+        Self {
+            completed,
+            last_path: documents[completed - 1].path.clone(),
+            total: documents.len(),
+        }
+    }
+
+    /// Read a marker, treating a missing or unreadable one as absent.
+    fn load(path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Whether the marker still describes `documents`.
+    fn matches(&self, documents: &[Document]) -> bool {
+        self.total == documents.len()
+            && self.completed > 0
+            && documents
+                .get(self.completed - 1)
+                .is_some_and(|doc| doc.path == self.last_path)
+    }
+}
+
 /// Schema field handles for the Tantivy index.
 #[derive(Debug, Clone)]
 struct SchemaFields {
     title: Field,
     content: Field,
     category: Field,
+    /// Lowercased category, which filters match so they ignore case while
+    /// `category` keeps the casing for display. Absent from indexes built
+    /// before, which filter on `category` as stored.
+    category_key: Option<Field>,
     tags: Field,
     path: Field,
+    /// Body of source-code documents. Absent from indexes built before code
+    /// was indexed separately, which keep all bodies in `content`.
+    code: Option<Field>,
+    /// File modification time when the document was indexed, in nanoseconds
+    /// since the Unix epoch. Absent from indexes built before incremental
+    /// indexing, which are always rebuilt in full.
+    mtime: Option<Field>,
+    /// Hash of the indexed manifest metadata (title, category, tags, and
+    /// content type), which incremental indexing compares since editing
+    /// metadata doesn't touch the file. Absent from indexes built before.
+    metadata_hash: Option<Field>,
+    /// Title and prose body words, lowercased but not stemmed, so
+    /// suggestions are words a user can type back. Absent from indexes
+    /// built before, which suggest from the searched fields.
+    spelling: Option<Field>,
 }
 
 /// Tantivy-based search backend with BM25 ranking.
@@ -57,29 +273,72 @@ impl TantivyBackend {
     ///
     /// Fields:
     /// - `title`: Searchable text, stored for display
-    /// - `content`: Searchable text (document body)
-    /// - `category`: Exact match filter, stored
-    /// - `tags`: Stored for display (space-separated)
+    /// - `content`: Searchable text (prose document body)
+    /// - `code`: Searchable text (source-code document body), tokenized so
+    ///   identifiers like `parse_config` stay whole
+    /// - `category`: Stored for display
+    /// - `category_key`: Lowercased category, for filters that ignore case
+    /// - `tags`: Searchable text for `tags:` queries, stored for display
+    ///   (space-separated)
     /// - `path`: Stored for result retrieval
-    fn build_schema() -> (Schema, SchemaFields) {
+    /// - `mtime`: Stored file modification time, compared by incremental
+    ///   indexing
+    /// - `metadata_hash`: Stored hash of the manifest metadata, compared by
+    ///   incremental indexing
+    /// - `spelling`: Unstemmed title and prose words, for query suggestions
+    ///
+    /// `title` and `content` are stemmed, with stopwords dropped, by the
+    /// rules for `language` (a code from [`LANGUAGES`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `language` is not supported.
+    fn build_schema(language: &str) -> anyhow::Result<(Schema, SchemaFields)> {
+        stemmer_language(language)?;
         let mut schema_builder = Schema::builder();
 
-        let title = schema_builder.add_text_field("title", TEXT | STORED);
-        let content = schema_builder.add_text_field("content", TEXT);
+        let stem_indexing = TextFieldIndexing::default()
+            .set_tokenizer(&format!("{STEM_TOKENIZER_PREFIX}{language}"))
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        let prose = TextOptions::default().set_indexing_options(stem_indexing);
+        let title = schema_builder.add_text_field("title", prose.clone().set_stored());
+        let content = schema_builder.add_text_field("content", prose);
         let category = schema_builder.add_text_field("category", STRING | STORED | FAST);
-        let tags = schema_builder.add_text_field("tags", STORED);
+        let category_key = schema_builder.add_text_field("category_key", STRING);
+        let tags = schema_builder.add_text_field("tags", TEXT | STORED);
         let path = schema_builder.add_text_field("path", STRING | STORED);
+        let code_indexing = TextFieldIndexing::default()
+            .set_tokenizer(CODE_TOKENIZER)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        let code = schema_builder.add_text_field(
+            "code",
+            TextOptions::default().set_indexing_options(code_indexing),
+        );
+        let mtime = schema_builder.add_u64_field("mtime", STORED);
+        let metadata_hash = schema_builder.add_u64_field("metadata_hash", STORED);
+        let spelling_indexing = TextFieldIndexing::default()
+            .set_tokenizer(SPELLING_TOKENIZER)
+            .set_index_option(IndexRecordOption::Basic);
+        let spelling = schema_builder.add_text_field(
+            "spelling",
+            TextOptions::default().set_indexing_options(spelling_indexing),
+        );
 
         let schema = schema_builder.build();
         let fields = SchemaFields {
             title,
             content,
             category,
+            category_key: Some(category_key),
             tags,
             path,
+            code: Some(code),
+            mtime: Some(mtime),
+            metadata_hash: Some(metadata_hash),
+            spelling: Some(spelling),
         };
 
-        (schema, fields)
+        Ok((schema, fields))
     }
 
     /// Open or create a Tantivy index at the specified path.
@@ -91,19 +350,24 @@ impl TantivyBackend {
     ///
     /// # Errors
     ///
-    /// Returns an error if the index cannot be opened or created.
+    /// Returns an error if the index cannot be opened or created, or if it
+    /// is opened read-only and predates stemming, so queries would be
+    /// stemmed differently from its terms.
     pub fn open(index_path: &Path, mode: IndexMode) -> anyhow::Result<Self> {
         // Open or create index first, then extract schema from the actual index
         let index = if index_path.exists() {
             // Open existing index - use its stored schema
             let directory = MmapDirectory::open(index_path)?;
-            Index::open(directory)?
+            let index = Index::open(directory)?;
+            if mode == IndexMode::ReadOnly && index_language(&index.schema()).is_none() {
+                anyhow::bail!(
+                    "Index at {} predates stemming; reindex required (run `kvault index`)",
+                    index_path.display()
+                );
+            }
+            index
         } else if mode == IndexMode::ReadWrite {
-            // Create new index with our schema
-            let (schema, _) = Self::build_schema();
-            std::fs::create_dir_all(index_path)?;
-            let directory = MmapDirectory::open(index_path)?;
-            Index::create(directory, schema, IndexSettings::default())?
+            create_index(index_path, DEFAULT_INDEX_LANGUAGE)?
         } else {
             anyhow::bail!(
                 "Index not found at {} (read-only mode)",
@@ -111,15 +375,26 @@ impl TantivyBackend {
             );
         };
 
+        Self::from_index(index, mode, index_path)
+    }
+
+    /// Wrap an opened index, reading field handles from its stored schema.
+    fn from_index(index: Index, mode: IndexMode, index_path: &Path) -> anyhow::Result<Self> {
         // Get schema from the actual index (handles schema evolution correctly)
         let schema = index.schema();
         let fields = SchemaFields {
             title: schema.get_field("title")?,
             content: schema.get_field("content")?,
             category: schema.get_field("category")?,
+            category_key: schema.get_field("category_key").ok(),
             tags: schema.get_field("tags")?,
             path: schema.get_field("path")?,
+            code: schema.get_field("code").ok(),
+            mtime: schema.get_field("mtime").ok(),
+            metadata_hash: schema.get_field("metadata_hash").ok(),
+            spelling: schema.get_field("spelling").ok(),
         };
+        register_tokenizers(&index)?;
 
         let reader = index
             .reader_builder()
@@ -147,6 +422,41 @@ impl TantivyBackend {
         Self::open(&index_path, mode)
     }
 
+    /// Open a corpus's index for indexing, built for `language`.
+    ///
+    /// An index built for another language, before stemming, or without
+    /// the fields suggestions and incremental indexing need can't be updated
+    /// in place, so it is replaced with an empty one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `language` is not supported or the index cannot
+    /// be opened, removed, or created.
+    pub fn open_for_indexing(corpus: &Corpus, language: &str) -> anyhow::Result<Self> {
+        stemmer_language(language)?;
+        let index_path = corpus.root.join(INDEX_DIR);
+        if index_path.exists() {
+            let backend = Self::open(&index_path, IndexMode::ReadWrite)?;
+            let fields = &backend.fields;
+            let current = fields.spelling.is_some() && fields.metadata_hash.is_some();
+            if current && backend.language().as_deref() == Some(language) {
+                return Ok(backend);
+            }
+            drop(backend);
+            std::fs::remove_dir_all(&index_path)?;
+        }
+
+        let index = create_index(&index_path, language)?;
+        Self::from_index(index, IndexMode::ReadWrite, &index_path)
+    }
+
+    /// Language code whose stemming the index applies, or `None` if it
+    /// predates stemming.
+    #[must_use]
+    pub fn language(&self) -> Option<String> {
+        index_language(&self.index.schema())
+    }
+
     /// Check if the index exists for a corpus.
     #[must_use]
     pub fn index_exists(corpus: &Corpus) -> bool {
@@ -159,71 +469,331 @@ impl TantivyBackend {
         &self.index_path
     }
 
-    /// Build a fuzzy query that searches both title and content fields.
+    /// Build a fuzzy query that searches the title and body fields.
     ///
     /// Creates `FuzzyTermQuery` for each word in the query string, allowing
-    /// typo-tolerant matching up to the specified edit distance.
-    fn build_fuzzy_query(&self, query_str: &str, distance: u8) -> Box<dyn tantivy::query::Query> {
-        let clauses: Vec<(Occur, Box<dyn tantivy::query::Query>)> = query_str
-            .split_whitespace()
-            .flat_map(|word| {
-                let title_term = Term::from_field_text(self.fields.title, word);
-                let content_term = Term::from_field_text(self.fields.content, word);
-
+    /// typo-tolerant matching up to the specified edit distance. Each word is
+    /// lowercased and stemmed as the field indexes words, so an inflected
+    /// form matches exactly; stopwords are dropped. Title matches are boosted
+    /// by [`TITLE_BOOST`].
+    fn build_fuzzy_query(
+        &self,
+        query_str: &str,
+        distance: u8,
+    ) -> anyhow::Result<Box<dyn tantivy::query::Query>> {
+        let mut clauses: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::new();
+        for word in query_str.split_whitespace() {
+            for field in self.text_fields() {
+                let Some(term) = self.analyze(field, word)? else {
+                    continue;
+                };
                 // Third parameter enables prefix matching (e.g., "lamb" matches "lambda")
-                let title_fuzzy = FuzzyTermQuery::new(title_term, distance, true);
-                let content_fuzzy = FuzzyTermQuery::new(content_term, distance, true);
-
-                vec![
-                    (
-                        Occur::Should,
-                        Box::new(title_fuzzy) as Box<dyn tantivy::query::Query>,
-                    ),
-                    (
-                        Occur::Should,
-                        Box::new(content_fuzzy) as Box<dyn tantivy::query::Query>,
-                    ),
-                ]
-            })
-            .collect();
+                let query: Box<dyn tantivy::query::Query> =
+                    Box::new(FuzzyTermQuery::new(term, distance, true));
+                if field == self.fields.title {
+                    clauses.push((Occur::Should, Box::new(BoostQuery::new(query, TITLE_BOOST))));
+                } else {
+                    clauses.push((Occur::Should, query));
+                }
+            }
+        }
 
-        Box::new(BooleanQuery::new(clauses))
+        Ok(Box::new(BooleanQuery::new(clauses)))
     }
 
-    /// Build a search query from the user's query string.
+    /// The term `field` indexes for `word`, or `None` if its tokenizer drops
+    /// the word (e.g., a stopword).
+    fn analyze(&self, field: Field, word: &str) -> anyhow::Result<Option<Term>> {
+        let mut analyzer = self.index.tokenizer_for_field(field)?;
+        let mut stream = analyzer.token_stream(word);
+        Ok(stream
+            .advance()
+            .then(|| Term::from_field_text(field, &stream.token().text)))
+    }
+
+    /// Suggest a corrected query for a search that returned no results.
+    ///
+    /// Runs a fuzzy query internally and, if it would have matched, replaces
+    /// each query word with the closest indexed term. Returns `None` when the
+    /// fuzzy query finds nothing or every word is already an indexed term.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fuzzy search or term dictionary lookup fails.
+    pub fn suggest(
+        &self,
+        query: &str,
+        corpus: &Corpus,
+        options: &SearchOptions,
+    ) -> anyhow::Result<Option<String>> {
+        let fuzzy_options = SearchOptions {
+            limit: Some(1),
+            fuzzy: Some(SUGGEST_DISTANCE),
+            ..options.clone()
+        };
+        if self.search(query, corpus, &fuzzy_options)?.is_empty() {
+            return Ok(None);
+        }
+
+        let searcher = self.reader.searcher();
+        let mut changed = false;
+        let mut words = Vec::new();
+        for word in query.split_whitespace() {
+            if QueryField::parse_token(word).is_some() {
+                words.push(word.to_string());
+                continue;
+            }
+            let lowered = word.to_lowercase();
+            match self.closest_term(&searcher, &lowered)? {
+                Some(term) if term != lowered => {
+                    changed = true;
+                    words.push(term);
+                }
+                _ => words.push(word.to_string()),
+            }
+        }
+
+        Ok(changed.then(|| words.join(" ")))
+    }
+
+    /// Title and body fields searched by queries.
+    fn text_fields(&self) -> Vec<Field> {
+        let mut fields = vec![self.fields.title, self.fields.content];
+        fields.extend(self.fields.code);
+        fields
+    }
+
+    /// Find the indexed word closest to `word`, as written rather than
+    /// stemmed where the index keeps unstemmed words.
+    ///
+    /// Ties on edit distance are broken by document frequency, so common
+    /// terms are preferred over rare ones.
+    fn closest_term(&self, searcher: &Searcher, word: &str) -> anyhow::Result<Option<String>> {
+        let mut best: Option<(usize, u32, String)> = None;
+        let fields = match self.fields.spelling {
+            Some(spelling) => std::iter::once(spelling).chain(self.fields.code).collect(),
+            None => self.text_fields(),
+        };
+
+        for field in fields {
+            for segment in searcher.segment_readers() {
+                let inverted_index = segment.inverted_index(field)?;
+                let mut stream = inverted_index.terms().stream()?;
+                while stream.advance() {
+                    let Ok(term) = std::str::from_utf8(stream.key()) else {
+                        continue;
+                    };
+                    let distance = edit_distance(word, term);
+                    if distance > usize::from(SUGGEST_DISTANCE) {
+                        continue;
+                    }
+                    let doc_freq = stream.value().doc_freq;
+                    let is_better = best.as_ref().is_none_or(|(best_distance, best_freq, _)| {
+                        distance < *best_distance
+                            || (distance == *best_distance && doc_freq > *best_freq)
+                    });
+                    if is_better {
+                        best = Some((distance, doc_freq, term.to_string()));
+                    }
+                }
+            }
+        }
+
+        Ok(best.map(|(_, _, term)| term))
+    }
+
+    /// Look up how often a term occurs across the index.
+    ///
+    /// The term is lowercased and stemmed as each field indexes words, so
+    /// `running` counts occurrences of `run`; a stopword is never found.
+    /// Deleted documents that haven't been merged away yet are not counted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `term` is empty or more than one word, or if the
+    /// index cannot be read.
+    pub fn term_stats(&self, term: &str) -> anyhow::Result<TermStats> {
+        let word = term.trim().to_lowercase();
+        if word.is_empty() || word.contains(char::is_whitespace) {
+            anyhow::bail!("Expected a single term, got '{term}'");
+        }
+
+        let mut terms = Vec::new();
+        for field in self.text_fields() {
+            terms.extend(self.analyze(field, &word)?);
+        }
+
+        let searcher = self.reader.searcher();
+        let mut documents = HashSet::new();
+        let mut term_freq = 0;
+
+        for (segment_ord, segment) in searcher.segment_readers().iter().enumerate() {
+            let alive = segment.alive_bitset();
+            for term in &terms {
+                let inverted_index = segment.inverted_index(term.field())?;
+                let Some(mut postings) =
+                    inverted_index.read_postings(term, IndexRecordOption::WithFreqs)?
+                else {
+                    continue;
+                };
+
+                let mut doc = postings.doc();
+                while doc != TERMINATED {
+                    if alive.is_none_or(|bitset| bitset.is_alive(doc)) {
+                        documents.insert((segment_ord, doc));
+                        term_freq += u64::from(postings.term_freq());
+                    }
+                    doc = postings.advance();
+                }
+            }
+        }
+
+        // Report the form titles and prose index, which the code field may not share
+        let term = terms
+            .first()
+            .and_then(|term| term.value().as_str().map(str::to_string))
+            .unwrap_or(word);
+        Ok(TermStats {
+            term,
+            doc_freq: documents.len() as u64,
+            term_freq,
+            num_docs: searcher.num_docs(),
+        })
+    }
+
+    /// Build a search query from the user's parsed query.
     ///
     /// If `fuzzy_distance` is set, uses fuzzy term matching for typo tolerance.
+    /// Either way, words matched in the title score [`TITLE_BOOST`] times
+    /// higher than in the body. Field filters (`title:`, `category:`,
+    /// `tags:`) must all match; they are never fuzzy.
     fn build_query(
         &self,
-        query_str: &str,
+        query: &FieldQuery,
         fuzzy_distance: Option<u8>,
         category_filter: Option<&str>,
+        category_match: CategoryMatch,
     ) -> anyhow::Result<Box<dyn tantivy::query::Query>> {
-        let content_query: Box<dyn tantivy::query::Query> = if let Some(distance) = fuzzy_distance {
-            self.build_fuzzy_query(query_str, distance)
-        } else {
-            let query_parser =
-                QueryParser::for_index(&self.index, vec![self.fields.title, self.fields.content]);
-            query_parser.parse_query(query_str)?
-        };
+        let mut clauses: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::new();
+
+        if !query.text.trim().is_empty() {
+            let content_query = if let Some(distance) = fuzzy_distance {
+                self.build_fuzzy_query(&query.text, distance)?
+            } else {
+                let mut query_parser = QueryParser::for_index(&self.index, self.text_fields());
+                query_parser.set_field_boost(self.fields.title, TITLE_BOOST);
+                query_parser.parse_query(&query.text)?
+            };
+            clauses.push((Occur::Must, content_query));
+        }
 
-        // Add category filter if specified
+        for title in &query.title {
+            clauses.push((Occur::Must, self.field_query(self.fields.title, title)?));
+        }
+        if !query.tags.is_empty()
+            && !self
+                .index
+                .schema()
+                .get_field_entry(self.fields.tags)
+                .is_indexed()
+        {
+            anyhow::bail!("This index predates tag search; run `kvault index` to rebuild it");
+        }
+        for tag in &query.tags {
+            clauses.push((Occur::Must, self.field_query(self.fields.tags, tag)?));
+        }
+        for category in &query.category {
+            clauses.push((
+                Occur::Must,
+                self.category_query(category, CategoryMatch::Exact),
+            ));
+        }
         if let Some(category) = category_filter {
-            let category_term = Term::from_field_text(self.fields.category, category);
-            let category_query =
-                TermQuery::new(category_term, tantivy::schema::IndexRecordOption::Basic);
+            clauses.push((Occur::Must, self.category_query(category, category_match)));
+        }
 
-            let combined = BooleanQuery::new(vec![
-                (Occur::Must, content_query),
-                (Occur::Must, Box::new(category_query)),
-            ]);
+        // A lone clause is searched as is, keeping explanations simple
+        if clauses.len() == 1 {
+            return Ok(clauses.remove(0).1);
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
 
-            Ok(Box::new(combined))
+    /// Match `value` (a word, or a phrase if it has spaces) in one field.
+    fn field_query(
+        &self,
+        field: Field,
+        value: &str,
+    ) -> anyhow::Result<Box<dyn tantivy::query::Query>> {
+        let value = if value.contains(char::is_whitespace) {
+            format!("\"{value}\"")
         } else {
-            Ok(content_query)
+            value.to_string()
+        };
+        Ok(QueryParser::for_index(&self.index, vec![field]).parse_query(&value)?)
+    }
+
+    /// Match documents whose category passes `filter`.
+    ///
+    /// A prefix match is the exact category plus the term range
+    /// `filter/` up to (excluding) `filter0`, `0` being the character after
+    /// `/`, which covers every nested category.
+    fn category_query(
+        &self,
+        filter: &str,
+        category_match: CategoryMatch,
+    ) -> Box<dyn tantivy::query::Query> {
+        let (field, filter) = match self.fields.category_key {
+            Some(category_key) => (category_key, filter.to_lowercase()),
+            None => (self.fields.category, filter.to_string()),
+        };
+        let term = |text: &str| Term::from_field_text(field, text);
+
+        let filter = match category_match {
+            CategoryMatch::Exact => &filter,
+            CategoryMatch::Prefix => filter.trim_end_matches('/'),
+        };
+        let exact = Box::new(TermQuery::new(term(filter), IndexRecordOption::Basic));
+        match category_match {
+            CategoryMatch::Exact => exact,
+            CategoryMatch::Prefix => {
+                let nested = RangeQuery::new(
+                    Bound::Included(term(&format!("{filter}/"))),
+                    Bound::Excluded(term(&format!("{filter}0"))),
+                );
+                Box::new(BooleanQuery::new(vec![
+                    (Occur::Should, exact),
+                    (Occur::Should, Box::new(nested)),
+                ]))
+            }
         }
     }
 
+    /// Create an empty index held in memory, for searching a handful of
+    /// documents without touching disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `language` is not supported or the index reader
+    /// cannot be created.
+    pub fn in_memory(language: &str) -> anyhow::Result<Self> {
+        let (schema, fields) = Self::build_schema(language)?;
+        let index = Index::create_in_ram(schema);
+        register_tokenizers(&index)?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+
+        Ok(Self {
+            index,
+            reader,
+            fields,
+            mode: IndexMode::ReadWrite,
+            index_path: PathBuf::new(),
+        })
+    }
+
     /// Index all documents from a corpus.
     ///
     /// This clears the existing index and rebuilds it from scratch.
@@ -232,112 +802,833 @@ impl TantivyBackend {
     ///
     /// Returns an error if indexing fails or if in read-only mode.
     pub fn index_corpus(&self, corpus: &Corpus) -> anyhow::Result<()> {
+        let documents: Vec<&Document> = corpus.documents().iter().collect();
+        self.index_documents(corpus, &documents)
+    }
+
+    /// Replace the index contents with the given documents from a corpus.
+    ///
+    /// Documents that cannot be read are skipped with a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if indexing fails or if in read-only mode.
+    pub fn index_documents(&self, corpus: &Corpus, documents: &[&Document]) -> anyhow::Result<()> {
         if self.mode == IndexMode::ReadOnly {
             anyhow::bail!("Cannot index in read-only mode");
         }
 
+        let storage = LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest)?;
         let mut writer: IndexWriter = self.index.writer(WRITER_HEAP_SIZE)?;
 
         // Clear existing documents
         writer.delete_all_documents()?;
 
-        // Index each document
-        for doc in corpus.documents() {
-            let full_path = corpus.resolve_document_path(doc);
+        for (doc, content) in read_documents(corpus, &storage, documents) {
+            writer.add_document(self.to_tantivy_document(corpus, doc, &content))?;
+        }
 
-            // Read document content
-            let content = match std::fs::read_to_string(&full_path) {
-                Ok(c) => c,
-                Err(e) => {
-                    eprintln!("Warning: Could not read {}: {e}", full_path.display());
-                    continue;
-                }
-            };
+        writer.commit()?;
+        self.reader.reload()?;
+
+        Ok(())
+    }
+
+    /// Add documents to the index, replacing any indexed copies, without
+    /// touching the rest of the index.
+    ///
+    /// Documents that cannot be read are skipped with a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if indexing fails or if in read-only mode.
+    pub fn update_documents(&self, corpus: &Corpus, documents: &[&Document]) -> anyhow::Result<()> {
+        if self.mode == IndexMode::ReadOnly {
+            anyhow::bail!("Cannot index in read-only mode");
+        }
 
-            // Create Tantivy document
-            let mut tantivy_doc = tantivy::TantivyDocument::new();
-            tantivy_doc.add_text(self.fields.title, &doc.title);
-            tantivy_doc.add_text(self.fields.content, &content);
-            tantivy_doc.add_text(self.fields.category, &doc.category);
-            tantivy_doc.add_text(self.fields.tags, doc.tags.join(" "));
-            tantivy_doc.add_text(self.fields.path, doc.path.to_string_lossy());
+        let storage = LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest)?;
+        let mut writer: IndexWriter = self.index.writer(WRITER_HEAP_SIZE)?;
 
-            writer.add_document(tantivy_doc)?;
+        for (doc, content) in read_documents(corpus, &storage, documents) {
+            writer.delete_term(Term::from_field_text(
+                self.fields.path,
+                &doc.path.to_string_lossy(),
+            ));
+            writer.add_document(self.to_tantivy_document(corpus, doc, &content))?;
         }
 
         writer.commit()?;
+        self.reader.reload()?;
 
         Ok(())
     }
-    /// Convert a Tantivy document to a `SearchResult`.
+
+    /// Index a corpus in committed batches, recording progress so an
+    /// interrupted run can be resumed.
     ///
-    /// Note: `matched_line` currently uses the title as a placeholder.
-    /// TODO: Extract actual content snippet for better search result display.
-    fn doc_to_search_result(
+    /// After each batch of [`COMMIT_BATCH_SIZE`] documents is committed, a
+    /// progress marker is written to the index directory; it is removed once
+    /// every document is indexed. With [`Rebuild::Resume`], documents before
+    /// the marker are skipped. A marker that no longer matches the manifest
+    /// (documents were added or removed) triggers a full rebuild instead.
+    /// A complete run also stores the corpus fingerprint checked by
+    /// [`Self::is_up_to_date`].
+    ///
+    /// # Arguments
+    ///
+    /// * `corpus` - Corpus to index
+    /// * `rebuild` - Start from scratch or continue from the marker
+    /// * `max_documents` - Stop after indexing this many documents, leaving
+    ///   the marker in place as if interrupted
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if indexing fails, the marker cannot be written, or
+    /// the index is read-only.
+    pub fn index_corpus_resumable(
         &self,
-        doc: &tantivy::TantivyDocument,
-        score: f32,
         corpus: &Corpus,
-    ) -> SearchResult {
-        let title = doc
-            .get_first(self.fields.title)
-            .and_then(|v| v.as_str())
-            .unwrap_or("Unknown")
-            .to_string();
+        rebuild: Rebuild,
+        max_documents: Option<usize>,
+    ) -> anyhow::Result<IndexProgress> {
+        if self.mode == IndexMode::ReadOnly {
+            anyhow::bail!("Cannot index in read-only mode");
+        }
 
-        let path_str = doc
-            .get_first(self.fields.path)
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
+        let documents = corpus.documents();
+        let marker_path = self.index_path.join(PROGRESS_FILE);
+        let fingerprint_path = self.index_path.join(FINGERPRINT_FILE);
+        // Until this run completes, the index matches no corpus state
+        remove_if_exists(&fingerprint_path)?;
+        let skipped = match rebuild {
+            Rebuild::Resume => ProgressMarker::load(&marker_path)
+                .filter(|marker| marker.matches(documents))
+                .map_or(0, |marker| marker.completed),
+            Rebuild::Full => 0,
+        };
 
-        SearchResult {
-            path: corpus.root.join(path_str),
-            matched_line: title.clone(),
-            title,
-            line_number: 1,
-            score: Some(score),
+        let storage = LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest)?;
+        let mut writer: IndexWriter = self.index.writer(WRITER_HEAP_SIZE)?;
+        if skipped == 0 {
+            writer.delete_all_documents()?;
+        }
+
+        let remaining = &documents[skipped..];
+        let to_index = max_documents.map_or(remaining.len(), |max| max.min(remaining.len()));
+        let mut completed = skipped;
+
+        for batch in remaining[..to_index].chunks(COMMIT_BATCH_SIZE) {
+            let batch: Vec<&Document> = batch.iter().collect();
+            for (doc, content) in read_documents(corpus, &storage, &batch) {
+                // A crash between commit and marker write re-runs the batch
+                writer.delete_term(Term::from_field_text(
+                    self.fields.path,
+                    &doc.path.to_string_lossy(),
+                ));
+                writer.add_document(self.to_tantivy_document(corpus, doc, &content))?;
+            }
+            writer.commit()?;
+
+            completed += batch.len();
+            ProgressMarker::new(documents, completed).save(&marker_path)?;
+        }
+        if to_index == 0 {
+            // Nothing left to add, but a full rebuild still clears the index
+            writer.commit()?;
         }
+
+        let complete = completed == documents.len();
+        if complete {
+            remove_if_exists(&marker_path)?;
+            if let Some(fingerprint) = corpus_fingerprint(corpus) {
+                std::fs::write(&fingerprint_path, fingerprint)?;
+            }
+        }
+        self.reader.reload()?;
+
+        Ok(IndexProgress {
+            indexed: completed - skipped,
+            skipped,
+            complete,
+        })
     }
-}
 
-impl SearchBackend for TantivyBackend {
-    fn search(
-        &self,
-        query: &str,
-        corpus: &Corpus,
-        options: &SearchOptions,
-    ) -> anyhow::Result<Vec<SearchResult>> {
-        if query.trim().is_empty() {
-            return Ok(vec![]);
+    /// Bring the index up to date with the corpus, re-indexing only what
+    /// changed since it was built.
+    ///
+    /// Each document's modification time and manifest metadata are compared
+    /// with those stored when it was indexed; new and modified documents are
+    /// (re)indexed, and indexed documents no longer in the manifest are
+    /// deleted. Indexes built before both were stored are rebuilt in full.
+    /// Like a complete [`Self::index_corpus_resumable`] run, this clears any
+    /// progress marker and stores the corpus fingerprint.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if indexing fails, the index cannot be read, or the
+    /// index is read-only.
+    pub fn index_incremental(&self, corpus: &Corpus) -> anyhow::Result<IncrementalReport> {
+        if self.mode == IndexMode::ReadOnly {
+            anyhow::bail!("Cannot index in read-only mode");
         }
+        let (Some(mtime_field), Some(metadata_field)) =
+            (self.fields.mtime, self.fields.metadata_hash)
+        else {
+            let progress = self.index_corpus_resumable(corpus, Rebuild::Full, None)?;
+            return Ok(IncrementalReport {
+                indexed: progress.indexed,
+                removed: 0,
+                unchanged: 0,
+            });
+        };
 
         let searcher = self.reader.searcher();
-        let limit = options.limit.unwrap_or(10);
-        let tantivy_query = self.build_query(query, options.fuzzy, options.category.as_deref())?;
-        let top_docs = searcher.search(&tantivy_query, &TopDocs::with_limit(limit))?;
+        let mut indexed: HashMap<String, (Option<u64>, Option<u64>)> = HashMap::new();
+        for address in searcher.search(&AllQuery, &DocSetCollector)? {
+            let doc: tantivy::TantivyDocument = searcher.doc(address)?;
+            if let Some(path) = doc.get_first(self.fields.path).and_then(|v| v.as_str()) {
+                let mtime = doc.get_first(mtime_field).and_then(|v| v.as_u64());
+                let metadata = doc.get_first(metadata_field).and_then(|v| v.as_u64());
+                indexed.insert(path.to_string(), (mtime, metadata));
+            }
+        }
 
-        let mut results = Vec::with_capacity(top_docs.len());
-        for (score, doc_address) in top_docs {
-            let doc: tantivy::TantivyDocument = searcher.doc(doc_address)?;
-            results.push(self.doc_to_search_result(&doc, score, corpus));
+        let mut changed = Vec::new();
+        let mut unchanged = 0;
+        for doc in corpus.documents() {
+            let path = doc.path.to_string_lossy();
+            let current = (
+                Some(modification_time(&corpus.resolve_document_path(doc))),
+                Some(metadata_hash(doc)),
+            );
+            if indexed.remove(path.as_ref()) == Some(current) {
+                unchanged += 1;
+            } else {
+                changed.push(doc);
+            }
         }
+        // Whatever the manifest didn't claim has been removed from the corpus
+        let removed = indexed.into_keys().collect::<Vec<_>>();
 
-        Ok(results)
+        let storage = LocalStorageBackend::for_manifest(corpus.root.clone(), &corpus.manifest)?;
+        let mut writer: IndexWriter = self.index.writer(WRITER_HEAP_SIZE)?;
+        for path in &removed {
+            writer.delete_term(Term::from_field_text(self.fields.path, path));
+        }
+        for (doc, content) in read_documents(corpus, &storage, &changed) {
+            writer.delete_term(Term::from_field_text(
+                self.fields.path,
+                &doc.path.to_string_lossy(),
+            ));
+            writer.add_document(self.to_tantivy_document(corpus, doc, &content))?;
+        }
+        writer.commit()?;
+
+        remove_if_exists(&self.index_path.join(PROGRESS_FILE))?;
+        if let Some(fingerprint) = corpus_fingerprint(corpus) {
+            std::fs::write(self.index_path.join(FINGERPRINT_FILE), fingerprint)?;
+        }
+        self.reader.reload()?;
+
+        Ok(IncrementalReport {
+            indexed: changed.len(),
+            removed: removed.len(),
+            unchanged,
+        })
     }
 
-    fn index(&self, corpus: &Corpus) -> anyhow::Result<()> {
-        self.index_corpus(corpus)
+    /// Whether the last complete indexing run saw the corpus exactly as it is
+    /// now: the same manifest and the same size and modification time for
+    /// every document.
+    ///
+    /// Indexes built by another kvault version are never up to date, in case
+    /// the schema changed.
+    #[must_use]
+    pub fn is_up_to_date(&self, corpus: &Corpus) -> bool {
+        let stored = std::fs::read_to_string(self.index_path.join(FINGERPRINT_FILE));
+        stored.is_ok_and(|stored| Some(stored) == corpus_fingerprint(corpus))
     }
 
-    fn needs_indexing(&self) -> bool {
-        true
+    /// Build the Tantivy document for a corpus document's metadata and body.
+    fn to_tantivy_document(
+        &self,
+        corpus: &Corpus,
+        doc: &Document,
+        content: &str,
+    ) -> tantivy::TantivyDocument {
+        let mut tantivy_doc = tantivy::TantivyDocument::new();
+        tantivy_doc.add_text(self.fields.title, &doc.title);
+        let body = match self.fields.code {
+            Some(code) if is_code_content_type(doc.resolved_content_type()) => code,
+            _ => self.fields.content,
+        };
+        tantivy_doc.add_text(body, content);
+        if let Some(spelling) = self.fields.spelling {
+            tantivy_doc.add_text(spelling, &doc.title);
+            if body == self.fields.content {
+                tantivy_doc.add_text(spelling, content);
+            }
+        }
+        tantivy_doc.add_text(self.fields.category, &doc.category);
+        if let Some(category_key) = self.fields.category_key {
+            tantivy_doc.add_text(category_key, doc.category.to_lowercase());
+        }
+        tantivy_doc.add_text(self.fields.tags, doc.tags.join(" "));
+        tantivy_doc.add_text(self.fields.path, doc.path.to_string_lossy());
+        if let Some(mtime) = self.fields.mtime {
+            let modified = modification_time(&corpus.resolve_document_path(doc));
+            tantivy_doc.add_u64(mtime, modified);
+        }
+        if let Some(metadata) = self.fields.metadata_hash {
+            tantivy_doc.add_u64(metadata, metadata_hash(doc));
+        }
+        tantivy_doc
     }
-}
+
+    /// Number of searchable segments in the committed index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index metadata cannot be read.
+    pub fn segment_count(&self) -> anyhow::Result<usize> {
+        Ok(self.index.searchable_segment_ids()?.len())
+    }
+
+    /// Merge all searchable segments into one and remove stale files.
+    ///
+    /// Incremental updates accumulate segments over time, which slows search.
+    /// Compaction is a no-op when the index already has fewer than two segments.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if in read-only mode or if merging fails.
+    pub fn compact(&self) -> anyhow::Result<CompactionReport> {
+        if self.mode == IndexMode::ReadOnly {
+            anyhow::bail!("Cannot compact in read-only mode");
+        }
+
+        let segment_ids = self.index.searchable_segment_ids()?;
+        let segments_before = segment_ids.len();
+
+        if segments_before >= 2 {
+            let mut writer: IndexWriter = self.index.writer(WRITER_HEAP_SIZE)?;
+            writer.merge(&segment_ids).wait()?;
+            writer.garbage_collect_files().wait()?;
+            writer.wait_merging_threads()?;
+        }
+
+        Ok(CompactionReport {
+            segments_before,
+            segments_after: self.segment_count()?,
+        })
+    }
+
+    /// Convert a Tantivy document to a `SearchResult`.
+    ///
+    /// The result has no snippet until [`Snippets::fill`] adds one from the
+    /// body text.
+    fn doc_to_search_result(
+        &self,
+        doc: &tantivy::TantivyDocument,
+        score: f32,
+        corpus: &Corpus,
+    ) -> SearchResult {
+        let title = doc
+            .get_first(self.fields.title)
+            .and_then(|v| v.as_str())
+            .unwrap_or("Unknown")
+            .to_string();
+
+        let path_str = doc
+            .get_first(self.fields.path)
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+
+        let category = doc
+            .get_first(self.fields.category)
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        let tags = doc
+            .get_first(self.fields.tags)
+            .and_then(|v| v.as_str())
+            .map(|t| t.split_whitespace().map(ToString::to_string).collect())
+            .unwrap_or_default();
+
+        SearchResult {
+            path: corpus.root.join(path_str),
+            title,
+            category,
+            tags,
+            snippet: None,
+            score: Some(score),
+            hit_count: None,
+            explanation: None,
+        }
+    }
+}
+
+/// Query terms and snippet generation for one body field.
+struct FieldSnippets {
+    generator: SnippetGenerator,
+    terms: HashSet<String>,
+    tokenizer: TextAnalyzer,
+}
+
+impl FieldSnippets {
+    fn new(
+        searcher: &Searcher,
+        query: &dyn tantivy::query::Query,
+        field: Field,
+    ) -> anyhow::Result<Self> {
+        let mut generator = SnippetGenerator::create(searcher, query, field)?;
+        generator.set_max_num_chars(SNIPPET_CHARS);
+
+        let mut terms = HashSet::new();
+        query.query_terms(&mut |term, _| {
+            if term.field() == field
+                && let Some(text) = term.value().as_str()
+            {
+                terms.insert(text.to_string());
+            }
+        });
+
+        Ok(Self {
+            generator,
+            terms,
+            tokenizer: searcher.index().tokenizer_for_field(field)?,
+        })
+    }
+
+    /// The highest-scoring fragment, with newlines shown as spaces.
+    fn best(&self, text: &str) -> Option<Snippet> {
+        let snippet = self.generator.snippet(text);
+        if snippet.is_empty() {
+            return None;
+        }
+
+        let fragment = snippet.fragment();
+        let offset = text.find(fragment).unwrap_or_default();
+        Some(Snippet {
+            text: fragment.replace(['\n', '\r'], " "),
+            highlights: snippet.highlighted().to_vec(),
+            line: Some(text[..offset].matches('\n').count() + 1),
+        })
+    }
+
+    /// The first line containing a query term, with every term on it
+    /// highlighted.
+    fn first(&self, text: &str) -> Option<Snippet> {
+        let mut matches = Vec::new();
+        let mut tokenizer = self.tokenizer.clone();
+        let mut stream = tokenizer.token_stream(text);
+        while stream.advance() {
+            let token = stream.token();
+            if self.terms.contains(&token.text) {
+                matches.push(token.offset_from..token.offset_to);
+            }
+        }
+
+        let first = matches.first()?.start;
+        let start = text[..first].rfind('\n').map_or(0, |i| i + 1);
+        let end = text[first..].find('\n').map_or(text.len(), |i| first + i);
+        let raw = &text[start..end];
+        let leading = raw.len() - raw.trim_start().len();
+        let line = raw.trim();
+
+        let ranges = matches
+            .into_iter()
+            .filter(|range| range.start < end)
+            .map(|range| {
+                let shift = start + leading;
+                (range.start - shift).min(line.len())..(range.end - shift).min(line.len())
+            })
+            .filter(|range| range.start < range.end)
+            .collect();
+
+        Some(Snippet {
+            text: line.to_string(),
+            highlights: ranges,
+            line: Some(text[..start].matches('\n').count() + 1),
+        })
+    }
+}
+
+/// Chooses the body snippet for each result of one search.
+struct Snippets {
+    strategy: SnippetStrategy,
+    content: FieldSnippets,
+    code: Option<FieldSnippets>,
+}
+
+impl Snippets {
+    fn new(
+        backend: &TantivyBackend,
+        searcher: &Searcher,
+        query: &dyn tantivy::query::Query,
+        strategy: SnippetStrategy,
+    ) -> anyhow::Result<Self> {
+        let code = backend
+            .fields
+            .code
+            .map(|field| FieldSnippets::new(searcher, query, field))
+            .transpose()?;
+        Ok(Self {
+            strategy,
+            content: FieldSnippets::new(searcher, query, backend.fields.content)?,
+            code,
+        })
+    }
+
+    /// Give `result` a snippet of its document.
+    ///
+    /// Encrypted and unreadable documents are left without one, as are
+    /// title-only matches unless `SnippetStrategy::Prefix` is chosen.
+    fn fill(&self, result: &mut SearchResult, corpus: &Corpus) {
+        let relative = result
+            .path
+            .strip_prefix(&corpus.root)
+            .unwrap_or(&result.path);
+        let Some(doc) = corpus.find_document(relative) else {
+            return;
+        };
+        if doc.encrypted {
+            return;
+        }
+        let Ok(text) = std::fs::read_to_string(&result.path) else {
+            return;
+        };
+
+        let field = match &self.code {
+            Some(code) if is_code_content_type(doc.resolved_content_type()) => code,
+            _ => &self.content,
+        };
+        let excerpt = match self.strategy {
+            SnippetStrategy::Best => field.best(&text),
+            SnippetStrategy::First => field.first(&text),
+            SnippetStrategy::Prefix => prefix_excerpt(&text),
+        };
+
+        if excerpt.is_some() {
+            result.snippet = excerpt;
+        }
+    }
+}
+
+/// The first [`SNIPPET_CHARS`] characters of `text`, whitespace collapsed.
+fn prefix_excerpt(text: &str) -> Option<Snippet> {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let line: String = collapsed.chars().take(SNIPPET_CHARS).collect();
+    (!line.is_empty()).then_some(Snippet {
+        text: line,
+        highlights: Vec::new(),
+        line: Some(1),
+    })
+}
+
+/// Render a Tantivy score explanation as an indented tree, one
+/// `value description` node per line.
+fn render_explanation(explanation: &Explanation) -> String {
+    fn render(node: &serde_json::Value, depth: usize, output: &mut String) {
+        let value = node["value"].as_f64().unwrap_or_default();
+        let description = node["description"].as_str().unwrap_or_default();
+        let indent = "  ".repeat(depth);
+        let _ = writeln!(output, "{indent}{value:.4} {description}");
+
+        for context in node["context"].as_array().into_iter().flatten() {
+            let _ = writeln!(
+                output,
+                "{indent}  ({})",
+                context.as_str().unwrap_or_default()
+            );
+        }
+        for detail in node["details"].as_array().into_iter().flatten() {
+            render(detail, depth + 1, output);
+        }
+    }
+
+    let mut output = String::new();
+    if let Ok(tree) = serde_json::to_value(explanation) {
+        render(&tree, 0, &mut output);
+    }
+    output.trim_end().to_string()
+}
+
+/// Levenshtein distance between two strings, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b_chars.len() + 1];
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b_chars.len()]
+}
+
+/// Read documents in parallel (decrypting if needed), skipping unreadable
+/// ones with a warning. Reads are I/O-bound, while writers stay
+/// single-threaded.
+fn read_documents<'a>(
+    corpus: &Corpus,
+    storage: &LocalStorageBackend,
+    documents: &[&'a Document],
+) -> Vec<(&'a Document, String)> {
+    documents
+        .par_iter()
+        .filter_map(|&doc| match storage.read_document(&doc.path) {
+            Ok(content) => Some((doc, content)),
+            Err(e) => {
+                let full_path = corpus.resolve_document_path(doc);
+                eprintln!("Warning: Could not read {}: {e}", full_path.display());
+                None
+            }
+        })
+        .collect()
+}
+
+/// Create an empty index at `index_path` with stemming for `language`.
+fn create_index(index_path: &Path, language: &str) -> anyhow::Result<Index> {
+    let (schema, _) = TantivyBackend::build_schema(language)?;
+    std::fs::create_dir_all(index_path)?;
+    let directory = MmapDirectory::open(index_path)?;
+    Ok(Index::create(directory, schema, IndexSettings::default())?)
+}
+
+/// Stemming rules for a language code from [`LANGUAGES`].
+fn stemmer_language(code: &str) -> anyhow::Result<Language> {
+    LANGUAGES
+        .iter()
+        .find(|(known, _)| *known == code)
+        .map(|&(_, language)| language)
+        .ok_or_else(|| {
+            let known: Vec<&str> = LANGUAGES.iter().map(|(code, _)| *code).collect();
+            anyhow::anyhow!(
+                "Unsupported index language '{code}' (expected one of: {})",
+                known.join(", ")
+            )
+        })
+}
+
+/// Language code of the stemming tokenizer on the schema's `title` field,
+/// or `None` if the index predates stemming.
+fn index_language(schema: &Schema) -> Option<String> {
+    let title = schema.get_field("title").ok()?;
+    let FieldType::Str(options) = schema.get_field_entry(title).field_type() else {
+        return None;
+    };
+    let tokenizer = options.get_indexing_options()?.tokenizer();
+    tokenizer
+        .strip_prefix(STEM_TOKENIZER_PREFIX)
+        .map(str::to_string)
+}
+
+/// Register the custom tokenizers the schema refers to. Tantivy doesn't
+/// persist them, so every opened index needs this.
+fn register_tokenizers(index: &Index) -> anyhow::Result<()> {
+    // Words are runs of letters, digits, and underscores, so `snake_case`
+    // identifiers aren't split the way the default tokenizer splits them
+    let code = TextAnalyzer::builder(RegexTokenizer::new(r"[\p{L}\p{N}_]+")?)
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .build();
+    index.tokenizers().register(CODE_TOKENIZER, code);
+    let spelling = TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .build();
+    index.tokenizers().register(SPELLING_TOKENIZER, spelling);
+
+    if let Some(code) = index_language(&index.schema()) {
+        let language = stemmer_language(&code)?;
+        let mut prose = TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser)
+            .dynamic();
+        if let Some(stopwords) = StopWordFilter::new(language) {
+            prose = prose.filter_dynamic(stopwords);
+        }
+        let prose = prose.filter_dynamic(Stemmer::new(language)).build();
+        index
+            .tokenizers()
+            .register(&format!("{STEM_TOKENIZER_PREFIX}{code}"), prose);
+    }
+    Ok(())
+}
+
+impl SearchBackend for TantivyBackend {
+    fn search(
+        &self,
+        query: &str,
+        corpus: &Corpus,
+        options: &SearchOptions,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        if query.trim().is_empty() {
+            return Ok(vec![]);
+        }
+
+        let searcher = self.reader.searcher();
+        let limit = options.limit.unwrap_or(10);
+        let tantivy_query = self.build_query(
+            &FieldQuery::parse(query).with_tags(&options.tags),
+            options.fuzzy,
+            options.category.as_deref(),
+            options.category_match,
+        )?;
+        let top_docs = searcher.search(&tantivy_query, &TopDocs::with_limit(limit))?;
+        let snippets = Snippets::new(self, &searcher, &*tantivy_query, options.snippet_strategy)?;
+
+        let mut results = Vec::with_capacity(top_docs.len());
+        for (score, doc_address) in top_docs {
+            let doc: tantivy::TantivyDocument = searcher.doc(doc_address)?;
+            let mut result = self.doc_to_search_result(&doc, score, corpus);
+            snippets.fill(&mut result, corpus);
+            if options.raw_titles {
+                result.title = file_stem_title(&result.path);
+            }
+            if options.explain {
+                let explanation = tantivy_query.explain(&searcher, doc_address)?;
+                result.explanation = Some(render_explanation(&explanation));
+            }
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    fn index(&self, corpus: &Corpus) -> anyhow::Result<()> {
+        self.index_corpus(corpus)
+    }
+
+    fn needs_indexing(&self) -> bool {
+        true
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            supports_fuzzy: true,
+            supports_phrase: true,
+            supports_scoring: true,
+            supports_tag_filter: true,
+            supports_regex: false,
+            supports_within: false,
+            supports_max_per_file: false,
+            supports_group_by_document: false,
+            supports_score_matches: false,
+            supports_include_metadata: false,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "ranked"
+    }
+}
+
+/// Remove a file, treating one that is already gone as removed.
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// 64-bit FNV-1a hasher, which unlike `DefaultHasher` is stable across Rust
+/// releases, so hashes stored in the index stay comparable.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Hash of the manifest metadata a document is indexed with: its title,
+/// category, tags, and content type (which picks the body field).
+fn metadata_hash(doc: &Document) -> u64 {
+    let mut hash = Fnv1a::new();
+    let fields = [doc.title.as_str(), doc.category.as_str()]
+        .into_iter()
+        .chain(doc.tags.iter().map(String::as_str))
+        .chain([doc.resolved_content_type()]);
+    for field in fields {
+        hash.write(field.as_bytes());
+        // Separates fields, so moving text between them changes the hash
+        hash.write(&[0]);
+    }
+    hash.finish()
+}
+
+/// A file's modification time in nanoseconds since the Unix epoch, or 0 if
+/// it cannot be read.
+fn modification_time(path: &Path) -> u64 {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |duration| {
+            u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+        })
+}
+
+/// Hash of the kvault version, the manifest file, and each document's path,
+/// size, and modification time.
+///
+/// Uses [`Fnv1a`], so stored fingerprints stay comparable.
+///
+/// # Returns
+///
+/// The hash in hex, or `None` if the manifest cannot be read.
+fn corpus_fingerprint(corpus: &Corpus) -> Option<String> {
+    let mut hash = Fnv1a::new();
+    let mut write = |bytes: &[u8]| hash.write(bytes);
+
+    let (manifest_path, _) = ManifestFormat::detect(&corpus.root)?;
+    write(env!("CARGO_PKG_VERSION").as_bytes());
+    write(&std::fs::read(manifest_path).ok()?);
+    for doc in corpus.documents() {
+        write(doc.path.as_os_str().as_encoded_bytes());
+        // Missing files hash differently from any existing one
+        let Ok(metadata) = std::fs::metadata(corpus.resolve_document_path(doc)) else {
+            write(&[0]);
+            continue;
+        };
+        write(&[1]);
+        write(&metadata.len().to_le_bytes());
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        write(&modified.as_nanos().to_le_bytes());
+    }
+
+    Some(format!("{:016x}", hash.finish()))
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::corpus::{Document, Manifest};
+    use crate::corpus::Manifest;
     use tempfile::TempDir;
 
     fn create_test_corpus(temp_dir: &TempDir) -> Corpus {
@@ -355,12 +1646,19 @@ mod tests {
         // Create manifest
         let manifest = Manifest {
             version: "1".to_string(),
+            name: None,
             documents: vec![Document {
                 path: PathBuf::from("test/example.md"),
                 title: "Example Document".to_string(),
                 category: "test".to_string(),
                 tags: vec!["lambda".to_string(), "serverless".to_string()],
+                encrypted: false,
+                word_count: None,
+                aliases: vec![],
+                content_type: None,
             }],
+            encryption_salt: None,
+            encryption_check: None,
         };
 
         std::fs::write(
@@ -374,7 +1672,7 @@ mod tests {
 
     #[test]
     fn test_schema_creation() {
-        let (schema, _fields) = TantivyBackend::build_schema();
+        let (schema, _fields) = TantivyBackend::build_schema("en").unwrap();
 
         assert!(schema.get_field("title").is_ok());
         assert!(schema.get_field("content").is_ok());
@@ -422,6 +1720,94 @@ mod tests {
         assert!(results[0].score.is_some());
     }
 
+    #[test]
+    fn test_snippet_highlights_query_terms() {
+        let temp_dir = TempDir::new().unwrap();
+        let corpus = create_test_corpus(&temp_dir);
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+
+        let results = backend
+            .search("lambda", &corpus, &SearchOptions::default())
+            .unwrap();
+        let snippet = results[0].snippet.as_ref().unwrap();
+        // The short document fits in one fragment, from its first line
+        assert_eq!(snippet.line, Some(1));
+        assert!(!snippet.highlights.is_empty());
+        for range in &snippet.highlights {
+            assert_eq!(snippet.text[range.clone()].to_lowercase(), "lambda");
+        }
+    }
+
+    #[test]
+    fn test_snippet_left_unset_without_matching_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        std::fs::write(
+            corpus.root.join("test/quokka.md"),
+            "Nothing relevant in the body.",
+        )
+        .unwrap();
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("test/quokka.md"),
+            title: "Quokka Notes".to_string(),
+            category: "test".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+        let snippet = |query, snippet_strategy| {
+            let options = SearchOptions {
+                snippet_strategy,
+                ..Default::default()
+            };
+            let results = backend.search(query, &corpus, &options).unwrap();
+            assert_eq!(results.len(), 1);
+            results[0].snippet.clone()
+        };
+
+        // A title-only match has no body text to show, except with `Prefix`
+        assert_eq!(snippet("quokka", SnippetStrategy::Best), None);
+        assert_eq!(snippet("quokka", SnippetStrategy::First), None);
+        assert!(snippet("quokka", SnippetStrategy::Prefix).is_some());
+
+        // Nor does a document that can no longer be read
+        std::fs::remove_file(corpus.root.join("test/example.md")).unwrap();
+        assert_eq!(snippet("serverless", SnippetStrategy::Prefix), None);
+    }
+
+    #[test]
+    fn test_explain_reports_score_components() {
+        let temp_dir = TempDir::new().unwrap();
+        let corpus = create_test_corpus(&temp_dir);
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+
+        let options = SearchOptions {
+            explain: true,
+            ..Default::default()
+        };
+        let results = backend.search("lambda", &corpus, &options).unwrap();
+        let explanation = results[0].explanation.as_deref().unwrap();
+
+        assert!(explanation.contains("TermQuery"));
+        assert!(explanation.contains("idf"));
+        assert!(explanation.contains("freq, occurrences of term within document"));
+        assert!(explanation.starts_with(&format!("{:.4}", results[0].score.unwrap())));
+
+        let plain = backend
+            .search("lambda", &corpus, &SearchOptions::default())
+            .unwrap();
+        assert!(plain[0].explanation.is_none());
+    }
+
     #[test]
     fn test_category_filter() {
         let temp_dir = TempDir::new().unwrap();
@@ -449,6 +1835,836 @@ mod tests {
         assert!(results.is_empty());
     }
 
+    #[test]
+    fn test_snippet_strategies() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        let filler = "Unrelated filler text about configuration and deployment. ".repeat(4);
+        let content = format!(
+            "# Crafted\n\nAn introduction without the term.\n  \
+             Cold starts affect lambda latency.\n{filler}\n\
+             Tuning lambda memory changes lambda cold start lambda cost.\n"
+        );
+        std::fs::write(corpus.root.join("test/crafted.md"), &content).unwrap();
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("test/crafted.md"),
+            title: "Crafted".to_string(),
+            category: "test".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+        let snippet = |snippet_strategy| {
+            let options = SearchOptions {
+                snippet_strategy,
+                ..Default::default()
+            };
+            backend
+                .search("lambda", &corpus, &options)
+                .unwrap()
+                .into_iter()
+                .find(|result| result.title == "Crafted")
+                .unwrap()
+                .snippet
+                .unwrap()
+        };
+
+        // The densest fragment, which ends with the last line
+        let best = snippet(SnippetStrategy::Best);
+        assert!(best.text.contains("Tuning lambda memory"));
+        assert!(!best.text.contains("Cold starts affect"));
+        assert!(best.line > Some(4));
+        assert_eq!(best.highlights.len(), 3);
+        assert_eq!(&best.text[best.highlights[0].clone()], "lambda");
+
+        // The first line mentioning the term, trimmed
+        let first = snippet(SnippetStrategy::First);
+        assert_eq!(first.text, "Cold starts affect lambda latency.");
+        assert_eq!(first.line, Some(4));
+        assert_eq!(first.highlights, vec![19..25]);
+
+        // The start of the document
+        let prefix = snippet(SnippetStrategy::Prefix);
+        assert!(
+            prefix
+                .text
+                .starts_with("# Crafted An introduction without the term. Cold starts")
+        );
+        assert_eq!(prefix.text.chars().count(), SNIPPET_CHARS);
+        assert_eq!(prefix.line, Some(1));
+        assert!(prefix.highlights.is_empty());
+    }
+
+    #[test]
+    fn test_field_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        std::fs::create_dir_all(corpus.root.join("aws")).unwrap();
+        std::fs::write(
+            corpus.root.join("aws/pricing.md"),
+            "Lambda pricing depends on memory and duration.",
+        )
+        .unwrap();
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("aws/pricing.md"),
+            title: "Pricing".to_string(),
+            category: "aws".to_string(),
+            tags: vec!["billing".to_string()],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+        let titles = |query: &str| -> Vec<String> {
+            backend
+                .search(query, &corpus, &SearchOptions::default())
+                .unwrap()
+                .into_iter()
+                .map(|result| result.title)
+                .collect()
+        };
+
+        // Both bodies mention lambda
+        assert_eq!(titles("lambda").len(), 2);
+        assert_eq!(titles("title:pricing"), vec!["Pricing"]);
+        assert!(titles("title:lambda").is_empty());
+
+        assert_eq!(titles("category:aws lambda"), vec!["Pricing"]);
+        assert_eq!(titles("category:test lambda"), vec!["Example Document"]);
+        assert_eq!(titles("tags:billing"), vec!["Pricing"]);
+        assert_eq!(titles("content:memory"), vec!["Pricing"]);
+
+        let tagged = |tags: &[&str]| -> Vec<String> {
+            let options = SearchOptions {
+                tags: tags.iter().map(ToString::to_string).collect(),
+                ..SearchOptions::default()
+            };
+            backend
+                .search("lambda", &corpus, &options)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.title)
+                .collect()
+        };
+        assert_eq!(tagged(&["billing"]), vec!["Pricing"]);
+        assert_eq!(tagged(&["Billing"]), vec!["Pricing"]);
+        assert!(tagged(&["billing", "compute"]).is_empty());
+    }
+
+    #[test]
+    fn test_category_prefix_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        for (path, category) in [
+            ("aws/overview.md", "aws"),
+            ("aws/lambda/cold-starts.md", "aws/lambda"),
+            ("aws-legacy/notes.md", "aws-legacy"),
+        ] {
+            let full_path = corpus.root.join(path);
+            std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+            std::fs::write(full_path, "serverless functions").unwrap();
+            corpus.manifest.documents.push(Document {
+                path: PathBuf::from(path),
+                title: path.to_string(),
+                category: category.to_string(),
+                tags: vec![],
+                encrypted: false,
+                word_count: None,
+                aliases: vec![],
+                content_type: None,
+            });
+        }
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+
+        let categories = |category_match| {
+            let options = SearchOptions {
+                category: Some("aws".to_string()),
+                category_match,
+                ..Default::default()
+            };
+            let mut categories: Vec<String> = backend
+                .search("serverless", &corpus, &options)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.category)
+                .collect();
+            categories.sort();
+            categories
+        };
+        assert_eq!(categories(CategoryMatch::Exact), vec!["aws"]);
+        assert_eq!(categories(CategoryMatch::Prefix), vec!["aws", "aws/lambda"]);
+    }
+
+    #[test]
+    fn test_category_filter_ignores_case() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        for (path, category) in [
+            ("AWS/overview.md", "AWS"),
+            ("AWS/Lambda/cold-starts.md", "AWS/Lambda"),
+        ] {
+            let full_path = corpus.root.join(path);
+            std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+            std::fs::write(full_path, "serverless functions").unwrap();
+            corpus.manifest.documents.push(Document {
+                path: PathBuf::from(path),
+                title: path.to_string(),
+                category: category.to_string(),
+                tags: vec![],
+                encrypted: false,
+                word_count: None,
+                aliases: vec![],
+                content_type: None,
+            });
+        }
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+
+        let categories = |query: &str, category: Option<&str>, category_match| {
+            let options = SearchOptions {
+                category: category.map(str::to_string),
+                category_match,
+                ..Default::default()
+            };
+            let mut categories: Vec<String> = backend
+                .search(query, &corpus, &options)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.category)
+                .collect();
+            categories.sort();
+            categories
+        };
+        // Results keep the category as typed
+        assert_eq!(
+            categories("serverless", Some("aws"), CategoryMatch::Exact),
+            vec!["AWS"]
+        );
+        assert_eq!(
+            categories("serverless", Some("aws"), CategoryMatch::Prefix),
+            vec!["AWS", "AWS/Lambda"]
+        );
+        assert_eq!(
+            categories("category:aws/lambda serverless", None, CategoryMatch::Exact),
+            vec!["AWS/Lambda"]
+        );
+    }
+
+    #[test]
+    fn test_compact_merges_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        let corpus = create_test_corpus(&temp_dir);
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+
+        // Each commit produces a new segment
+        let mut writer: IndexWriter = backend.index.writer(WRITER_HEAP_SIZE).unwrap();
+        for i in 0..3 {
+            let mut doc = tantivy::TantivyDocument::new();
+            doc.add_text(backend.fields.title, format!("Doc {i}"));
+            doc.add_text(backend.fields.content, "segment content");
+            doc.add_text(backend.fields.path, format!("test/doc-{i}.md"));
+            writer.add_document(doc).unwrap();
+            writer.commit().unwrap();
+        }
+        drop(writer);
+
+        assert_eq!(backend.segment_count().unwrap(), 3);
+
+        let report = backend.compact().unwrap();
+        assert_eq!(report.segments_before, 3);
+        assert!(report.segments_after < report.segments_before);
+        assert_eq!(report.segments_after, 1);
+    }
+
+    #[test]
+    fn test_compact_requires_read_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let corpus = create_test_corpus(&temp_dir);
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadOnly).unwrap();
+        assert!(backend.compact().is_err());
+    }
+
+    #[test]
+    fn test_index_many_documents_skips_unreadable() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+
+        let doc_dir = corpus.root.join("bulk");
+        std::fs::create_dir_all(&doc_dir).unwrap();
+        for i in 0..200 {
+            std::fs::write(
+                doc_dir.join(format!("doc-{i}.md")),
+                format!("# Bulk {i}\n\nshared bulkterm content"),
+            )
+            .unwrap();
+            corpus.manifest.documents.push(Document {
+                path: PathBuf::from(format!("bulk/doc-{i}.md")),
+                title: format!("Bulk {i}"),
+                category: "bulk".to_string(),
+                tags: vec![],
+                encrypted: false,
+                word_count: None,
+                aliases: vec![],
+                content_type: None,
+            });
+        }
+
+        // In the manifest but missing on disk
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("bulk/missing.md"),
+            title: "Missing".to_string(),
+            category: "bulk".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadOnly).unwrap();
+        assert_eq!(backend.reader.searcher().num_docs(), 201);
+
+        let options = SearchOptions {
+            limit: Some(500),
+            ..Default::default()
+        };
+        let results = backend.search("bulkterm", &corpus, &options).unwrap();
+        assert_eq!(results.len(), 200);
+        assert!(!results.iter().any(|r| r.title == "Missing"));
+    }
+
+    #[test]
+    fn test_code_documents_keep_identifiers_whole() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+
+        std::fs::write(
+            corpus.root.join("test/config.rs"),
+            "fn parse_config(path: &Path) {}",
+        )
+        .unwrap();
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("test/config.rs"),
+            title: "Config Loader".to_string(),
+            category: "test".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: Some("text/x-rust".to_string()),
+        });
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+
+        let options = SearchOptions::default();
+        let results = backend.search("parse_config", &corpus, &options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Config Loader");
+        // Part of an identifier is not a word of its own
+        assert!(
+            backend
+                .search("parse", &corpus, &options)
+                .unwrap()
+                .is_empty()
+        );
+        // Prose documents are still tokenized as before
+        assert_eq!(
+            backend
+                .search("serverless", &corpus, &options)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_term_stats() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        std::fs::write(
+            corpus.root.join("test/other.md"),
+            "Serverless patterns, more serverless patterns.",
+        )
+        .unwrap();
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("test/other.md"),
+            title: "Other".to_string(),
+            category: "test".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+
+        let lambda = backend.term_stats("Lambda").unwrap();
+        assert_eq!(
+            lambda,
+            TermStats {
+                term: "lambda".to_string(),
+                doc_freq: 1,
+                term_freq: 1,
+                num_docs: 2,
+            }
+        );
+
+        let serverless = backend.term_stats("serverless").unwrap();
+        assert_eq!((serverless.doc_freq, serverless.term_freq), (2, 3));
+        assert!(lambda.idf() > serverless.idf());
+
+        assert_eq!(backend.term_stats("absent").unwrap().doc_freq, 0);
+        assert!(backend.term_stats("two words").is_err());
+    }
+
+    /// Add `count` bulk documents after the test corpus's one document.
+    fn add_bulk_documents(corpus: &mut Corpus, count: usize) {
+        let doc_dir = corpus.root.join("bulk");
+        std::fs::create_dir_all(&doc_dir).unwrap();
+        for i in 0..count {
+            std::fs::write(doc_dir.join(format!("doc-{i}.md")), "shared bulkterm").unwrap();
+            corpus.manifest.documents.push(Document {
+                path: PathBuf::from(format!("bulk/doc-{i}.md")),
+                title: format!("Bulk {i}"),
+                category: "bulk".to_string(),
+                tags: vec![],
+                encrypted: false,
+                word_count: None,
+                aliases: vec![],
+                content_type: None,
+            });
+        }
+    }
+
+    #[test]
+    fn test_resume_indexes_only_remaining_documents() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        add_bulk_documents(&mut corpus, 9);
+
+        // Interrupted after 4 of 10 documents
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        let progress = backend
+            .index_corpus_resumable(&corpus, Rebuild::Full, Some(4))
+            .unwrap();
+        assert_eq!(
+            progress,
+            IndexProgress {
+                indexed: 4,
+                skipped: 0,
+                complete: false
+            }
+        );
+        assert_eq!(backend.reader.searcher().num_docs(), 4);
+        let marker_path = backend.index_path().join(PROGRESS_FILE);
+        assert!(marker_path.exists());
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        let progress = backend
+            .index_corpus_resumable(&corpus, Rebuild::Resume, None)
+            .unwrap();
+        assert_eq!(
+            progress,
+            IndexProgress {
+                indexed: 6,
+                skipped: 4,
+                complete: true
+            }
+        );
+        assert_eq!(backend.reader.searcher().num_docs(), 10);
+        assert!(!marker_path.exists());
+
+        let options = SearchOptions {
+            limit: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(
+            backend.search("bulkterm", &corpus, &options).unwrap().len(),
+            9
+        );
+    }
+
+    #[test]
+    fn test_resume_rebuilds_when_manifest_changed() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        add_bulk_documents(&mut corpus, 5);
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend
+            .index_corpus_resumable(&corpus, Rebuild::Full, Some(3))
+            .unwrap();
+
+        // Removing an already indexed document invalidates the marker
+        corpus.manifest.documents.remove(1);
+        let progress = backend
+            .index_corpus_resumable(&corpus, Rebuild::Resume, None)
+            .unwrap();
+        assert_eq!(progress.skipped, 0);
+        assert_eq!(progress.indexed, 5);
+        assert_eq!(backend.reader.searcher().num_docs(), 5);
+    }
+
+    #[test]
+    fn test_incremental_reindexes_only_changed_documents() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        add_bulk_documents(&mut corpus, 3);
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        let report = backend.index_incremental(&corpus).unwrap();
+        assert_eq!(report.indexed, 4);
+        assert_eq!(backend.reader.searcher().num_docs(), 4);
+        assert!(backend.is_up_to_date(&corpus));
+
+        let report = backend.index_incremental(&corpus).unwrap();
+        assert_eq!(
+            report,
+            IncrementalReport {
+                indexed: 0,
+                removed: 0,
+                unchanged: 4
+            }
+        );
+
+        // Edit one document, drop another from the manifest, and add a third
+        let edited = corpus.root.join("bulk/doc-0.md");
+        std::fs::write(&edited, "edited freshterm").unwrap();
+        // Pin the time so the edit registers however coarse the clock is
+        std::fs::File::options()
+            .write(true)
+            .open(&edited)
+            .unwrap()
+            .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_mins(1))
+            .unwrap();
+        corpus
+            .manifest
+            .documents
+            .retain(|doc| doc.path != Path::new("bulk/doc-1.md"));
+        std::fs::write(corpus.root.join("bulk/new.md"), "brand new").unwrap();
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("bulk/new.md"),
+            title: "New".to_string(),
+            category: "bulk".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+
+        let report = backend.index_incremental(&corpus).unwrap();
+        assert_eq!(
+            report,
+            IncrementalReport {
+                indexed: 2,
+                removed: 1,
+                unchanged: 2
+            }
+        );
+        assert_eq!(backend.reader.searcher().num_docs(), 4);
+        let titles: Vec<String> = backend
+            .search("freshterm", &corpus, &SearchOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|result| result.title)
+            .collect();
+        assert_eq!(titles, vec!["Bulk 0"]);
+    }
+
+    #[test]
+    fn test_stemming_matches_word_forms() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        std::fs::write(
+            corpus.root.join("test/jobs.md"),
+            "Each job can run on a schedule.",
+        )
+        .unwrap();
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("test/jobs.md"),
+            title: "Jobs".to_string(),
+            category: "test".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+
+        let backend = TantivyBackend::open_for_indexing(&corpus, "en").unwrap();
+        backend.index_corpus(&corpus).unwrap();
+        assert_eq!(backend.language().as_deref(), Some("en"));
+
+        let titles = |query: &str| -> Vec<String> {
+            backend
+                .search(query, &corpus, &SearchOptions::default())
+                .unwrap()
+                .into_iter()
+                .map(|result| result.title)
+                .collect()
+        };
+        assert_eq!(titles("running"), vec!["Jobs"]);
+        assert_eq!(titles("scheduled jobs"), vec!["Jobs"]);
+        // Stopwords aren't indexed
+        assert!(titles("the").is_empty());
+        assert_eq!(backend.term_stats("running").unwrap().term, "run");
+    }
+
+    #[test]
+    fn test_fuzzy_search_matches_inflected_forms() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        std::fs::write(
+            corpus.root.join("test/batch.md"),
+            "Configure schedules for batch jobs",
+        )
+        .unwrap();
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("test/batch.md"),
+            title: "Batch".to_string(),
+            category: "test".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+
+        let backend = TantivyBackend::open_for_indexing(&corpus, "en").unwrap();
+        backend.index_corpus(&corpus).unwrap();
+
+        let options = SearchOptions {
+            fuzzy: Some(1),
+            ..Default::default()
+        };
+        for query in ["schedules", "Scheduled", "shedules"] {
+            let results = backend.search(query, &corpus, &options).unwrap();
+            let titles: Vec<&str> = results.iter().map(|r| r.title.as_str()).collect();
+            assert_eq!(titles, vec!["Batch"], "query: {query}");
+        }
+    }
+
+    #[test]
+    fn test_index_without_stemming_requires_reindex() {
+        let temp_dir = TempDir::new().unwrap();
+        let corpus = create_test_corpus(&temp_dir);
+        let index_path = corpus.root.join(INDEX_DIR);
+
+        // The schema from before stemming, with default-tokenized text
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("title", TEXT | STORED);
+        schema_builder.add_text_field("content", TEXT);
+        schema_builder.add_text_field("category", STRING | STORED | FAST);
+        schema_builder.add_text_field("tags", TEXT | STORED);
+        schema_builder.add_text_field("path", STRING | STORED);
+        std::fs::create_dir_all(&index_path).unwrap();
+        Index::create_in_dir(&index_path, schema_builder.build()).unwrap();
+
+        let error = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadOnly)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("reindex required"));
+
+        let backend = TantivyBackend::open_for_indexing(&corpus, "en").unwrap();
+        assert_eq!(backend.language().as_deref(), Some("en"));
+        backend.index_incremental(&corpus).unwrap();
+        assert!(TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadOnly).is_ok());
+    }
+
+    #[test]
+    fn test_unsupported_language_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let corpus = create_test_corpus(&temp_dir);
+
+        let error = TantivyBackend::open_for_indexing(&corpus, "xx")
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("Unsupported index language 'xx'")
+        );
+        assert!(!TantivyBackend::index_exists(&corpus));
+    }
+
+    #[test]
+    fn test_title_matches_outrank_body_matches() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        for (path, title, body) in [
+            (
+                "test/body.md",
+                "Deployment Notes",
+                "Kafka topics, kafka brokers, and kafka consumers.",
+            ),
+            ("test/title.md", "Kafka", "Notes on topics and brokers."),
+        ] {
+            std::fs::write(corpus.root.join(path), body).unwrap();
+            corpus.manifest.documents.push(Document {
+                path: PathBuf::from(path),
+                title: title.to_string(),
+                category: "test".to_string(),
+                tags: vec![],
+                encrypted: false,
+                word_count: None,
+                aliases: vec![],
+                content_type: None,
+            });
+        }
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+
+        for fuzzy in [None, Some(1)] {
+            let options = SearchOptions {
+                fuzzy,
+                ..Default::default()
+            };
+            let results = backend.search("kafka", &corpus, &options).unwrap();
+            let titles: Vec<&str> = results.iter().map(|r| r.title.as_str()).collect();
+            assert_eq!(
+                titles,
+                vec!["Kafka", "Deployment Notes"],
+                "fuzzy: {fuzzy:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_incremental_reindexes_changed_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+
+        let backend = TantivyBackend::open_for_indexing(&corpus, "en").unwrap();
+        backend.index_incremental(&corpus).unwrap();
+
+        // Tagging edits the manifest, not the file
+        corpus.manifest.documents[0]
+            .tags
+            .push("perfnote".to_string());
+        let report = backend.index_incremental(&corpus).unwrap();
+        assert_eq!(
+            report,
+            IncrementalReport {
+                indexed: 1,
+                removed: 0,
+                unchanged: 0
+            }
+        );
+        let results = backend
+            .search("tags:perfnote", &corpus, &SearchOptions::default())
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].tags.contains(&"perfnote".to_string()));
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let temp_dir = TempDir::new().unwrap();
+        let corpus = create_test_corpus(&temp_dir);
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+
+        let caps = backend.capabilities();
+        assert!(caps.supports_fuzzy);
+        assert!(caps.supports_phrase);
+        assert!(caps.supports_scoring);
+        assert!(caps.supports_tag_filter);
+        assert!(!caps.supports_regex);
+        assert!(!caps.supports_within);
+        assert!(!caps.supports_max_per_file);
+        assert!(!caps.supports_group_by_document);
+        assert!(!caps.supports_score_matches);
+        assert!(!caps.supports_include_metadata);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("lambda", "lambda"), 0);
+        assert_eq!(edit_distance("lamda", "lambda"), 1);
+        assert_eq!(edit_distance("lambdaa", "lambda"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_suggest_corrects_misspelled_term() {
+        let temp_dir = TempDir::new().unwrap();
+        let corpus = create_test_corpus(&temp_dir);
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        backend.index_corpus(&corpus).unwrap();
+
+        let backend = TantivyBackend::open_for_corpus(&corpus, IndexMode::ReadWrite).unwrap();
+        let options = SearchOptions::default();
+
+        assert!(
+            backend
+                .search("lambdda", &corpus, &options)
+                .unwrap()
+                .is_empty()
+        );
+        let suggestion = backend.suggest("lambdda", &corpus, &options).unwrap();
+        assert_eq!(suggestion.as_deref(), Some("lambda"));
+
+        // Correctly spelled queries produce no suggestion
+        let suggestion = backend.suggest("lambda", &corpus, &options).unwrap();
+        assert!(suggestion.is_none());
+    }
+
+    #[test]
+    fn test_suggest_offers_words_not_stems() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut corpus = create_test_corpus(&temp_dir);
+        std::fs::write(
+            corpus.root.join("test/batch.md"),
+            "Configure schedules for batch jobs",
+        )
+        .unwrap();
+        corpus.manifest.documents.push(Document {
+            path: PathBuf::from("test/batch.md"),
+            title: "Batch".to_string(),
+            category: "test".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        });
+
+        let backend = TantivyBackend::open_for_indexing(&corpus, "en").unwrap();
+        backend.index_corpus(&corpus).unwrap();
+        let options = SearchOptions::default();
+
+        let suggestion = backend.suggest("schedulez", &corpus, &options).unwrap();
+        assert_eq!(suggestion.as_deref(), Some("schedules"));
+        // The suggestion finds what the fuzzy search did
+        assert_eq!(
+            backend.search("schedules", &corpus, &options).unwrap()[0].title,
+            "Batch"
+        );
+    }
+
     #[test]
     fn test_empty_query_returns_empty() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,258 @@
+//! Encryption of document bodies at rest.
+//!
+//! Documents are sealed with ChaCha20-Poly1305 using a key derived from a
+//! passphrase (`KVAULT_PASSPHRASE`) and a per-corpus salt with Argon2id.
+//! Each encrypted file is laid out as `MAGIC || nonce || ciphertext`. A
+//! known constant sealed the same way is kept beside the salt, so a wrong
+//! passphrase is caught before any document is read or written.
+
+use std::fmt::Write;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use crate::storage::StorageError;
+
+/// Environment variable holding the passphrase for encrypted corpora.
+pub const PASSPHRASE_ENV: &str = "KVAULT_PASSPHRASE";
+
+/// Header identifying an encrypted document.
+const MAGIC: &[u8] = b"KVENC\x01";
+
+/// Length of the per-corpus key derivation salt in bytes.
+const SALT_LEN: usize = 16;
+
+/// Length of a ChaCha20-Poly1305 nonce in bytes.
+const NONCE_LEN: usize = 12;
+
+/// Plaintext sealed into a corpus's key check value.
+const KEY_CHECK: &[u8] = b"kvault key check";
+
+/// Symmetric cipher for document bodies.
+pub struct Cipher {
+    aead: ChaCha20Poly1305,
+}
+
+impl Cipher {
+    /// Derive a cipher from a passphrase and salt.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::CryptoError` if key derivation fails.
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self, StorageError> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| StorageError::CryptoError(format!("key derivation: {e}")))?;
+
+        Ok(Self {
+            aead: ChaCha20Poly1305::new(Key::from_slice(&key)),
+        })
+    }
+
+    /// Derive a cipher from `KVAULT_PASSPHRASE` and the given salt.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::CryptoError` if the variable is unset or empty,
+    /// or if key derivation fails.
+    pub fn from_env(salt: &[u8]) -> Result<Self, StorageError> {
+        match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => Self::from_passphrase(&passphrase, salt),
+            _ => Err(StorageError::CryptoError(format!(
+                "{PASSPHRASE_ENV} must be set to access encrypted documents"
+            ))),
+        }
+    }
+
+    /// Generate a random salt for a new encrypted corpus.
+    #[must_use]
+    pub fn generate_salt() -> Vec<u8> {
+        let mut salt = vec![0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        salt
+    }
+
+    /// Encrypt a document body.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::CryptoError` if encryption fails.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .aead
+            .encrypt(&nonce, plaintext)
+            .map_err(|e| StorageError::CryptoError(format!("encrypt: {e}")))?;
+
+        let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Seal the key check value stored in the manifest, hex-encoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::CryptoError` if encryption fails.
+    pub fn key_check(&self) -> Result<String, StorageError> {
+        Ok(encode_hex(&self.encrypt(KEY_CHECK)?))
+    }
+
+    /// Check that this cipher's key is the one `key_check` was sealed with.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::CryptoError` if the value is not valid hex or
+    /// was sealed with another key (a wrong passphrase).
+    pub fn verify_key_check(&self, key_check: &str) -> Result<(), StorageError> {
+        let sealed = decode_hex(key_check, "key check")?;
+        match self.decrypt(&sealed) {
+            Ok(plaintext) if plaintext == KEY_CHECK => Ok(()),
+            _ => Err(StorageError::CryptoError(format!(
+                "{PASSPHRASE_ENV} does not match the corpus encryption key"
+            ))),
+        }
+    }
+
+    /// Decrypt a document body produced by [`Cipher::encrypt`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::CryptoError` if the data is not an encrypted
+    /// document or fails authentication (wrong passphrase or tampering).
+    pub fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let body = sealed
+            .strip_prefix(MAGIC)
+            .filter(|body| body.len() >= NONCE_LEN)
+            .ok_or_else(|| StorageError::CryptoError("not an encrypted document".to_string()))?;
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+
+        self.aead
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                StorageError::CryptoError("decryption failed (wrong passphrase?)".to_string())
+            })
+    }
+}
+
+/// Returns true if the data starts with the encrypted document header.
+#[must_use]
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Encode a salt as lowercase hex for storage in the manifest.
+#[must_use]
+pub fn encode_salt(salt: &[u8]) -> String {
+    encode_hex(salt)
+}
+
+/// Decode a hex salt stored in the manifest.
+///
+/// # Errors
+///
+/// Returns `StorageError::CryptoError` if the value is not valid hex.
+pub fn decode_salt(hex: &str) -> Result<Vec<u8>, StorageError> {
+    decode_hex(hex, "salt")
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        })
+}
+
+/// Decode hex from the manifest, naming the value as `what` in errors.
+fn decode_hex(hex: &str, what: &str) -> Result<Vec<u8>, StorageError> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(StorageError::CryptoError(format!("invalid {what} length")));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|e| StorageError::CryptoError(format!("invalid {what}: {e}")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageBackend;
+    use crate::storage::local::LocalStorageBackend;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let salt = Cipher::generate_salt();
+        let cipher = Cipher::from_passphrase("correct horse", &salt).unwrap();
+
+        let sealed = cipher.encrypt(b"# Secret\n\nBody").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.windows(6).any(|w| w == b"Secret"));
+
+        assert_eq!(cipher.decrypt(&sealed).unwrap(), b"# Secret\n\nBody");
+    }
+
+    #[test]
+    fn wrong_passphrase_fails() {
+        let salt = Cipher::generate_salt();
+        let cipher = Cipher::from_passphrase("correct horse", &salt).unwrap();
+        let other = Cipher::from_passphrase("battery staple", &salt).unwrap();
+
+        let sealed = cipher.encrypt(b"secret").unwrap();
+        let err = other.decrypt(&sealed).unwrap_err();
+        assert!(err.to_string().contains("decryption failed"));
+    }
+
+    #[test]
+    fn key_check_detects_wrong_passphrase() {
+        let salt = Cipher::generate_salt();
+        let cipher = Cipher::from_passphrase("correct horse", &salt).unwrap();
+        let check = cipher.key_check().unwrap();
+
+        assert!(cipher.verify_key_check(&check).is_ok());
+        let other = Cipher::from_passphrase("battery staple", &salt).unwrap();
+        let err = other.verify_key_check(&check).unwrap_err();
+        assert!(err.to_string().contains("does not match"));
+    }
+
+    #[test]
+    fn decrypt_rejects_plaintext() {
+        let cipher = Cipher::from_passphrase("pw", &Cipher::generate_salt()).unwrap();
+        assert!(cipher.decrypt(b"# Plain markdown").is_err());
+    }
+
+    #[test]
+    fn salt_hex_round_trip() {
+        let salt = Cipher::generate_salt();
+        assert_eq!(decode_salt(&encode_salt(&salt)).unwrap(), salt);
+        assert!(decode_salt("abc").is_err());
+        assert!(decode_salt("zz").is_err());
+    }
+
+    #[test]
+    fn local_storage_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let cipher = Cipher::from_passphrase("pw", &Cipher::generate_salt()).unwrap();
+        let storage = LocalStorageBackend::with_cipher(temp_dir.path().to_path_buf(), cipher);
+        let path = Path::new("notes/secret.md");
+
+        storage.write_document(path, "# Secret\n\nBody").unwrap();
+
+        let on_disk = std::fs::read(temp_dir.path().join(path)).unwrap();
+        assert!(is_encrypted(&on_disk));
+        assert_eq!(storage.read_document(path).unwrap(), "# Secret\n\nBody");
+    }
+}
@@ -1,41 +1,132 @@
 //! Local filesystem storage backend.
 
-use std::fs;
+use std::fs::{self, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
-use crate::corpus::Manifest;
+use crate::corpus::{Document, Manifest, ManifestFormat};
 use crate::storage::{StorageBackend, StorageError};
 
+#[cfg(feature = "crypto")]
+use crate::storage::crypto::{self, Cipher};
+
 /// Storage backend for local filesystem operations.
+///
+/// With the `crypto` feature, a backend created with a [`Cipher`] encrypts
+/// documents on write and decrypts them on read.
+///
+/// The manifest is read from whichever of `manifest.json` or `manifest.jsonl`
+/// exists, and written in the backend's [`ManifestFormat`] (JSON by default).
 pub struct LocalStorageBackend {
     root: PathBuf,
+    manifest_format: ManifestFormat,
+    #[cfg(feature = "crypto")]
+    cipher: Option<Cipher>,
 }
 
 impl LocalStorageBackend {
     /// Create a new local storage backend rooted at the given path.
     #[must_use]
     pub fn new(root: PathBuf) -> Self {
-        Self { root }
+        Self {
+            root,
+            manifest_format: ManifestFormat::default(),
+            #[cfg(feature = "crypto")]
+            cipher: None,
+        }
+    }
+
+    /// Create a backend that encrypts documents with the given cipher.
+    #[cfg(feature = "crypto")]
+    #[must_use]
+    pub fn with_cipher(root: PathBuf, cipher: Cipher) -> Self {
+        Self {
+            root,
+            manifest_format: ManifestFormat::default(),
+            cipher: Some(cipher),
+        }
+    }
+
+    /// Write the manifest in the given format.
+    ///
+    /// A manifest stored in the other format is converted on the next write.
+    #[must_use]
+    pub fn with_manifest_format(mut self, format: ManifestFormat) -> Self {
+        self.manifest_format = format;
+        self
+    }
+
+    /// Create a backend for reading an existing corpus.
+    ///
+    /// If the manifest records an encryption salt and `KVAULT_PASSPHRASE` is
+    /// set, the backend can decrypt encrypted documents. Without a passphrase,
+    /// plaintext documents remain readable and encrypted ones report an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::CryptoError` if the stored salt is invalid, key
+    /// derivation fails, or the passphrase fails the manifest's key check.
+    #[cfg_attr(not(feature = "crypto"), allow(clippy::unnecessary_wraps))]
+    pub fn for_manifest(root: PathBuf, manifest: &Manifest) -> Result<Self, StorageError> {
+        #[cfg(feature = "crypto")]
+        if let Some(salt) = &manifest.encryption_salt
+            && std::env::var_os(crypto::PASSPHRASE_ENV).is_some()
+        {
+            let cipher = Cipher::from_env(&crypto::decode_salt(salt)?)?;
+            if let Some(check) = &manifest.encryption_check {
+                cipher.verify_key_check(check)?;
+            }
+            return Ok(Self::with_cipher(root, cipher));
+        }
+
+        #[cfg(not(feature = "crypto"))]
+        let _ = manifest;
+
+        Ok(Self::new(root))
     }
 
     fn manifest_path(&self) -> PathBuf {
-        self.root.join("manifest.json")
+        self.root.join(self.manifest_format.file_name())
+    }
+
+    /// Append a line to the JSONL manifest, first terminating a final line
+    /// left without a newline.
+    fn append_manifest_line(&self, line: &str) -> std::io::Result<()> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(self.manifest_path())?;
+
+        if file.metadata()?.len() > 0 {
+            let mut last = [0u8];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
+        }
+
+        file.write_all(line.as_bytes())
     }
 }
 
 impl StorageBackend for LocalStorageBackend {
     fn read_manifest(&self) -> Result<Manifest, StorageError> {
-        let path = self.manifest_path();
-
-        if !path.exists() {
+        let Some((path, format)) = ManifestFormat::detect(&self.root) else {
             return Ok(Manifest::empty());
-        }
+        };
 
-        let contents = fs::read_to_string(&path)
+        let file = fs::File::open(&path)
             .map_err(|e| StorageError::ReadError(format!("{}: {e}", path.display())))?;
 
-        serde_json::from_str(&contents)
-            .map_err(|e| StorageError::ParseError(format!("{}: {e}", path.display())))
+        Manifest::from_reader(BufReader::new(file), format).map_err(|e| {
+            let message = format!("{}: {e}", path.display());
+            if e.is_io() {
+                StorageError::ReadError(message)
+            } else {
+                StorageError::ParseError(message)
+            }
+        })
     }
 
     fn write_manifest(&self, manifest: &Manifest) -> Result<(), StorageError> {
@@ -47,11 +138,60 @@ impl StorageBackend for LocalStorageBackend {
             })?;
         }
 
-        let contents = serde_json::to_string_pretty(manifest)
+        let contents = manifest
+            .encode(self.manifest_format)
+            .map_err(|e| StorageError::SerializeError(e.to_string()))?;
+
+        // Write beside the manifest and rename over it, so readers never see
+        // a partly written manifest and a failed write leaves the old one
+        let temp = self
+            .root
+            .join(format!(".{}.tmp", self.manifest_format.file_name()));
+        fs::write(&temp, contents)
+            .and_then(|()| fs::rename(&temp, &path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&temp);
+                StorageError::WriteError(format!("{}: {e}", path.display()))
+            })?;
+
+        // Remove a manifest left in the other format so the one just written
+        // is the only one read back
+        for format in [ManifestFormat::Json, ManifestFormat::Jsonl] {
+            let stale = self.root.join(format.file_name());
+            if format != self.manifest_format && stale.exists() {
+                fs::remove_file(&stale)
+                    .map_err(|e| StorageError::WriteError(format!("{}: {e}", stale.display())))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn append_document(
+        &self,
+        manifest: &mut Manifest,
+        document: Document,
+    ) -> Result<(), StorageError> {
+        // Appending only works when the JSONL manifest is the one being read;
+        // otherwise fall back to a full write, which also converts formats
+        let appendable = self.manifest_format == ManifestFormat::Jsonl
+            && ManifestFormat::detect(&self.root)
+                .is_some_and(|(_, format)| format == ManifestFormat::Jsonl);
+
+        if !appendable {
+            manifest.documents.push(document);
+            return self.write_manifest(manifest);
+        }
+
+        let line = document
+            .to_jsonl()
             .map_err(|e| StorageError::SerializeError(e.to_string()))?;
+        self.append_manifest_line(&line).map_err(|e| {
+            StorageError::WriteError(format!("{}: {e}", self.manifest_path().display()))
+        })?;
 
-        fs::write(&path, contents)
-            .map_err(|e| StorageError::WriteError(format!("{}: {e}", path.display())))
+        manifest.documents.push(document);
+        Ok(())
     }
 
     fn read_document(&self, path: &Path) -> Result<String, StorageError> {
@@ -61,7 +201,24 @@ impl StorageBackend for LocalStorageBackend {
             return Err(StorageError::NotFound(full_path.display().to_string()));
         }
 
-        fs::read_to_string(&full_path)
+        let data = fs::read(&full_path)
+            .map_err(|e| StorageError::ReadError(format!("{}: {e}", full_path.display())))?;
+
+        #[cfg(feature = "crypto")]
+        let data = if crypto::is_encrypted(&data) {
+            let cipher = self.cipher.as_ref().ok_or_else(|| {
+                StorageError::CryptoError(format!(
+                    "{} is encrypted; set {}",
+                    full_path.display(),
+                    crypto::PASSPHRASE_ENV
+                ))
+            })?;
+            cipher.decrypt(&data)?
+        } else {
+            data
+        };
+
+        String::from_utf8(data)
             .map_err(|e| StorageError::ReadError(format!("{}: {e}", full_path.display())))
     }
 
@@ -74,10 +231,28 @@ impl StorageBackend for LocalStorageBackend {
             })?;
         }
 
+        #[cfg(feature = "crypto")]
+        if let Some(cipher) = &self.cipher {
+            let sealed = cipher.encrypt(content.as_bytes())?;
+            return fs::write(&full_path, sealed)
+                .map_err(|e| StorageError::WriteError(format!("{}: {e}", full_path.display())));
+        }
+
         fs::write(&full_path, content)
             .map_err(|e| StorageError::WriteError(format!("{}: {e}", full_path.display())))
     }
 
+    fn delete_document(&self, path: &Path) -> Result<(), StorageError> {
+        let full_path = self.root.join(path);
+
+        if !full_path.exists() {
+            return Err(StorageError::NotFound(full_path.display().to_string()));
+        }
+
+        fs::remove_file(&full_path)
+            .map_err(|e| StorageError::WriteError(format!("remove {}: {e}", full_path.display())))
+    }
+
     fn exists(&self, path: &Path) -> bool {
         self.root.join(path).exists()
     }
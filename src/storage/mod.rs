@@ -5,9 +5,15 @@
 
 pub mod local;
 
+#[cfg(feature = "crypto")]
+pub mod crypto;
+
+#[cfg(feature = "s3")]
+pub mod s3;
+
 use std::path::Path;
 
-use crate::corpus::Manifest;
+use crate::corpus::{Document, Manifest};
 
 /// Errors that can occur during storage operations.
 #[derive(Debug, thiserror::Error)]
@@ -26,6 +32,9 @@ pub enum StorageError {
 
     #[error("Failed to serialize: {0}")]
     SerializeError(String),
+
+    #[error("Encryption error: {0}")]
+    CryptoError(String),
 }
 
 /// Trait for storage backends (local filesystem, S3, database, etc.).
@@ -44,6 +53,24 @@ pub trait StorageBackend: Send + Sync {
     /// Returns `StorageError` if the manifest cannot be written.
     fn write_manifest(&self, manifest: &Manifest) -> Result<(), StorageError>;
 
+    /// Add a document to the manifest and persist it.
+    ///
+    /// `manifest` must be the manifest as last read or written. The default
+    /// implementation rewrites the whole manifest; backends with an
+    /// append-friendly format can override it to write only the new entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError` if the manifest cannot be written.
+    fn append_document(
+        &self,
+        manifest: &mut Manifest,
+        document: Document,
+    ) -> Result<(), StorageError> {
+        manifest.documents.push(document);
+        self.write_manifest(manifest)
+    }
+
     /// Read a document's content.
     ///
     /// # Errors
@@ -58,6 +85,13 @@ pub trait StorageBackend: Send + Sync {
     /// Returns `StorageError` if the document cannot be written.
     fn write_document(&self, path: &Path, content: &str) -> Result<(), StorageError>;
 
+    /// Delete a document's content.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError` if the document cannot be deleted.
+    fn delete_document(&self, path: &Path) -> Result<(), StorageError>;
+
     /// Check if a path exists in storage.
     fn exists(&self, path: &Path) -> bool;
 
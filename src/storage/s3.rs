@@ -0,0 +1,320 @@
+//! S3 storage backend.
+//!
+//! A corpus in a bucket is laid out as on disk, under a key prefix:
+//! `<prefix>/manifest.json` and `<prefix>/<category>/<slug>.md`.
+
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+use aws_sdk_s3::Client;
+use aws_sdk_s3::config::{BehaviorVersion, Region};
+use aws_sdk_s3::primitives::ByteStream;
+use tokio::runtime::Runtime;
+
+use crate::config::S3Config;
+use crate::corpus::{Manifest, ManifestFormat};
+use crate::storage::{StorageBackend, StorageError};
+
+/// Storage backend for a corpus in an S3 bucket.
+///
+/// The manifest is read from whichever of `manifest.json` or `manifest.jsonl`
+/// exists under the prefix, and written in the backend's [`ManifestFormat`]
+/// (JSON by default). Documents are stored as given; encryption at rest is
+/// left to the bucket.
+pub struct S3StorageBackend {
+    client: Client,
+    bucket: String,
+    prefix: String,
+    root: PathBuf,
+    manifest_format: ManifestFormat,
+    /// Runs the SDK's requests, since the trait is synchronous.
+    runtime: &'static Runtime,
+}
+
+impl S3StorageBackend {
+    /// Create a backend for the bucket and prefix in `config`.
+    ///
+    /// Credentials come from the usual AWS sources (environment, shared
+    /// profile, or instance role), as does the region unless `config` sets
+    /// one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::ReadError` if the async runtime cannot start.
+    pub fn new(config: &S3Config) -> Result<Self, StorageError> {
+        let runtime = shared_runtime()?;
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = &config.region {
+            loader = loader.region(Region::new(region.clone()));
+        }
+        let sdk_config = block_on(runtime, loader.load());
+
+        Ok(Self::with_client(
+            Client::new(&sdk_config),
+            runtime,
+            &config.bucket,
+            &config.prefix,
+        ))
+    }
+
+    fn with_client(client: Client, runtime: &'static Runtime, bucket: &str, prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/').to_string();
+        let root = if prefix.is_empty() {
+            PathBuf::from(format!("s3://{bucket}"))
+        } else {
+            PathBuf::from(format!("s3://{bucket}/{prefix}"))
+        };
+        Self {
+            client,
+            bucket: bucket.to_string(),
+            prefix,
+            root,
+            manifest_format: ManifestFormat::default(),
+            runtime,
+        }
+    }
+
+    /// Write the manifest in the given format.
+    ///
+    /// A manifest stored in the other format is converted on the next write.
+    #[must_use]
+    pub fn with_manifest_format(mut self, format: ManifestFormat) -> Self {
+        self.manifest_format = format;
+        self
+    }
+
+    /// The object key for a path relative to the corpus root.
+    ///
+    /// Only plain names are kept, so no path reaches outside the prefix.
+    fn key(&self, path: &Path) -> String {
+        let relative = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        if self.prefix.is_empty() {
+            relative
+        } else {
+            format!("{}/{relative}", self.prefix)
+        }
+    }
+
+    /// The object's contents, or `None` if there is no such object.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let request = self.client.get_object().bucket(&self.bucket).key(key);
+        let object = match self.block_on(request.send()) {
+            Ok(object) => object,
+            Err(e) => {
+                let e = e.into_service_error();
+                if e.is_no_such_key() {
+                    return Ok(None);
+                }
+                return Err(StorageError::ReadError(self.describe(key, &e)));
+            }
+        };
+
+        let body = self
+            .block_on(object.body.collect())
+            .map_err(|e| StorageError::ReadError(self.describe(key, &e)))?;
+        Ok(Some(body.to_vec()))
+    }
+
+    fn put(&self, key: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(contents));
+        self.block_on(request.send())
+            .map(|_| ())
+            .map_err(|e| StorageError::WriteError(self.describe(key, &e.into_service_error())))
+    }
+
+    fn head(&self, key: &str) -> Result<bool, StorageError> {
+        let request = self.client.head_object().bucket(&self.bucket).key(key);
+        match self.block_on(request.send()) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let e = e.into_service_error();
+                if e.is_not_found() {
+                    Ok(false)
+                } else {
+                    Err(StorageError::ReadError(self.describe(key, &e)))
+                }
+            }
+        }
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let request = self.client.delete_object().bucket(&self.bucket).key(key);
+        self.block_on(request.send())
+            .map(|_| ())
+            .map_err(|e| StorageError::WriteError(self.describe(key, &e.into_service_error())))
+    }
+
+    /// Run `future` on the backend's runtime.
+    fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        block_on(self.runtime, future)
+    }
+
+    /// An error message naming the object.
+    fn describe(&self, key: &str, error: &dyn std::fmt::Display) -> String {
+        format!("s3://{}/{key}: {error}", self.bucket)
+    }
+}
+
+impl StorageBackend for S3StorageBackend {
+    fn read_manifest(&self) -> Result<Manifest, StorageError> {
+        for format in [ManifestFormat::Json, ManifestFormat::Jsonl] {
+            let key = self.key(Path::new(format.file_name()));
+            let Some(contents) = self.get(&key)? else {
+                continue;
+            };
+            return Manifest::from_reader(contents.as_slice(), format)
+                .map_err(|e| StorageError::ParseError(self.describe(&key, &e)));
+        }
+        Ok(Manifest::empty())
+    }
+
+    fn write_manifest(&self, manifest: &Manifest) -> Result<(), StorageError> {
+        let contents = manifest
+            .encode(self.manifest_format)
+            .map_err(|e| StorageError::SerializeError(e.to_string()))?;
+        self.put(
+            &self.key(Path::new(self.manifest_format.file_name())),
+            contents.into_bytes(),
+        )?;
+
+        // Remove a manifest left in the other format so the one just written
+        // is the only one read back
+        for format in [ManifestFormat::Json, ManifestFormat::Jsonl] {
+            let stale = self.key(Path::new(format.file_name()));
+            if format != self.manifest_format && self.head(&stale)? {
+                self.delete(&stale)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_document(&self, path: &Path) -> Result<String, StorageError> {
+        let key = self.key(path);
+        let contents = self
+            .get(&key)?
+            .ok_or_else(|| StorageError::NotFound(format!("s3://{}/{key}", self.bucket)))?;
+        String::from_utf8(contents).map_err(|e| StorageError::ReadError(self.describe(&key, &e)))
+    }
+
+    fn write_document(&self, path: &Path, content: &str) -> Result<(), StorageError> {
+        self.put(&self.key(path), content.as_bytes().to_vec())
+    }
+
+    fn delete_document(&self, path: &Path) -> Result<(), StorageError> {
+        let key = self.key(path);
+        if !self.head(&key)? {
+            return Err(StorageError::NotFound(format!(
+                "s3://{}/{key}",
+                self.bucket
+            )));
+        }
+        self.delete(&key)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        // A failed lookup is reported as missing, as for an unreadable file
+        self.head(&self.key(path)).unwrap_or(false)
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+/// The runtime shared by all S3 backends.
+///
+/// It lives for the rest of the process: dropping a runtime from inside
+/// another one (as a backend created by the MCP server would be) panics.
+fn shared_runtime() -> Result<&'static Runtime, StorageError> {
+    static RUNTIME: OnceLock<Result<Runtime, String>> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| e.to_string())
+        })
+        .as_ref()
+        .map_err(|e| StorageError::ReadError(format!("start async runtime: {e}")))
+}
+
+/// Run `future` to completion on `runtime`.
+///
+/// Blocking a thread of another runtime (such as the MCP server's) on this
+/// one panics, so from inside one the future runs on a thread of its own.
+fn block_on<F>(runtime: &Runtime, future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    if tokio::runtime::Handle::try_current().is_err() {
+        return runtime.block_on(future);
+    }
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| runtime.block_on(future))
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend(bucket: &str, prefix: &str) -> S3StorageBackend {
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .build();
+        S3StorageBackend::with_client(
+            Client::from_conf(config),
+            shared_runtime().unwrap(),
+            bucket,
+            prefix,
+        )
+    }
+
+    #[test]
+    fn keys_are_relative_paths_under_the_prefix() {
+        let storage = backend("kb", "/team/notes/");
+        assert_eq!(storage.root(), Path::new("s3://kb/team/notes"));
+        assert_eq!(
+            storage.key(Path::new("aws/lambda.md")),
+            "team/notes/aws/lambda.md"
+        );
+        assert_eq!(
+            storage.key(Path::new("./aws/../manifest.json")),
+            "team/notes/aws/manifest.json"
+        );
+
+        let storage = backend("kb", "");
+        assert_eq!(storage.root(), Path::new("s3://kb"));
+        assert_eq!(storage.key(Path::new("manifest.json")), "manifest.json");
+    }
+
+    #[tokio::test]
+    async fn block_on_works_inside_another_runtime() {
+        let storage = backend("kb", "");
+        assert_eq!(storage.block_on(async { 42 }), 42);
+        // Dropping the backend here must not panic either
+        drop(storage);
+    }
+}
@@ -0,0 +1,367 @@
+//! Interactive search (`kvault search --interactive`).
+//!
+//! A full-screen view with a query line and a result list. The search
+//! re-runs shortly after the query stops changing, and Enter picks the
+//! selected result. The view only drives a search function it is given, so
+//! the scriptable `search` path is unaffected by it.
+
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::Frame;
+use ratatui::Terminal;
+use ratatui::backend::Backend;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+
+use crate::search::SearchResult;
+
+/// How long the query must stay unchanged before the search re-runs.
+pub const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// How long to wait for input when no search is pending.
+const IDLE_POLL: Duration = Duration::from_millis(500);
+
+/// A source of terminal events.
+pub trait EventSource {
+    /// Wait up to `timeout` for the next event.
+    ///
+    /// # Returns
+    ///
+    /// The event, or `None` if the timeout passed without one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from the terminal fails.
+    fn next_event(&mut self, timeout: Duration) -> std::io::Result<Option<Event>>;
+}
+
+/// Events read from the real terminal.
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout: Duration) -> std::io::Result<Option<Event>> {
+        if event::poll(timeout)? {
+            event::read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// What a key press asks the loop to do.
+enum Action {
+    Continue,
+    Quit,
+    Select,
+}
+
+/// The state of the interactive view.
+struct App {
+    query: String,
+    results: Vec<SearchResult>,
+    error: Option<String>,
+    list: ListState,
+    /// When the query last changed, if the search hasn't caught up yet.
+    edited: Option<Instant>,
+}
+
+impl App {
+    fn new(query: String) -> Self {
+        let edited = (!query.is_empty()).then(Instant::now);
+        Self {
+            query,
+            results: Vec::new(),
+            error: None,
+            list: ListState::default(),
+            edited,
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Action::Quit,
+            KeyCode::Char('c') if ctrl => return Action::Quit,
+            KeyCode::Enter if self.list.selected().is_some() => return Action::Select,
+            KeyCode::Up => self.list.select_previous(),
+            KeyCode::Down => self.list.select_next(),
+            KeyCode::Char('u') if ctrl => self.edit(String::clear),
+            KeyCode::Backspace => self.edit(|query| {
+                query.pop();
+            }),
+            KeyCode::Char(c) if !ctrl => self.edit(|query| query.push(c)),
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    fn edit(&mut self, change: impl FnOnce(&mut String)) {
+        change(&mut self.query);
+        self.edited = Some(Instant::now());
+    }
+
+    /// Re-run the search once the query has been unchanged for [`DEBOUNCE`].
+    fn refresh<F>(&mut self, search: &mut F)
+    where
+        F: FnMut(&str) -> anyhow::Result<Vec<SearchResult>>,
+    {
+        if self.edited.is_none_or(|edited| edited.elapsed() < DEBOUNCE) {
+            return;
+        }
+        self.edited = None;
+
+        let query = self.query.trim();
+        let outcome = if query.is_empty() {
+            Ok(Vec::new())
+        } else {
+            search(query)
+        };
+        match outcome {
+            Ok(results) => {
+                self.error = None;
+                self.list.select((!results.is_empty()).then_some(0));
+                self.results = results;
+            }
+            Err(e) => {
+                self.error = Some(format!("{e:#}"));
+                self.list.select(None);
+                self.results.clear();
+            }
+        }
+    }
+
+    /// How long the next wait for input may take without delaying a
+    /// pending search.
+    fn poll_timeout(&self) -> Duration {
+        self.edited.map_or(IDLE_POLL, |edited| {
+            DEBOUNCE.saturating_sub(edited.elapsed())
+        })
+    }
+
+    fn selected(mut self) -> Option<SearchResult> {
+        let index = self.list.selected()?;
+        (index < self.results.len()).then(|| self.results.swap_remove(index))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [input, results, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(
+            Paragraph::new(self.query.as_str()).block(Block::bordered().title("Search")),
+            input,
+        );
+        let cursor_x = u16::try_from(self.query.chars().count()).unwrap_or(u16::MAX);
+        frame.set_cursor_position((
+            input.x.saturating_add(1).saturating_add(cursor_x),
+            input.y + 1,
+        ));
+
+        let items: Vec<ListItem> = self
+            .results
+            .iter()
+            .map(|result| {
+                ListItem::new(format!(
+                    "{}/{}: {}",
+                    result.category,
+                    result.title,
+                    result.matched_line().trim()
+                ))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(format!("Results ({})", self.results.len())))
+            .highlight_style(Style::new().reversed())
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, results, &mut self.list);
+
+        let status_line = match &self.error {
+            Some(error) => Paragraph::new(error.as_str()).red(),
+            None => Paragraph::new("Enter: open  Up/Down: select  Ctrl-U: clear  Esc: quit").dim(),
+        };
+        frame.render_widget(status_line, status);
+    }
+}
+
+/// Run the interactive view until a result is picked or the user quits.
+///
+/// # Arguments
+///
+/// * `terminal` - The terminal to draw on
+/// * `events` - Where key presses come from
+/// * `query` - The starting query (may be empty)
+/// * `search` - Runs a query; its errors are shown in the status line
+///
+/// # Returns
+///
+/// The picked result, or `None` if the user quit.
+///
+/// # Errors
+///
+/// Returns an error if drawing or reading input fails.
+pub fn run<B, E, F>(
+    terminal: &mut Terminal<B>,
+    events: &mut E,
+    query: String,
+    mut search: F,
+) -> anyhow::Result<Option<SearchResult>>
+where
+    B: Backend,
+    B::Error: Send + Sync + 'static,
+    E: EventSource,
+    F: FnMut(&str) -> anyhow::Result<Vec<SearchResult>>,
+{
+    let mut app = App::new(query);
+    loop {
+        app.refresh(&mut search);
+        terminal.draw(|frame| app.draw(frame))?;
+
+        let Some(Event::Key(key)) = events.next_event(app.poll_timeout())? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.handle_key(key) {
+            Action::Continue => {}
+            Action::Quit => return Ok(None),
+            Action::Select => return Ok(app.selected()),
+        }
+    }
+}
+
+/// Run the interactive view on the real terminal, restoring it afterwards.
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be drawn on or read from.
+pub fn run_in_terminal<F>(query: String, search: F) -> anyhow::Result<Option<SearchResult>>
+where
+    F: FnMut(&str) -> anyhow::Result<Vec<SearchResult>>,
+{
+    let mut terminal = ratatui::init();
+    let picked = run(&mut terminal, &mut TerminalEvents, query, search);
+    ratatui::restore();
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::path::PathBuf;
+
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    /// One step of simulated input.
+    enum Step {
+        Key(KeyCode),
+        /// A wait for input that times out.
+        Wait,
+    }
+
+    struct Scripted(VecDeque<Step>);
+
+    impl Scripted {
+        fn new(steps: impl IntoIterator<Item = Step>) -> Self {
+            Self(steps.into_iter().collect())
+        }
+    }
+
+    impl EventSource for Scripted {
+        fn next_event(&mut self, timeout: Duration) -> std::io::Result<Option<Event>> {
+            let step = self
+                .0
+                .pop_front()
+                .expect("the interactive loop read past the scripted input");
+            Ok(match step {
+                Step::Key(code) => Some(Event::Key(KeyEvent::from(code))),
+                Step::Wait => {
+                    std::thread::sleep(timeout);
+                    None
+                }
+            })
+        }
+    }
+
+    fn result(title: &str) -> SearchResult {
+        SearchResult {
+            path: PathBuf::from(format!("/corpus/notes/{title}.md")),
+            title: title.to_string(),
+            category: "notes".to_string(),
+            tags: Vec::new(),
+            snippet: None,
+            score: None,
+            hit_count: None,
+            explanation: None,
+        }
+    }
+
+    fn terminal() -> Terminal<TestBackend> {
+        Terminal::new(TestBackend::new(60, 12)).unwrap()
+    }
+
+    #[test]
+    fn test_quit_key_exits_without_a_pick() {
+        let mut terminal = terminal();
+        let mut events = Scripted::new([Step::Key(KeyCode::Char('r')), Step::Key(KeyCode::Esc)]);
+
+        let picked = run(&mut terminal, &mut events, String::new(), |_| {
+            panic!("search ran before the query settled")
+        })
+        .unwrap();
+
+        assert!(picked.is_none());
+        assert!(events.0.is_empty());
+        let screen = format!("{:?}", terminal.backend().buffer());
+        assert!(screen.contains("Search"));
+        assert!(screen.contains("Esc: quit"));
+    }
+
+    #[test]
+    fn test_settled_query_searches_and_enter_picks() {
+        let mut terminal = terminal();
+        let mut events = Scripted::new([
+            Step::Key(KeyCode::Char('r')),
+            Step::Key(KeyCode::Char('u')),
+            Step::Wait,
+            Step::Key(KeyCode::Down),
+            Step::Key(KeyCode::Enter),
+        ]);
+        let mut queries = Vec::new();
+
+        let picked = run(&mut terminal, &mut events, String::new(), |query| {
+            queries.push(query.to_string());
+            Ok(vec![result("first"), result("second")])
+        })
+        .unwrap();
+
+        assert_eq!(queries, ["ru"]);
+        assert_eq!(picked.unwrap().title, "second");
+    }
+
+    #[test]
+    fn test_search_errors_show_in_the_status_line() {
+        let mut terminal = terminal();
+        let mut events = Scripted::new([
+            Step::Wait,
+            Step::Key(KeyCode::Enter),
+            Step::Key(KeyCode::Esc),
+        ]);
+
+        let picked = run(&mut terminal, &mut events, "r".to_string(), |_| {
+            anyhow::bail!("Query too short")
+        })
+        .unwrap();
+
+        assert!(picked.is_none());
+        assert!(format!("{:?}", terminal.backend().buffer()).contains("Query too short"));
+    }
+}
@@ -3,8 +3,10 @@
 //! These tests exercise the full CLI binary with isolated test environments.
 //! Each test creates its own temporary corpus and config to ensure isolation.
 
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use assert_cmd::Command;
 use assert_cmd::cargo::cargo_bin_cmd;
@@ -84,6 +86,7 @@ impl TestEnv {
     fn command(&self) -> Command {
         let mut cmd = cargo_bin_cmd!("kvault");
         cmd.env("KVAULT_CONFIG", &self.config_path);
+        cmd.env_remove("KVAULT_PROFILE");
         cmd
     }
 
@@ -246,7 +249,11 @@ fn tc_2_10_search_invalid_manifest() {
         .args(["search", "test"])
         .assert()
         .failure()
-        .stderr(predicate::str::contains("Search failed"));
+        .stderr(predicate::str::contains("Search failed"))
+        .stderr(predicate::str::contains(format!(
+            "Failed to parse manifest {}",
+            env.corpus().join("manifest.json").display()
+        )));
 }
 
 #[test]
@@ -294,558 +301,4198 @@ fn tc_2_13_search_case_sensitive_flag() {
         .stdout(predicate::str::contains("Lambda Patterns"));
 }
 
-// =============================================================================
-// 3. List Command Tests
-// =============================================================================
-
 #[test]
-fn tc_3_1_list_all_documents() {
+fn tc_2_16_search_fuzzy_with_ripgrep_rejected() {
     let env = TestEnv::with_documents();
 
     env.command()
-        .arg("list")
+        .args(["search", "lamda", "--fuzzy", "1", "--backend", "ripgrep"])
         .assert()
-        .success()
-        .stdout(predicate::str::contains("rust: Error Handling"))
-        .stdout(predicate::str::contains("aws: Lambda Patterns"))
-        .stdout(predicate::str::contains("[rust, errors]"))
-        .stdout(predicate::str::contains("[aws, lambda]"));
+        .failure()
+        .stderr(predicate::str::contains(
+            "ripgrep does not support fuzzy search",
+        ));
 }
 
 #[test]
-fn tc_3_2_list_no_documents() {
-    let env = TestEnv::new();
+fn tc_2_17_search_highlight_modes() {
+    let env = TestEnv::with_documents();
 
     env.command()
-        .arg("list")
+        .args(["search", "lambda", "--highlight", "markdown", "-c", "aws"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("No documents found"));
-}
-
-#[test]
-fn tc_3_3_list_with_category_filter_matching() {
-    let env = TestEnv::with_documents();
+        .stdout(predicate::str::contains(
+            "Best practices for AWS **Lambda** functions.",
+        ));
 
+    // Piped output defaults to no highlighting
     env.command()
-        .args(["list", "--category", "rust"])
+        .args(["search", "lambda", "-c", "aws"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("Error Handling"))
-        .stdout(predicate::str::contains("Lambda Patterns").not());
-}
-
-#[test]
-fn tc_3_4_list_with_category_filter_non_matching() {
-    let env = TestEnv::with_documents();
+        .stdout(predicate::str::contains("**").not())
+        .stdout(predicate::str::contains("\x1b[").not());
 
+    // An explicit mode wins over NO_COLOR; --no-color rules one out
     env.command()
-        .args(["list", "--category", "nonexistent"])
+        .args(["search", "lambda", "--highlight", "ansi", "-c", "aws"])
+        .env("NO_COLOR", "1")
         .assert()
         .success()
-        .stdout(predicate::str::contains("No documents found"));
+        .stdout(predicate::str::contains("\x1b[1;31mLambda\x1b[0m"));
+    env.command()
+        .args(["search", "lambda", "-c", "aws", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+    env.command()
+        .args(["search", "lambda", "--no-color", "--highlight", "ansi"])
+        .assert()
+        .failure();
 }
 
 #[test]
-fn tc_3_5_list_document_without_tags() {
-    let env = TestEnv::new();
+fn tc_2_18_search_fail_on_empty() {
+    let env = TestEnv::with_documents();
 
-    // Create document without tags
-    fs::create_dir_all(env.corpus().join("test")).unwrap();
-    fs::write(env.corpus().join("test/doc.md"), "# Test\n\nContent").unwrap();
+    env.command()
+        .args(["search", "xyznonexistent123", "--fail-on-empty"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("No matches found"));
 
-    let manifest = r#"{
-    "version": "1",
-    "documents": [
-        {"path": "test/doc.md", "title": "Test Doc", "category": "test", "tags": []}
-    ]
-}"#;
-    fs::write(env.corpus().join("manifest.json"), manifest).unwrap();
+    env.command()
+        .args(["search", "xyznonexistent123"])
+        .assert()
+        .code(0);
 
     env.command()
-        .arg("list")
+        .args(["search", "Lambda", "--fail-on-empty"])
         .assert()
-        .success()
-        .stdout(predicate::str::contains("test: Test Doc"))
-        // Should not have empty brackets
-        .stdout(predicate::str::contains("[]").not());
+        .code(0);
 }
 
-// =============================================================================
-// 4. Add Command Tests
-// =============================================================================
+/// Point the environment's config at a ripgrep binary that doesn't exist.
+fn with_missing_ripgrep(env: &TestEnv) {
+    let config = format!(
+        "[corpus]\npaths = [\"{}\"]\n\n[search]\nripgrep_path = \"/nonexistent/rg\"\n",
+        env.corpus().display()
+    );
+    fs::write(&env.config_path, config).unwrap();
+}
 
 #[test]
-fn tc_4_1_add_document_from_stdin() {
-    let env = TestEnv::new();
+fn tc_2_14_search_missing_ripgrep_without_index_fails() {
+    let env = TestEnv::with_documents();
+    with_missing_ripgrep(&env);
 
     env.command()
-        .args(["add", "--title", "Test Doc", "--category", "test"])
-        .write_stdin("# Test Document\n\nThis is test content.")
+        .args(["search", "Lambda"])
         .assert()
-        .success()
-        .stdout(predicate::str::contains("Added: Test Doc"))
-        .stdout(predicate::str::contains("Category: test"));
-
-    // Verify file was created
-    assert!(env.corpus().join("test/test-doc.md").exists());
+        .failure()
+        .stderr(predicate::str::contains("ripgrep not found"));
 }
 
+#[cfg(feature = "ranked")]
 #[test]
-fn tc_4_2_add_document_from_file() {
-    let env = TestEnv::new();
-    let input_file = env.corpus().join("input.md");
-    fs::write(&input_file, "# From File\n\nContent from file.").unwrap();
+fn tc_2_15_search_missing_ripgrep_falls_back_to_index() {
+    let env = TestEnv::with_documents();
+    env.command().arg("index").assert().success();
+    with_missing_ripgrep(&env);
 
     env.command()
-        .args([
-            "add",
-            "--title",
-            "From File",
-            "--category",
-            "test",
-            "--file",
-        ])
-        .arg(&input_file)
+        .args(["search", "Lambda"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("Added: From File"));
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stderr(predicate::str::contains("ripgrep unavailable"));
 }
 
 #[test]
-fn tc_4_3_add_document_with_tags() {
-    let env = TestEnv::new();
+fn tc_2_19_search_explain_ripgrep() {
+    let env = TestEnv::with_documents();
 
     env.command()
-        .args([
-            "add",
-            "--title",
-            "Tagged",
-            "--category",
-            "test",
-            "--tags",
-            "tag1, tag2, tag3",
-        ])
-        .write_stdin("# Tagged\n\nContent")
+        .args(["search", "Lambda", "--explain"])
         .assert()
-        .success();
-
-    // Verify tags in manifest
-    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
-    assert!(manifest.contains("tag1"));
-    assert!(manifest.contains("tag2"));
-    assert!(manifest.contains("tag3"));
-}
+        .success()
+        .stdout(predicate::str::contains("unscored: ripgrep match on line"));
 
-#[test]
-fn tc_4_4_add_empty_title() {
-    let env = TestEnv::new();
+    env.command()
+        .args(["search", "Lambda", "--rank", "--explain"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "5 = 2 matching line(s) + 3 x 1 query term(s) in the title",
+        ));
 
     env.command()
-        .args(["add", "--title", "", "--category", "test"])
-        .write_stdin("content")
+        .args(["search", "Lambda"])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Title cannot be empty"));
+        .success()
+        .stdout(predicate::str::contains("unscored").not());
 }
 
+#[cfg(feature = "ranked")]
 #[test]
-fn tc_4_5_add_title_too_long() {
-    let env = TestEnv::new();
-    let long_title = "a".repeat(201);
+fn tc_2_20_search_explain_ranked() {
+    let env = TestEnv::with_documents();
+    env.command().arg("index").assert().success();
 
     env.command()
-        .args(["add", "--title", &long_title, "--category", "test"])
-        .write_stdin("content")
+        .args(["search", "lambda", "--backend", "ranked", "--explain"])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Title too long"));
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("TermQuery"))
+        .stdout(predicate::str::contains("idf"));
 }
 
 #[test]
-fn tc_4_6_add_invalid_category_slash() {
-    let env = TestEnv::new();
+fn tc_2_21_search_exclude_globs() {
+    let env = TestEnv::with_documents();
+    fs::write(env.corpus().join("aws/scratch.tmp"), "Lambda scratch notes").unwrap();
 
     env.command()
-        .args(["add", "--title", "Test", "--category", "my/category"])
-        .write_stdin("content")
+        .args(["search", "Lambda"])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("invalid character"));
-}
+        .success()
+        .stdout(predicate::str::contains("scratch.tmp"));
 
-#[test]
-fn tc_4_7_add_category_starting_with_hyphen() {
-    let env = TestEnv::new();
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[search]\nexclude_globs = [\"*.tmp\"]\n"),
+    )
+    .unwrap();
 
-    // Use --category=-invalid syntax to prevent clap from treating -invalid as a flag
+    // The manifest mentions "Lambda Patterns" but is always excluded
     env.command()
-        .args(["add", "--title", "Test", "--category=-invalid"])
-        .write_stdin("content")
+        .args(["search", "Lambda"])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("must start with"));
+        .success()
+        .stdout(predicate::str::contains("lambda-patterns.md"))
+        .stdout(predicate::str::contains("scratch.tmp").not())
+        .stdout(predicate::str::contains("manifest.json").not());
 }
 
 #[test]
-fn tc_4_8_add_category_with_spaces() {
-    let env = TestEnv::new();
+fn tc_2_22_search_exclude_globs_rejects_reinclusion() {
+    let env = TestEnv::with_documents();
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[search]\nexclude_globs = [\"!manifest.json\"]\n"),
+    )
+    .unwrap();
 
     env.command()
-        .args(["add", "--title", "Test", "--category", "my category"])
-        .write_stdin("content")
+        .args(["search", "Lambda"])
         .assert()
         .failure()
-        .stderr(predicate::str::contains("invalid character"));
+        .stderr(predicate::str::contains(
+            "Invalid search.exclude_globs entry",
+        ));
 }
 
 #[test]
-fn tc_4_9_add_invalid_tag() {
-    let env = TestEnv::new();
+fn tc_2_23_search_order_title_across_corpora() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
 
-    env.command()
-        .args([
-            "add",
-            "--title",
-            "Test",
-            "--category",
-            "test",
-            "--tags",
-            "valid, in/valid",
-        ])
-        .write_stdin("content")
-        .assert()
-        .failure()
-        .stderr(predicate::str::contains("Tag").and(predicate::str::contains("invalid character")));
+    // Titles alternate between the corpora
+    let corpora = [
+        ("first", ["Bravo", "Delta"]),
+        ("second", ["Alpha", "Charlie"]),
+    ];
+    for (name, titles) in corpora {
+        let corpus = root.join(name);
+        fs::create_dir_all(corpus.join("notes")).unwrap();
+        let documents: Vec<String> = titles
+            .iter()
+            .map(|title| {
+                let path = format!("notes/{}.md", title.to_lowercase());
+                fs::write(corpus.join(&path), "shared keyword\n").unwrap();
+                format!(r#"{{"path":"{path}","title":"{title}","category":"notes","tags":[]}}"#)
+            })
+            .collect();
+        fs::write(
+            corpus.join("manifest.json"),
+            format!(r#"{{"version":"1","documents":[{}]}}"#, documents.join(",")),
+        )
+        .unwrap();
+    }
+
+    let config_path = root.join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[corpus]\npaths = [\"{}\", \"{}\"]\n",
+            root.join("first").display(),
+            root.join("second").display()
+        ),
+    )
+    .unwrap();
+
+    let titles = |args: &[&str]| -> Vec<String> {
+        let output = cargo_bin_cmd!("kvault")
+            .env("KVAULT_CONFIG", &config_path)
+            .args(["search", "shared", "--format", "csv"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        csv::Reader::from_reader(output.stdout.as_slice())
+            .records()
+            .map(|record| record.unwrap()[0].to_string())
+            .collect()
+    };
+
+    assert_eq!(
+        titles(&["--order", "title"]),
+        ["Alpha", "Bravo", "Charlie", "Delta"]
+    );
+    assert_eq!(titles(&[]), ["Bravo", "Delta", "Alpha", "Charlie"]);
+}
+
+/// Add an `aws/lambda` document nested under the `aws` category.
+fn add_nested_category_document(env: &TestEnv) {
+    fs::create_dir_all(env.corpus().join("aws/lambda")).unwrap();
+    fs::write(
+        env.corpus().join("aws/lambda/cold-starts.md"),
+        "# Cold Starts\n\nLambda functions start slowly after idling.",
+    )
+    .unwrap();
+    fs::write(
+        env.corpus().join("manifest.json"),
+        r#"{
+    "version": "1",
+    "documents": [
+        {"path": "rust/error-handling.md", "title": "Error Handling", "category": "rust", "tags": []},
+        {"path": "aws/lambda-patterns.md", "title": "Lambda Patterns", "category": "aws", "tags": []},
+        {"path": "aws/lambda/cold-starts.md", "title": "Cold Starts", "category": "aws/lambda", "tags": []}
+    ]
+}"#,
+    )
+    .unwrap();
 }
 
 #[test]
-fn tc_4_10_add_empty_content_stdin() {
-    let env = TestEnv::new();
+fn tc_2_24_search_category_prefix() {
+    let env = TestEnv::with_documents();
+    add_nested_category_document(&env);
 
     env.command()
-        .args(["add", "--title", "Empty", "--category", "test"])
-        .write_stdin("")
+        .args(["search", "lambda", "--category", "aws"])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Content cannot be empty"));
-}
-
-#[test]
-fn tc_4_11_add_whitespace_only_content() {
-    let env = TestEnv::new();
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("Cold Starts").not());
 
     env.command()
-        .args(["add", "--title", "Whitespace", "--category", "test"])
-        .write_stdin("   \n\t\n   ")
+        .args(["search", "lambda", "--category", "aws", "--category-prefix"])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Content cannot be empty"));
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("Cold Starts"));
 }
 
 #[test]
-fn tc_4_12_add_duplicate_document() {
-    let env = TestEnv::new();
+fn tc_2_25_search_no_footer_and_separator() {
+    let env = TestEnv::with_documents();
 
-    // First add should succeed
     env.command()
-        .args(["add", "--title", "Duplicate", "--category", "test"])
-        .write_stdin("content")
+        .args(["search", "Use", "--no-footer", "--separator", "-----"])
         .assert()
-        .success();
+        .success()
+        .stdout(predicate::str::contains("result(s) found").not())
+        .stdout(predicate::str::contains(
+            "  Use Result and Option types for error handling.\n-----\n",
+        ))
+        .stdout(predicate::str::contains("-----").count(1));
 
-    // Second add with same title/category should fail
     env.command()
-        .args(["add", "--title", "Duplicate", "--category", "test"])
-        .write_stdin("content")
+        .args(["search", "Use", "--no-header"])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("already exists"));
+        .success()
+        .stdout(predicate::str::contains("Error Handling").not())
+        .stdout(predicate::str::contains("2 result(s) found"));
 }
 
 #[test]
-fn tc_4_13_add_file_not_found() {
-    let env = TestEnv::new();
+fn tc_2_26_search_within_previous_results() {
+    let env = TestEnv::with_documents();
 
+    // "Use" appears in both documents
     env.command()
-        .args([
-            "add",
-            "--title",
-            "Test",
+        .args(["search", "Use"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 result(s) found"));
+
+    let output = env
+        .command()
+        .args(["search", "lambda", "--format", "paths"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let paths = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(paths.lines().count(), 1);
+    assert!(paths.contains("aws/lambda-patterns.md"));
+
+    let within = env.corpus().join("results.txt");
+    fs::write(&within, &paths).unwrap();
+
+    env.command()
+        .args(["search", "Use", "--within", within.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("Error Handling").not())
+        .stdout(predicate::str::contains("1 result(s) found"));
+
+    // Relative paths resolve against the working directory
+    fs::write(&within, "aws/lambda-patterns.md\n").unwrap();
+    env.command()
+        .current_dir(env.corpus())
+        .args(["search", "Use", "--within", within.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("1 result(s) found"));
+
+    // Nothing listed, nothing searched
+    fs::write(&within, "").unwrap();
+    env.command()
+        .args(["search", "Use", "--within", within.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No matches found"));
+}
+
+#[test]
+fn tc_2_27_search_normalize_query() {
+    let env = TestEnv::with_documents();
+    // Quoted, so the words must appear exactly as spaced
+    let query = "  \"AWS \u{a0}  Lambda\" ";
+
+    env.command()
+        .args(["search", query])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No matches found"));
+
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[search]\nnormalize_query = true\n"),
+    )
+    .unwrap();
+    env.command()
+        .args(["search", query])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"));
+}
+
+#[test]
+fn tc_2_28_search_export_to() {
+    let env = TestEnv::with_documents();
+    let dest = env.corpus().parent().unwrap().join("lambda");
+
+    // "Lambda" matches several lines of one document
+    env.command()
+        .args(["search", "Lambda", "--export-to", dest.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stderr(predicate::str::contains("Exported 1 documents"));
+
+    assert_eq!(
+        fs::read_to_string(dest.join("aws/lambda-patterns.md")).unwrap(),
+        fs::read_to_string(env.corpus().join("aws/lambda-patterns.md")).unwrap()
+    );
+    assert!(!dest.join("rust").exists());
+
+    // The export loads as a corpus of its own
+    fs::write(
+        &env.config_path,
+        format!("[corpus]\npaths = [\"{}\"]\n", dest.display()),
+    )
+    .unwrap();
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("aws: Lambda Patterns"))
+        .stdout(predicate::str::contains("[aws, lambda]"))
+        .stdout(predicate::str::contains("Error Handling").not());
+
+    // An existing export isn't overwritten
+    env.command()
+        .args(["search", "Lambda", "--export-to", dest.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not empty"));
+}
+
+#[cfg(feature = "ranked")]
+#[test]
+fn tc_2_29_search_auto_suggests_index_for_large_corpus() {
+    let env = TestEnv::with_documents();
+    let search = || {
+        env.command()
+            .args(["search", "Lambda", "--backend", "auto"])
+            .assert()
+    };
+
+    // Two documents is small: ripgrep, without comment
+    search()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stderr(predicate::str::contains("kvault index").not());
+
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[search]\nindex_hint_documents = 2\n"),
+    )
+    .unwrap();
+    search()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stderr(predicate::str::contains("has 2 documents and no index"))
+        .stderr(predicate::str::contains("kvault index"));
+
+    // Once indexed, auto uses the index and the note goes away
+    env.command().arg("index").assert().success();
+    search()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stderr(predicate::str::contains("kvault index").not());
+}
+
+#[test]
+fn tc_2_30_search_rejects_short_query() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["search", "a"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("shorter than 2 characters"))
+        .stderr(predicate::str::contains("--force"));
+
+    env.command()
+        .args(["search", "a", "--force"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"));
+
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[search]\nmin_query_length = 0\n"),
+    )
+    .unwrap();
+    env.command().args(["search", "a"]).assert().success();
+}
+
+#[test]
+fn tc_2_31_search_queries_from_stdin() {
+    let env = TestEnv::with_documents();
+    let queries = "Lambda\n\nnonexistent_xyz\n  Result  \n";
+
+    let output = env
+        .command()
+        .args(["search", "--queries-from-stdin"])
+        .write_stdin(queries)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let headers: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.starts_with("== "))
+        .collect();
+    assert_eq!(
+        headers,
+        ["== Lambda ==", "== nonexistent_xyz ==", "== Result =="]
+    );
+    let lambda = stdout.find("Lambda Patterns").unwrap();
+    let none = stdout
+        .find("No matches found for 'nonexistent_xyz'")
+        .unwrap();
+    let result = stdout.find("Error Handling").unwrap();
+    assert!(lambda < none && none < result);
+
+    let output = env
+        .command()
+        .args(["search", "--queries-from-stdin", "--format", "json"])
+        .write_stdin(queries)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let groups: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let groups = groups.as_array().unwrap();
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[0]["query"], "Lambda");
+    assert_eq!(groups[0]["results"][0]["title"], "Lambda Patterns");
+    assert_eq!(groups[1]["query"], "nonexistent_xyz");
+    assert_eq!(groups[1]["results"].as_array().unwrap().len(), 0);
+    assert_eq!(groups[2]["query"], "Result");
+    assert_eq!(groups[2]["results"][0]["category"], "rust");
+}
+
+#[test]
+fn tc_2_32_search_format_json() {
+    let env = TestEnv::with_documents();
+
+    let output = env
+        .command()
+        .args(["search", "Lambda", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let first = &results.as_array().unwrap()[0];
+    assert_eq!(first["title"], "Lambda Patterns");
+    assert_eq!(first["tags"], serde_json::json!(["aws", "lambda"]));
+    assert!(
+        first["snippet"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("Lambda")
+    );
+}
+
+#[test]
+fn tc_2_33_search_skips_ignored_corpus_paths() {
+    let env = TestEnv::with_documents();
+    let backup = env.corpus().join("aws/lambda-patterns.md.bak");
+    fs::write(&backup, "Lambda backup").unwrap();
+    fs::write(
+        env.corpus().join("aws/lambda-patterns.md~"),
+        "Lambda editor backup",
+    )
+    .unwrap();
+    fs::create_dir_all(env.corpus().join(".git")).unwrap();
+    fs::write(env.corpus().join(".git/notes.md"), "Lambda in git").unwrap();
+
+    env.command()
+        .args(["search", "Lambda"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("lambda-patterns.md"))
+        .stdout(predicate::str::contains(".bak").not())
+        .stdout(predicate::str::contains("md~").not())
+        .stdout(predicate::str::contains(".git").not());
+
+    // Paths named with --within skip ripgrep's globs but are still ignored
+    let within = env.corpus().join("results.txt");
+    fs::write(&within, format!("{}\n", backup.display())).unwrap();
+    env.command()
+        .args(["search", "Lambda", "--within", within.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No matches found"));
+}
+
+#[cfg(feature = "ranked")]
+#[test]
+fn tc_2_34_search_ranked_normalizes_scores_across_corpora() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let write_corpus = |name: &str, documents: &[(String, String)]| {
+        let corpus = root.join(name);
+        fs::create_dir_all(corpus.join("notes")).unwrap();
+        let entries: Vec<String> = documents
+            .iter()
+            .enumerate()
+            .map(|(i, (title, content))| {
+                let path = format!("notes/{i}.md");
+                fs::write(corpus.join(&path), content).unwrap();
+                format!(r#"{{"path":"{path}","title":"{title}","category":"notes","tags":[]}}"#)
+            })
+            .collect();
+        fs::write(
+            corpus.join("manifest.json"),
+            format!(r#"{{"version":"1","documents":[{}]}}"#, entries.join(",")),
+        )
+        .unwrap();
+    };
+
+    // In the large corpus "kafka" is rare, so its passing mentions get a
+    // high IDF; in the small one every document is about it
+    let filler = "notes about queues, storage, caching and deployment ".repeat(20);
+    let large: Vec<(String, String)> = (0..20)
+        .map(|i| {
+            let mention = if i < 3 { "kafka " } else { "" };
+            (format!("Large {i}"), format!("{mention}{filler}"))
+        })
+        .collect();
+    write_corpus("large", &large);
+    write_corpus(
+        "small",
+        &[
+            (
+                "Kafka Guide".to_string(),
+                "kafka kafka kafka: running kafka brokers and kafka topics".to_string(),
+            ),
+            ("Kafka Aside".to_string(), format!("kafka {filler}")),
+        ],
+    );
+
+    let config_path = root.join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[corpus]\npaths = [\"{}\", \"{}\"]\n",
+            root.join("large").display(),
+            root.join("small").display()
+        ),
+    )
+    .unwrap();
+    let kvault = || {
+        let mut cmd = cargo_bin_cmd!("kvault");
+        cmd.env("KVAULT_CONFIG", &config_path);
+        cmd
+    };
+    kvault().arg("index").assert().success();
+
+    let output = kvault()
+        .args(["search", "kafka", "--backend", "ranked", "--format", "csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let titles: Vec<String> = csv::Reader::from_reader(output.stdout.as_slice())
+        .records()
+        .map(|record| record.unwrap()[0].to_string())
+        .collect();
+    assert_eq!(titles.len(), 5);
+    let guide = titles.iter().position(|t| t == "Kafka Guide").unwrap();
+    assert!(guide < 2, "Kafka Guide buried: {titles:?}");
+}
+
+#[test]
+fn tc_2_35_search_categorizes_unlisted_files_by_path() {
+    let env = TestEnv::with_documents();
+    fs::write(env.corpus().join("aws/scratch.txt"), "Lambda scratch notes").unwrap();
+    let categories = |args: &[&str]| -> Vec<(String, String)> {
+        let output = env
+            .command()
+            .args(["search", "Lambda", "--format", "csv"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let mut found: Vec<(String, String)> = csv::Reader::from_reader(output.stdout.as_slice())
+            .records()
+            .map(|record| {
+                let record = record.unwrap();
+                let file = Path::new(&record[3]).file_name().unwrap();
+                (file.to_string_lossy().into_owned(), record[1].to_string())
+            })
+            .collect();
+        found.dedup();
+        found
+    };
+
+    // Unlisted files take their first directory as category, and category
+    // filters match them like manifest documents
+    assert_eq!(
+        categories(&["-c", "aws"]),
+        [
+            ("lambda-patterns.md".to_string(), "aws".to_string()),
+            ("scratch.txt".to_string(), "aws".to_string()),
+        ]
+    );
+
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[[corpus.category_rules]]\nprefix = \"aws\"\ncategory = \"cloud\"\n"),
+    )
+    .unwrap();
+    // Rules apply to unlisted files; the manifest still decides for listed ones
+    assert_eq!(
+        categories(&["-c", "cloud"]),
+        [("scratch.txt".to_string(), "cloud".to_string())]
+    );
+}
+
+#[test]
+fn tc_2_36_search_defaults_from_config() {
+    let env = TestEnv::with_documents();
+    let titles = |args: &[&str]| {
+        let output = env.command().args(args).output().unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        ["Error Handling", "Lambda Patterns"]
+            .into_iter()
+            .filter(|title| stdout.contains(title))
+            .count()
+    };
+
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[search]\ndefault_limit = 1\n"),
+    )
+    .unwrap();
+    assert_eq!(titles(&["search", "Use"]), 1);
+    assert_eq!(titles(&["search", "Use", "--limit", "5"]), 2);
+
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[search]\ndefault_category = \"rust\"\n"),
+    )
+    .unwrap();
+    env.command()
+        .args(["search", "Use"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"))
+        .stdout(predicate::str::contains("Lambda Patterns").not());
+    env.command()
+        .args(["search", "Use", "--category", "aws"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("Error Handling").not());
+    assert_eq!(titles(&["search", "Use", "--all-categories"]), 2);
+    env.command()
+        .args(["search", "Use", "--all-categories", "--category", "aws"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn tc_2_37_search_words_anywhere_or_quoted_phrase() {
+    let env = TestEnv::with_documents();
+
+    // The words are on different lines of the Rust document, and only
+    // "Result" is in the AWS one
+    env.command()
+        .args(["search", "Result elegantly"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"))
+        .stdout(predicate::str::contains("Lambda Patterns").not());
+    env.command()
+        .args(["search", "Result lambda"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No matches found"));
+
+    env.command()
+        .args(["search", "\"Result elegantly\""])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No matches found"));
+    env.command()
+        .args(["search", "\"propagates errors\""])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"));
+}
+
+#[test]
+fn tc_2_38_search_ranks_ripgrep_results_by_score() {
+    let env = TestEnv::with_documents();
+    fs::write(
+        env.corpus().join("rust/error-handling.md"),
+        "# Error Handling in Rust\n\nWrap errors with context.\nMap errors at module boundaries.\n",
+    )
+    .unwrap();
+    fs::write(
+        env.corpus().join("aws/lambda-patterns.md"),
+        "Retry on errors.\n",
+    )
+    .unwrap();
+
+    let output = env
+        .command()
+        .args(["search", "errors", "--rank", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let ranked: Vec<(&str, u64, f64)> = results
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["title"].as_str().unwrap(),
+                r["snippet"]["line"].as_u64().unwrap(),
+                r["score"].as_f64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        ranked,
+        [
+            ("Error Handling", 3, 2.0),
+            ("Error Handling", 4, 2.0),
+            ("Lambda Patterns", 1, 1.0),
+        ]
+    );
+}
+
+#[test]
+fn tc_2_39_search_group_by_document() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["search", "lambda", "--group"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "lambda-patterns.md (line 1) (2 hit(s))",
+        ))
+        .stdout(predicate::str::contains("(line 3)").not());
+
+    let output = env
+        .command()
+        .args(["search", "lambda", "--group", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(results.as_array().unwrap().len(), 1);
+    assert_eq!(results[0]["hit_count"], 2);
+
+    env.command()
+        .args(["search", "lambda", "--group", "--max-per-file", "2"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn tc_2_40_search_tag_filter() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["search", "use", "--tag", "errors"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("error-handling.md"))
+        .stdout(predicate::str::contains("lambda-patterns.md").not());
+
+    env.command()
+        .args(["search", "use", "-t", "AWS", "-t", "lambda"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("lambda-patterns.md"))
+        .stdout(predicate::str::contains("error-handling.md").not());
+
+    env.command()
+        .args(["search", "use", "--tag", "aws", "--tag", "errors"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No matches found"));
+}
+
+#[test]
+fn tc_2_41_search_queries_from_stdin_reports_failed_queries() {
+    let env = TestEnv::with_documents();
+    let queries = "Lambda\nx\nResult\n";
+
+    let output = env
+        .command()
+        .args(["search", "--queries-from-stdin"])
+        .write_stdin(queries)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Lambda Patterns"));
+    assert!(stdout.contains("== x =="));
+    assert!(stdout.contains("Error Handling"));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("'x' is shorter than"));
+    assert!(stderr.contains("1 of 3 queries failed"));
+
+    let output = env
+        .command()
+        .args(["search", "--queries-from-stdin", "--format", "json"])
+        .write_stdin(queries)
+        .output()
+        .unwrap();
+    let groups: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(groups[0]["results"][0]["title"], "Lambda Patterns");
+    assert!(groups[0].get("error").is_none());
+    assert!(
+        groups[1]["error"]
+            .as_str()
+            .unwrap()
+            .contains("is shorter than")
+    );
+    assert_eq!(groups[2]["results"][0]["category"], "rust");
+}
+
+#[cfg(feature = "ranked")]
+#[test]
+fn tc_2_42_search_suggest_needs_index_backend() {
+    let env = TestEnv::with_documents();
+    env.command().arg("index").assert().success();
+
+    env.command()
+        .args(["search", "lamda", "--suggest", "--backend", "ranked"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Did you mean 'lambda'?"));
+    env.command()
+        .args(["search", "lamda", "--suggest"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "ripgrep does not support --suggest",
+        ));
+}
+
+#[test]
+fn tc_2_43_search_include_metadata_matches_tags() {
+    let env = TestEnv::with_documents();
+    let manifest_path = env.corpus().join("manifest.json");
+    let manifest = fs::read_to_string(&manifest_path).unwrap();
+    fs::write(
+        &manifest_path,
+        manifest.replace(r#"["aws", "lambda"]"#, r#"["aws", "lambda", "serverless"]"#),
+    )
+    .unwrap();
+
+    env.command()
+        .args(["search", "serverless"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No matches found"));
+    env.command()
+        .args(["search", "serverless", "--include-metadata"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "lambda-patterns.md (title or tags)",
+        ))
+        .stdout(predicate::str::contains("tags: aws, lambda, serverless"));
+
+    #[cfg(feature = "ranked")]
+    {
+        env.command().arg("index").assert().success();
+        for backend in ["ranked", "auto"] {
+            env.command()
+                .args([
+                    "search",
+                    "serverless",
+                    "--include-metadata",
+                    "--backend",
+                    backend,
+                ])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(
+                    "ranked does not support --include-metadata",
+                ));
+        }
+    }
+}
+
+#[cfg(feature = "ranked")]
+#[test]
+fn tc_2_44_search_ripgrep_result_options_rejected_by_index() {
+    let env = TestEnv::with_documents();
+    env.command().arg("index").assert().success();
+
+    let cases: [(&[&str], &str); 3] = [
+        (
+            &["--max-per-file", "1"],
+            "does not support limiting results per file",
+        ),
+        (
+            &["--group"],
+            "does not support grouping matches by document",
+        ),
+        (&["--rank"], "does not support ranking matches"),
+    ];
+    for (flags, message) in cases {
+        for backend in ["ranked", "auto"] {
+            env.command()
+                .args(["search", "lambda", "--backend", backend])
+                .args(flags)
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains(format!("ranked {message}")));
+        }
+        env.command()
+            .args(["search", "lambda", "--backend", "ripgrep"])
+            .args(flags)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Lambda Patterns"));
+    }
+}
+
+// =============================================================================
+// 3. List Command Tests
+// =============================================================================
+
+#[test]
+fn tc_3_1_list_all_documents() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rust: Error Handling"))
+        .stdout(predicate::str::contains("aws: Lambda Patterns"))
+        .stdout(predicate::str::contains("[rust, errors]"))
+        .stdout(predicate::str::contains("[aws, lambda]"));
+}
+
+#[test]
+fn tc_3_2_list_no_documents() {
+    let env = TestEnv::new();
+
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No documents found"));
+}
+
+#[test]
+fn tc_3_3_list_with_category_filter_matching() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["list", "--category", "rust"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"))
+        .stdout(predicate::str::contains("Lambda Patterns").not());
+}
+
+#[test]
+fn tc_3_4_list_with_category_filter_non_matching() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["list", "--category", "nonexistent"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No documents found"));
+}
+
+#[test]
+fn tc_3_5_list_document_without_tags() {
+    let env = TestEnv::new();
+
+    // Create document without tags
+    fs::create_dir_all(env.corpus().join("test")).unwrap();
+    fs::write(env.corpus().join("test/doc.md"), "# Test\n\nContent").unwrap();
+
+    let manifest = r#"{
+    "version": "1",
+    "documents": [
+        {"path": "test/doc.md", "title": "Test Doc", "category": "test", "tags": []}
+    ]
+}"#;
+    fs::write(env.corpus().join("manifest.json"), manifest).unwrap();
+
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("test: Test Doc"))
+        // Should not have empty brackets
+        .stdout(predicate::str::contains("[]").not());
+}
+
+#[test]
+fn tc_3_6_list_word_count() {
+    let env = TestEnv::new();
+    let content = "# Counting\n\nOne two  three\nfour\tfive.";
+
+    env.command()
+        .args(["add", "--title", "Counting", "--category", "notes"])
+        .write_stdin(content)
+        .assert()
+        .success();
+
+    let expected = content.split_whitespace().count();
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(manifest["documents"][0]["word_count"], expected);
+
+    env.command()
+        .args(["list", "--word-count"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Counting ({expected} words)"
+        )));
+
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("words").not());
+}
+
+#[test]
+fn tc_3_7_list_word_count_computed_when_missing() {
+    // Fixture manifest has no cached word counts
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["list", "--word-count", "--category", "rust"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Error Handling [rust, errors] (19 words)",
+        ));
+}
+
+#[test]
+fn tc_3_8_list_json_schema() {
+    let env = TestEnv::new();
+
+    let output = env
+        .command()
+        .args(["list", "--json-schema"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let required = schema["required"].as_array().unwrap();
+    assert!(required.contains(&"version".into()));
+    assert!(schema["properties"]["documents"].is_object());
+
+    let document = &schema["$defs"]["Document"];
+    for field in ["path", "title", "category", "tags"] {
+        assert!(
+            document["properties"][field].is_object(),
+            "missing Document.{field}"
+        );
+    }
+    let required = document["required"].as_array().unwrap();
+    assert!(required.contains(&"path".into()));
+    assert!(!required.contains(&"tags".into()));
+}
+
+#[test]
+fn tc_3_9_list_fail_on_empty() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args(["list", "--fail-on-empty"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("No documents found"));
+
+    env.command().arg("list").assert().code(0);
+
+    let env = TestEnv::with_documents();
+    env.command()
+        .args(["list", "--fail-on-empty"])
+        .assert()
+        .code(0);
+}
+
+#[test]
+fn tc_3_10_list_category_prefix() {
+    let env = TestEnv::with_documents();
+    add_nested_category_document(&env);
+
+    env.command()
+        .args(["list", "--category", "aws", "--category-prefix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("Cold Starts"))
+        .stdout(predicate::str::contains("Error Handling").not());
+
+    // The prefix matches whole category segments only
+    env.command()
+        .args(["list", "--category", "aw", "--category-prefix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No documents found"));
+}
+
+#[test]
+fn tc_3_11_list_limit_and_offset() {
+    let env = TestEnv::new();
+    fs::create_dir_all(env.corpus().join("notes")).unwrap();
+    let documents: Vec<String> = (1..=25)
+        .map(|n| {
+            fs::write(env.corpus().join(format!("notes/note-{n:02}.md")), "Body").unwrap();
+            format!(
+                r#"{{"path": "notes/note-{n:02}.md", "title": "Note {n:02}", "category": "notes", "tags": []}}"#
+            )
+        })
+        .collect();
+    fs::write(
+        env.corpus().join("manifest.json"),
+        format!(
+            r#"{{"version": "1", "documents": [{}]}}"#,
+            documents.join(",")
+        ),
+    )
+    .unwrap();
+
+    let output = env
+        .command()
+        .args(["list", "--limit", "5", "--offset", "10"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let titles: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("notes: "))
+        .collect();
+    assert_eq!(
+        titles,
+        vec!["Note 11", "Note 12", "Note 13", "Note 14", "Note 15"]
+    );
+    assert!(stdout.ends_with("Showing 11-15 of 25\n"));
+
+    // The last page is short, and a page past the end is empty
+    env.command()
+        .args(["list", "--limit", "10", "--offset", "20"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Note 25"))
+        .stdout(predicate::str::contains("Note 20").not())
+        .stdout(predicate::str::contains("Showing 21-25 of 25"));
+    env.command()
+        .args(["list", "--offset", "30", "--fail-on-empty"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("Showing none of 25"));
+}
+
+#[test]
+fn tc_3_12_list_broken() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["list", "--broken", "--fail-on-empty"])
+        .assert()
+        .code(3);
+
+    fs::remove_file(env.corpus().join("aws/lambda-patterns.md")).unwrap();
+    env.command()
+        .args(["list", "--broken"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("Error Handling").not());
+
+    let output = env
+        .command()
+        .args(["list", "--broken", "--format", "json"])
+        .output()
+        .unwrap();
+    let documents: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(documents.as_array().unwrap().len(), 1);
+    assert_eq!(documents[0]["title"], "Lambda Patterns");
+}
+
+#[test]
+fn tc_3_13_list_default_category_from_config() {
+    let env = TestEnv::with_documents();
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[list]\ndefault_category = \"aws\"\n"),
+    )
+    .unwrap();
+
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("Error Handling").not());
+    env.command()
+        .args(["list", "--category", "rust"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"))
+        .stdout(predicate::str::contains("Lambda Patterns").not());
+    env.command()
+        .args(["list", "--all-categories"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"))
+        .stdout(predicate::str::contains("Lambda Patterns"));
+}
+
+#[test]
+fn tc_3_14_list_tag_filter() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["list", "--tag", "lambda"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("Error Handling").not());
+
+    env.command()
+        .args(["list", "--tag", "rust", "--tag", "Errors"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"))
+        .stdout(predicate::str::contains("Lambda Patterns").not());
+
+    env.command()
+        .args([
+            "list",
+            "--tag",
+            "rust",
+            "--tag",
+            "lambda",
+            "--fail-on-empty",
+        ])
+        .assert()
+        .code(3);
+}
+
+// =============================================================================
+// 4. Add Command Tests
+// =============================================================================
+
+#[test]
+fn tc_4_1_add_document_from_stdin() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args(["add", "--title", "Test Doc", "--category", "test"])
+        .write_stdin("# Test Document\n\nThis is test content.")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added: Test Doc"))
+        .stdout(predicate::str::contains("Category: test"));
+
+    // Verify file was created
+    assert!(env.corpus().join("test/test-doc.md").exists());
+}
+
+#[test]
+fn tc_4_2_add_document_from_file() {
+    let env = TestEnv::new();
+    let input_file = env.corpus().join("input.md");
+    fs::write(&input_file, "# From File\n\nContent from file.").unwrap();
+
+    env.command()
+        .args([
+            "add",
+            "--title",
+            "From File",
+            "--category",
+            "test",
+            "--file",
+        ])
+        .arg(&input_file)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added: From File"));
+}
+
+#[test]
+fn tc_4_3_add_document_with_tags() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args([
+            "add",
+            "--title",
+            "Tagged",
+            "--category",
+            "test",
+            "--tags",
+            "tag1, tag2, tag3",
+        ])
+        .write_stdin("# Tagged\n\nContent")
+        .assert()
+        .success();
+
+    // Verify tags in manifest
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    assert!(manifest.contains("tag1"));
+    assert!(manifest.contains("tag2"));
+    assert!(manifest.contains("tag3"));
+}
+
+#[test]
+fn tc_4_4_add_empty_title() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args(["add", "--title", "", "--category", "test"])
+        .write_stdin("content")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Title cannot be empty"));
+}
+
+#[test]
+fn tc_4_5_add_title_too_long() {
+    let env = TestEnv::new();
+    let long_title = "a".repeat(201);
+
+    env.command()
+        .args(["add", "--title", &long_title, "--category", "test"])
+        .write_stdin("content")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Title too long"));
+}
+
+#[test]
+fn tc_4_6_add_invalid_category_slash() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args(["add", "--title", "Test", "--category", "my/category"])
+        .write_stdin("content")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid character"));
+}
+
+#[test]
+fn tc_4_7_add_category_starting_with_hyphen() {
+    let env = TestEnv::new();
+
+    // Use --category=-invalid syntax to prevent clap from treating -invalid as a flag
+    env.command()
+        .args(["add", "--title", "Test", "--category=-invalid"])
+        .write_stdin("content")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("must start with"));
+}
+
+#[test]
+fn tc_4_8_add_category_with_spaces() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args(["add", "--title", "Test", "--category", "my category"])
+        .write_stdin("content")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid character"));
+}
+
+#[test]
+fn tc_4_9_add_invalid_tag() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args([
+            "add",
+            "--title",
+            "Test",
+            "--category",
+            "test",
+            "--tags",
+            "valid, in/valid",
+        ])
+        .write_stdin("content")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Tag").and(predicate::str::contains("invalid character")));
+}
+
+#[test]
+fn tc_4_10_add_empty_content_stdin() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args(["add", "--title", "Empty", "--category", "test"])
+        .write_stdin("")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Content cannot be empty"));
+}
+
+#[test]
+fn tc_4_11_add_whitespace_only_content() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args(["add", "--title", "Whitespace", "--category", "test"])
+        .write_stdin("   \n\t\n   ")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Content cannot be empty"));
+}
+
+#[test]
+fn tc_4_12_add_duplicate_document() {
+    let env = TestEnv::new();
+
+    // First add should succeed
+    env.command()
+        .args(["add", "--title", "Duplicate", "--category", "test"])
+        .write_stdin("content")
+        .assert()
+        .success();
+
+    // Second add with same title/category should fail
+    env.command()
+        .args(["add", "--title", "Duplicate", "--category", "test"])
+        .write_stdin("content")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+}
+
+#[test]
+fn tc_4_13_add_file_not_found() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args([
+            "add",
+            "--title",
+            "Test",
+            "--category",
+            "test",
+            "--file",
+            "/nonexistent/path.md",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read file"));
+}
+
+#[test]
+fn tc_4_14_add_slugify_special_chars() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args([
+            "add",
+            "--title",
+            "AWS Lambda: Best Practices!",
+            "--category",
+            "aws",
+        ])
+        .write_stdin("content")
+        .assert()
+        .success();
+
+    // Verify slug was created correctly
+    assert!(
+        env.corpus()
+            .join("aws/aws-lambda-best-practices.md")
+            .exists()
+    );
+}
+
+#[test]
+fn tc_4_15_add_tags_with_whitespace() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args([
+            "add",
+            "--title",
+            "Test",
+            "--category",
+            "test",
+            "--tags",
+            "  tag1  ,  tag2  ",
+        ])
+        .write_stdin("content")
+        .assert()
+        .success();
+
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    assert!(manifest.contains("\"tag1\""));
+    assert!(manifest.contains("\"tag2\""));
+    // Should not have extra whitespace
+    assert!(!manifest.contains("\"  tag1  \""));
+}
+
+#[test]
+fn tc_4_16_add_tags_empty_entries_filtered() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args([
+            "add",
+            "--title",
+            "Test",
+            "--category",
+            "test",
+            "--tags",
+            "tag1,,tag2,",
+        ])
+        .write_stdin("content")
+        .assert()
+        .success();
+
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    assert!(manifest.contains("\"tag1\""));
+    assert!(manifest.contains("\"tag2\""));
+}
+
+#[test]
+fn tc_4_17_add_category_path_traversal() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args(["add", "--title", "Test", "--category", ".."])
+        .write_stdin("content")
+        .assert()
+        .failure()
+        .stderr(
+            predicate::str::contains("invalid character")
+                .or(predicate::str::contains("must start with")),
+        );
+}
+
+#[cfg(feature = "crypto")]
+#[test]
+fn tc_4_18_add_encrypted_document_round_trip() {
+    let env = TestEnv::new();
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        config.replace("[corpus]", "[corpus]\nencrypted = true"),
+    )
+    .unwrap();
+
+    env.command()
+        .env("KVAULT_PASSPHRASE", "correct horse")
+        .args(["add", "--title", "Secret", "--category", "private"])
+        .write_stdin("# Secret\n\nLaunch codes.")
+        .assert()
+        .success();
+
+    // Body is not stored in plaintext; manifest marks the document
+    let on_disk = fs::read(env.corpus().join("private/secret.md")).unwrap();
+    assert!(!String::from_utf8_lossy(&on_disk).contains("Launch codes"));
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    assert!(manifest.contains("\"encrypted\": true"));
+    assert!(manifest.contains("encryption_salt"));
+
+    env.command()
+        .env("KVAULT_PASSPHRASE", "correct horse")
+        .args(["get", "private/secret.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Launch codes."));
+
+    env.command()
+        .env_remove("KVAULT_PASSPHRASE")
+        .args(["get", "private/secret.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("KVAULT_PASSPHRASE"));
+
+    // A wrong passphrase can't add documents under another key
+    env.command()
+        .env("KVAULT_PASSPHRASE", "battery staple")
+        .args(["add", "--title", "Other", "--category", "private"])
+        .write_stdin("More codes.")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("does not match"));
+    assert!(!env.corpus().join("private/other.md").exists());
+}
+
+#[test]
+fn tc_4_19_add_from_template() {
+    let env = TestEnv::new();
+    let template_path = env.corpus().join("../meeting.md");
+    fs::write(
+        &template_path,
+        "# {{title}}\n\nDate: {{date}}\n\n## Notes\n",
+    )
+    .unwrap();
+
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    let config = format!(
+        "{config}\n[templates]\nmeeting = \"{}\"\n",
+        template_path.display()
+    );
+    fs::write(&env.config_path, config).unwrap();
+
+    env.command()
+        .args([
+            "add",
+            "--title",
+            "Weekly Sync",
+            "--category",
+            "meetings",
+            "--template",
+            "meeting",
+        ])
+        .assert()
+        .success();
+
+    let content = fs::read_to_string(env.corpus().join("meetings/weekly-sync.md")).unwrap();
+    assert!(content.starts_with("# Weekly Sync\n"));
+    assert!(!content.contains("{{"));
+}
+
+#[test]
+fn tc_4_20_add_unknown_template() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args([
+            "add",
+            "--title",
+            "T",
+            "--category",
+            "c",
+            "--template",
+            "nope",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown template: nope"));
+}
+
+#[test]
+fn tc_4_21_add_with_jsonl_manifest() {
+    let env = TestEnv::with_documents();
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[storage]\nmanifest_format = \"jsonl\"\n"),
+    )
+    .unwrap();
+
+    // First add converts the existing manifest.json
+    env.command()
+        .args(["add", "--title", "First", "--category", "notes"])
+        .write_stdin("first body")
+        .assert()
+        .success();
+
+    assert!(!env.corpus().join("manifest.json").exists());
+    let before = fs::read_to_string(env.corpus().join("manifest.jsonl")).unwrap();
+    assert_eq!(before.lines().count(), 4);
+
+    // Later adds append a single line
+    env.command()
+        .args(["add", "--title", "Second", "--category", "notes"])
+        .write_stdin("second body")
+        .assert()
+        .success();
+
+    let after = fs::read_to_string(env.corpus().join("manifest.jsonl")).unwrap();
+    assert!(after.starts_with(&before));
+    assert!(after.lines().last().unwrap().contains("notes/second.md"));
+
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"))
+        .stdout(predicate::str::contains("Second"));
+
+    env.command()
+        .args(["search", "second body"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Second"))
+        .stdout(predicate::str::contains("manifest.jsonl").not());
+}
+
+#[test]
+fn tc_4_22_add_case_insensitive_category_collision() {
+    let env = TestEnv::with_documents();
+
+    // Only meaningful where the filesystem folds case (macOS, Windows)
+    fs::write(env.corpus().join("case-probe"), "").unwrap();
+    let case_insensitive = env.corpus().join("CASE-PROBE").exists();
+    fs::remove_file(env.corpus().join("case-probe")).unwrap();
+    if !case_insensitive {
+        return;
+    }
+
+    env.command()
+        .args(["add", "--title", "S3 Notes", "--category", "AWS"])
+        .write_stdin("bucket notes")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Category 'AWS' collides with existing category 'aws'",
+        ));
+}
+
+#[test]
+fn tc_4_23_add_stdin_json() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["add", "--stdin-json"])
+        .write_stdin(
+            r#"{"title": "Step Functions", "category": "aws", "tags": ["workflow", " serverless "], "content": "State machines."}"#,
+        )
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added: Step Functions"))
+        .stdout(predicate::str::contains("aws/step-functions.md"));
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(env.corpus().join("manifest.json")).unwrap())
+            .unwrap();
+    let doc = manifest["documents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|doc| doc["path"] == "aws/step-functions.md")
+        .expect("document in manifest");
+    assert_eq!(doc["title"], "Step Functions");
+    assert_eq!(doc["category"], "aws");
+    assert_eq!(doc["tags"], serde_json::json!(["workflow", "serverless"]));
+    assert_eq!(
+        fs::read_to_string(env.corpus().join("aws/step-functions.md")).unwrap(),
+        "State machines."
+    );
+
+    // Fields are validated like the flag-based form
+    env.command()
+        .args(["add", "--stdin-json"])
+        .write_stdin(r#"{"title": "Bad", "category": "../etc", "content": "x"}"#)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Category"));
+
+    env.command()
+        .args(["add", "--stdin-json"])
+        .write_stdin(r#"{"title": "Missing content", "category": "aws"}"#)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid document JSON"));
+
+    env.command()
+        .args(["add", "--stdin-json", "--title", "Both"])
+        .write_stdin("{}")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn tc_4_24_add_duplicate_title_in_category() {
+    let env = TestEnv::with_documents();
+
+    // Same title as an existing document stored under a different file name
+    fs::rename(
+        env.corpus().join("aws/lambda-patterns.md"),
+        env.corpus().join("aws/lambda.md"),
+    )
+    .unwrap();
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    fs::write(
+        env.corpus().join("manifest.json"),
+        manifest.replace("aws/lambda-patterns.md", "aws/lambda.md"),
+    )
+    .unwrap();
+
+    env.command()
+        .args(["add", "--title", "Lambda Patterns", "--category", "aws"])
+        .arg("--no-duplicate-titles")
+        .write_stdin("Second copy.")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "A document titled 'Lambda Patterns' already exists in category 'aws': aws/lambda.md",
+        ));
+    assert!(!env.corpus().join("aws/lambda-patterns.md").exists());
+
+    env.command()
+        .args(["add", "--title", "Lambda Patterns", "--category", "aws"])
+        .write_stdin("Second copy.")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Warning: A document titled 'Lambda Patterns' already exists in category 'aws': aws/lambda.md",
+        ))
+        .stdout(predicate::str::contains("aws/lambda-patterns.md"));
+
+    // The same title in another category is not a duplicate
+    env.command()
+        .args(["add", "--title", "Lambda Patterns", "--category", "notes"])
+        .write_stdin("Different category.")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Warning").not());
+}
+
+#[cfg(feature = "ranked")]
+#[test]
+fn tc_4_25_add_auto_index_on_add() {
+    let env = TestEnv::with_documents();
+    env.command().arg("index").assert().success();
+
+    // Off by default: the index is stale until the next `kvault index`
+    env.command()
+        .args(["add", "--title", "Step Functions", "--category", "aws"])
+        .write_stdin("Orchestrate workflows with state machines.")
+        .assert()
+        .success();
+    env.command()
+        .args(["search", "workflows", "--backend", "ranked"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Step Functions").not());
+
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[search]\nauto_index_on_add = true\n"),
+    )
+    .unwrap();
+
+    env.command()
+        .args(["add", "--title", "EventBridge Rules", "--category", "aws"])
+        .write_stdin("Route events between services on a schedule.")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Warning").not());
+    env.command()
+        .args(["search", "schedule", "--backend", "ranked"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("EventBridge Rules"));
+
+    // Documents already in the index are untouched
+    env.command()
+        .args(["search", "lambda", "--backend", "ranked"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"));
+}
+
+#[test]
+fn tc_4_26_add_selects_corpus() {
+    let env = TestEnv::with_documents();
+    let notes = env.corpus().parent().unwrap().join("notes");
+    fs::create_dir_all(&notes).unwrap();
+    fs::write(
+        notes.join("manifest.json"),
+        r#"{"version": "1", "documents": []}"#,
+    )
+    .unwrap();
+    fs::write(
+        &env.config_path,
+        format!(
+            "[corpus]\npaths = [\"{}\", \"{}\"]\n",
+            env.corpus().display(),
+            notes.display()
+        ),
+    )
+    .unwrap();
+    let add = |corpus: Option<&str>| {
+        let mut cmd = env.command();
+        cmd.args(["add", "--title", "Standup", "--category", "meetings"]);
+        if let Some(corpus) = corpus {
+            cmd.args(["--corpus", corpus]);
+        }
+        cmd.write_stdin("Daily notes.");
+        cmd.assert()
+    };
+
+    // With several corpora, the target must be explicit
+    add(None)
+        .failure()
+        .stderr(predicate::str::contains("Several corpora are configured"));
+    add(Some("elsewhere"))
+        .failure()
+        .stderr(predicate::str::contains("Not a configured corpus"));
+    assert!(!notes.join("meetings").exists());
+    assert!(!env.corpus().join("meetings").exists());
+
+    // By directory name
+    add(Some("notes")).success();
+    assert!(notes.join("meetings/standup.md").exists());
+    assert!(!env.corpus().join("meetings").exists());
+
+    // By path
+    add(Some(env.corpus().to_str().unwrap())).success();
+    assert!(env.corpus().join("meetings/standup.md").exists());
+}
+
+#[test]
+fn tc_4_27_add_manifest_only() {
+    let env = TestEnv::with_documents();
+    let add = || {
+        let mut cmd = env.command();
+        cmd.args([
+            "add",
+            "--title",
+            "Existing Note",
             "--category",
-            "test",
-            "--file",
-            "/nonexistent/path.md",
+            "notes",
+            "--manifest-only",
+        ]);
+        cmd.assert()
+    };
+
+    // The file must already exist
+    add()
+        .failure()
+        .stderr(predicate::str::contains("File not found"));
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    assert!(!manifest.contains("Existing Note"));
+
+    let file = env.corpus().join("notes/existing-note.md");
+    fs::create_dir_all(file.parent().unwrap()).unwrap();
+    let content = "# Existing Note\n\nWritten by hand.\n";
+    fs::write(&file, content).unwrap();
+
+    add()
+        .success()
+        .stdout(predicate::str::contains("Added: Existing Note"));
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    assert!(manifest.contains("Existing Note"));
+    assert!(manifest.contains("notes/existing-note.md"));
+    assert_eq!(fs::read_to_string(&file).unwrap(), content);
+
+    // A second run would duplicate the entry
+    add()
+        .failure()
+        .stderr(predicate::str::contains("already in manifest"));
+}
+
+#[test]
+fn tc_4_28_add_stdin_jsonl_is_all_or_nothing() {
+    let env = TestEnv::with_documents();
+    let manifest_path = env.corpus().join("manifest.json");
+    let manifest = fs::read_to_string(&manifest_path).unwrap();
+    let add = |jsonl: &str| {
+        env.command()
+            .args(["add", "--stdin-jsonl"])
+            .write_stdin(jsonl)
+            .assert()
+    };
+    let first = r#"{"title": "First Note", "category": "journal", "content": "One."}"#;
+
+    // Validation fails on the second record before anything is written
+    add(&format!(
+        "{first}\n{}\n",
+        r#"{"title": "Second", "category": "bad category", "content": "Two."}"#
+    ))
+    .failure()
+    .stderr(predicate::str::contains(
+        "Document 2: Category contains invalid",
+    ));
+    assert!(!env.corpus().join("journal").exists());
+    assert_eq!(fs::read_to_string(&manifest_path).unwrap(), manifest);
+
+    // Writing the second document fails after the first was written
+    fs::write(env.corpus().join("blocked"), "not a directory").unwrap();
+    add(&format!(
+        "{first}\n{}\n",
+        r#"{"title": "Second", "category": "blocked", "content": "Two."}"#
+    ))
+    .failure();
+    assert!(!env.corpus().join("journal").exists());
+    assert_eq!(fs::read_to_string(&manifest_path).unwrap(), manifest);
+
+    add(&format!(
+        "{first}\n\n{}\n",
+        r#"{"title": "Second Note", "category": "journal", "tags": ["daily"], "content": "Two."}"#
+    ))
+    .success()
+    .stdout(predicate::str::contains("Added: First Note"))
+    .stdout(predicate::str::contains("Added: Second Note"));
+    assert_eq!(
+        fs::read_to_string(env.corpus().join("journal/first-note.md")).unwrap(),
+        "One."
+    );
+    assert!(env.corpus().join("journal/second-note.md").exists());
+    let entries: Vec<_> = fs::read_dir(env.corpus().join("journal"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(entries.len(), 2, "temporary files left behind: {entries:?}");
+
+    env.command()
+        .args(["list", "--category", "journal"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("journal: First Note"))
+        .stdout(predicate::str::contains("journal: Second Note"));
+}
+
+#[test]
+fn tc_4_29_add_date_layout() {
+    let env = TestEnv::new();
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(&env.config_path, format!("{config}layout = \"date\"\n")).unwrap();
+
+    env.command()
+        .args(["add", "--title", "Journal Entry", "--category", "journal"])
+        .write_stdin("Today I wrote tests.")
+        .assert()
+        .success();
+
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    let doc = &manifest["documents"][0];
+    assert_eq!(doc["category"], "journal");
+
+    // YYYY/MM/DD/journal-entry.md
+    let path = doc["path"].as_str().unwrap();
+    let parts: Vec<&str> = path.split('/').collect();
+    assert_eq!(
+        parts.iter().map(|part| part.len()).collect::<Vec<_>>(),
+        [4, 2, 2, "journal-entry.md".len()],
+        "unexpected path {path}"
+    );
+    assert!(
+        parts[..3]
+            .iter()
+            .all(|part| part.chars().all(|c| c.is_ascii_digit()))
+    );
+    assert_eq!(parts[3], "journal-entry.md");
+    assert_eq!(
+        fs::read_to_string(env.corpus().join(path)).unwrap(),
+        "Today I wrote tests."
+    );
+    assert!(!env.corpus().join("journal").exists());
+
+    // Directories that don't match categories aren't an issue in this layout
+    env.command()
+        .arg("verify")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No issues found"));
+}
+
+#[test]
+fn tc_4_30_add_preserves_category_case() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args([
+            "add",
+            "--title",
+            "Billing",
+            "--category",
+            "AWS",
+            "--tags",
+            "Cost",
+        ])
+        .write_stdin("Lambda billing notes.")
+        .assert()
+        .success();
+
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("AWS: Billing [Cost]"));
+
+    // Filters ignore case
+    env.command()
+        .args(["list", "--category", "aws"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("AWS: Billing"));
+    env.command()
+        .args(["search", "billing", "--category", "aws"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Billing"));
+    env.command()
+        .args(["query", "category = aws AND tag = cost"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("AWS: Billing"));
+
+    // Retagging with other casing doesn't duplicate the tag
+    env.command()
+        .args(["tag", "AWS/billing.md", "--add", "COST"])
+        .assert()
+        .success();
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("AWS: Billing [Cost]"));
+
+    // Other casing joins the existing category instead of starting `aws/`
+    env.command()
+        .args(["add", "--title", "Quotas", "--category", "aws"])
+        .write_stdin("Concurrency limits.")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Category: AWS"));
+    assert!(env.corpus().join("AWS/quotas.md").exists());
+    assert!(!env.corpus().join("aws").exists());
+}
+
+#[test]
+fn tc_4_31_add_reads_frontmatter() {
+    let env = TestEnv::new();
+    let dir = env.corpus().parent().unwrap().to_path_buf();
+
+    // Full frontmatter: every field from the file, which is stored without it
+    let full = dir.join("full.md");
+    fs::write(
+        &full,
+        "---\ntitle: Cold Starts\ncategory: aws\ntags: [lambda, latency]\ndate: 2024-01-02\n---\n\n# Cold Starts\n\nKeep packages small.\n",
+    )
+    .unwrap();
+    env.command()
+        .args(["add", "--file"])
+        .arg(&full)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added: Cold Starts"))
+        .stdout(predicate::str::contains("Category: aws"));
+    assert_eq!(
+        fs::read_to_string(env.corpus().join("aws/cold-starts.md")).unwrap(),
+        "# Cold Starts\n\nKeep packages small.\n"
+    );
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "aws: Cold Starts [lambda, latency]",
+        ));
+
+    // Partial frontmatter: flags fill in and override
+    let partial = dir.join("partial.md");
+    fs::write(
+        &partial,
+        "---\ntitle: Borrowing\ntags: ownership\n---\nReferences must not outlive their owner.\n",
+    )
+    .unwrap();
+    env.command()
+        .args(["add", "--category", "rust", "--tags", "lifetimes", "--file"])
+        .arg(&partial)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added: Borrowing"));
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rust: Borrowing [lifetimes]"));
+
+    // No frontmatter: title and category must come from flags
+    let plain = dir.join("plain.md");
+    fs::write(&plain, "Just text.\n").unwrap();
+    env.command()
+        .args(["add", "--category", "notes", "--file"])
+        .arg(&plain)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Specify --title"));
+    env.command()
+        .args(["add", "--title", "Plain", "--category", "notes", "--file"])
+        .arg(&plain)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(env.corpus().join("notes/plain.md")).unwrap(),
+        "Just text.\n"
+    );
+}
+
+// =============================================================================
+// 5. Get Command Tests
+// =============================================================================
+
+#[test]
+fn tc_5_1_get_existing_document() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["get", "rust/error-handling.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Error Handling in Rust"))
+        .stdout(predicate::str::contains("Result and Option"));
+}
+
+#[test]
+fn tc_5_2_get_document_not_found() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["get", "nonexistent/doc.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Document not found"));
+}
+
+#[test]
+fn tc_5_3_get_path_traversal_attempt() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["get", "../../../etc/passwd"])
+        .assert()
+        .failure()
+        .stderr(
+            predicate::str::contains("Invalid document path")
+                .or(predicate::str::contains("contains '..'")),
+        );
+}
+
+#[test]
+fn tc_5_4_get_file_not_in_manifest() {
+    let env = TestEnv::with_documents();
+
+    // Create orphan file not in manifest
+    fs::create_dir_all(env.corpus().join("orphan")).unwrap();
+    fs::write(env.corpus().join("orphan/file.md"), "# Orphan").unwrap();
+
+    env.command()
+        .args(["get", "orphan/file.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Document not found"));
+}
+
+#[test]
+fn tc_5_5_get_limit_bytes_truncates() {
+    let env = TestEnv::new();
+
+    // Two-byte chars after an odd-length header put byte 100 mid-char
+    let body = format!("# Big\n\n{}", "é".repeat(5000));
+    fs::create_dir_all(env.corpus().join("big")).unwrap();
+    fs::write(env.corpus().join("big/large.md"), &body).unwrap();
+
+    let manifest = r#"{
+    "version": "1",
+    "documents": [
+        {"path": "big/large.md", "title": "Big", "category": "big", "tags": []}
+    ]
+}"#;
+    fs::write(env.corpus().join("manifest.json"), manifest).unwrap();
+
+    let output = env
+        .command()
+        .args(["get", "big/large.md", "--limit-bytes", "100"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("truncated output is valid UTF-8");
+    let kept = stdout
+        .strip_suffix("\n… [truncated]")
+        .expect("output ends with truncation marker");
+    assert_eq!(kept.len(), 99);
+    assert!(body.starts_with(kept));
+
+    env.command()
+        .args(["get", "big/large.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[truncated]").not());
+}
+
+/// Add a document whose lines read `line 1` through `line {count}`.
+fn with_numbered_document(env: &TestEnv, count: usize) {
+    let body = (1..=count)
+        .map(|n| format!("line {n}\n"))
+        .collect::<Vec<_>>()
+        .concat();
+    fs::create_dir_all(env.corpus().join("notes")).unwrap();
+    fs::write(env.corpus().join("notes/numbered.md"), body).unwrap();
+
+    let manifest = r#"{
+    "version": "1",
+    "documents": [
+        {"path": "notes/numbered.md", "title": "Numbered", "category": "notes", "tags": []}
+    ]
+}"#;
+    fs::write(env.corpus().join("manifest.json"), manifest).unwrap();
+}
+
+#[test]
+fn tc_5_6_get_line_range_mid_file() {
+    let env = TestEnv::new();
+    with_numbered_document(&env, 100);
+
+    env.command()
+        .args(["get", "notes/numbered.md", "--line-range", "41:43"])
+        .assert()
+        .success()
+        .stdout("line 41\nline 42\nline 43\n");
+}
+
+#[test]
+fn tc_5_7_get_line_range_clamps_out_of_bounds() {
+    let env = TestEnv::new();
+    with_numbered_document(&env, 5);
+
+    env.command()
+        .args(["get", "notes/numbered.md", "--line-range", "4:50"])
+        .assert()
+        .success()
+        .stdout("line 4\nline 5\n");
+
+    env.command()
+        .args(["get", "notes/numbered.md", "--line-range", "9:12"])
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn tc_5_8_get_line_range_invalid() {
+    let env = TestEnv::new();
+    with_numbered_document(&env, 5);
+
+    env.command()
+        .args(["get", "notes/numbered.md", "--line-range", "5:2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("start line 5 is after end line 2"));
+}
+
+#[test]
+fn tc_5_9_get_and_search_extension_less_path() {
+    let env = TestEnv::with_documents();
+    fs::create_dir_all(env.corpus().join("notes")).unwrap();
+    fs::write(
+        env.corpus().join("notes/todo"),
+        "# Todo\n\nRenew the certificates.",
+    )
+    .unwrap();
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    fs::write(
+        env.corpus().join("manifest.json"),
+        manifest.replace(
+            "\"documents\": [",
+            r#""documents": [
+        {"path": "notes/todo", "title": "Todo List", "category": "notes"},"#,
+        ),
+    )
+    .unwrap();
+
+    for path in ["notes/todo", "notes/todo.md"] {
+        env.command()
+            .args(["get", path])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Renew the certificates."));
+    }
+
+    env.command()
+        .args(["search", "certificates"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Todo List: "))
+        .stdout(predicate::str::contains("notes/todo (line 3)"));
+
+    env.command()
+        .args(["tag", "notes/todo.md", "--add", "ops"])
+        .assert()
+        .success();
+    env.command()
+        .args(["search", "tags:ops certificates"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Todo List"));
+}
+
+// =============================================================================
+// 6. Edge Cases and Config Tests
+// =============================================================================
+
+#[test]
+fn tc_6_1_no_corpus_paths_configured() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    fs::write(&config_path, "[corpus]\npaths = []\n").unwrap();
+
+    cargo_bin_cmd!("kvault")
+        .env("KVAULT_CONFIG", &config_path)
+        .args(["search", "test"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No matches found"));
+}
+
+#[test]
+fn tc_6_2_invalid_config_toml() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    fs::write(&config_path, "this is not valid toml {{{{").unwrap();
+
+    cargo_bin_cmd!("kvault")
+        .env("KVAULT_CONFIG", &config_path)
+        .args(["search", "test"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Error"));
+}
+
+#[test]
+fn tc_6_3_multiple_corpora() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    // Create two corpora
+    let corpus1 = root.join("corpus1");
+    let corpus2 = root.join("corpus2");
+    fs::create_dir_all(corpus1.join("cat1")).unwrap();
+    fs::create_dir_all(corpus2.join("cat2")).unwrap();
+
+    fs::write(
+        corpus1.join("cat1/doc1.md"),
+        "# Doc1\n\nUnique content alpha",
+    )
+    .unwrap();
+    fs::write(
+        corpus2.join("cat2/doc2.md"),
+        "# Doc2\n\nUnique content beta",
+    )
+    .unwrap();
+
+    fs::write(corpus1.join("manifest.json"), r#"{"version":"1","documents":[{"path":"cat1/doc1.md","title":"Doc1","category":"cat1","tags":[]}]}"#).unwrap();
+    fs::write(corpus2.join("manifest.json"), r#"{"version":"1","documents":[{"path":"cat2/doc2.md","title":"Doc2","category":"cat2","tags":[]}]}"#).unwrap();
+
+    let config_path = root.join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[corpus]\npaths = [\"{}\", \"{}\"]\n",
+            corpus1.display(),
+            corpus2.display()
+        ),
+    )
+    .unwrap();
+
+    // Search should find results from both corpora
+    cargo_bin_cmd!("kvault")
+        .env("KVAULT_CONFIG", &config_path)
+        .args(["search", "Unique"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Doc1"))
+        .stdout(predicate::str::contains("Doc2"));
+}
+
+#[test]
+fn tc_6_4_document_in_manifest_but_file_missing() {
+    let env = TestEnv::new();
+
+    // Add document to manifest but don't create file
+    let manifest = r#"{
+    "version": "1",
+    "documents": [
+        {"path": "missing/file.md", "title": "Missing", "category": "missing", "tags": []}
+    ]
+}"#;
+    fs::write(env.corpus().join("manifest.json"), manifest).unwrap();
+
+    env.command()
+        .args(["get", "missing/file.md"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn tc_6_5_kvault_config_env_overrides_default() {
+    let env = TestEnv::with_documents();
+
+    // This test verifies KVAULT_CONFIG works (which all other tests depend on)
+    env.command()
+        .args(["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"));
+}
+
+#[test]
+fn tc_6_6_config_not_found_uses_defaults() {
+    let temp_dir = TempDir::new().unwrap();
+    let nonexistent_config = temp_dir.path().join("nonexistent/config.toml");
+
+    // When config doesn't exist, should use defaults (which point to ~/.kvault)
+    // This will likely show "No documents" or similar since default path probably doesn't exist
+    cargo_bin_cmd!("kvault")
+        .env("KVAULT_CONFIG", &nonexistent_config)
+        .args(["list"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn tc_6_7_partial_corpus_failure_reports_warning() {
+    let env = TestEnv::with_documents();
+    let root = env.corpus().parent().unwrap().to_path_buf();
+
+    // Second corpus with a broken manifest
+    let broken = root.join("broken");
+    fs::create_dir_all(&broken).unwrap();
+    fs::write(broken.join("manifest.json"), "not valid json").unwrap();
+
+    let config_path = root.join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[corpus]\npaths = [\"{}\", \"{}\"]\n",
+            env.corpus().display(),
+            broken.display()
+        ),
+    )
+    .unwrap();
+
+    env.command()
+        .args(["search", "Lambda"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stderr(predicate::str::contains(format!(
+            "Warning:\n  Load {}",
+            broken.display()
+        )));
+
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"))
+        .stderr(predicate::str::contains("Warning:\n  Load"));
+}
+
+#[test]
+fn tc_6_8_too_many_corpora_warns() {
+    let env = TestEnv::with_documents();
+
+    let extra: Vec<String> = (0..60)
+        .map(|i| {
+            format!(
+                "\"{}\"",
+                env.corpus().join(format!("missing-{i}")).display()
+            )
+        })
+        .collect();
+    let config = format!(
+        "[corpus]\npaths = [\"{}\", {}]\n",
+        env.corpus().display(),
+        extra.join(", ")
+    );
+    fs::write(&env.config_path, config).unwrap();
+
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"))
+        .stderr(predicate::str::contains("61 corpus paths configured"));
+
+    // Default fixture config stays silent
+    let env = TestEnv::with_documents();
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn tc_6_9_corpora_over_hard_cap_fails() {
+    let env = TestEnv::with_documents();
+    let config = format!(
+        "[corpus]\npaths = [\"{0}\", \"{0}\"]\nmax_corpora = 1\n",
+        env.corpus().display()
+    );
+    fs::write(&env.config_path, config).unwrap();
+
+    env.command()
+        .arg("list")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "2 corpus paths configured (max 1)",
+        ));
+}
+
+#[test]
+fn tc_6_10_corpus_paths_relative_to_config() {
+    let env = TestEnv::with_documents();
+
+    // Config lives next to the corpus dir; run from an unrelated directory
+    let config = "[corpus]\npaths = [\"corpus\"]\npaths_relative_to_config = true\n";
+    fs::write(&env.config_path, config).unwrap();
+    let elsewhere = TempDir::new().unwrap();
+
+    env.command()
+        .current_dir(elsewhere.path())
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"));
+
+    // Without the option, the relative path resolves against the cwd
+    fs::write(&env.config_path, "[corpus]\npaths = [\"corpus\"]\n").unwrap();
+    env.command()
+        .current_dir(elsewhere.path())
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No documents found"));
+}
+
+#[test]
+fn tc_6_11_profile_selects_corpus_paths() {
+    let env = TestEnv::with_documents();
+    let empty = TempDir::new().unwrap();
+    fs::write(
+        empty.path().join("manifest.json"),
+        r#"{"version": "1", "documents": []}"#,
+    )
+    .unwrap();
+
+    let config = format!(
+        "[corpus]\npaths = [\"{}\"]\n\n[profiles.work.corpus]\npaths = [\"{}\"]\n",
+        empty.path().display(),
+        env.corpus().display()
+    );
+    fs::write(&env.config_path, config).unwrap();
+
+    // Top-level settings apply without a profile
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No documents found"));
+
+    env.command()
+        .args(["list", "--profile", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"));
+
+    env.command()
+        .env("KVAULT_PROFILE", "work")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"));
+
+    env.command()
+        .args(["--profile", "home", "list"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unknown profile: home (configured: work)",
+        ));
+}
+
+#[test]
+fn tc_6_12_dedupe_across_corpora() {
+    let env = TestEnv::with_documents();
+    let root = env.corpus().parent().unwrap().to_path_buf();
+
+    // A mirror holding the same Rust document
+    let mirror = root.join("mirror");
+    fs::create_dir_all(mirror.join("rust")).unwrap();
+    fs::copy(
+        env.corpus().join("rust/error-handling.md"),
+        mirror.join("rust/error-handling.md"),
+    )
+    .unwrap();
+    fs::write(
+        mirror.join("manifest.json"),
+        r#"{"version":"1","documents":[{"path":"rust/error-handling.md","title":"Error Handling","category":"rust","tags":[]}]}"#,
+    )
+    .unwrap();
+    fs::write(
+        root.join("config.toml"),
+        format!(
+            "[corpus]\npaths = [\"{}\", \"{}\"]\n",
+            env.corpus().display(),
+            mirror.display()
+        ),
+    )
+    .unwrap();
+
+    let count = |args: &[&str]| {
+        let output = env.command().args(args).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .matches("Error Handling")
+            .count()
+    };
+
+    assert_eq!(count(&["list"]), 2);
+    assert_eq!(count(&["list", "--dedupe"]), 1);
+    assert_eq!(count(&["search", "propagates", "--format", "csv"]), 2);
+    assert_eq!(
+        count(&["search", "propagates", "--format", "csv", "--dedupe"]),
+        1
+    );
+
+    // The first configured corpus's copy is kept
+    env.command()
+        .args(["search", "propagates", "--format", "csv", "--dedupe"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(env.corpus().display().to_string()))
+        .stdout(predicate::str::contains("mirror").not());
+}
+
+#[test]
+fn tc_6_13_s3_section_is_not_silently_ignored() {
+    let env = TestEnv::with_documents();
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[corpus.s3]\nbucket = \"team-kb\"\n"),
+    )
+    .unwrap();
+
+    for args in [
+        &["list"][..],
+        &["search", "Lambda"],
+        &["get", "aws/lambda-patterns.md"],
+    ] {
+        env.command()
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "[corpus.s3] is set (bucket team-kb)",
+            ));
+    }
+}
+
+// =============================================================================
+// 7. Output Format Tests
+// =============================================================================
+
+/// Create a test environment with a document whose title contains a comma.
+fn env_with_comma_title() -> TestEnv {
+    let env = TestEnv::new();
+
+    fs::create_dir_all(env.corpus().join("rust")).unwrap();
+    fs::write(
+        env.corpus().join("rust/results.md"),
+        "# Results\n\nPrefer Result over panics.",
+    )
+    .unwrap();
+
+    let manifest = r#"{
+    "version": "1",
+    "documents": [
+        {"path": "rust/results.md", "title": "Errors, Results, and Options", "category": "rust", "tags": ["rust", "errors"]}
+    ]
+}"#;
+    fs::write(env.corpus().join("manifest.json"), manifest).unwrap();
+
+    env
+}
+
+#[test]
+fn tc_7_4_list_csv_quotes_commas() {
+    let env = env_with_comma_title();
+
+    env.command()
+        .args(["list", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "title,category,tags,path,line,score\n",
+        ))
+        .stdout(predicate::str::contains(
+            "\"Errors, Results, and Options\",rust,rust;errors,",
+        ));
+}
+
+#[test]
+fn tc_7_5_search_csv_quotes_commas() {
+    let env = env_with_comma_title();
+
+    env.command()
+        .args(["search", "panics", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "title,category,tags,path,line,score\n",
+        ))
+        .stdout(predicate::str::contains(
+            "\"Errors, Results, and Options\",rust,rust;errors,",
+        ))
+        .stdout(predicate::str::contains("results.md,3,\n"));
+}
+
+#[test]
+fn tc_7_6_json_flag_is_format_json() {
+    let env = TestEnv::with_documents();
+
+    let output = env
+        .command()
+        .args(["search", "Lambda", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let first = &results[0];
+    assert_eq!(first["title"], "Lambda Patterns");
+    assert!(
+        first["path"]
+            .as_str()
+            .unwrap()
+            .ends_with("lambda-patterns.md")
+    );
+    // Ripgrep only scores its matches with --rank
+    assert!(first["score"].is_null());
+
+    let output = env.command().args(["list", "--json"]).output().unwrap();
+    assert!(output.status.success());
+    let documents: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(documents.as_array().unwrap().len(), 2);
+    assert!(documents[0]["path"].is_string());
+
+    env.command()
+        .args(["list", "--json", "--format", "csv"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[cfg(feature = "ranked")]
+#[test]
+fn tc_7_7_json_scores_ranked_results() {
+    let env = TestEnv::with_documents();
+    env.command().arg("index").assert().success();
+
+    let output = env
+        .command()
+        .args(["search", "Lambda", "--backend", "ranked", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(results[0]["score"].as_f64().unwrap() > 0.0);
+}
+
+// =============================================================================
+// 8. Query Command Tests
+// =============================================================================
+
+#[test]
+fn tc_8_1_query_by_category() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["query", "category = aws"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("aws: Lambda Patterns"))
+        .stdout(predicate::str::contains("Error Handling").not());
+}
+
+#[test]
+fn tc_8_2_query_tag_and_category() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["query", "tag = lambda AND category = aws"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns"))
+        .stdout(predicate::str::contains("Error Handling").not());
+
+    env.command()
+        .args(["query", "tag = lambda AND category = rust"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No documents found"));
+}
+
+#[test]
+fn tc_8_3_query_malformed_expression() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["query", "category = aws AND"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid query"))
+        .stderr(predicate::str::contains("Expected a field name"));
+}
+
+// =============================================================================
+// 9. Tag Command Tests
+// =============================================================================
+
+/// Read the tags recorded in the manifest for a document.
+fn manifest_tags(env: &TestEnv, doc_path: &str) -> Vec<String> {
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    manifest["documents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|doc| doc["path"] == doc_path)
+        .unwrap()["tags"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t.as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn tc_9_1_tag_add() {
+    let env = TestEnv::with_documents();
+    let before = fs::read_to_string(env.corpus().join("rust/error-handling.md")).unwrap();
+
+    env.command()
+        .args(["tag", "rust/error-handling.md", "--add", "result,errors"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tags: rust, errors, result"));
+
+    assert_eq!(
+        manifest_tags(&env, "rust/error-handling.md"),
+        ["rust", "errors", "result"]
+    );
+    let after = fs::read_to_string(env.corpus().join("rust/error-handling.md")).unwrap();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn tc_9_2_tag_remove() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["tag", "rust/error-handling.md", "--remove", "errors"])
+        .assert()
+        .success();
+
+    assert_eq!(manifest_tags(&env, "rust/error-handling.md"), ["rust"]);
+}
+
+#[test]
+fn tc_9_3_tag_invalid_tag_rejected() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["tag", "rust/error-handling.md", "--add", "bad tag"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid character"));
+
+    assert_eq!(
+        manifest_tags(&env, "rust/error-handling.md"),
+        ["rust", "errors"]
+    );
+}
+
+#[test]
+fn tc_9_4_tag_document_not_found() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["tag", "nonexistent/doc.md", "--add", "x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Document not found"));
+}
+
+#[test]
+fn tc_9_5_tag_graph_counts_pairs() {
+    let env = TestEnv::with_documents();
+    env.command()
+        .args(["tag", "rust/error-handling.md", "--add", "aws,lambda"])
+        .assert()
+        .success();
+
+    env.command()
+        .arg("tag-graph")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("aws, lambda: 2\n"))
+        .stdout(predicate::str::contains("errors, rust: 1"));
+
+    env.command()
+        .args(["tag-graph", "--format", "dot"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("graph tags {\n"))
+        .stdout(predicate::str::contains(
+            "\"aws\" -- \"lambda\" [weight=2, label=2];",
+        ));
+
+    let output = env
+        .command()
+        .args(["tag-graph", "--format", "json"])
+        .output()
+        .unwrap();
+    let pairs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(pairs[0]["tags"], serde_json::json!(["aws", "lambda"]));
+    assert_eq!(pairs[0]["count"], 2);
+}
+
+#[test]
+fn tc_9_6_tag_add_and_remove_together() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args([
+            "tag",
+            "rust/error-handling.md",
+            "--add",
+            "result,cleanup",
+            "--remove",
+            "errors,cleanup",
         ])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Failed to read file"));
+        .success()
+        .stdout(predicate::str::contains("Tags: rust, result"));
+
+    assert_eq!(
+        manifest_tags(&env, "rust/error-handling.md"),
+        ["rust", "result"]
+    );
+}
+
+// =============================================================================
+// 10. Links Command Tests
+// =============================================================================
+
+/// Add `notes/index.md` linking to one existing and one missing document.
+fn env_with_links() -> TestEnv {
+    let env = TestEnv::with_documents();
+    fs::create_dir_all(env.corpus().join("notes")).unwrap();
+    fs::write(
+        env.corpus().join("notes/index.md"),
+        "# Index\n\nSee [[error-handling]].\nAlso [[missing-note]].\n",
+    )
+    .unwrap();
+
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    fs::write(
+        env.corpus().join("manifest.json"),
+        manifest.replace(
+            "\n    ]",
+            ",\n        {\"path\": \"notes/index.md\", \"title\": \"Index\", \"category\": \"notes\"}\n    ]",
+        ),
+    )
+    .unwrap();
+    env
+}
+
+#[test]
+fn tc_10_1_links_classifies_valid_and_broken() {
+    let env = env_with_links();
+
+    env.command()
+        .args(["links", "notes/index.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "line 3: [[error-handling]] -> rust/error-handling.md",
+        ))
+        .stdout(predicate::str::contains(
+            "line 4: [[missing-note]] (broken)",
+        ));
+}
+
+#[test]
+fn tc_10_2_links_check_all_reports_broken() {
+    let env = env_with_links();
+
+    env.command()
+        .args(["links", "--check-all"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "notes/index.md:4: [[missing-note]]",
+        ))
+        .stdout(predicate::str::contains("error-handling").not())
+        .stderr(predicate::str::contains("1 broken link(s) out of 2"));
+
+    // Fixing the link makes the check pass
+    fs::write(
+        env.corpus().join("notes/index.md"),
+        "See [[error-handling]] and [lambda](../aws/lambda-patterns.md).\n",
+    )
+    .unwrap();
+    env.command()
+        .args(["links", "--check-all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("All 2 link(s) resolve"));
+}
+
+#[test]
+fn tc_10_3_links_requires_path_or_check_all() {
+    let env = TestEnv::with_documents();
+
+    env.command().arg("links").assert().failure();
+    env.command()
+        .args(["links", "nonexistent/doc.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Document not found"));
+}
+
+#[test]
+fn tc_10_4_get_resolve_links_appends_linked_documents() {
+    let env = env_with_links();
+    // A repeated link and a link back to the index are included once and
+    // not at all
+    fs::write(
+        env.corpus().join("rust/error-handling.md"),
+        "# Error Handling in Rust\n\nBack to [[index]].\n",
+    )
+    .unwrap();
+    fs::write(
+        env.corpus().join("notes/index.md"),
+        "# Index\n\nSee [[error-handling]].\nAgain [[Error Handling]].\nAlso [[missing-note]].\n",
+    )
+    .unwrap();
+
+    let output = env
+        .command()
+        .args(["get", "notes/index.md", "--resolve-links"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("# Index\n"));
+    assert_eq!(
+        stdout.matches("# Linked: ").count(),
+        1,
+        "one linked document expected:\n{stdout}"
+    );
+    assert!(stdout.contains(
+        "# Linked: Error Handling (rust/error-handling.md)\n\n# Error Handling in Rust\n\nBack to [[index]]."
+    ));
+
+    // Without the flag the document is returned as-is
+    env.command()
+        .args(["get", "notes/index.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Linked:").not());
+}
+
+// =============================================================================
+// 11. Duplicates Command Tests
+// =============================================================================
+
+/// Add `rust/errors-copy.md` with the same content as `rust/error-handling.md`.
+fn env_with_duplicate() -> TestEnv {
+    let env = TestEnv::with_documents();
+    fs::copy(
+        env.corpus().join("rust/error-handling.md"),
+        env.corpus().join("rust/errors-copy.md"),
+    )
+    .unwrap();
+
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    fs::write(
+        env.corpus().join("manifest.json"),
+        manifest.replace(
+            "\n    ]",
+            ",\n        {\"path\": \"rust/errors-copy.md\", \"title\": \"Errors Copy\", \"category\": \"rust\", \"tags\": [\"result\"]}\n    ]",
+        ),
+    )
+    .unwrap();
+    env
 }
 
 #[test]
-fn tc_4_14_add_slugify_special_chars() {
-    let env = TestEnv::new();
+fn tc_11_1_duplicates_reports_identical_documents() {
+    let env = env_with_duplicate();
 
     env.command()
-        .args([
-            "add",
-            "--title",
-            "AWS Lambda: Best Practices!",
-            "--category",
-            "aws",
-        ])
+        .arg("duplicates")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "rust/error-handling.md (identical):",
+        ))
+        .stdout(predicate::str::contains("rust/errors-copy.md"))
+        .stdout(predicate::str::contains("lambda-patterns").not());
+
+    // Reporting changes nothing
+    assert!(env.corpus().join("rust/errors-copy.md").exists());
+}
+
+#[test]
+fn tc_11_2_duplicates_merge_records_aliases() {
+    let env = env_with_duplicate();
+
+    env.command()
+        .args(["duplicates", "--merge"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("merged"));
+
+    assert!(!env.corpus().join("rust/errors-copy.md").exists());
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    let documents = manifest["documents"].as_array().unwrap();
+    assert_eq!(documents.len(), 2);
+    assert_eq!(
+        documents[0]["aliases"],
+        serde_json::json!(["rust/errors-copy.md"])
+    );
+    assert_eq!(
+        manifest_tags(&env, "rust/error-handling.md"),
+        ["rust", "errors", "result"]
+    );
+
+    // The old path still finds the surviving document
+    env.command()
+        .args(["get", "rust/errors-copy.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling in Rust"));
+    env.command()
+        .arg("duplicates")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No duplicates found"));
+}
+
+#[test]
+fn tc_11_3_duplicates_rejects_invalid_similarity() {
+    let env = TestEnv::with_documents();
+
+    if cfg!(feature = "similarity") {
+        env.command()
+            .args(["duplicates", "--similarity", "1.5"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "Similarity threshold must be in (0, 1]",
+            ));
+    } else {
+        env.command()
+            .args(["duplicates", "--similarity", "0.8"])
+            .assert()
+            .failure();
+    }
+}
+
+// =============================================================================
+// 12. Lock Command Tests
+// =============================================================================
+
+#[test]
+fn tc_12_1_locked_corpus_refuses_writes() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .arg("lock")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Locked:"));
+    assert!(env.corpus().join(".locked").exists());
+
+    let manifest_before = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    env.command()
+        .args(["add", "--title", "Blocked", "--category", "notes"])
+        .write_stdin("content")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Corpus is locked"));
+    env.command()
+        .args(["tag", "rust/error-handling.md", "--add", "blocked"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Corpus is locked"));
+    assert!(!env.corpus().join("notes/blocked.md").exists());
+    assert_eq!(
+        fs::read_to_string(env.corpus().join("manifest.json")).unwrap(),
+        manifest_before
+    );
+
+    // Reads still work
+    env.command()
+        .args(["get", "rust/error-handling.md"])
+        .assert()
+        .success();
+
+    env.command()
+        .arg("unlock")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Unlocked:"));
+    env.command()
+        .args(["add", "--title", "Blocked", "--category", "notes"])
         .write_stdin("content")
         .assert()
         .success();
+    assert!(env.corpus().join("notes/blocked.md").exists());
+}
+
+#[test]
+fn tc_12_2_lock_takes_explicit_corpus() {
+    let env = TestEnv::with_documents();
+    let corpus = env.corpus().display().to_string();
+
+    env.command().args(["lock", &corpus]).assert().success();
+    // Locking twice and unlocking twice are both fine
+    env.command().args(["lock", &corpus]).assert().success();
+    env.command().args(["unlock", &corpus]).assert().success();
+    env.command().args(["unlock", &corpus]).assert().success();
+    assert!(!env.corpus().join(".locked").exists());
+
+    env.command()
+        .args(["lock", "/nonexistent/corpus"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Corpus not found"));
+}
+
+// =============================================================================
+// 13. MCP Server Tests (requires `mcp`)
+// =============================================================================
+
+/// Run `kvault serve` with `args`, call `get_document` for each path, and
+/// return the JSON-RPC responses to those calls in order.
+#[cfg(feature = "mcp")]
+fn mcp_get_documents(env: &TestEnv, args: &[&str], paths: &[&str]) -> Vec<serde_json::Value> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::Stdio;
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("kvault"))
+        .env("KVAULT_CONFIG", &env.config_path)
+        .env_remove("KVAULT_PROFILE")
+        .arg("serve")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = child.stdin.take().unwrap();
+    let mut send = |message: serde_json::Value| writeln!(stdin, "{message}").unwrap();
+    send(serde_json::json!({
+        "jsonrpc": "2.0", "id": 0, "method": "initialize",
+        "params": {
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "e2e", "version": "0"}
+        }
+    }));
+    send(serde_json::json!({"jsonrpc": "2.0", "method": "notifications/initialized"}));
+    for (id, path) in paths.iter().enumerate() {
+        send(serde_json::json!({
+            "jsonrpc": "2.0", "id": id + 1, "method": "tools/call",
+            "params": {"name": "get_document", "arguments": {"path": path}}
+        }));
+    }
+
+    let mut responses = vec![serde_json::Value::Null; paths.len()];
+    let mut pending = paths.len();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    for line in stdout.lines() {
+        let message: serde_json::Value = serde_json::from_str(&line.unwrap()).unwrap();
+        if let Some(id) = message["id"].as_u64().filter(|&id| id > 0) {
+            responses[usize::try_from(id).unwrap() - 1] = message;
+            pending -= 1;
+            if pending == 0 {
+                break;
+            }
+        }
+    }
+
+    drop(stdin);
+    child.wait().unwrap();
+    responses
+}
+
+#[cfg(feature = "mcp")]
+#[test]
+fn tc_13_1_mcp_allowed_categories_hide_documents() {
+    let env = TestEnv::with_documents();
+
+    let responses = mcp_get_documents(
+        &env,
+        &["--allowed-categories", "aws"],
+        &["aws/lambda-patterns.md", "rust/error-handling.md"],
+    );
+
+    let allowed = responses[0]["result"]["content"][0]["text"]
+        .as_str()
+        .unwrap();
+    assert!(allowed.contains("AWS Lambda Patterns"));
+
+    let hidden = responses[1]["error"]["message"].as_str().unwrap();
+    assert!(
+        hidden.contains("Document not found: rust/error-handling.md"),
+        "{hidden}"
+    );
+
+    // Without the flag, every category is visible
+    let responses = mcp_get_documents(&env, &[], &["rust/error-handling.md"]);
+    assert!(responses[0]["result"].is_object());
+}
+
+// =============================================================================
+// 14. Gen-Index Command Tests
+// =============================================================================
+
+#[test]
+fn tc_14_1_gen_index_links_every_document() {
+    let env = TestEnv::with_documents();
+    let output = env.corpus().join("index.md");
+
+    env.command()
+        .args(["gen-index", output.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Wrote index of 2 document(s)"));
+
+    let index = fs::read_to_string(&output).unwrap();
+    assert!(index.contains("## aws\n"));
+    assert!(index.contains("## rust\n"));
+    assert!(index.contains("- [Lambda Patterns](<aws/lambda-patterns.md>)"));
+    assert!(index.contains("- [Error Handling](<rust/error-handling.md>)"));
+    assert!(index.find("## aws").unwrap() < index.find("## rust").unwrap());
+}
+
+// =============================================================================
+// 15. Term-Stats Command Tests (requires `ranked`)
+// =============================================================================
+
+#[cfg(feature = "ranked")]
+#[test]
+fn tc_15_1_term_stats_reports_document_frequency() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .args(["term-stats", "lambda"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No search index found"));
+
+    env.command().arg("index").assert().success();
+
+    env.command()
+        .args(["term-stats", "Lambda"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("corpus: lambda"))
+        .stdout(predicate::str::contains("Documents: 1 of 2"))
+        .stdout(predicate::str::contains("IDF: "));
+
+    env.command()
+        .args(["term-stats", "use"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Documents: 2 of 2"));
+}
+
+#[cfg(feature = "ranked")]
+#[test]
+fn tc_15_2_term_stats_skips_missing_and_unindexed_corpora() {
+    let env = TestEnv::with_documents();
+    let root = env.corpus().parent().unwrap().to_path_buf();
+    fs::write(
+        &env.config_path,
+        format!(
+            "[corpus]\npaths = [\"{}\", \"{}\"]\n",
+            root.join("missing").display(),
+            env.corpus().display()
+        ),
+    )
+    .unwrap();
+
+    env.command()
+        .args(["term-stats", "lambda"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "No search index found (run `kvault index` first)",
+        ));
+
+    env.command().arg("index").assert().success();
+    env.command()
+        .args(["term-stats", "lambda"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Documents: 1 of 2"));
+}
+
+// =============================================================================
+// 16. Verify Command Tests
+// =============================================================================
+
+/// Declare the rust document's category as `aws` while its file stays under
+/// `rust/`.
+fn mismatch_category(env: &TestEnv) {
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    fs::write(
+        env.corpus().join("manifest.json"),
+        manifest.replace(
+            r#""title": "Error Handling", "category": "rust""#,
+            r#""title": "Error Handling", "category": "aws""#,
+        ),
+    )
+    .unwrap();
+}
+
+#[test]
+fn tc_16_1_verify_detects_and_moves_mismatched_document() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .arg("verify")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No issues found"));
+
+    mismatch_category(&env);
+    env.command()
+        .arg("verify")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "rust/error-handling.md: category 'aws' doesn't match directory 'rust'",
+        ))
+        .stderr(predicate::str::contains("1 issue(s) found"));
+
+    env.command()
+        .args(["verify", "--fix", "move"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fixed 1 of 1 issue(s)"));
+
+    assert!(!env.corpus().join("rust/error-handling.md").exists());
+    assert!(env.corpus().join("aws/error-handling.md").exists());
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    assert!(manifest.contains(r#""path": "aws/error-handling.md""#));
+    assert!(manifest.contains(r#""aliases": ["#));
+
+    env.command().arg("verify").assert().success();
+    // The old path still finds the document
+    env.command()
+        .args(["get", "rust/error-handling.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling in Rust"));
+}
+
+#[test]
+fn tc_16_2_verify_fix_category() {
+    let env = TestEnv::with_documents();
+    mismatch_category(&env);
+
+    env.command()
+        .args(["verify", "--fix", "category"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fixed 1 of 1 issue(s)"));
+
+    assert!(env.corpus().join("rust/error-handling.md").exists());
+    env.command()
+        .args(["list", "--category", "rust"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Error Handling"));
+    env.command().arg("verify").assert().success();
+}
+
+// =============================================================================
+// 17. Index Command Tests (requires `ranked`)
+// =============================================================================
+
+/// Names and modification times of the files in a corpus's index directory.
+#[cfg(feature = "ranked")]
+fn index_files(env: &TestEnv) -> Vec<(std::ffi::OsString, std::time::SystemTime)> {
+    let mut files: Vec<_> = fs::read_dir(env.corpus().join(".index"))
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            (
+                entry.file_name(),
+                entry.metadata().unwrap().modified().unwrap(),
+            )
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(feature = "ranked")]
+#[test]
+fn tc_17_1_index_skips_unchanged_corpus() {
+    let env = TestEnv::with_documents();
+
+    env.command()
+        .arg("index")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Indexed: "));
+    let before = index_files(&env);
+
+    env.command()
+        .arg("index")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Index up to date: "));
+    assert_eq!(index_files(&env), before);
+
+    // Any document change makes the index stale again
+    fs::write(
+        env.corpus().join("aws/lambda-patterns.md"),
+        "# Lambda Patterns\n\nRewritten.\n",
+    )
+    .unwrap();
+    env.command()
+        .arg("index")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Indexed: "));
+
+    env.command()
+        .args(["index", "--full"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Index up to date").not());
+
+    env.command()
+        .args(["index", "--force"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Index up to date").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn tc_16_3_verify_fix_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let env = TestEnv::with_documents();
+    let file = env.corpus().join("aws/lambda-patterns.md");
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o000)).unwrap();
+    if fs::File::open(&file).is_ok() {
+        // Permissions don't apply (e.g., running as root)
+        return;
+    }
+
+    env.command()
+        .arg("verify")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "lambda-patterns.md: file can't be read",
+        ))
+        .stderr(predicate::str::contains("1 issue(s) found"));
 
-    // Verify slug was created correctly
-    assert!(
-        env.corpus()
-            .join("aws/aws-lambda-best-practices.md")
-            .exists()
-    );
+    env.command()
+        .args(["verify", "--fix-permissions"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fixed 1 of 1 issue(s)"));
+    assert!(fs::File::open(&file).is_ok());
+    env.command().arg("verify").assert().success();
 }
 
 #[test]
-fn tc_4_15_add_tags_with_whitespace() {
-    let env = TestEnv::new();
-
+fn tc_16_4_verify_ci_reports_issues_as_json_lines() {
+    let env = TestEnv::with_documents();
     env.command()
-        .args([
-            "add",
-            "--title",
-            "Test",
-            "--category",
-            "test",
-            "--tags",
-            "  tag1  ,  tag2  ",
-        ])
-        .write_stdin("content")
+        .args(["verify", "--ci"])
         .assert()
-        .success();
+        .success()
+        .stdout(predicate::str::is_empty());
 
     let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
-    assert!(manifest.contains("\"tag1\""));
-    assert!(manifest.contains("\"tag2\""));
-    // Should not have extra whitespace
-    assert!(!manifest.contains("\"  tag1  \""));
+    let broken = manifest.replace(
+        "\n    ]",
+        ",\n        {\"path\": \"aws/lambda-patterns.md\", \"title\": \"Copy\", \"category\": \"aws\"},\
+         \n        {\"path\": \"aws/gone.md\", \"title\": \"Gone\", \"category\": \"aws\"}\n    ]",
+    );
+    fs::write(env.corpus().join("manifest.json"), &broken).unwrap();
+
+    let output = env.command().args(["verify", "--ci"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 issue(s) found"));
+    let reports: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let kinds: Vec<&str> = reports
+        .iter()
+        .map(|r| r["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, ["duplicate_path", "missing_file"]);
+    assert!(
+        reports[0]["path"]
+            .as_str()
+            .unwrap()
+            .ends_with("aws/lambda-patterns.md")
+    );
+    assert_eq!(reports[0]["message"], "listed 2 times in the manifest");
+    assert!(
+        reports[1]["path"]
+            .as_str()
+            .unwrap()
+            .ends_with("aws/gone.md")
+    );
+
+    // Nothing was changed
+    let after = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    assert_eq!(after, broken);
+    env.command()
+        .args(["verify", "--ci", "--fix", "move"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
 }
 
 #[test]
-fn tc_4_16_add_tags_empty_entries_filtered() {
-    let env = TestEnv::new();
+fn tc_16_5_verify_ci_fails_on_unparseable_manifest() {
+    let env = TestEnv::with_documents();
+    fs::write(env.corpus().join("manifest.json"), "{ not json").unwrap();
 
-    env.command()
-        .args([
-            "add",
-            "--title",
-            "Test",
-            "--category",
-            "test",
-            "--tags",
-            "tag1,,tag2,",
-        ])
-        .write_stdin("content")
-        .assert()
-        .success();
+    let output = env.command().args(["verify", "--ci"]).output().unwrap();
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["kind"], "manifest_error");
+    assert!(report["path"].as_str().unwrap().ends_with("manifest.json"));
 
-    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
-    assert!(manifest.contains("\"tag1\""));
-    assert!(manifest.contains("\"tag2\""));
+    // Without --ci the failure is only a warning
+    env.command().arg("verify").assert().success();
 }
 
 #[test]
-fn tc_4_17_add_category_path_traversal() {
-    let env = TestEnv::new();
+fn tc_16_6_verify_failed_fix_leaves_manifest() {
+    let env = TestEnv::with_documents();
+    mismatch_category(&env);
+    fs::write(env.corpus().join("aws/error-handling.md"), "In the way").unwrap();
+    let manifest_path = env.corpus().join("manifest.json");
+    let before = fs::read_to_string(&manifest_path).unwrap();
 
     env.command()
-        .args(["add", "--title", "Test", "--category", ".."])
-        .write_stdin("content")
+        .args(["verify", "--fix", "move"])
         .assert()
-        .failure()
-        .stderr(
-            predicate::str::contains("invalid character")
-                .or(predicate::str::contains("must start with")),
-        );
+        .stderr(predicate::str::contains("already exists"));
+
+    assert_eq!(fs::read_to_string(&manifest_path).unwrap(), before);
+    assert!(env.corpus().join("rust/error-handling.md").exists());
 }
 
 // =============================================================================
-// 5. Get Command Tests
+// 18. Compare Command Tests (requires `ranked`)
 // =============================================================================
 
+#[cfg(feature = "ranked")]
 #[test]
-fn tc_5_1_get_existing_document() {
+fn tc_18_1_compare_shows_both_backends() {
+    let env = TestEnv::with_documents();
+    env.command().arg("index").assert().success();
+    // Not in the manifest, so only ripgrep sees it
+    fs::write(env.corpus().join("aws/scratch.txt"), "Lambda scratch notes").unwrap();
+
+    let output = env.command().args(["compare", "Lambda"]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let (ripgrep, rest) = stdout.split_once("== ranked ==").unwrap();
+    let (ranked, differences) = rest.split_once("== differences ==").unwrap();
+    assert!(ripgrep.contains("== ripgrep =="));
+    assert!(ripgrep.contains("lambda-patterns.md"));
+    assert!(ranked.contains("lambda-patterns.md"));
+    assert!(!ranked.contains("scratch.txt"));
+    assert!(differences.contains("Only ripgrep:"));
+    assert!(differences.contains("scratch.txt"));
+    assert!(!differences.contains("lambda-patterns.md"));
+}
+
+#[cfg(feature = "ranked")]
+#[test]
+fn tc_18_2_compare_requires_index() {
     let env = TestEnv::with_documents();
 
     env.command()
-        .args(["get", "rust/error-handling.md"])
+        .args(["compare", "Lambda"])
         .assert()
-        .success()
-        .stdout(predicate::str::contains("# Error Handling in Rust"))
-        .stdout(predicate::str::contains("Result and Option"));
+        .failure()
+        .stderr(predicate::str::contains("kvault index"));
 }
 
+#[cfg(feature = "ranked")]
 #[test]
-fn tc_5_2_get_document_not_found() {
+fn tc_18_3_compare_uses_search_defaults() {
     let env = TestEnv::with_documents();
+    env.command().arg("index").assert().success();
+    let config = fs::read_to_string(&env.config_path).unwrap();
+    fs::write(
+        &env.config_path,
+        format!("{config}\n[search]\ndefault_category = \"rust\"\n"),
+    )
+    .unwrap();
 
     env.command()
-        .args(["get", "nonexistent/doc.md"])
+        .args(["compare", "Use"])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Document not found"));
+        .success()
+        .stdout(predicate::str::contains("error-handling.md"))
+        .stdout(predicate::str::contains("lambda-patterns.md").not());
+    env.command()
+        .args(["compare", "Use", "--all-categories"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("lambda-patterns.md"));
 }
 
+// =============================================================================
+// 19. Remove Command Tests
+// =============================================================================
+
 #[test]
-fn tc_5_3_get_path_traversal_attempt() {
+fn tc_19_1_remove_deletes_document_and_entry() {
     let env = TestEnv::with_documents();
 
     env.command()
-        .args(["get", "../../../etc/passwd"])
+        .args(["remove", "rust/error-handling.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed: Error Handling"));
+    assert!(!env.corpus().join("rust/error-handling.md").exists());
+    let manifest = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
+    assert!(!manifest.contains("error-handling.md"));
+    assert!(manifest.contains("lambda-patterns.md"));
+
+    env.command()
+        .args(["remove", "rust/error-handling.md"])
         .assert()
         .failure()
-        .stderr(
-            predicate::str::contains("Invalid document path")
-                .or(predicate::str::contains("contains '..'")),
-        );
+        .stderr(predicate::str::contains(
+            "Document not found: rust/error-handling.md",
+        ));
+
+    // `delete` is an alias, and a missing file doesn't block removing the entry
+    fs::remove_file(env.corpus().join("aws/lambda-patterns.md")).unwrap();
+    env.command()
+        .args(["delete", "aws/lambda-patterns.md"])
+        .assert()
+        .success();
+    env.command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Lambda Patterns").not());
 }
 
 #[test]
-fn tc_5_4_get_file_not_in_manifest() {
+fn tc_19_2_remove_rejects_traversal_and_locked_corpus() {
     let env = TestEnv::with_documents();
 
-    // Create orphan file not in manifest
-    fs::create_dir_all(env.corpus().join("orphan")).unwrap();
-    fs::write(env.corpus().join("orphan/file.md"), "# Orphan").unwrap();
+    env.command()
+        .args(["remove", "../outside.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("contains '..' component"));
 
+    env.command().arg("lock").assert().success();
     env.command()
-        .args(["get", "orphan/file.md"])
+        .args(["remove", "rust/error-handling.md"])
         .assert()
         .failure()
-        .stderr(predicate::str::contains("Document not found"));
+        .stderr(predicate::str::contains("Corpus is locked"));
+    assert!(env.corpus().join("rust/error-handling.md").exists());
 }
 
 // =============================================================================
-// 6. Edge Cases and Config Tests
+// 20. Update Command Tests
 // =============================================================================
 
 #[test]
-fn tc_6_1_no_corpus_paths_configured() {
-    let temp_dir = TempDir::new().unwrap();
-    let config_path = temp_dir.path().join("config.toml");
-
-    fs::write(&config_path, "[corpus]\npaths = []\n").unwrap();
+fn tc_20_1_update_replaces_content() {
+    let env = TestEnv::with_documents();
+    let manifest_before = fs::read_to_string(env.corpus().join("manifest.json")).unwrap();
 
-    cargo_bin_cmd!("kvault")
-        .env("KVAULT_CONFIG", &config_path)
-        .args(["search", "test"])
+    env.command()
+        .args(["update", "rust/error-handling.md"])
+        .write_stdin("# Error Handling\n\nPrefer thiserror in libraries.\n")
         .assert()
         .success()
-        .stdout(predicate::str::contains("No matches found"));
+        .stdout(predicate::str::contains("Updated: Error Handling"));
+    assert_eq!(
+        fs::read_to_string(env.corpus().join("rust/error-handling.md")).unwrap(),
+        "# Error Handling\n\nPrefer thiserror in libraries.\n"
+    );
+    assert_eq!(
+        fs::read_to_string(env.corpus().join("manifest.json")).unwrap(),
+        manifest_before
+    );
+
+    let file = env.corpus().parent().unwrap().join("new.md");
+    fs::write(&file, "From a file.\n").unwrap();
+    env.command()
+        .args(["update", "aws/lambda-patterns.md", "--file"])
+        .arg(&file)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(env.corpus().join("aws/lambda-patterns.md")).unwrap(),
+        "From a file.\n"
+    );
 }
 
 #[test]
-fn tc_6_2_invalid_config_toml() {
-    let temp_dir = TempDir::new().unwrap();
-    let config_path = temp_dir.path().join("config.toml");
+fn tc_20_2_update_rejects_empty_content_and_unknown_documents() {
+    let env = TestEnv::with_documents();
+    let before = fs::read_to_string(env.corpus().join("rust/error-handling.md")).unwrap();
 
-    fs::write(&config_path, "this is not valid toml {{{{").unwrap();
+    env.command()
+        .args(["update", "rust/error-handling.md"])
+        .write_stdin("  \n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Content cannot be empty"));
+    assert_eq!(
+        fs::read_to_string(env.corpus().join("rust/error-handling.md")).unwrap(),
+        before
+    );
 
-    cargo_bin_cmd!("kvault")
-        .env("KVAULT_CONFIG", &config_path)
-        .args(["search", "test"])
+    env.command()
+        .args(["update", "rust/missing.md"])
+        .write_stdin("content")
         .assert()
         .failure()
-        .stderr(predicate::str::contains("Error"));
+        .stderr(predicate::str::contains(
+            "Document not found: rust/missing.md",
+        ));
+    assert!(!env.corpus().join("rust/missing.md").exists());
+
+    env.command()
+        .args(["update", "../outside.md"])
+        .write_stdin("content")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("contains '..' component"));
 }
 
 #[test]
-fn tc_6_3_multiple_corpora() {
-    let temp_dir = TempDir::new().unwrap();
-    let root = temp_dir.path();
+fn tc_20_3_update_refreshes_word_count() {
+    let env = TestEnv::new();
+    env.command()
+        .args(["add", "--title", "Counted", "--category", "notes"])
+        .write_stdin("one two three")
+        .assert()
+        .success();
+    env.command()
+        .args(["list", "--word-count"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(3 words)"));
 
-    // Create two corpora
-    let corpus1 = root.join("corpus1");
-    let corpus2 = root.join("corpus2");
-    fs::create_dir_all(corpus1.join("cat1")).unwrap();
-    fs::create_dir_all(corpus2.join("cat2")).unwrap();
+    env.command()
+        .args(["update", "notes/counted.md"])
+        .write_stdin("one two three four five")
+        .assert()
+        .success();
+    env.command()
+        .args(["list", "--word-count"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(5 words)"));
+}
 
+// =============================================================================
+// 21. Sync Command Tests
+// =============================================================================
+
+#[test]
+fn tc_21_1_sync_reconciles_manifest_with_files() {
+    let env = TestEnv::with_documents();
     fs::write(
-        corpus1.join("cat1/doc1.md"),
-        "# Doc1\n\nUnique content alpha",
-    )
-    .unwrap();
-    fs::write(
-        corpus2.join("cat2/doc2.md"),
-        "# Doc2\n\nUnique content beta",
+        env.corpus().join("rust/ownership.md"),
+        "Intro line\n\n# Ownership Rules\n\nEach value has one owner.\n",
     )
     .unwrap();
-
-    fs::write(corpus1.join("manifest.json"), r#"{"version":"1","documents":[{"path":"cat1/doc1.md","title":"Doc1","category":"cat1","tags":[]}]}"#).unwrap();
-    fs::write(corpus2.join("manifest.json"), r#"{"version":"1","documents":[{"path":"cat2/doc2.md","title":"Doc2","category":"cat2","tags":[]}]}"#).unwrap();
-
-    let config_path = root.join("config.toml");
     fs::write(
-        &config_path,
-        format!(
-            "[corpus]\npaths = [\"{}\", \"{}\"]\n",
-            corpus1.display(),
-            corpus2.display()
-        ),
+        env.corpus().join("aws/step-functions.md"),
+        "No heading here.\n",
     )
     .unwrap();
+    fs::write(env.corpus().join("loose.md"), "# Loose\n").unwrap();
+    fs::create_dir_all(env.corpus().join(".index")).unwrap();
+    fs::write(env.corpus().join(".index/meta.md"), "# Index\n").unwrap();
+    fs::remove_file(env.corpus().join("aws/lambda-patterns.md")).unwrap();
 
-    // Search should find results from both corpora
-    cargo_bin_cmd!("kvault")
-        .env("KVAULT_CONFIG", &config_path)
-        .args(["search", "Unique"])
+    env.command()
+        .arg("sync")
         .assert()
         .success()
-        .stdout(predicate::str::contains("Doc1"))
-        .stdout(predicate::str::contains("Doc2"));
-}
-
-#[test]
-fn tc_6_4_document_in_manifest_but_file_missing() {
-    let env = TestEnv::new();
+        .stdout(predicate::str::contains("2 added, 1 removed"))
+        .stdout(
+            predicate::str::contains("Removed: ")
+                .and(predicate::str::contains("lambda-patterns.md")),
+        )
+        .stderr(predicate::str::contains(
+            "loose.md: not in a category directory",
+        ));
 
-    // Add document to manifest but don't create file
-    let manifest = r#"{
-    "version": "1",
-    "documents": [
-        {"path": "missing/file.md", "title": "Missing", "category": "missing", "tags": []}
-    ]
-}"#;
-    fs::write(env.corpus().join("manifest.json"), manifest).unwrap();
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(env.corpus().join("manifest.json")).unwrap())
+            .unwrap();
+    let documents = manifest["documents"].as_array().unwrap();
+    let summary: Vec<(&str, &str, &str)> = documents
+        .iter()
+        .map(|doc| {
+            (
+                doc["path"].as_str().unwrap(),
+                doc["title"].as_str().unwrap(),
+                doc["category"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("rust/error-handling.md", "Error Handling", "rust"),
+            ("aws/step-functions.md", "Step Functions", "aws"),
+            ("rust/ownership.md", "Ownership Rules", "rust"),
+        ]
+    );
+    // Existing entries keep their tags; new ones have none
+    assert_eq!(documents[0]["tags"], serde_json::json!(["rust", "errors"]));
+    assert_eq!(documents[1]["tags"], serde_json::json!([]));
 
     env.command()
-        .args(["get", "missing/file.md"])
+        .arg("sync")
         .assert()
-        .failure();
+        .success()
+        .stdout(predicate::str::contains("0 added, 0 removed"));
 }
 
 #[test]
-fn tc_6_5_kvault_config_env_overrides_default() {
+fn tc_21_2_sync_refuses_locked_corpus_with_changes() {
     let env = TestEnv::with_documents();
+    env.command().arg("lock").assert().success();
+    fs::write(env.corpus().join("rust/new.md"), "# New\n").unwrap();
 
-    // This test verifies KVAULT_CONFIG works (which all other tests depend on)
     env.command()
-        .args(["list"])
-        .assert()
-        .success()
-        .stdout(predicate::str::contains("Error Handling"));
-}
-
-#[test]
-fn tc_6_6_config_not_found_uses_defaults() {
-    let temp_dir = TempDir::new().unwrap();
-    let nonexistent_config = temp_dir.path().join("nonexistent/config.toml");
-
-    // When config doesn't exist, should use defaults (which point to ~/.kvault)
-    // This will likely show "No documents" or similar since default path probably doesn't exist
-    cargo_bin_cmd!("kvault")
-        .env("KVAULT_CONFIG", &nonexistent_config)
-        .args(["list"])
+        .arg("sync")
         .assert()
-        .success();
+        .failure()
+        .stderr(predicate::str::contains("locked"));
 }
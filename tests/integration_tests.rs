@@ -4,6 +4,8 @@
 //! Tests that require a full corpus setup are marked with #[ignore] and can be
 //! run manually with `cargo test -- --ignored` in an appropriate environment.
 
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
 use std::fs;
 use std::path::PathBuf;
 
@@ -164,6 +166,85 @@ mod corpus_tests {
         assert!(loaded.is_err());
     }
 
+    #[test]
+    fn corpus_load_jsonl_manifest() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        fs::write(
+            temp_dir.path().join("manifest.jsonl"),
+            concat!(
+                "{\"version\":\"1\"}\n",
+                "{\"path\":\"rust/a.md\",\"title\":\"A\",\"category\":\"rust\",\"tags\":[\"x\"]}\n",
+                "{\"path\":\"aws/b.md\",\"title\":\"B\",\"category\":\"aws\"}\n",
+            ),
+        )
+        .expect("Failed to write");
+
+        let loaded = kvault::corpus::Corpus::load(temp_dir.path()).unwrap();
+        assert_eq!(loaded.documents().len(), 2);
+        assert_eq!(loaded.documents()[0].tags, ["x"]);
+        assert_eq!(loaded.documents()[1].title, "B");
+    }
+
+    #[test]
+    fn corpus_load_large_manifest() {
+        use kvault::corpus::{Document, Manifest};
+
+        const COUNT: usize = 20_000;
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let mut manifest = Manifest::empty();
+        manifest.documents = (0..COUNT)
+            .map(|n| Document {
+                path: PathBuf::from(format!("notes/note-{n}.md")),
+                title: format!("Note {n}"),
+                category: format!("category-{}", n % 50),
+                tags: vec![format!("tag-{}", n % 7)],
+                encrypted: false,
+                word_count: Some(n),
+                aliases: vec![],
+                content_type: None,
+            })
+            .collect();
+        let file = fs::File::create(temp_dir.path().join("manifest.json")).unwrap();
+        serde_json::to_writer(std::io::BufWriter::new(file), &manifest).unwrap();
+
+        let loaded = kvault::corpus::Corpus::load(temp_dir.path()).unwrap();
+        assert_eq!(loaded.documents().len(), COUNT);
+        let last = &loaded.documents()[COUNT - 1];
+        assert_eq!(last.title, "Note 19999");
+        assert_eq!(last.category, "category-49");
+        assert_eq!(last.word_count, Some(19_999));
+
+        // A truncated manifest is still a parse error
+        let json = fs::read_to_string(temp_dir.path().join("manifest.json")).unwrap();
+        fs::write(
+            temp_dir.path().join("manifest.json"),
+            &json[..json.len() / 2],
+        )
+        .unwrap();
+        assert!(matches!(
+            kvault::corpus::Corpus::load(temp_dir.path()),
+            Err(kvault::corpus::CorpusError::ParseError { .. })
+        ));
+    }
+
+    #[test]
+    fn synthetic_corpus_is_deterministic() {
+        let first = TempDir::new().expect("Failed to create temp dir");
+        let second = TempDir::new().expect("Failed to create temp dir");
+
+        let corpus = kvault::commands::generate_synthetic_corpus(first.path(), 25).unwrap();
+        kvault::commands::generate_synthetic_corpus(second.path(), 25).unwrap();
+
+        assert_eq!(corpus.documents().len(), 25);
+        let doc = &corpus.documents()[7];
+        assert_eq!(doc.title, "Synthetic Document 7");
+        assert!(doc.word_count.unwrap() > 200);
+
+        let read = |root: &std::path::Path| fs::read_to_string(root.join(&doc.path)).unwrap();
+        assert_eq!(read(first.path()), read(second.path()));
+        assert!(corpus.resolve_document_path(doc).exists());
+    }
+
     #[test]
     fn corpus_resolve_document_path() {
         let corpus = TestCorpus::with_documents();
@@ -183,6 +264,7 @@ mod corpus_tests {
 
 mod storage_tests {
     use super::*;
+    use kvault::corpus::{Document, ManifestFormat};
     use kvault::storage::StorageBackend;
     use kvault::storage::local::LocalStorageBackend;
 
@@ -196,6 +278,96 @@ mod storage_tests {
         assert_eq!(manifest.unwrap().documents.len(), 2);
     }
 
+    fn document(path: &str) -> Document {
+        Document {
+            path: PathBuf::from(path),
+            title: path.to_string(),
+            category: "notes".to_string(),
+            tags: vec![],
+            encrypted: false,
+            word_count: None,
+            aliases: vec![],
+            content_type: None,
+        }
+    }
+
+    #[test]
+    fn local_storage_jsonl_append_keeps_prior_lines() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let storage = LocalStorageBackend::new(temp_dir.path().to_path_buf())
+            .with_manifest_format(ManifestFormat::Jsonl);
+
+        let mut manifest = storage.read_manifest().unwrap();
+        storage
+            .append_document(&mut manifest, document("notes/one.md"))
+            .unwrap();
+        let before = fs::read_to_string(temp_dir.path().join("manifest.jsonl")).unwrap();
+
+        storage
+            .append_document(&mut manifest, document("notes/two.md"))
+            .unwrap();
+        let after = fs::read_to_string(temp_dir.path().join("manifest.jsonl")).unwrap();
+
+        assert!(after.starts_with(&before));
+        assert_eq!(after.lines().count(), before.lines().count() + 1);
+        assert_eq!(storage.read_manifest().unwrap().documents.len(), 2);
+        assert_eq!(manifest.documents.len(), 2);
+    }
+
+    #[test]
+    fn local_storage_jsonl_append_terminates_last_line() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        fs::write(
+            temp_dir.path().join("manifest.jsonl"),
+            "{\"version\":\"1\"}",
+        )
+        .expect("Failed to write");
+        let storage = LocalStorageBackend::new(temp_dir.path().to_path_buf())
+            .with_manifest_format(ManifestFormat::Jsonl);
+
+        let mut manifest = storage.read_manifest().unwrap();
+        storage
+            .append_document(&mut manifest, document("notes/one.md"))
+            .unwrap();
+
+        assert_eq!(storage.read_manifest().unwrap().documents.len(), 1);
+    }
+
+    #[test]
+    fn local_storage_converts_json_manifest_to_jsonl() {
+        let corpus = TestCorpus::with_documents();
+        let storage = LocalStorageBackend::new(corpus.root.clone())
+            .with_manifest_format(ManifestFormat::Jsonl);
+
+        let mut manifest = storage.read_manifest().unwrap();
+        storage
+            .append_document(&mut manifest, document("notes/one.md"))
+            .unwrap();
+
+        assert!(!corpus.root.join("manifest.json").exists());
+        let loaded = kvault::corpus::Corpus::load(&corpus.root).unwrap();
+        assert_eq!(loaded.documents().len(), 3);
+    }
+
+    #[test]
+    fn local_storage_reads_large_jsonl_manifest() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let mut manifest = kvault::corpus::Manifest::empty();
+        manifest.documents = (0..10_000)
+            .map(|n| document(&format!("notes/{n}.md")))
+            .collect();
+        fs::write(
+            temp_dir.path().join("manifest.jsonl"),
+            manifest.encode(ManifestFormat::Jsonl).unwrap(),
+        )
+        .unwrap();
+
+        let storage = LocalStorageBackend::new(temp_dir.path().to_path_buf());
+        let read = storage.read_manifest().unwrap();
+        assert_eq!(read.documents.len(), 10_000);
+        assert_eq!(read.documents[9_999].path, PathBuf::from("notes/9999.md"));
+    }
+
     #[test]
     fn local_storage_write_document() {
         let corpus = TestCorpus::new();
@@ -237,7 +409,222 @@ mod storage_tests {
 mod search_tests {
     use super::*;
     use kvault::search::ripgrep::RipgrepBackend;
-    use kvault::search::{SearchBackend, SearchOptions};
+    use kvault::search::{
+        BackendCapabilities, MatchMode, SearchBackend, SearchOptions, SearchResult,
+    };
+
+    /// Corpus with one document containing `lambda` as a word and as an infix.
+    fn match_mode_corpus() -> TestCorpus {
+        let corpus = TestCorpus::new();
+        fs::create_dir_all(corpus.root.join("test")).expect("Failed to create dir");
+        fs::write(
+            corpus.root.join("test/modes.md"),
+            "# Modes\n\nprelambdapost\nplain lambda here\nlambda42\n",
+        )
+        .expect("Failed to write doc");
+        fs::write(
+            corpus.root.join("manifest.json"),
+            r#"{"version": "1", "documents": [{"path": "test/modes.md", "title": "Modes", "category": "test", "tags": []}]}"#,
+        )
+        .expect("Failed to write manifest");
+        corpus
+    }
+
+    fn matched_lines(query: &str, match_mode: MatchMode) -> anyhow::Result<Vec<String>> {
+        let corpus = match_mode_corpus();
+        let loaded = kvault::corpus::Corpus::load(&corpus.root).unwrap();
+        let options = SearchOptions {
+            match_mode,
+            ..Default::default()
+        };
+
+        let results = RipgrepBackend::new().search(query, &loaded, &options)?;
+        Ok(results
+            .into_iter()
+            .map(|r| r.matched_line().to_string())
+            .collect())
+    }
+
+    #[test]
+    fn ripgrep_match_mode_substring() {
+        if RipgrepBackend::check_available().is_err() {
+            return;
+        }
+
+        let lines = matched_lines("lambda", MatchMode::Substring).unwrap();
+        assert_eq!(lines, ["prelambdapost", "plain lambda here", "lambda42"]);
+    }
+
+    #[test]
+    fn ripgrep_match_mode_word() {
+        if RipgrepBackend::check_available().is_err() {
+            return;
+        }
+
+        let lines = matched_lines("lambda", MatchMode::Word).unwrap();
+        assert_eq!(lines, ["plain lambda here"]);
+    }
+
+    #[test]
+    fn ripgrep_match_mode_regex() {
+        if RipgrepBackend::check_available().is_err() {
+            return;
+        }
+
+        let lines = matched_lines(r"lambda\d+", MatchMode::Regex).unwrap();
+        assert_eq!(lines, ["lambda42"]);
+
+        // The same pattern is literal text in substring mode
+        assert!(
+            matched_lines(r"lambda\d+", MatchMode::Substring)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn ripgrep_match_mode_invalid_regex_errors() {
+        if RipgrepBackend::check_available().is_err() {
+            return;
+        }
+
+        let err = matched_lines("lambda(", MatchMode::Regex).unwrap_err();
+        assert!(err.to_string().contains("ripgrep failed"));
+    }
+
+    #[test]
+    fn ripgrep_capabilities() {
+        let caps = RipgrepBackend::new().capabilities();
+        assert_eq!(
+            caps,
+            BackendCapabilities {
+                supports_scoring: true,
+                supports_tag_filter: true,
+                supports_regex: true,
+                supports_within: true,
+                supports_max_per_file: true,
+                supports_group_by_document: true,
+                supports_score_matches: true,
+                supports_include_metadata: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn ripgrep_rejects_fuzzy_option() {
+        let options = SearchOptions {
+            fuzzy: Some(1),
+            ..Default::default()
+        };
+
+        let err = RipgrepBackend::new()
+            .capabilities()
+            .check("ripgrep", &options)
+            .unwrap_err();
+        assert!(err.to_string().contains("does not support fuzzy"));
+    }
+
+    /// Write a fake `rg` that prints `lines` JSON matches, then creates
+    /// `finished` in the corpus root if it was allowed to run to completion.
+    #[cfg(unix)]
+    fn fake_ripgrep(root: &std::path::Path, lines: usize) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = root.join("fake-rg");
+        let doc = root.join("notes/big.md");
+        fs::write(
+            &script,
+            format!(
+                r#"#!/bin/sh
+[ "$1" = "--version" ] && {{ echo "ripgrep 14.0.0"; exit 0; }}
+i=0
+while [ $i -lt {lines} ]; do
+  i=$((i + 1))
+  printf '%s\n' '{{"type":"match","data":{{"path":{{"text":"{doc}"}},"lines":{{"text":"match here\n"}},"line_number":'$i',"submatches":[]}}}}'
+done
+touch "{finished}"
+"#,
+                doc = doc.display(),
+                finished = root.join("finished").display(),
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        script
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ripgrep_stops_reading_at_limit() {
+        let corpus = TestCorpus::new();
+        let loaded = kvault::corpus::Corpus::load(&corpus.root).unwrap();
+        let backend = RipgrepBackend::with_binary(fake_ripgrep(&corpus.root, 200_000));
+
+        let results = backend
+            .search(
+                "match",
+                &loaded,
+                &SearchOptions {
+                    limit: Some(3),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let lines: Vec<_> = results.iter().map(SearchResult::line_number).collect();
+        assert_eq!(lines, [Some(1), Some(2), Some(3)]);
+        assert!(
+            !corpus.root.join("finished").exists(),
+            "ripgrep should be stopped once the limit is reached"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ripgrep_scoring_reads_past_limit() {
+        let corpus = TestCorpus::new();
+        let loaded = kvault::corpus::Corpus::load(&corpus.root).unwrap();
+        let backend = RipgrepBackend::with_binary(fake_ripgrep(&corpus.root, 5));
+
+        let results = backend
+            .search(
+                "match",
+                &loaded,
+                &SearchOptions {
+                    limit: Some(2),
+                    score_matches: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.score == Some(5.0)));
+        assert!(corpus.root.join("finished").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ripgrep_reads_all_output_under_limit() {
+        let corpus = TestCorpus::new();
+        let loaded = kvault::corpus::Corpus::load(&corpus.root).unwrap();
+        let backend = RipgrepBackend::with_binary(fake_ripgrep(&corpus.root, 5));
+
+        let results = backend
+            .search(
+                "match",
+                &loaded,
+                &SearchOptions {
+                    limit: Some(10),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(results.len(), 5);
+        assert!(corpus.root.join("finished").exists());
+    }
 
     #[test]
     fn ripgrep_search_finds_content() {
@@ -259,6 +646,7 @@ mod search_tests {
                 category: None,
                 case_sensitive: false,
                 fuzzy: None,
+                ..Default::default()
             },
         );
 
@@ -270,6 +658,122 @@ mod search_tests {
         );
     }
 
+    #[test]
+    fn ripgrep_search_order_is_deterministic() {
+        use kvault::corpus::{Document, Manifest};
+
+        if RipgrepBackend::check_available().is_err() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let notes = temp_dir.path().join("notes");
+        fs::create_dir_all(&notes).unwrap();
+        let mut manifest = Manifest::empty();
+        for n in 0..40 {
+            let path = format!("notes/note-{n:02}.md");
+            fs::write(
+                temp_dir.path().join(&path),
+                "needle one\nfiller\nneedle two\n",
+            )
+            .unwrap();
+            manifest.documents.push(Document {
+                path: PathBuf::from(path),
+                title: format!("Note {n}"),
+                category: "notes".to_string(),
+                tags: vec![],
+                encrypted: false,
+                word_count: None,
+                aliases: vec![],
+                content_type: None,
+            });
+        }
+        let file = fs::File::create(temp_dir.path().join("manifest.json")).unwrap();
+        serde_json::to_writer(file, &manifest).unwrap();
+
+        let loaded = kvault::corpus::Corpus::load(temp_dir.path()).unwrap();
+        let options = SearchOptions {
+            limit: Some(15),
+            ..Default::default()
+        };
+        let search = || {
+            RipgrepBackend::new()
+                .search("needle", &loaded, &options)
+                .unwrap()
+                .into_iter()
+                .map(|result| (result.path.clone(), result.line_number()))
+                .collect::<Vec<_>>()
+        };
+
+        let first = search();
+        assert_eq!(first, search());
+
+        // The limit keeps the first matches in path order
+        let expected: Vec<_> = (0..8)
+            .flat_map(|n| {
+                let path = notes.join(format!("note-{n:02}.md"));
+                [(path.clone(), Some(1)), (path, Some(3))]
+            })
+            .take(15)
+            .collect();
+        assert_eq!(first, expected);
+    }
+
+    #[test]
+    fn ripgrep_snippet_highlights_query() {
+        if RipgrepBackend::check_available().is_err() {
+            return;
+        }
+
+        let corpus = TestCorpus::with_documents();
+        let loaded = kvault::corpus::Corpus::load(&corpus.root).unwrap();
+        let results = RipgrepBackend::new()
+            .search("lambda", &loaded, &SearchOptions::default())
+            .unwrap();
+
+        assert!(!results.is_empty());
+        for result in &results {
+            let snippet = result.snippet.as_ref().unwrap();
+            assert_eq!(result.matched_line(), snippet.text);
+            assert!(!snippet.highlights.is_empty());
+            for range in &snippet.highlights {
+                assert_eq!(snippet.text[range.clone()].to_lowercase(), "lambda");
+            }
+        }
+    }
+
+    #[test]
+    fn ripgrep_include_metadata_matches_tags() {
+        if RipgrepBackend::check_available().is_err() {
+            return;
+        }
+
+        let corpus = TestCorpus::with_documents();
+        let loaded = kvault::corpus::Corpus::load(&corpus.root).unwrap();
+        let backend = RipgrepBackend::new();
+
+        // "errors" is only a tag of the Rust document
+        let results = backend
+            .search("errors", &loaded, &SearchOptions::default())
+            .unwrap();
+        assert!(results.is_empty());
+
+        let options = SearchOptions {
+            include_metadata: true,
+            ..Default::default()
+        };
+        let results = backend.search("errors", &loaded, &options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Error Handling");
+        assert_eq!(results[0].line_number(), None);
+        assert_eq!(results[0].matched_line(), "tags: rust, errors");
+
+        // Documents with body matches aren't repeated for their metadata
+        let results = backend.search("lambda", &loaded, &options).unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|result| result.line_number().is_some()));
+    }
+
     #[test]
     fn ripgrep_search_empty_query() {
         if RipgrepBackend::check_available().is_err() {
@@ -321,6 +825,7 @@ mod search_tests {
                 category: Some("rust".to_string()),
                 case_sensitive: false,
                 fuzzy: None,
+                ..Default::default()
             },
         );
 
@@ -333,6 +838,55 @@ mod search_tests {
         );
     }
 
+    #[test]
+    fn ripgrep_field_query_title_matches_only_titles() {
+        if RipgrepBackend::check_available().is_err() {
+            return;
+        }
+
+        let corpus = TestCorpus::with_documents();
+        let loaded = kvault::corpus::Corpus::load(&corpus.root).unwrap();
+        let backend = RipgrepBackend::new();
+
+        let results = backend
+            .search("title:lambda", &loaded, &SearchOptions::default())
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Lambda Patterns");
+
+        // "Rust" appears in a body but in no title
+        let results = backend
+            .search("title:rust", &loaded, &SearchOptions::default())
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn ripgrep_field_query_combines_category_and_text() {
+        if RipgrepBackend::check_available().is_err() {
+            return;
+        }
+
+        let corpus = TestCorpus::with_documents();
+        let loaded = kvault::corpus::Corpus::load(&corpus.root).unwrap();
+        let backend = RipgrepBackend::new();
+
+        let results = backend
+            .search("category:aws practices", &loaded, &SearchOptions::default())
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].matched_line(), "Best practices for AWS Lambda.");
+
+        let results = backend
+            .search(
+                "category:rust practices",
+                &loaded,
+                &SearchOptions::default(),
+            )
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
     #[test]
     fn ripgrep_rejects_long_query() {
         if RipgrepBackend::check_available().is_err() {
@@ -394,3 +948,59 @@ mod config_tests {
         assert_eq!(result, PathBuf::from("relative/path"));
     }
 }
+
+// =============================================================================
+// Near-Real-Time Search Tests (requires `ranked`; sets KVAULT_CONFIG)
+// =============================================================================
+
+#[cfg(feature = "ranked")]
+mod overlay_tests {
+    use super::TestCorpus;
+    use kvault::cli::Backend;
+    use kvault::commands;
+    use kvault::config::KVAULT_CONFIG_ENV;
+    use kvault::search::SearchOptions;
+    use kvault::search::tantivy::Rebuild;
+    use std::fs;
+
+    #[test]
+    fn added_document_is_searchable_before_reindex() {
+        let corpus = TestCorpus::with_documents();
+        let config_path = corpus.root.join("config.toml");
+        fs::write(
+            &config_path,
+            format!("[corpus]\npaths = [\"{}\"]\n", corpus.root.display()),
+        )
+        .unwrap();
+
+        // SAFETY: No other test in this binary reads the environment
+        unsafe {
+            std::env::set_var(KVAULT_CONFIG_ENV, &config_path);
+        }
+
+        commands::index_all(Some(Rebuild::Full), false).unwrap();
+        commands::add(
+            "Borrow Checker",
+            "Lifetimes keep references valid.",
+            "rust",
+            vec![],
+            false,
+            None,
+        )
+        .unwrap();
+
+        let outcome =
+            commands::search("lifetimes", Backend::Ranked, &SearchOptions::default()).unwrap();
+        assert_eq!(outcome.results.len(), 1);
+        assert_eq!(outcome.results[0].title, "Borrow Checker");
+
+        // Documents already in the index are still found
+        let outcome =
+            commands::search("lambda", Backend::Ranked, &SearchOptions::default()).unwrap();
+        assert_eq!(outcome.results[0].title, "Lambda Patterns");
+
+        unsafe {
+            std::env::remove_var(KVAULT_CONFIG_ENV);
+        }
+    }
+}